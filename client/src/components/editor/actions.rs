@@ -1,16 +1,20 @@
 use super::{types::Mode, Editor, Msg};
-use crate::{components::alert::Context, Rule};
-use anyhow::{format_err, Context as _, Result};
+use crate::{components::alert::Context, prelude::namespace, RecipeResponse, Rule};
+use anyhow::{bail, format_err, Context as _, Result};
 use log::error;
+use shared::PayloadKind;
 use std::convert::TryInto;
+use uuid::Uuid;
 use validator::Validate;
 use yew::{
     format::{Nothing, Text},
     prelude::*,
     services::{
         fetch::{Request, Response, StatusCode},
-        FetchService,
+        reader::FileData,
+        FetchService, ReaderService,
     },
+    web_sys::{FileList, HtmlSelectElement},
 };
 
 impl Editor {
@@ -33,6 +37,7 @@ impl Editor {
                 .id
                 .ok_or_else(|| format_err!("Cannot fetch recipe, ID is not set!"))?
         ))
+        .header(shared::NAMESPACE_HEADER, namespace())
         .body(Nothing)
         .map_err(anyhow::Error::from)?;
         let task = FetchService::fetch(
@@ -60,6 +65,7 @@ impl Editor {
         self.state = state.into();
         self.fetch_tsk = None;
         self.link.send_message(Msg::FetchConfig);
+        self.link.send_message(Msg::FetchCandidates);
         Ok(true)
     }
 
@@ -99,11 +105,173 @@ impl Editor {
         Ok(true)
     }
 
+    pub(super) fn handle_status_change(&mut self, status: String) -> Result<ShouldRender> {
+        self.state.status = status
+            .trim()
+            .parse()
+            .with_context(|| format!("The status, {}, must be a number!", status))?;
+        Ok(true)
+    }
+
+    pub(super) fn handle_priority_change(&mut self, priority: String) -> Result<ShouldRender> {
+        self.state.priority = priority
+            .trim()
+            .parse()
+            .with_context(|| format!("The priority, {}, must be a whole number!", priority))?;
+        Ok(true)
+    }
+
+    pub(super) fn handle_scenario_change(&mut self, scenario: String) -> Result<ShouldRender> {
+        self.state.scenario = scenario.trim().to_owned();
+        Ok(true)
+    }
+
+    pub(super) fn handle_required_state_change(&mut self, state: String) -> Result<ShouldRender> {
+        self.state.required_state = state.trim().to_owned();
+        Ok(true)
+    }
+
+    pub(super) fn handle_new_state_change(&mut self, state: String) -> Result<ShouldRender> {
+        self.state.new_state = state.trim().to_owned();
+        Ok(true)
+    }
+
     pub(super) fn handle_payload_change(&mut self, payload: String) -> Result<ShouldRender> {
         self.state.payload = payload;
         Ok(true)
     }
 
+    pub(super) fn handle_content_type_change(
+        &mut self,
+        content_type: String,
+    ) -> Result<ShouldRender> {
+        let previous = PayloadKind::of(&self.state.content_type);
+        self.state.content_type = content_type.trim().to_owned();
+        let current = PayloadKind::of(&self.state.content_type);
+        // an uploaded file is kept base64 encoded so it can't be edited as text, nor can text be
+        // served as the bytes of a file
+        if previous != current
+            && (previous == PayloadKind::Binary || current == PayloadKind::Binary)
+        {
+            self.state.payload = String::default();
+        }
+        Ok(true)
+    }
+
+    pub(super) fn handle_payload_file(&mut self, files: FileList) -> Result<ShouldRender> {
+        if let Some(file) = files.get(0) {
+            let task =
+                ReaderService::new().read_file(file, self.link.callback(Msg::PayloadFileRead))?;
+            self.reader_tsk = Some(task);
+        }
+        Ok(false)
+    }
+
+    pub(super) fn handle_payload_file_read(&mut self, file: FileData) -> Result<ShouldRender> {
+        self.reader_tsk = None;
+        self.state.payload = base64::encode(&file.content);
+        Ok(true)
+    }
+
+    pub(super) fn handle_templated_toggle(&mut self) -> Result<ShouldRender> {
+        self.state.templated = !self.state.templated;
+        Ok(true)
+    }
+
+    pub(super) fn handle_promote(&mut self) -> Result<ShouldRender> {
+        // a draft recorded by the proxy is served once it is saved without the flag
+        self.state.draft = false;
+        self.link.send_message(Msg::Post);
+        Ok(false)
+    }
+
+    pub(super) fn handle_fetch_candidates(&mut self) -> Result<ShouldRender> {
+        let id = self
+            .state
+            .id
+            .ok_or_else(|| format_err!("Cannot fetch other recipes for the URL, ID is not set!"))?;
+        let request = Request::get(format!("/ajax/recipe/{}/candidates", id))
+            .header(shared::NAMESPACE_HEADER, namespace())
+            .body(Nothing)
+            .map_err(anyhow::Error::from)?;
+        let task = FetchService::fetch(
+            request,
+            self.link.callback(
+                move |response: Response<Text>| match response.into_parts() {
+                    (meta, Ok(body)) if meta.status >= StatusCode::BAD_REQUEST => {
+                        Msg::Failure(body)
+                    }
+                    (_, Ok(body)) => Msg::FetchedCandidates(body),
+                    (_, Err(error)) => {
+                        error!("{}", error);
+                        Msg::Failure(format!("{}", error))
+                    }
+                },
+            ),
+        )?;
+        self.candidates_tsk = Some(task);
+        Ok(false)
+    }
+
+    pub(super) fn handle_fetched_candidates(&mut self, body: String) -> Result<ShouldRender> {
+        let candidates: Vec<shared::Recipe> = serde_json::from_str(&body).with_context(|| {
+            "Error parsing JSON when trying to fetch the other recipes for the URL!"
+        })?;
+        self.candidates = candidates.into_iter().map(Into::into).collect();
+        // reordering changes the priority of this recipe along with the others
+        if let Some(this) = self
+            .candidates
+            .iter()
+            .find(|candidate| candidate.id == self.state.id)
+        {
+            self.state.priority = this.priority;
+        }
+        self.candidates_tsk = None;
+        Ok(true)
+    }
+
+    pub(super) fn handle_candidate_dragged(&mut self, index: usize) -> Result<ShouldRender> {
+        self.dragging = Some(index);
+        Ok(false)
+    }
+
+    pub(super) fn handle_candidate_dropped(&mut self, index: usize) -> Result<ShouldRender> {
+        let from = match self.dragging.take() {
+            Some(from) if from != index => from,
+            _ => return Ok(false),
+        };
+        let moved = self.candidates.remove(from);
+        self.candidates.insert(index, moved);
+        let ids = self
+            .candidates
+            .iter()
+            .map(|candidate| candidate.id)
+            .collect::<Option<Vec<Uuid>>>()
+            .ok_or_else(|| format_err!("Cannot reorder recipes that haven't been saved!"))?;
+        let request = Request::put("/ajax/recipe/priority")
+            .header(shared::NAMESPACE_HEADER, namespace())
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_string(&ids).map_err(anyhow::Error::from))
+            .map_err(anyhow::Error::from)?;
+        let task = FetchService::fetch(
+            request,
+            self.link.callback(
+                move |response: Response<Text>| match response.into_parts() {
+                    (meta, Ok(body)) if meta.status >= StatusCode::BAD_REQUEST => {
+                        Msg::Failure(body)
+                    }
+                    (_, Ok(body)) => Msg::FetchedCandidates(body),
+                    (_, Err(error)) => {
+                        error!("{}", error);
+                        Msg::Failure(format!("{}", error))
+                    }
+                },
+            ),
+        )?;
+        self.candidates_tsk = Some(task);
+        Ok(true)
+    }
+
     pub(super) fn handle_failure(&mut self, error: String) -> Result<ShouldRender> {
         self.alert_ctx = Context::Danger(error);
         Ok(true)
@@ -124,6 +292,248 @@ impl Editor {
         Ok(true)
     }
 
+    pub(super) fn handle_add_header(&mut self) -> Result<ShouldRender> {
+        self.state.headers.push(shared::Header {
+            name: String::default(),
+            value: String::default(),
+        });
+        Ok(true)
+    }
+
+    pub(super) fn handle_header_name_changed(
+        &mut self,
+        index: usize,
+        name: String,
+    ) -> Result<ShouldRender> {
+        self.state.headers[index].name = name;
+        Ok(true)
+    }
+
+    pub(super) fn handle_header_value_changed(
+        &mut self,
+        index: usize,
+        value: String,
+    ) -> Result<ShouldRender> {
+        self.state.headers[index].value = value;
+        Ok(true)
+    }
+
+    pub(super) fn handle_remove_header(&mut self, index: usize) -> Result<ShouldRender> {
+        self.state.headers.remove(index);
+        Ok(true)
+    }
+
+    pub(super) fn handle_add_response(&mut self) -> Result<ShouldRender> {
+        self.state.responses.push(RecipeResponse::default());
+        Ok(true)
+    }
+
+    pub(super) fn handle_response_status(
+        &mut self,
+        index: usize,
+        status: String,
+    ) -> Result<ShouldRender> {
+        self.state.responses[index].status = status
+            .trim()
+            .parse()
+            .with_context(|| format!("The status, {}, must be a number!", status))?;
+        Ok(true)
+    }
+
+    pub(super) fn handle_response_content_type(
+        &mut self,
+        index: usize,
+        content_type: String,
+    ) -> Result<ShouldRender> {
+        let response = &mut self.state.responses[index];
+        let previous = PayloadKind::of(&response.content_type);
+        response.content_type = content_type.trim().to_owned();
+        let current = PayloadKind::of(&response.content_type);
+        // as with the recipe's own payload, a file and text can't stand in for each other
+        if previous != current
+            && (previous == PayloadKind::Binary || current == PayloadKind::Binary)
+        {
+            response.payload = String::default();
+        }
+        Ok(true)
+    }
+
+    pub(super) fn handle_response_payload(
+        &mut self,
+        index: usize,
+        payload: String,
+    ) -> Result<ShouldRender> {
+        self.state.responses[index].payload = payload;
+        Ok(true)
+    }
+
+    pub(super) fn handle_response_file(
+        &mut self,
+        index: usize,
+        files: FileList,
+    ) -> Result<ShouldRender> {
+        if let Some(file) = files.get(0) {
+            let task = ReaderService::new().read_file(
+                file,
+                self.link
+                    .callback(move |file| Msg::ResponseFileRead(index, file)),
+            )?;
+            self.reader_tsk = Some(task);
+        }
+        Ok(false)
+    }
+
+    pub(super) fn handle_response_file_read(
+        &mut self,
+        index: usize,
+        file: FileData,
+    ) -> Result<ShouldRender> {
+        self.reader_tsk = None;
+        self.state.responses[index].payload = base64::encode(&file.content);
+        Ok(true)
+    }
+
+    pub(super) fn handle_response_templated_toggle(
+        &mut self,
+        index: usize,
+    ) -> Result<ShouldRender> {
+        let response = &mut self.state.responses[index];
+        response.templated = !response.templated;
+        Ok(true)
+    }
+
+    pub(super) fn handle_remove_response(&mut self, index: usize) -> Result<ShouldRender> {
+        self.state.responses.remove(index);
+        Ok(true)
+    }
+
+    pub(super) fn handle_sequence_mode_change(
+        &mut self,
+        selected: HtmlSelectElement,
+    ) -> Result<ShouldRender> {
+        self.state.sequence_mode = match selected.selected_index() {
+            0 => shared::SequenceMode::StickOnLast,
+            1 => shared::SequenceMode::Cycle,
+            2 => shared::SequenceMode::FallThrough,
+            _ => bail!("Invalid selection for what follows the last response!"),
+        };
+        Ok(true)
+    }
+
+    pub(super) fn handle_delay_type_change(
+        &mut self,
+        selected: HtmlSelectElement,
+    ) -> Result<ShouldRender> {
+        use shared::Delay::*;
+        self.state.delay = match selected.selected_index() {
+            0 => None,
+            1 => Some(Fixed { millis: 100 }),
+            2 => Some(Uniform {
+                min_millis: 100,
+                max_millis: 500,
+            }),
+            3 => Some(Normal {
+                mean_millis: 200,
+                std_dev_millis: 50,
+            }),
+            4 => Some(LogNormal {
+                median_millis: 200,
+                sigma: 0.5,
+            }),
+            _ => bail!("Invalid selection for delay!"),
+        };
+        Ok(true)
+    }
+
+    pub(super) fn handle_delay_setting_change(
+        &mut self,
+        index: usize,
+        value: String,
+    ) -> Result<ShouldRender> {
+        use shared::Delay::*;
+        let millis = || -> Result<u32> {
+            value.trim().parse().with_context(|| {
+                format!(
+                    "The delay, {}, must be a whole number of milliseconds!",
+                    value
+                )
+            })
+        };
+        match (self.state.delay.as_mut(), index) {
+            (Some(Fixed { millis: fixed }), 0) => *fixed = millis()?,
+            (Some(Uniform { min_millis, .. }), 0) => *min_millis = millis()?,
+            (Some(Uniform { max_millis, .. }), 1) => *max_millis = millis()?,
+            (Some(Normal { mean_millis, .. }), 0) => *mean_millis = millis()?,
+            (Some(Normal { std_dev_millis, .. }), 1) => *std_dev_millis = millis()?,
+            (Some(LogNormal { median_millis, .. }), 0) => *median_millis = millis()?,
+            (Some(LogNormal { sigma, .. }), 1) => {
+                *sigma = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("The sigma, {}, must be a number!", value))?
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub(super) fn handle_fault_type_change(
+        &mut self,
+        selected: HtmlSelectElement,
+    ) -> Result<ShouldRender> {
+        use shared::Fault::*;
+        self.state.fault = match selected.selected_index() {
+            0 => None,
+            1 => Some(ConnectionReset),
+            2 => Some(PartialThenReset),
+            3 => Some(Trickle {
+                chunk_bytes: 16,
+                interval_millis: 100,
+            }),
+            4 => Some(EmptyReply),
+            _ => bail!("Invalid selection for fault!"),
+        };
+        Ok(true)
+    }
+
+    pub(super) fn handle_trickle_chunk_bytes_change(
+        &mut self,
+        bytes: String,
+    ) -> Result<ShouldRender> {
+        if let Some(shared::Fault::Trickle { chunk_bytes, .. }) = self.state.fault.as_mut() {
+            *chunk_bytes = bytes
+                .trim()
+                .parse()
+                .ok()
+                .filter(|bytes| *bytes > 0)
+                .ok_or_else(|| {
+                    format_err!(
+                        "The chunk size, {}, must be a positive whole number!",
+                        bytes
+                    )
+                })?;
+        }
+        Ok(true)
+    }
+
+    pub(super) fn handle_trickle_interval_change(
+        &mut self,
+        millis: String,
+    ) -> Result<ShouldRender> {
+        if let Some(shared::Fault::Trickle {
+            interval_millis, ..
+        }) = self.state.fault.as_mut()
+        {
+            *interval_millis = millis.trim().parse().with_context(|| {
+                format!(
+                    "The interval, {}, must be a whole number of milliseconds!",
+                    millis
+                )
+            })?;
+        }
+        Ok(true)
+    }
+
     pub(super) fn handle_post(&mut self) -> Result<ShouldRender> {
         if let Err(errors) = self.state.validate() {
             error!("Validation errors {:?}", errors);
@@ -137,6 +547,7 @@ impl Editor {
         } else {
             let body: shared::Recipe = self.state.clone().try_into()?;
             let request = Request::post("/ajax/recipe/")
+                .header(shared::NAMESPACE_HEADER, namespace())
                 .header("Content-Type", "application/json")
                 .body(serde_json::to_string(&body).map_err(anyhow::Error::from))
                 .map_err(anyhow::Error::from)?;
@@ -167,6 +578,8 @@ impl Editor {
         self.alert_ctx = Context::Success("Saved!".into());
         self.mode = Mode::View;
         self.fetch_tsk = None;
+        // the URL or priority may have changed, and with them the other recipes to list
+        self.link.send_message(Msg::FetchCandidates);
         Ok(true)
     }
 }
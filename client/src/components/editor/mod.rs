@@ -11,14 +11,24 @@ use crate::{
 use bootstrap_rs::{prelude::*, Card, Container, Jumbotron};
 use uuid::Uuid;
 use validator::ValidationErrors;
-use yew::{prelude::*, services::fetch::FetchTask};
+use yew::{
+    prelude::*,
+    services::{
+        fetch::FetchTask,
+        reader::{FileData, ReaderTask},
+    },
+};
 
 pub(crate) struct Editor {
     link: ComponentLink<Self>,
     fetch_tsk: Option<FetchTask>,
+    candidates_tsk: Option<FetchTask>,
+    reader_tsk: Option<ReaderTask>,
     props: Props,
     state: Recipe,
     config: shared::Config,
+    candidates: Vec<Recipe>,
+    dragging: Option<usize>,
     mode: Mode,
     alert_ctx: Context,
     errors: Option<ValidationErrors>,
@@ -32,7 +42,21 @@ pub(crate) enum Msg {
     FetchConfig,
     FetchedConfig(String),
     UrlChanged(String),
+    StatusChanged(String),
+    PriorityChanged(String),
+    ScenarioChanged(String),
+    RequiredStateChanged(String),
+    NewStateChanged(String),
+    ContentTypeChanged(String),
     PayloadChanged(String),
+    PayloadFileChosen(ChangeData),
+    PayloadFileRead(FileData),
+    TemplatedToggled,
+    Promote,
+    FetchCandidates,
+    FetchedCandidates(String),
+    CandidateDragged(usize),
+    CandidateDropped(usize),
     Post,
     Posted(String),
     Failure(String),
@@ -40,6 +64,24 @@ pub(crate) enum Msg {
     AddRule,
     RuleChanged(Rule, usize),
     RemoveRule(usize),
+    AddHeader,
+    HeaderNameChanged(usize, String),
+    HeaderValueChanged(usize, String),
+    RemoveHeader(usize),
+    AddResponse,
+    ResponseStatusChanged(usize, String),
+    ResponseContentTypeChanged(usize, String),
+    ResponsePayloadChanged(usize, String),
+    ResponseFileChosen(usize, ChangeData),
+    ResponseFileRead(usize, FileData),
+    ResponseTemplatedToggled(usize),
+    RemoveResponse(usize),
+    SequenceModeChanged(ChangeData),
+    DelayTypeChanged(ChangeData),
+    DelaySettingChanged(usize, String),
+    FaultTypeChanged(ChangeData),
+    TrickleChunkBytesChanged(String),
+    TrickleIntervalChanged(String),
 }
 
 #[derive(Properties, Debug, Clone)]
@@ -61,17 +103,25 @@ impl Component for Editor {
             link.send_message(Self::Message::FetchConfig);
         }
         let fetch_tsk = None;
+        let candidates_tsk = None;
+        let reader_tsk = None;
         let state = Recipe::default();
         let config = shared::Config::default();
+        let candidates = Vec::new();
+        let dragging = None;
         let alert_ctx = Context::default();
         let mode = props.mode.clone();
         let errors = None;
         Self {
             link,
             fetch_tsk,
+            candidates_tsk,
+            reader_tsk,
             props,
             state,
             config,
+            candidates,
+            dragging,
             mode,
             alert_ctx,
             errors,
@@ -90,7 +140,22 @@ impl Component for Editor {
             Post => self.handle_post(),
             Posted(body) => self.handle_posted(body),
             UrlChanged(url) => self.handle_url_change(url),
+            StatusChanged(status) => self.handle_status_change(status),
+            PriorityChanged(priority) => self.handle_priority_change(priority),
+            ScenarioChanged(scenario) => self.handle_scenario_change(scenario),
+            RequiredStateChanged(state) => self.handle_required_state_change(state),
+            NewStateChanged(state) => self.handle_new_state_change(state),
+            ContentTypeChanged(content_type) => self.handle_content_type_change(content_type),
             PayloadChanged(payload) => self.handle_payload_change(payload),
+            PayloadFileChosen(ChangeData::Files(files)) => self.handle_payload_file(files),
+            PayloadFileChosen(_) => Ok(false),
+            PayloadFileRead(file) => self.handle_payload_file_read(file),
+            TemplatedToggled => self.handle_templated_toggle(),
+            Promote => self.handle_promote(),
+            FetchCandidates => self.handle_fetch_candidates(),
+            FetchedCandidates(body) => self.handle_fetched_candidates(body),
+            CandidateDragged(index) => self.handle_candidate_dragged(index),
+            CandidateDropped(index) => self.handle_candidate_dropped(index),
             Failure(error) => self.handle_failure(error),
             ClearAlert => {
                 self.alert_ctx = Context::None;
@@ -99,6 +164,38 @@ impl Component for Editor {
             AddRule => self.handle_add_rule(),
             RuleChanged(rule, index) => self.handle_rule_changed(rule, index),
             RemoveRule(index) => self.handle_remove_rule(index),
+            AddHeader => self.handle_add_header(),
+            HeaderNameChanged(index, name) => self.handle_header_name_changed(index, name),
+            HeaderValueChanged(index, value) => self.handle_header_value_changed(index, value),
+            RemoveHeader(index) => self.handle_remove_header(index),
+            AddResponse => self.handle_add_response(),
+            ResponseStatusChanged(index, status) => self.handle_response_status(index, status),
+            ResponseContentTypeChanged(index, content_type) => {
+                self.handle_response_content_type(index, content_type)
+            }
+            ResponsePayloadChanged(index, payload) => self.handle_response_payload(index, payload),
+            ResponseFileChosen(index, ChangeData::Files(files)) => {
+                self.handle_response_file(index, files)
+            }
+            ResponseFileChosen(_, _) => Ok(false),
+            ResponseFileRead(index, file) => self.handle_response_file_read(index, file),
+            ResponseTemplatedToggled(index) => self.handle_response_templated_toggle(index),
+            RemoveResponse(index) => self.handle_remove_response(index),
+            SequenceModeChanged(ChangeData::Select(selected)) => {
+                self.handle_sequence_mode_change(selected)
+            }
+            SequenceModeChanged(_) => Ok(false),
+            DelayTypeChanged(ChangeData::Select(selected)) => {
+                self.handle_delay_type_change(selected)
+            }
+            DelayTypeChanged(_) => Ok(false),
+            DelaySettingChanged(index, value) => self.handle_delay_setting_change(index, value),
+            FaultTypeChanged(ChangeData::Select(selected)) => {
+                self.handle_fault_type_change(selected)
+            }
+            FaultTypeChanged(_) => Ok(false),
+            TrickleChunkBytesChanged(bytes) => self.handle_trickle_chunk_bytes_change(bytes),
+            TrickleIntervalChanged(millis) => self.handle_trickle_interval_change(millis),
         };
         match result {
             Ok(should_render) => should_render,
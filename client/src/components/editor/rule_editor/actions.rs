@@ -1,6 +1,6 @@
 use super::RuleEditor;
-use crate::RuleType;
-use anyhow::{bail, Result};
+use crate::{prelude::*, MatchMode, RuleType};
+use anyhow::{bail, format_err, Context, Result};
 use yew::{prelude::*, web_sys::HtmlSelectElement};
 
 impl RuleEditor {
@@ -9,11 +9,77 @@ impl RuleEditor {
             1 => RuleType::Authenticated,
             2 => RuleType::Subject,
             3 => RuleType::HttpMethod,
+            4 => RuleType::Header,
+            5 => RuleType::PathParam,
+            6 => RuleType::Body,
+            7 => RuleType::QueryParam,
+            8 => RuleType::NthCall,
+            9 => RuleType::Claim,
+            10 => RuleType::Unexpired,
+            11 => RuleType::BasicAuth,
+            12 => RuleType::ApiKey,
+            13 => RuleType::ClientCert,
             _ => bail!("Invalid selection for rule type!"),
         });
         Ok(true)
     }
 
+    pub(super) fn handle_key_location(
+        &mut self,
+        selected: HtmlSelectElement,
+    ) -> Result<ShouldRender> {
+        let key_location = match selected.selected_index() {
+            1 => shared::ApiKeyLocation::Header,
+            2 => shared::ApiKeyLocation::Query,
+            _ => bail!("Invalid selection for where the API key is sent!"),
+        };
+        opt_render_on_assign(&mut self.state.key_location, key_location)
+    }
+
+    pub(super) fn handle_match_mode(&mut self, match_mode: MatchMode) -> Result<ShouldRender> {
+        // a value is meaningless when only checking for presence or absence so clear it rather
+        // than saving a stale value
+        if !match_mode.requires_value() {
+            self.state.match_value = None;
+        }
+        opt_render_on_assign(&mut self.state.match_mode, match_mode)
+    }
+
+    pub(super) fn handle_nth(&mut self, nth: String) -> Result<ShouldRender> {
+        let nth = nth.trim();
+        let nth =
+            if nth.is_empty() {
+                None
+            } else {
+                Some(nth.parse().with_context(|| {
+                    format!("The call number, {}, must be a whole number!", nth)
+                })?)
+            };
+        opt_render_on_assign(&mut self.state.nth, nth)
+    }
+
+    pub(super) fn handle_algorithms(&mut self, algorithms: String) -> Result<ShouldRender> {
+        let algorithms = algorithms
+            .split(',')
+            .map(str::trim)
+            .filter(|algorithm| !algorithm.is_empty())
+            .map(|algorithm| {
+                shared::Algorithm::named(&algorithm.to_uppercase()).ok_or_else(|| {
+                    format_err!(
+                        "The algorithm, {}, is not one of {}!",
+                        algorithm,
+                        shared::Algorithm::ALL
+                            .iter()
+                            .map(|algorithm| algorithm.name())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    )
+                })
+            })
+            .collect::<Result<Vec<shared::Algorithm>>>()?;
+        render_on_assign(&mut self.state.algorithms, algorithms)
+    }
+
     pub(super) fn handle_remove(&self) -> Result<ShouldRender> {
         self.props.on_remove.emit(());
         Ok(false)
@@ -45,7 +45,7 @@ pub(crate) struct Props {
     pub(crate) name: Option<String>,
     #[prop_or_default]
     pub(crate) value: Option<String>,
-    pub(crate) key_path_is_file: bool,
+    pub(crate) key_path_kind: shared::KeyPathKind,
     #[prop_or_default]
     pub(crate) class: Classes,
     pub(crate) on_change: Callback<String>,
@@ -79,9 +79,9 @@ impl Component for KeyPathSelector {
             selected: props
                 .value
                 .as_deref()
-                .map(into_selected)
+                .map(|value| into_selected(props.key_path_kind, value))
                 .unwrap_or_default(),
-            kind: shared::KeyPathKind::Directory,
+            kind: props.key_path_kind,
             complete,
         };
         let select_ref = NodeRef::default();
@@ -131,7 +131,11 @@ impl Component for KeyPathSelector {
     }
 }
 
-fn into_selected(s: &str) -> Vec<String> {
+fn into_selected(kind: shared::KeyPathKind, s: &str) -> Vec<String> {
+    // a key in a JWKS is chosen by its kid, which is never split
+    if kind == shared::KeyPathKind::Jwks {
+        return vec![s.to_owned()];
+    }
     let separator = if s.contains('/') { '/' } else { '.' };
     s.split(separator).map(ToOwned::to_owned).collect()
 }
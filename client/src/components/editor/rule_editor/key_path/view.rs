@@ -24,15 +24,15 @@ impl KeyPathSelector {
     }
 
     pub(super) fn view_select(&self) -> Html {
-        let label_txt = if self.props.key_path_is_file {
-            "Key Reference"
-        } else {
-            "Key Path"
+        let label_txt = match self.props.key_path_kind {
+            shared::KeyPathKind::File => "Key Reference",
+            shared::KeyPathKind::Directory => "Key Path",
+            shared::KeyPathKind::Jwks => "Key ID",
         };
-        let prompt = if self.props.key_path_is_file {
-            "next property for key reference"
-        } else {
-            "next component for key path"
+        let prompt = match self.props.key_path_kind {
+            shared::KeyPathKind::File => "next property for key reference",
+            shared::KeyPathKind::Directory => "next component for key path",
+            shared::KeyPathKind::Jwks => "key ID, or * for the key the token names",
         };
         if self.state.complete {
             html! {}
@@ -61,26 +61,26 @@ impl KeyPathSelector {
     }
 
     pub(super) fn kind_help(&self) -> &str {
-        if self.props.key_path_is_file {
-            "The value for the Key Reference is the property path to a specific key within a JSON file, for example \"my_service.0\"."
-        } else {
-            "The value for the Key Path is the path relative to a directory full of keys, for example \"/qa/my_service/public/sso/0\"."
+        match self.props.key_path_kind {
+            shared::KeyPathKind::File => "The value for the Key Reference is the property path to a specific key within a JSON file, for example \"my_service.0\".",
+            shared::KeyPathKind::Directory => "The value for the Key Path is the path relative to a directory full of keys, for example \"/qa/my_service/public/sso/0\".",
+            shared::KeyPathKind::Jwks => "The value for the Key ID is the kid of a key in the JWKS, or \"*\" for whichever key the JWT names by its kid.",
         }
     }
 
     pub(super) fn separator(&self) -> &str {
-        if let shared::KeyPathKind::Directory { .. } = self.state.kind {
-            "/"
-        } else {
-            "."
+        match self.state.kind {
+            shared::KeyPathKind::Directory => "/",
+            shared::KeyPathKind::File => ".",
+            shared::KeyPathKind::Jwks => "",
         }
     }
 
     fn label_txt(&self) -> &str {
-        if self.props.key_path_is_file {
-            "Selected Key Reference"
-        } else {
-            "Selected Key Path"
+        match self.props.key_path_kind {
+            shared::KeyPathKind::File => "Selected Key Reference",
+            shared::KeyPathKind::Directory => "Selected Key Path",
+            shared::KeyPathKind::Jwks => "Selected Key ID",
         }
     }
 
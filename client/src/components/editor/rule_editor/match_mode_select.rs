@@ -0,0 +1,105 @@
+use crate::MatchMode;
+use anyhow::{bail, Result};
+use bootstrap_rs::prelude::*;
+use yew::{prelude::*, web_sys::HtmlSelectElement, Classes};
+
+pub(crate) struct MatchModeSelect {
+    props: Props,
+    link: ComponentLink<Self>,
+    state: Option<MatchMode>,
+}
+
+pub(crate) enum Msg {
+    ModeChange(ChangeData),
+}
+
+#[derive(Properties, Debug, Clone, PartialEq)]
+pub(crate) struct Props {
+    #[prop_or_default]
+    pub(crate) mode: Option<MatchMode>,
+    pub(crate) on_change: Callback<MatchMode>,
+    pub(crate) on_error: Callback<String>,
+    #[prop_or_default]
+    pub(crate) class: Classes,
+}
+
+impl Component for MatchModeSelect {
+    type Properties = Props;
+    type Message = Msg;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let state = props.mode.clone();
+        Self { props, link, state }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let result = if let Msg::ModeChange(ChangeData::Select(selected)) = msg {
+            self.handle_mode(selected)
+        } else {
+            Ok(false)
+        };
+        match result {
+            Ok(true) => {
+                if let Some(mode) = self.state.as_ref() {
+                    self.props.on_change.emit(mode.clone());
+                } else {
+                    self.props
+                        .on_error
+                        .emit("Invalid selection for match mode!".to_owned());
+                }
+                true
+            }
+            Ok(false) => false,
+            Err(error) => {
+                self.props.on_error.emit(error.to_string());
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        render_on_change(&mut self.props, props)
+    }
+
+    fn view(&self) -> Html {
+        use MatchMode::*;
+        let class = Classes::from("form-control");
+        let class = class.extend(self.props.class.clone());
+        html! {
+            <>
+                <label for="match_mode">{ "Match" }</label>
+                <select
+                    class=class
+                    name="match_mode"
+                    onchange=self.link.callback(move |evt| Msg::ModeChange(evt))
+                >
+                    <option selected={self.state.is_none()} disabled=true>{ "Choose How to Match" }</option>
+                    <option selected={self.state == Some(Exact)}>{ "Exact" }</option>
+                    <option selected={self.state == Some(Present)}>{ "Present" }</option>
+                    <option selected={self.state == Some(Absent)}>{ "Absent" }</option>
+                    <option selected={self.state == Some(CaseInsensitive)}>{ "Case Insensitive" }</option>
+                    <option selected={self.state == Some(Regex)}>{ "Regular Expression" }</option>
+                    <option selected={self.state == Some(Contains)}>{ "Contains" }</option>
+                    <option selected={self.state == Some(AnyOf)}>{ "Any Of" }</option>
+                </select>
+            </>
+        }
+    }
+}
+
+impl MatchModeSelect {
+    fn handle_mode(&mut self, selected: HtmlSelectElement) -> Result<ShouldRender> {
+        use MatchMode::*;
+        self.state = Some(match selected.selected_index() {
+            1 => Exact,
+            2 => Present,
+            3 => Absent,
+            4 => CaseInsensitive,
+            5 => Regex,
+            6 => Contains,
+            7 => AnyOf,
+            _ => bail!("Invalid selection for match mode!"),
+        });
+        Ok(true)
+    }
+}
@@ -1,9 +1,10 @@
 mod actions;
 mod key_path;
+mod match_mode_select;
 mod verb_select;
 mod view;
 
-use crate::{prelude::*, HttpVerb, Rule, RuleType};
+use crate::{prelude::*, HttpVerb, MatchMode, Rule, RuleType};
 use bootstrap_rs::prelude::*;
 use validator::ValidationErrors;
 use yew::prelude::*;
@@ -17,7 +18,7 @@ pub(super) struct RuleEditor {
 #[derive(Properties, Debug, Clone, PartialEq)]
 pub(super) struct Props {
     pub(super) rule: Rule,
-    pub(super) key_path_is_file: bool,
+    pub(super) key_path_kind: shared::KeyPathKind,
     pub(super) on_change: Callback<Rule>,
     pub(super) on_error: Callback<String>,
     pub(super) on_remove: Callback<()>,
@@ -30,6 +31,21 @@ pub(super) enum Msg {
     KeyPathChange(String),
     SubjectChange(String),
     HttpMethodChange(HttpVerb),
+    HeaderNameChange(String),
+    MatchModeChange(MatchMode),
+    MatchValueChange(String),
+    ParamNameChange(String),
+    BodyPathChange(String),
+    FormFieldChange(String),
+    NthChange(String),
+    ClaimChange(String),
+    AlgorithmsChange(String),
+    UsernameChange(String),
+    PasswordChange(String),
+    KeyLocationChange(ChangeData),
+    KeyNameChange(String),
+    ApiKeyChange(String),
+    FingerprintChange(String),
     Remove,
     Failure(String),
 }
@@ -57,7 +73,42 @@ impl Component for RuleEditor {
             HttpMethodChange(http_verb) => {
                 opt_render_on_assign(&mut self.state.http_method, http_verb)
             }
-            TypeChange(_) => Ok(false),
+            HeaderNameChange(header_name) => {
+                opt_render_on_assign(&mut self.state.header_name, InputString(header_name))
+            }
+            MatchModeChange(match_mode) => self.handle_match_mode(match_mode),
+            MatchValueChange(match_value) => {
+                opt_render_on_assign(&mut self.state.match_value, InputString(match_value))
+            }
+            ParamNameChange(param_name) => {
+                opt_render_on_assign(&mut self.state.param_name, InputString(param_name))
+            }
+            BodyPathChange(body_path) => {
+                opt_render_on_assign(&mut self.state.body_path, InputString(body_path))
+            }
+            FormFieldChange(form_field) => {
+                opt_render_on_assign(&mut self.state.form_field, InputString(form_field))
+            }
+            NthChange(nth) => self.handle_nth(nth),
+            ClaimChange(claim) => opt_render_on_assign(&mut self.state.claim, InputString(claim)),
+            AlgorithmsChange(algorithms) => self.handle_algorithms(algorithms),
+            UsernameChange(username) => {
+                opt_render_on_assign(&mut self.state.username, InputString(username))
+            }
+            PasswordChange(password) => {
+                opt_render_on_assign(&mut self.state.password, InputString(password))
+            }
+            KeyLocationChange(ChangeData::Select(selected)) => self.handle_key_location(selected),
+            KeyNameChange(key_name) => {
+                opt_render_on_assign(&mut self.state.key_name, InputString(key_name))
+            }
+            ApiKeyChange(api_key) => {
+                opt_render_on_assign(&mut self.state.api_key, InputString(api_key))
+            }
+            FingerprintChange(fingerprint) => {
+                opt_render_on_assign(&mut self.state.fingerprint, InputString(fingerprint))
+            }
+            TypeChange(_) | KeyLocationChange(_) => Ok(false),
             Remove => self.handle_remove(),
             Failure(error) => {
                 self.props.on_error.emit(error);
@@ -78,6 +78,9 @@ impl Component for VerbSelect {
                     <option selected={self.state == Some(Post)}>{ "Post" }</option>
                     <option selected={self.state == Some(Put)}>{ "Put" }</option>
                     <option selected={self.state == Some(Delete)}>{ "Delete" }</option>
+                    <option selected={self.state == Some(Patch)}>{ "Patch" }</option>
+                    <option selected={self.state == Some(Head)}>{ "Head" }</option>
+                    <option selected={self.state == Some(Options)}>{ "Options" }</option>
                 </select>
             </>
         }
@@ -92,6 +95,9 @@ impl VerbSelect {
             2 => Post,
             3 => Put,
             4 => Delete,
+            5 => Patch,
+            6 => Head,
+            7 => Options,
             _ => bail!("Invalid selection for HTTP method!"),
         });
         Ok(true)
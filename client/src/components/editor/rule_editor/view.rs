@@ -1,4 +1,7 @@
-use super::{key_path::KeyPathSelector, verb_select::VerbSelect, Msg, RuleEditor};
+use super::{
+    key_path::KeyPathSelector, match_mode_select::MatchModeSelect, verb_select::VerbSelect, Msg,
+    RuleEditor,
+};
 use crate::RuleType;
 use bootstrap_rs::{input::InputType, prelude::*, Button, ButtonToolbar, Input};
 use validator::ValidationErrors;
@@ -28,6 +31,16 @@ impl RuleEditor {
                             <option selected={self.state.rule_type == Some(RuleType::Authenticated)}>{ "Authenticated Call" }</option>
                             <option selected={self.state.rule_type == Some(RuleType::Subject)}>{ "With Subject" }</option>
                             <option selected={self.state.rule_type == Some(RuleType::HttpMethod)}>{ "HTTP Method" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::Header)}>{ "Header" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::PathParam)}>{ "Path Parameter" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::Body)}>{ "Body" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::QueryParam)}>{ "Query Parameter" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::NthCall)}>{ "Nth Call" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::Claim)}>{ "Token Claim" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::Unexpired)}>{ "Unexpired Token" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::BasicAuth)}>{ "Basic Auth" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::ApiKey)}>{ "API Key" }</option>
+                            <option selected={self.state.rule_type == Some(RuleType::ClientCert)}>{ "Client Certificate" }</option>
                         </select>
                         { self.render_validation_feedback("rule_type_required") }
                     </div>
@@ -36,6 +49,16 @@ impl RuleEditor {
                             Some(RuleType::Authenticated) => self.render_key_path(),
                             Some(RuleType::Subject) => self.render_subject(),
                             Some(RuleType::HttpMethod) => self.render_http_method(),
+                            Some(RuleType::Header) => self.render_header(),
+                            Some(RuleType::PathParam) => self.render_path_param(),
+                            Some(RuleType::Body) => self.render_body(),
+                            Some(RuleType::QueryParam) => self.render_query_param(),
+                            Some(RuleType::NthCall) => self.render_nth_call(),
+                            Some(RuleType::Claim) => self.render_claim(),
+                            Some(RuleType::Unexpired) => self.render_unexpired(),
+                            Some(RuleType::BasicAuth) => self.render_basic_auth(),
+                            Some(RuleType::ApiKey) => self.render_api_key(),
+                            Some(RuleType::ClientCert) => self.render_client_cert(),
                             _ => html! { <div class="col" /> }
                         }
                     }
@@ -55,7 +78,7 @@ impl RuleEditor {
             <div class="col">
                 <KeyPathSelector
                     name="key_path"
-                    key_path_is_file=self.props.key_path_is_file
+                    key_path_kind=self.props.key_path_kind
                     class=class
                     on_change=self.link.callback(move |value| Msg::KeyPathChange(value))
                     on_error=self.link.callback(move |error| Msg::Failure(error))
@@ -63,6 +86,16 @@ impl RuleEditor {
                     value=self.state.key_path.clone().unwrap_or_default()
                     errors=self.props.errors.clone()
                 />
+                <label for="algorithms">{ "Algorithms" }</label>
+                <Input
+                    name="algorithms"
+                    class="form-control"
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::AlgorithmsChange(value))
+                    aria_describedby="algorithms_help"
+                    value=self.state.algorithms.iter().map(|algorithm| algorithm.name()).collect::<Vec<&str>>().join(", ")
+                />
+                <small id="algorithms_help">{ "Optional, a comma separated list of the algorithms, like RS256, PS384, ES256 or HS512, that the JWT may be signed with. Any that suits the key is allowed when empty." }</small>
             </div>
         }
     }
@@ -100,6 +133,331 @@ impl RuleEditor {
         }
     }
 
+    fn render_header(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::Header,
+            &self.state.rule_type,
+            "invalid_header_rule",
+        );
+        html! {
+            <div class="col">
+                <label for="header_name">{ "Header Name" }</label>
+                <Input
+                    name="header_name"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::HeaderNameChange(value))
+                    aria_describedby="header_help"
+                    value=self.state.header_name.clone().unwrap_or_default()
+                />
+                <MatchModeSelect
+                    mode=self.state.match_mode.clone()
+                    class=class.clone()
+                    on_change=self.link.callback(Msg::MatchModeChange)
+                    on_error=self.link.callback(Msg::Failure)
+                />
+                { self.render_match_value(class) }
+                <small id="header_help">{ "This rule will match a header of the incoming HTTP request, by name." }</small>
+                { self.render_validation_feedback("invalid_header_rule") }
+            </div>
+        }
+    }
+
+    fn render_path_param(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::PathParam,
+            &self.state.rule_type,
+            "invalid_path_param_rule",
+        );
+        html! {
+            <div class="col">
+                <label for="param_name">{ "Parameter Name" }</label>
+                <Input
+                    name="param_name"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::ParamNameChange(value))
+                    aria_describedby="param_help"
+                    value=self.state.param_name.clone().unwrap_or_default()
+                />
+                <MatchModeSelect
+                    mode=self.state.match_mode.clone()
+                    class=class.clone()
+                    on_change=self.link.callback(Msg::MatchModeChange)
+                    on_error=self.link.callback(Msg::Failure)
+                />
+                { self.render_match_value(class) }
+                <small id="param_help">{ "This rule will match a parameter, like {id}, or the wildcard, *, captured from the path of the incoming HTTP request." }</small>
+                { self.render_validation_feedback("invalid_path_param_rule") }
+            </div>
+        }
+    }
+
+    fn render_query_param(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::QueryParam,
+            &self.state.rule_type,
+            "invalid_query_param_rule",
+        );
+        html! {
+            <div class="col">
+                <label for="param_name">{ "Parameter Name" }</label>
+                <Input
+                    name="param_name"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::ParamNameChange(value))
+                    aria_describedby="query_param_help"
+                    value=self.state.param_name.clone().unwrap_or_default()
+                />
+                <MatchModeSelect
+                    mode=self.state.match_mode.clone()
+                    class=class.clone()
+                    on_change=self.link.callback(Msg::MatchModeChange)
+                    on_error=self.link.callback(Msg::Failure)
+                />
+                { self.render_match_value(class) }
+                <small id="query_param_help">{ "This rule will match a parameter of the query string of the incoming HTTP request, by name, in any order." }</small>
+                { self.render_validation_feedback("invalid_query_param_rule") }
+            </div>
+        }
+    }
+
+    fn render_nth_call(&self) -> Html {
+        html! {
+            <div class="col">
+                <label for="nth">{ "Call Number" }</label>
+                <Input
+                    name="nth"
+                    class=super::validation_class_for_rule(&self.props.errors, RuleType::NthCall, &self.state.rule_type, "invalid_nth_call_rule")
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::NthChange(value))
+                    aria_describedby="nth_help"
+                    value=self.state.nth.map(|nth| nth.to_string()).unwrap_or_default()
+                />
+                <small id="nth_help">{ "This rule will match only the nth call with the same method and path, counting from 1, since the journal was last cleared." }</small>
+                { self.render_validation_feedback("invalid_nth_call_rule") }
+            </div>
+        }
+    }
+
+    fn render_claim(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::Claim,
+            &self.state.rule_type,
+            "invalid_claim_rule",
+        );
+        html! {
+            <div class="col">
+                <label for="claim">{ "Claim" }</label>
+                <Input
+                    name="claim"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::ClaimChange(value))
+                    aria_describedby="claim_help"
+                    value=self.state.claim.clone().unwrap_or_default()
+                />
+                <MatchModeSelect
+                    mode=self.state.match_mode.clone()
+                    class=class.clone()
+                    on_change=self.link.callback(Msg::MatchModeChange)
+                    on_error=self.link.callback(Msg::Failure)
+                />
+                { self.render_match_value(class) }
+                <small id="claim_help">{ "This rule will match a claim of the authentication JWT by name, like aud or scope, or by a JSON pointer or JSONPath, like $.tenant.id, into the claims. Each element of an array claim is matched on its own; Any Of takes a comma separated list." }</small>
+                { self.render_validation_feedback("invalid_claim_rule") }
+            </div>
+        }
+    }
+
+    fn render_unexpired(&self) -> Html {
+        html! {
+            <div class="col">
+                <small id="unexpired_help">{ "This rule will match an authentication JWT that has not expired and is past its not before time, if it has either, when the call is made." }</small>
+            </div>
+        }
+    }
+
+    fn render_basic_auth(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::BasicAuth,
+            &self.state.rule_type,
+            "invalid_basic_auth_rule",
+        );
+        html! {
+            <div class="col">
+                <label for="username">{ "Username" }</label>
+                <Input
+                    name="username"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::UsernameChange(value))
+                    aria_describedby="basic_auth_help"
+                    value=self.state.username.clone().unwrap_or_default()
+                />
+                <label for="password">{ "Password" }</label>
+                <Input
+                    name="password"
+                    class=class
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::PasswordChange(value))
+                    aria_describedby="basic_auth_help"
+                    value=self.state.password.clone().unwrap_or_default()
+                />
+                <small id="basic_auth_help">{ "This rule will match HTTP Basic credentials in the Authorization header of the incoming HTTP request with the username and, unless it is empty, the password." }</small>
+                { self.render_validation_feedback("invalid_basic_auth_rule") }
+            </div>
+        }
+    }
+
+    fn render_api_key(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::ApiKey,
+            &self.state.rule_type,
+            "invalid_api_key_rule",
+        );
+        let select_class = Classes::from("form-control").extend(class.clone());
+        html! {
+            <div class="col">
+                <label for="key_location">{ "Sent In" }</label>
+                <select
+                    name="key_location"
+                    class=select_class
+                    onchange=self.link.callback(move |evt| Msg::KeyLocationChange(evt))
+                >
+                    <option selected={self.state.key_location.is_none()} disabled=true>{ "Choose Where" }</option>
+                    <option selected={self.state.key_location == Some(shared::ApiKeyLocation::Header)}>{ "Header" }</option>
+                    <option selected={self.state.key_location == Some(shared::ApiKeyLocation::Query)}>{ "Query Parameter" }</option>
+                </select>
+                <label for="key_name">{ "Name" }</label>
+                <Input
+                    name="key_name"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::KeyNameChange(value))
+                    aria_describedby="api_key_help"
+                    value=self.state.key_name.clone().unwrap_or_default()
+                />
+                <label for="api_key">{ "API Key" }</label>
+                <Input
+                    name="api_key"
+                    class=class
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::ApiKeyChange(value))
+                    aria_describedby="api_key_help"
+                    value=self.state.api_key.clone().unwrap_or_default()
+                />
+                <small id="api_key_help">{ "This rule will match an API key sent in a header, like X-Api-Key, or a query parameter, like api_key, of the incoming HTTP request." }</small>
+                { self.render_validation_feedback("invalid_api_key_rule") }
+            </div>
+        }
+    }
+
+    fn render_client_cert(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::ClientCert,
+            &self.state.rule_type,
+            "invalid_client_cert_rule",
+        );
+        html! {
+            <div class="col">
+                <label for="subject">{ "Subject" }</label>
+                <Input
+                    name="subject"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::SubjectChange(value))
+                    aria_describedby="client_cert_help"
+                    value=self.state.subject.clone().unwrap_or_default()
+                />
+                <label for="fingerprint">{ "SHA-256 Fingerprint" }</label>
+                <Input
+                    name="fingerprint"
+                    class=class
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::FingerprintChange(value))
+                    aria_describedby="client_cert_help"
+                    value=self.state.fingerprint.clone().unwrap_or_default()
+                />
+                <small id="client_cert_help">{ "This rule will match the client certificate that the proxy terminating TLS forwards with the incoming HTTP request, by its subject, like CN=alice,O=Acme or just the common name, alice, its fingerprint or both." }</small>
+                { self.render_validation_feedback("invalid_client_cert_rule") }
+            </div>
+        }
+    }
+
+    fn render_body(&self) -> Html {
+        let class = super::validation_class_for_rule(
+            &self.props.errors,
+            RuleType::Body,
+            &self.state.rule_type,
+            "invalid_body_rule",
+        );
+        html! {
+            <div class="col">
+                <label for="body_path">{ "JSON Path" }</label>
+                <Input
+                    name="body_path"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::BodyPathChange(value))
+                    aria_describedby="body_help"
+                    value=self.state.body_path.clone().unwrap_or_default()
+                />
+                <label for="form_field">{ "Form Field" }</label>
+                <Input
+                    name="form_field"
+                    class=class.clone()
+                    input_type=InputType::Text
+                    on_change=self.link.callback(move |value| Msg::FormFieldChange(value))
+                    aria_describedby="body_help"
+                    value=self.state.form_field.clone().unwrap_or_default()
+                />
+                <MatchModeSelect
+                    mode=self.state.match_mode.clone()
+                    class=class.clone()
+                    on_change=self.link.callback(Msg::MatchModeChange)
+                    on_error=self.link.callback(Msg::Failure)
+                />
+                { self.render_match_value(class) }
+                <small id="body_help">{ "This rule will match the whole body of the incoming HTTP request, as text, unless given either a JSON pointer or JSONPath, like $.order.id, into a JSON body or the name of a field of a form body." }</small>
+                { self.render_validation_feedback("invalid_body_rule") }
+            </div>
+        }
+    }
+
+    fn render_match_value(&self, class: Classes) -> Html {
+        if self
+            .state
+            .match_mode
+            .as_ref()
+            .map(|match_mode| match_mode.requires_value())
+            .unwrap_or_default()
+        {
+            html! {
+                <>
+                    <label for="match_value">{ "Value" }</label>
+                    <Input
+                        name="match_value"
+                        class=class
+                        input_type=InputType::Text
+                        on_change=self.link.callback(move |value| Msg::MatchValueChange(value))
+                        value=self.state.match_value.clone().unwrap_or_default()
+                    />
+                </>
+            }
+        } else {
+            html! {}
+        }
+    }
+
     fn render_validation_feedback(&self, code: &'static str) -> Html {
         match self.props.errors.as_ref() {
             Some(Some(errors)) => {
@@ -1,9 +1,10 @@
 use super::{rule_editor::RuleEditor, Editor, Mode, Msg};
-use crate::{AppRoute, Rule};
+use crate::{AppRoute, Recipe, RecipeResponse, Rule};
 use bootstrap_rs::{
     input::InputType, Breadcrumb, BreadcrumbItem, ButtonGroup, CardBody, CardHeader, CardText,
     FormGroup, Input, TextArea,
 };
+use shared::PayloadKind;
 use validator::{ValidationErrors, ValidationErrorsKind};
 use yew::prelude::*;
 use yew_router::prelude::*;
@@ -96,13 +97,36 @@ impl Editor {
     }
 
     fn render_view_toolbar(&self) -> Html {
+        if let Some(file) = &self.state.file {
+            return html! {
+                <span class="badge badge-info p-2">
+                    { format!("Loaded from {}, edit that file to change it", file) }
+                </span>
+            };
+        }
         html! {
+            <ButtonGroup>
                 <button
                     type="button" onclick=self.link.callback(|_| Msg::Edit)
                     class="btn btn-primary"
                 >
                     { "Edit" }
                 </button>
+                {
+                    if self.state.draft {
+                        html! {
+                            <button
+                                type="button" onclick=self.link.callback(|_| Msg::Promote)
+                                class="btn btn-success"
+                            >
+                                { "Promote" }
+                            </button>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </ButtonGroup>
         }
     }
 
@@ -143,28 +167,323 @@ impl Editor {
                         { "Add New Rule" }
                     </button>
                 </FormGroup>
+                <FormGroup>
+                    <label for="status">
+                        { "Status Code" }
+                    </label>
+                    <Input
+                        id="status"
+                        input_type=InputType::Text
+                        value=self.state.status.to_string()
+                        on_change=self.link.callback(|value| Msg::StatusChanged(value))
+                        valid=is_valid("status", &self.errors)
+                    />
+                    { render_validation_feedback("status", &self.errors) }
+                </FormGroup>
+                <FormGroup>
+                    <label for="priority">
+                        { "Priority" }
+                    </label>
+                    <Input
+                        id="priority"
+                        input_type=InputType::Text
+                        value=self.state.priority.to_string()
+                        on_change=self.link.callback(|value| Msg::PriorityChanged(value))
+                        aria_describedby="priority_help"
+                    />
+                    <small id="priority_help">{ "When more than one recipe matches a call, the highest priority is tried first." }</small>
+                </FormGroup>
+                <FormGroup>
+                    <p>{ "Scenario" }</p>
+                    <div class="form-row">
+                        <div class="col">
+                            <label for="scenario">{ "Name" }</label>
+                            <Input
+                                id="scenario"
+                                input_type=InputType::Text
+                                value=self.state.scenario.clone()
+                                on_change=self.link.callback(|value| Msg::ScenarioChanged(value))
+                                valid=is_valid("scenario", &self.errors)
+                                aria_describedby="scenario_help"
+                            />
+                            { render_validation_feedback("scenario", &self.errors) }
+                        </div>
+                        {
+                            // states belong to a scenario so there's nothing to set until it's named
+                            if self.state.scenario.is_empty() {
+                                html! {}
+                            } else {
+                                html! {
+                                    <>
+                                        <div class="col">
+                                            <label for="required_state">{ "Required State" }</label>
+                                            <Input
+                                                id="required_state"
+                                                input_type=InputType::Text
+                                                value=self.state.required_state.clone()
+                                                on_change=self.link.callback(|value| Msg::RequiredStateChanged(value))
+                                            />
+                                        </div>
+                                        <div class="col">
+                                            <label for="new_state">{ "New State" }</label>
+                                            <Input
+                                                id="new_state"
+                                                input_type=InputType::Text
+                                                value=self.state.new_state.clone()
+                                                on_change=self.link.callback(|value| Msg::NewStateChanged(value))
+                                            />
+                                        </div>
+                                    </>
+                                }
+                            }
+                        }
+                    </div>
+                    <small id="scenario_help">{ format!("Every scenario starts in the state, {}. The recipe is only served while its scenario is in the required state, if one is set, and serving it moves the scenario to the new state, if one is set.", shared::SCENARIO_STARTED) }</small>
+                </FormGroup>
+                <FormGroup>
+                    <p>{ "Response Headers" }</p>
+                    {
+                        if self.state.headers.is_empty() {
+                            html! {}
+                        } else {
+                            html! {
+                                <ol class="list-group mb-3">
+                                    { for self.state.headers.iter().enumerate().map(|(index, h)| self.render_edit_header(h, index)) }
+                                </ol>
+                            }
+                        }
+                    }
+                    { render_validation_feedback("headers", &self.errors) }
+                    <button
+                        type="button"
+                        class="btn btn-secondary"
+                        onclick=self.link.callback(|_| Msg::AddHeader)
+                    >
+                        { "Add New Header" }
+                    </button>
+                </FormGroup>
+                <FormGroup>
+                    <label for="content_type">
+                        { "Content Type" }
+                    </label>
+                    <Input
+                        id="content_type"
+                        input_type=InputType::Text
+                        value=self.state.content_type.clone()
+                        on_change=self.link.callback(|value| Msg::ContentTypeChanged(value))
+                        valid=is_valid("__all__", &self.errors)
+                        aria_describedby="content_type_help"
+                    />
+                    <small id="content_type_help">{ "JSON and text types, like application/xml, text/html, or application/x-www-form-urlencoded, are edited below; any other type is served from an uploaded file." }</small>
+                </FormGroup>
                 <FormGroup>
                     <label for="payload">
                         { "Payload" }
                     </label>
-                    <TextArea
-                        name="payload"
-                        on_change=self.link.callback(|value| Msg::PayloadChanged(value))
-                        valid=is_valid("payload", &self.errors)
+                    { self.render_edit_payload() }
+                    { render_validation_feedback("__all__", &self.errors) }
+                    <div class="form-check">
+                        <input
+                            id="templated"
+                            type="checkbox"
+                            class="form-check-input"
+                            checked=self.state.templated
+                            onclick=self.link.callback(|_| Msg::TemplatedToggled)
+                        />
+                        <label class="form-check-label" for="templated">
+                            { "Replace placeholders in the payload, like {{path.id}}, {{query.page}}, {{header.X-Request-Id}}, {{claim.sub}}, {{body./user/id}}, {{uuid}}, or {{now}}, with values from each request" }
+                        </label>
+                    </div>
+                </FormGroup>
+                <FormGroup>
+                    <p>{ "Responses That Follow" }</p>
+                    {
+                        if self.state.responses.is_empty() {
+                            html! {}
+                        } else {
+                            html! {
+                                <>
+                                    <ol class="list-group mb-3">
+                                        { for self.state.responses.iter().enumerate().map(|(index, r)| self.render_edit_response(r, index)) }
+                                    </ol>
+                                    <label for="sequence_mode">{ "After the Last Response" }</label>
+                                    <select
+                                        id="sequence_mode"
+                                        class="form-control mb-3"
+                                        onchange=self.link.callback(Msg::SequenceModeChanged)
+                                    >
+                                        <option selected={self.state.sequence_mode == shared::SequenceMode::StickOnLast}>{ "Keep serving the last response" }</option>
+                                        <option selected={self.state.sequence_mode == shared::SequenceMode::Cycle}>{ "Start over from the first response" }</option>
+                                        <option selected={self.state.sequence_mode == shared::SequenceMode::FallThrough}>{ "Stop matching, so other recipes are tried" }</option>
+                                    </select>
+                                </>
+                            }
+                        }
+                    }
+                    <button
+                        type="button"
+                        class="btn btn-secondary"
+                        onclick=self.link.callback(|_| Msg::AddResponse)
                     >
-                        { self.state.payload.clone() }
-                    </TextArea>
-                    { render_validation_feedback("payload", &self.errors) }
+                        { "Add New Response" }
+                    </button>
+                    <small id="responses_help" class="d-block">{ "The response above is served first; each call that matches the recipe after it is served the next response, in order, with the same headers." }</small>
                 </FormGroup>
+                { self.render_edit_delay() }
+                { self.render_edit_fault() }
             </CardBody>
         }
     }
 
+    fn render_edit_delay(&self) -> Html {
+        use shared::Delay::*;
+        let settings: Vec<(&str, String)> = match self.state.delay {
+            None => Vec::new(),
+            Some(Fixed { millis }) => vec![("Milliseconds", millis.to_string())],
+            Some(Uniform {
+                min_millis,
+                max_millis,
+            }) => vec![
+                ("Fewest Milliseconds", min_millis.to_string()),
+                ("Most Milliseconds", max_millis.to_string()),
+            ],
+            Some(Normal {
+                mean_millis,
+                std_dev_millis,
+            }) => vec![
+                ("Mean Milliseconds", mean_millis.to_string()),
+                (
+                    "Standard Deviation in Milliseconds",
+                    std_dev_millis.to_string(),
+                ),
+            ],
+            Some(LogNormal {
+                median_millis,
+                sigma,
+            }) => vec![
+                ("Median Milliseconds", median_millis.to_string()),
+                ("Sigma", sigma.to_string()),
+            ],
+        };
+        html! {
+            <FormGroup>
+                <label for="delay">{ "Delay" }</label>
+                <select
+                    id="delay"
+                    class="form-control mb-3"
+                    onchange=self.link.callback(Msg::DelayTypeChanged)
+                >
+                    <option selected={self.state.delay.is_none()}>{ "None" }</option>
+                    <option selected={matches!(self.state.delay, Some(Fixed { .. }))}>{ "Fixed" }</option>
+                    <option selected={matches!(self.state.delay, Some(Uniform { .. }))}>{ "Uniform range" }</option>
+                    <option selected={matches!(self.state.delay, Some(Normal { .. }))}>{ "Normal distribution" }</option>
+                    <option selected={matches!(self.state.delay, Some(LogNormal { .. }))}>{ "Log-normal distribution" }</option>
+                </select>
+                <div class="form-row">
+                    { for settings.into_iter().enumerate().map(|(index, (label, value))| html! {
+                        <div class="col">
+                            <label for=format!("delay_{}", index)>{ label }</label>
+                            <Input
+                                id=format!("delay_{}", index)
+                                input_type=InputType::Text
+                                value=value
+                                on_change=self.link.callback(move |value| Msg::DelaySettingChanged(index, value))
+                            />
+                        </div>
+                    }) }
+                </div>
+                <small id="delay_help">{ "Each matching call waits this long before the response is sent; a log-normal delay is usually short with an occasional long wait." }</small>
+            </FormGroup>
+        }
+    }
+
+    fn render_edit_fault(&self) -> Html {
+        use shared::Fault::*;
+        html! {
+            <FormGroup>
+                <label for="fault">{ "Fault" }</label>
+                <select
+                    id="fault"
+                    class="form-control mb-3"
+                    onchange=self.link.callback(Msg::FaultTypeChanged)
+                >
+                    <option selected={self.state.fault.is_none()}>{ "None" }</option>
+                    <option selected={self.state.fault == Some(ConnectionReset)}>{ "Drop the connection" }</option>
+                    <option selected={self.state.fault == Some(PartialThenReset)}>{ "Send part of the body, then drop the connection" }</option>
+                    <option selected={matches!(self.state.fault, Some(Trickle { .. }))}>{ "Trickle the body out in chunks" }</option>
+                    <option selected={self.state.fault == Some(EmptyReply)}>{ "Send the status and headers with no body" }</option>
+                </select>
+                {
+                    if let Some(Trickle { chunk_bytes, interval_millis }) = self.state.fault {
+                        html! {
+                            <div class="form-row">
+                                <div class="col">
+                                    <label for="chunk_bytes">{ "Bytes per Chunk" }</label>
+                                    <Input
+                                        id="chunk_bytes"
+                                        input_type=InputType::Text
+                                        value=chunk_bytes.to_string()
+                                        on_change=self.link.callback(|value| Msg::TrickleChunkBytesChanged(value))
+                                    />
+                                </div>
+                                <div class="col">
+                                    <label for="interval_millis">{ "Milliseconds between Chunks" }</label>
+                                    <Input
+                                        id="interval_millis"
+                                        input_type=InputType::Text
+                                        value=interval_millis.to_string()
+                                        on_change=self.link.callback(|value| Msg::TrickleIntervalChanged(value))
+                                    />
+                                </div>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                <small id="fault_help">{ "Breaks the response on purpose, after any delay, to test how clients cope." }</small>
+            </FormGroup>
+        }
+    }
+
+    fn render_edit_payload(&self) -> Html {
+        if PayloadKind::of(&self.state.content_type) == PayloadKind::Binary {
+            html! {
+                <>
+                    <input
+                        id="payload"
+                        type="file"
+                        class="form-control-file"
+                        onchange=self.link.callback(Msg::PayloadFileChosen)
+                    />
+                    <small>{ render_binary_size(&self.state.payload) }</small>
+                </>
+            }
+        } else {
+            html! {
+                <TextArea
+                    name="payload"
+                    on_change=self.link.callback(|value| Msg::PayloadChanged(value))
+                    valid=is_valid("__all__", &self.errors)
+                >
+                    { self.state.payload.clone() }
+                </TextArea>
+            }
+        }
+    }
+
     fn render_view_body(&self) -> Html {
         html! {
             <>
                 <CardHeader>
                     { self.state.url.clone() }
+                    {
+                        if self.state.draft {
+                            html! { <span class="badge badge-warning ml-3">{ "Draft" }</span> }
+                        } else {
+                            html! {}
+                        }
+                    }
                 </CardHeader>
                 <CardBody>
                     <CardText>{ "Rules" }</CardText>
@@ -172,16 +491,215 @@ impl Editor {
                         { for self.state.rules.iter().map(render_view_rule) }
                     </ol>
                     <CardText>
-                        { "Payload" }
+                        { format!("Status Code, {}", self.state.status) }
                     </CardText>
-                    <TextArea readonly=true>
-                        { self.state.payload.clone() }
-                    </TextArea>
+                    <CardText>
+                        { format!("Priority, {}", self.state.priority) }
+                    </CardText>
+                    { render_view_scenario(&self.state) }
+                    {
+                        if self.state.headers.is_empty() {
+                            html! {}
+                        } else {
+                            html! {
+                                <>
+                                    <CardText>{ "Response Headers" }</CardText>
+                                    <ol class="list-group mb-3">
+                                        { for self.state.headers.iter().map(render_view_header) }
+                                    </ol>
+                                </>
+                            }
+                        }
+                    }
+                    <CardText>
+                        { format!("Content Type, {}", self.state.content_type) }
+                    </CardText>
+                    <CardText>
+                        { if self.state.templated { "Payload, Templated" } else { "Payload" } }
+                    </CardText>
+                    {
+                        if PayloadKind::of(&self.state.content_type) == PayloadKind::Binary {
+                            html! { <CardText>{ render_binary_size(&self.state.payload) }</CardText> }
+                        } else {
+                            html! {
+                                <TextArea readonly=true>
+                                    { self.state.payload.clone() }
+                                </TextArea>
+                            }
+                        }
+                    }
+                    { render_view_responses(&self.state) }
+                    { render_view_disruption(&self.state) }
+                    { self.render_candidates() }
                 </CardBody>
             </>
         }
     }
 
+    fn render_candidates(&self) -> Html {
+        if self.candidates.len() < 2 {
+            return html! {};
+        }
+        html! {
+            <>
+                <CardText>{ "Recipes for this endpoint, in the order they are tried; drag to reorder" }</CardText>
+                <ol class="list-group mb-3">
+                    { for self.candidates.iter().enumerate().map(|(index, c)| self.render_candidate(c, index)) }
+                </ol>
+            </>
+        }
+    }
+
+    fn render_candidate(&self, candidate: &Recipe, index: usize) -> Html {
+        let current = candidate.id == self.state.id;
+        html! {
+            <li
+                class=if current { "list-group-item active" } else { "list-group-item" }
+                draggable="true"
+                ondragstart=self.link.callback(move |_| Msg::CandidateDragged(index))
+                ondragover=Callback::from(|e: DragEvent| e.prevent_default())
+                ondrop=self.link.callback(move |e: DragEvent| {
+                    e.prevent_default();
+                    Msg::CandidateDropped(index)
+                })
+            >
+                { format!("Priority {}, {} rules, status {}", candidate.priority, candidate.rules.len(), candidate.status) }
+                {
+                    if candidate.draft {
+                        html! { <span class="badge badge-warning ml-3">{ "Draft" }</span> }
+                    } else {
+                        html! {}
+                    }
+                }
+            </li>
+        }
+    }
+
+    fn render_edit_header(&self, h: &shared::Header, index: usize) -> Html {
+        html! {
+            <li class="list-group-item">
+                <div class="form-row">
+                    <div class="col">
+                        <label for=format!("header_name_{}", index)>{ "Name" }</label>
+                        <Input
+                            id=format!("header_name_{}", index)
+                            input_type=InputType::Text
+                            value=h.name.clone()
+                            on_change=self.link.callback(move |value| Msg::HeaderNameChanged(index, value))
+                        />
+                    </div>
+                    <div class="col">
+                        <label for=format!("header_value_{}", index)>{ "Value" }</label>
+                        <Input
+                            id=format!("header_value_{}", index)
+                            input_type=InputType::Text
+                            value=h.value.clone()
+                            on_change=self.link.callback(move |value| Msg::HeaderValueChanged(index, value))
+                        />
+                    </div>
+                    <div class="col-auto align-self-end">
+                        <button
+                            type="button"
+                            class="btn btn-secondary"
+                            onclick=self.link.callback(move |_| Msg::RemoveHeader(index))
+                        >
+                            { "Remove" }
+                        </button>
+                    </div>
+                </div>
+            </li>
+        }
+    }
+
+    fn render_edit_response(&self, r: &RecipeResponse, index: usize) -> Html {
+        let errors = self
+            .errors
+            .as_ref()
+            .and_then(|errors| errors.errors().get("responses"))
+            .and_then(|errors| {
+                if let ValidationErrorsKind::List(errors) = errors {
+                    errors.get(&index).map(|errors| errors.as_ref().clone())
+                } else {
+                    None
+                }
+            });
+        html! {
+            <li class="list-group-item">
+                <div class="form-row">
+                    <div class="col">
+                        <label for=format!("response_status_{}", index)>{ "Status Code" }</label>
+                        <Input
+                            id=format!("response_status_{}", index)
+                            input_type=InputType::Text
+                            value=r.status.to_string()
+                            on_change=self.link.callback(move |value| Msg::ResponseStatusChanged(index, value))
+                            valid=is_valid("status", &errors)
+                        />
+                        { render_validation_feedback("status", &errors) }
+                    </div>
+                    <div class="col">
+                        <label for=format!("response_content_type_{}", index)>{ "Content Type" }</label>
+                        <Input
+                            id=format!("response_content_type_{}", index)
+                            input_type=InputType::Text
+                            value=r.content_type.clone()
+                            on_change=self.link.callback(move |value| Msg::ResponseContentTypeChanged(index, value))
+                            valid=is_valid("__all__", &errors)
+                        />
+                    </div>
+                    <div class="col-auto align-self-end">
+                        <button
+                            type="button"
+                            class="btn btn-secondary"
+                            onclick=self.link.callback(move |_| Msg::RemoveResponse(index))
+                        >
+                            { "Remove" }
+                        </button>
+                    </div>
+                </div>
+                <label for=format!("response_payload_{}", index)>{ "Payload" }</label>
+                {
+                    if PayloadKind::of(&r.content_type) == PayloadKind::Binary {
+                        html! {
+                            <>
+                                <input
+                                    id=format!("response_payload_{}", index)
+                                    type="file"
+                                    class="form-control-file"
+                                    onchange=self.link.callback(move |data| Msg::ResponseFileChosen(index, data))
+                                />
+                                <small>{ render_binary_size(&r.payload) }</small>
+                            </>
+                        }
+                    } else {
+                        html! {
+                            <TextArea
+                                name=format!("response_payload_{}", index)
+                                on_change=self.link.callback(move |value| Msg::ResponsePayloadChanged(index, value))
+                                valid=is_valid("__all__", &errors)
+                            >
+                                { r.payload.clone() }
+                            </TextArea>
+                        }
+                    }
+                }
+                { render_validation_feedback("__all__", &errors) }
+                <div class="form-check">
+                    <input
+                        id=format!("response_templated_{}", index)
+                        type="checkbox"
+                        class="form-check-input"
+                        checked=r.templated
+                        onclick=self.link.callback(move |_| Msg::ResponseTemplatedToggled(index))
+                    />
+                    <label class="form-check-label" for=format!("response_templated_{}", index)>
+                        { "Replace placeholders in the payload with values from each request" }
+                    </label>
+                </div>
+            </li>
+        }
+    }
+
     fn render_edit_rule(&self, r: &Rule, index: usize) -> Html {
         let errors = self
             .errors
@@ -197,7 +715,7 @@ impl Editor {
         html! {
             <RuleEditor
                 rule=r.to_owned()
-                key_path_is_file=shared::KeyPathKind::File == self.config.key_path_kind
+                key_path_kind=self.config.key_path_kind
                 on_change=self.link.callback(move |rule| Msg::RuleChanged(rule, index))
                 on_error=self.link.callback(Msg::Failure)
                 on_remove=self.link.callback(move |_| Msg::RemoveRule(index))
@@ -217,6 +735,16 @@ fn render_view_rule(r: &Rule) -> Html {
                     <>
                         { "Authenticated by verifying JWT with public key, " }
                         { r.key_path.clone().unwrap_or_default() }
+                        {
+                            if r.algorithms.is_empty() {
+                                String::new()
+                            } else {
+                                format!(
+                                    ", signed with one of {}",
+                                    r.algorithms.iter().map(|algorithm| algorithm.name()).collect::<Vec<&str>>().join(", ")
+                                )
+                            }
+                        }
                     </>
                 },
                 Some(Subject) => html! {
@@ -231,6 +759,79 @@ fn render_view_rule(r: &Rule) -> Html {
                         { r.http_method.clone().unwrap_or_default() }
                     </>
                 },
+                Some(Header) => html! {
+                    <>
+                        { format!("Header, {}, ", r.header_name.clone().unwrap_or_default()) }
+                        { r.match_mode.as_ref().map(ToString::to_string).unwrap_or_default() }
+                        { format!(" {}", r.match_value.clone().unwrap_or_default()) }
+                    </>
+                },
+                Some(PathParam) => html! {
+                    <>
+                        { format!("Path parameter, {}, ", r.param_name.clone().unwrap_or_default()) }
+                        { r.match_mode.as_ref().map(ToString::to_string).unwrap_or_default() }
+                        { format!(" {}", r.match_value.clone().unwrap_or_default()) }
+                    </>
+                },
+                Some(QueryParam) => html! {
+                    <>
+                        { format!("Query parameter, {}, ", r.param_name.clone().unwrap_or_default()) }
+                        { r.match_mode.as_ref().map(ToString::to_string).unwrap_or_default() }
+                        { format!(" {}", r.match_value.clone().unwrap_or_default()) }
+                    </>
+                },
+                Some(Body) => html! {
+                    <>
+                        {
+                            match (r.body_path.as_ref(), r.form_field.as_ref()) {
+                                (Some(body_path), _) => format!("Value at {} of the body of incoming request ", body_path),
+                                (_, Some(form_field)) => format!("Form field, {}, of incoming request ", form_field),
+                                _ => "Body of incoming request ".to_owned(),
+                            }
+                        }
+                        { r.match_mode.as_ref().map(ToString::to_string).unwrap_or_default() }
+                        { format!(" {}", r.match_value.clone().unwrap_or_default()) }
+                    </>
+                },
+                Some(NthCall) => html! {
+                    { format!("Only call number {} with the same method and path", r.nth.unwrap_or_default()) }
+                },
+                Some(Claim) => html! {
+                    <>
+                        { format!("Claim, {}, of the authentication JWT ", r.claim.clone().unwrap_or_default()) }
+                        { r.match_mode.as_ref().map(ToString::to_string).unwrap_or_default() }
+                        { format!(" {}", r.match_value.clone().unwrap_or_default()) }
+                    </>
+                },
+                Some(Unexpired) => html! {
+                    { "Authentication JWT has not expired and is past its not before time" }
+                },
+                Some(BasicAuth) => html! {
+                    {
+                        match r.password {
+                            Some(_) => format!("Basic credentials of user, {}, with the password", r.username.clone().unwrap_or_default()),
+                            None => format!("Basic credentials of user, {}, with any password", r.username.clone().unwrap_or_default()),
+                        }
+                    }
+                },
+                Some(ApiKey) => html! {
+                    {
+                        match r.key_location {
+                            Some(shared::ApiKeyLocation::Query) => format!("API key in query parameter, {}, matches the key", r.key_name.clone().unwrap_or_default()),
+                            _ => format!("API key in header, {}, matches the key", r.key_name.clone().unwrap_or_default()),
+                        }
+                    }
+                },
+                Some(ClientCert) => html! {
+                    {
+                        match (r.subject.as_ref(), r.fingerprint.as_ref()) {
+                            (Some(subject), Some(fingerprint)) => format!("Client certificate of subject, {}, with fingerprint {}", subject, fingerprint),
+                            (Some(subject), None) => format!("Client certificate of subject, {}", subject),
+                            (None, Some(fingerprint)) => format!("Client certificate with fingerprint {}", fingerprint),
+                            (None, None) => "Client certificate".to_owned(),
+                        }
+                    }
+                },
                 _ => html! {}
             }
         }
@@ -238,6 +839,119 @@ fn render_view_rule(r: &Rule) -> Html {
     }
 }
 
+fn render_binary_size(payload: &str) -> String {
+    match base64::decode(payload) {
+        Ok(bytes) if !bytes.is_empty() => format!("A file of {} bytes", bytes.len()),
+        _ => "No file has been uploaded".to_owned(),
+    }
+}
+
+fn render_view_header(h: &shared::Header) -> Html {
+    html! {
+        <li class="list-group-item">
+            { format!("{}: {}", h.name, h.value) }
+        </li>
+    }
+}
+
+fn render_view_responses(recipe: &Recipe) -> Html {
+    if recipe.responses.is_empty() {
+        return html! {};
+    }
+    let after = match recipe.sequence_mode {
+        shared::SequenceMode::StickOnLast => "keeps serving the last one",
+        shared::SequenceMode::Cycle => "starts over from the first",
+        shared::SequenceMode::FallThrough => "stops matching",
+    };
+    html! {
+        <>
+            <CardText>{ format!("Responses That Follow, then {}", after) }</CardText>
+            <ol class="list-group mb-3">
+                { for recipe.responses.iter().map(render_view_response) }
+            </ol>
+        </>
+    }
+}
+
+fn render_view_response(r: &RecipeResponse) -> Html {
+    html! {
+        <li class="list-group-item">
+            <CardText>
+                { format!("Status Code, {}, Content Type, {}{}", r.status, r.content_type, if r.templated { ", Templated" } else { "" }) }
+            </CardText>
+            {
+                if PayloadKind::of(&r.content_type) == PayloadKind::Binary {
+                    html! { <CardText>{ render_binary_size(&r.payload) }</CardText> }
+                } else {
+                    html! {
+                        <TextArea readonly=true>
+                            { r.payload.clone() }
+                        </TextArea>
+                    }
+                }
+            }
+        </li>
+    }
+}
+
+fn render_view_scenario(recipe: &Recipe) -> Html {
+    if recipe.scenario.is_empty() {
+        return html! {};
+    }
+    let mut description = format!("Scenario, {}", recipe.scenario);
+    if !recipe.required_state.is_empty() {
+        description.push_str(&format!(", requires the state, {}", recipe.required_state));
+    }
+    if !recipe.new_state.is_empty() {
+        description.push_str(&format!(", moves to the state, {}", recipe.new_state));
+    }
+    html! {
+        <CardText>{ description }</CardText>
+    }
+}
+
+fn render_view_disruption(recipe: &Recipe) -> Html {
+    use shared::{Delay::*, Fault::*};
+    let delay = recipe.delay.as_ref().map(|delay| match delay {
+        Fixed { millis } => format!("Delay, {}ms", millis),
+        Uniform {
+            min_millis,
+            max_millis,
+        } => format!("Delay, {}ms to {}ms", min_millis, max_millis),
+        Normal {
+            mean_millis,
+            std_dev_millis,
+        } => format!(
+            "Delay, normal with mean {}ms and standard deviation {}ms",
+            mean_millis, std_dev_millis
+        ),
+        LogNormal {
+            median_millis,
+            sigma,
+        } => format!(
+            "Delay, log-normal with median {}ms and sigma {}",
+            median_millis, sigma
+        ),
+    });
+    let fault = recipe.fault.as_ref().map(|fault| match fault {
+        ConnectionReset => "Fault, drops the connection".to_owned(),
+        PartialThenReset => "Fault, sends part of the body, then drops the connection".to_owned(),
+        Trickle {
+            chunk_bytes,
+            interval_millis,
+        } => format!(
+            "Fault, trickles the body out {} bytes every {}ms",
+            chunk_bytes, interval_millis
+        ),
+        EmptyReply => "Fault, sends no body".to_owned(),
+    });
+    html! {
+        <>
+            { for delay.into_iter().chain(fault).map(|description| html! { <CardText>{ description }</CardText> }) }
+        </>
+    }
+}
+
 fn render_validation_feedback(field: &'static str, errors: &Option<ValidationErrors>) -> Html {
     if let Some(ref errors) = errors {
         let errors = errors.field_errors();
@@ -1,5 +1,6 @@
 use super::{Home, Msg};
-use anyhow::Result;
+use crate::prelude::{namespace, select_namespace};
+use anyhow::{bail, Result};
 use log::{debug, error};
 use uuid::Uuid;
 use yew::{
@@ -9,6 +10,7 @@ use yew::{
         fetch::{Request, Response, StatusCode},
         FetchService,
     },
+    web_sys::HtmlSelectElement,
 };
 
 impl Home {
@@ -16,10 +18,12 @@ impl Home {
         debug!("Recipe {:?}", self.state);
         let request = if let Some(offset) = self.props.offset {
             Request::get(format!("/ajax/recipe/offset/{}", offset))
+                .header(shared::NAMESPACE_HEADER, namespace())
                 .body(Nothing)
                 .map_err(anyhow::Error::from)?
         } else {
             Request::get("/ajax/recipe/")
+                .header(shared::NAMESPACE_HEADER, namespace())
                 .body(Nothing)
                 .map_err(anyhow::Error::from)?
         };
@@ -50,6 +54,7 @@ impl Home {
 
     pub(super) fn handle_delete(&mut self, id: Uuid) -> Result<ShouldRender> {
         let request = Request::delete(format!("/ajax/recipe/{}", id))
+            .header(shared::NAMESPACE_HEADER, namespace())
             .body(Nothing)
             .map_err(anyhow::Error::from)?;
         let task = FetchService::fetch(
@@ -71,9 +76,122 @@ impl Home {
         Ok(false)
     }
 
+    pub(super) fn handle_fetch_scenarios(&mut self) -> Result<ShouldRender> {
+        let request = Request::get("/ajax/scenario/")
+            .header(shared::NAMESPACE_HEADER, namespace())
+            .body(Nothing)
+            .map_err(anyhow::Error::from)?;
+        let task = FetchService::fetch(
+            request,
+            self.link.callback(
+                move |response: Response<Text>| match response.into_parts() {
+                    (meta, Ok(body)) if meta.status >= StatusCode::BAD_REQUEST => {
+                        Msg::Failure(body)
+                    }
+                    (_, Ok(body)) => Msg::FetchedScenarios(body),
+                    (_, Err(error)) => {
+                        error!("{}", error);
+                        Msg::Failure(format!("{}", error))
+                    }
+                },
+            ),
+        )?;
+        self.scenarios_tsk = Some(task);
+        Ok(false)
+    }
+
+    pub(super) fn handle_fetched_scenarios(&mut self, body: String) -> Result<ShouldRender> {
+        self.scenarios = serde_json::from_str(&body)?;
+        self.scenarios_tsk = None;
+        Ok(true)
+    }
+
+    pub(super) fn handle_reset_scenario(&mut self, name: String) -> Result<ShouldRender> {
+        let request = Request::delete(format!("/ajax/scenario/{}", name))
+            .header(shared::NAMESPACE_HEADER, namespace())
+            .body(Nothing)
+            .map_err(anyhow::Error::from)?;
+        let task = FetchService::fetch(
+            request,
+            self.link.callback(
+                move |response: Response<Text>| match response.into_parts() {
+                    (meta, Ok(body)) if meta.status >= StatusCode::BAD_REQUEST => {
+                        Msg::Failure(body)
+                    }
+                    (_, Ok(_)) => Msg::FetchScenarios,
+                    (_, Err(error)) => {
+                        error!("{}", error);
+                        Msg::Failure(format!("{}", error))
+                    }
+                },
+            ),
+        )?;
+        self.scenarios_tsk = Some(task);
+        Ok(false)
+    }
+
+    pub(super) fn handle_fetch_namespaces(&mut self) -> Result<ShouldRender> {
+        let request = Request::get("/ajax/namespace/")
+            .body(Nothing)
+            .map_err(anyhow::Error::from)?;
+        let task = FetchService::fetch(
+            request,
+            self.link.callback(
+                move |response: Response<Text>| match response.into_parts() {
+                    (meta, Ok(body)) if meta.status >= StatusCode::BAD_REQUEST => {
+                        Msg::Failure(body)
+                    }
+                    (_, Ok(body)) => Msg::FetchedNamespaces(body),
+                    (_, Err(error)) => {
+                        error!("{}", error);
+                        Msg::Failure(format!("{}", error))
+                    }
+                },
+            ),
+        )?;
+        self.namespaces_tsk = Some(task);
+        Ok(false)
+    }
+
+    pub(super) fn handle_fetched_namespaces(&mut self, body: String) -> Result<ShouldRender> {
+        self.namespaces = serde_json::from_str(&body)?;
+        self.namespaces_tsk = None;
+        Ok(true)
+    }
+
+    pub(super) fn handle_namespace_selected(
+        &mut self,
+        selected: HtmlSelectElement,
+    ) -> Result<ShouldRender> {
+        self.handle_namespace_change(selected.value())
+    }
+
+    pub(super) fn handle_namespace_change(&mut self, name: String) -> Result<ShouldRender> {
+        let name = name.trim();
+        if name.is_empty() || name == self.namespace {
+            return Ok(false);
+        }
+        if !shared::is_url_safe(name) {
+            bail!(
+                "The namespace, {}, may only have letters, digits, '-', '_' and '.'!",
+                name
+            );
+        }
+        select_namespace(name)?;
+        self.namespace = name.to_owned();
+        self.state = Default::default();
+        self.scenarios.clear();
+        self.link.send_message(Msg::Fetch);
+        self.link.send_message(Msg::FetchScenarios);
+        self.link.send_message(Msg::FetchNamespaces);
+        Ok(true)
+    }
+
     pub(super) fn handle_deleted(&mut self) -> Result<ShouldRender> {
         self.fetch_tsk = None;
         self.link.send_message(Msg::Fetch);
+        // deleting a recipe may leave a scenario with no recipes
+        self.link.send_message(Msg::FetchScenarios);
         Ok(true)
     }
 }
@@ -4,10 +4,13 @@ mod types;
 use self::types::RecipesPage;
 use crate::{
     components::{alert::Context, Alert},
+    prelude::namespace,
     AppRoute,
 };
-use bootstrap_rs::{prelude::*, Button, Card, CardBody, Container, Jumbotron};
-use shared::Recipe;
+use bootstrap_rs::{
+    input::InputType, prelude::*, Button, Card, CardBody, Container, Input, Jumbotron,
+};
+use shared::{Namespace, Recipe, Scenario};
 use uuid::Uuid;
 use yew::{prelude::*, services::fetch::FetchTask};
 use yew_router::prelude::*;
@@ -15,7 +18,12 @@ use yew_router::prelude::*;
 pub(crate) struct Home {
     link: ComponentLink<Self>,
     fetch_tsk: Option<FetchTask>,
+    scenarios_tsk: Option<FetchTask>,
+    namespaces_tsk: Option<FetchTask>,
     state: RecipesPage,
+    scenarios: Vec<Scenario>,
+    namespace: String,
+    namespaces: Vec<Namespace>,
     alert_ctx: Context,
     props: Props,
 }
@@ -25,6 +33,13 @@ pub(crate) enum Msg {
     Fetched(String),
     Delete(Uuid),
     Deleted,
+    FetchScenarios,
+    FetchedScenarios(String),
+    ResetScenario(String),
+    FetchNamespaces,
+    FetchedNamespaces(String),
+    NamespaceSelected(ChangeData),
+    NamespaceEntered(String),
     Failure(String),
     ClearAlert,
 }
@@ -41,13 +56,25 @@ impl Component for Home {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         link.send_message(Self::Message::Fetch);
+        link.send_message(Self::Message::FetchScenarios);
+        link.send_message(Self::Message::FetchNamespaces);
         let fetch_tsk = None;
+        let scenarios_tsk = None;
+        let namespaces_tsk = None;
         let state = RecipesPage::default();
+        let scenarios = Vec::new();
+        let namespace = namespace();
+        let namespaces = Vec::new();
         let alert_ctx = Context::default();
         Self {
             link,
             fetch_tsk,
+            scenarios_tsk,
+            namespaces_tsk,
             state,
+            scenarios,
+            namespace,
+            namespaces,
             alert_ctx,
             props,
         }
@@ -60,6 +87,16 @@ impl Component for Home {
             Fetched(body) => self.handle_fetched(body),
             Delete(id) => self.handle_delete(id),
             Deleted => self.handle_deleted(),
+            FetchScenarios => self.handle_fetch_scenarios(),
+            FetchedScenarios(body) => self.handle_fetched_scenarios(body),
+            ResetScenario(name) => self.handle_reset_scenario(name),
+            FetchNamespaces => self.handle_fetch_namespaces(),
+            FetchedNamespaces(body) => self.handle_fetched_namespaces(body),
+            NamespaceSelected(ChangeData::Select(selected)) => {
+                self.handle_namespace_selected(selected)
+            }
+            NamespaceSelected(_) => Ok(false),
+            NamespaceEntered(name) => self.handle_namespace_change(name),
             Failure(error) => {
                 self.alert_ctx = Context::Danger(error);
                 Ok(true)
@@ -90,7 +127,7 @@ impl Component for Home {
     fn view(&self) -> Html {
         let recipes = &self.state.recipes;
         let view_recipe = move |r: &Recipe| {
-            let delete = if let Some(id) = r.id {
+            let delete = if let (Some(id), None) = (r.id, &r.file) {
                 html! {
                     <Button
                         margin=Margin(Edge::Right, 3)
@@ -117,6 +154,8 @@ impl Component for Home {
                     <RouterAnchor<AppRoute> route=AppRoute::View(r.id.clone().unwrap().to_string())>
                         { r.url.clone() }
                     </RouterAnchor<AppRoute>>
+                    { render_draft_badge(r.draft) }
+                    { render_file_badge(&r.file) }
                 </li>
             }
         };
@@ -136,6 +175,7 @@ impl Component for Home {
                         { self.view_pagination("mt-3") }
                     </CardBody>
                 </Card>
+                { self.view_scenarios() }
             </Container>
         }
     }
@@ -145,11 +185,91 @@ impl Home {
     fn view_toolbar(&self) -> Html {
         html! {
             <div class="btn-toolbar mb-3">
-                <div class="btn-group">
+                <div class="btn-group mr-3">
                     <RouterButton<AppRoute> classes="btn btn-primary" route=AppRoute::Add>
                         { "Add Recipe" }
                     </RouterButton<AppRoute>>
                 </div>
+                { self.view_namespaces() }
+            </div>
+        }
+    }
+
+    /// Switches between the namespaces that own recipes, or to a new one by name; recipes added
+    /// after switching belong to the selected namespace.
+    fn view_namespaces(&self) -> Html {
+        let view_namespace = |n: &Namespace| {
+            html! {
+                <option value=n.name.clone() selected={n.name == self.namespace}>
+                    { format!("{} ({})", n.name, n.recipes) }
+                </option>
+            }
+        };
+        let known = self
+            .namespaces
+            .iter()
+            .any(|namespace| namespace.name == self.namespace);
+        html! {
+            <div class="form-inline">
+                <label class="mr-2" for="namespace">{ "Namespace" }</label>
+                <select
+                    id="namespace"
+                    class="form-control mr-2"
+                    onchange=self.link.callback(Msg::NamespaceSelected)
+                >
+                    { for self.namespaces.iter().map(view_namespace) }
+                    {
+                        if known {
+                            html! {}
+                        } else {
+                            html! {
+                                <option value=self.namespace.clone() selected=true>
+                                    { format!("{} (0)", self.namespace) }
+                                </option>
+                            }
+                        }
+                    }
+                </select>
+                <label class="mr-2" for="new_namespace">{ "or new" }</label>
+                <Input
+                    id="new_namespace"
+                    input_type=InputType::Text
+                    on_change=self.link.callback(|value| Msg::NamespaceEntered(value))
+                />
+            </div>
+        }
+    }
+
+    fn view_scenarios(&self) -> Html {
+        if self.scenarios.is_empty() {
+            return html! {};
+        }
+        let view_scenario = |s: &Scenario| {
+            let name = s.name.clone();
+            html! {
+                <li class="list-group-item">
+                    <Button
+                        margin=Margin(Edge::Right, 3)
+                        color=Color::Secondary
+                        on_click=self.link.callback(move |_| Msg::ResetScenario(name.clone()))
+                    >
+                        { "Reset" }
+                    </Button>
+                    { s.name.clone() }
+                    { for s.states.iter().map(|state| render_state_badge(state, state == &s.state)) }
+                </li>
+            }
+        };
+        html! {
+            <div class="mt-3">
+                <Card border=Border(Edge::All, Color::Primary)>
+                    <CardBody>
+                        <h5>{ "Scenarios" }</h5>
+                        <ul class="list-group">
+                            { for self.scenarios.iter().map(view_scenario) }
+                        </ul>
+                    </CardBody>
+                </Card>
             </div>
         }
     }
@@ -220,6 +340,37 @@ fn show_next_button(offset: &Option<i64>, limit: i64, total: i64) -> Option<i64>
     }
 }
 
+fn render_draft_badge(draft: bool) -> Html {
+    if draft {
+        html! { <span class="badge badge-warning ml-3">{ "Draft" }</span> }
+    } else {
+        html! {}
+    }
+}
+
+/// Marks a recipe loaded from a file, which can only be changed by editing that file.
+fn render_file_badge(file: &Option<String>) -> Html {
+    if let Some(file) = file {
+        html! {
+            <span class="badge badge-info ml-3" title="Read-only, change it by editing the file">
+                { format!("Loaded from {}", file) }
+            </span>
+        }
+    } else {
+        html! {}
+    }
+}
+
+/// Marks the current state of a scenario among the rest of its states.
+fn render_state_badge(state: &str, current: bool) -> Html {
+    let class = if current {
+        "badge badge-primary ml-3"
+    } else {
+        "badge badge-light ml-3"
+    };
+    html! { <span class=class>{ state }</span> }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,5 +1,11 @@
-use anyhow::Result;
-use yew::prelude::*;
+use anyhow::{format_err, Result};
+use yew::{
+    prelude::*,
+    services::storage::{Area, StorageService},
+};
+
+/// Where the namespace selected in the UI is kept, so it lasts from one page to the next.
+const NAMESPACE_KEY: &str = "empholite.namespace";
 
 pub(crate) struct InputString(pub(crate) String);
 
@@ -37,6 +43,21 @@ where
     }
 }
 
+/// The namespace that every call to the server works in, the default until another is selected.
+pub(crate) fn namespace() -> String {
+    StorageService::new(Area::Local)
+        .ok()
+        .and_then(|storage| storage.restore::<Result<String>>(NAMESPACE_KEY).ok())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| shared::DEFAULT_NAMESPACE.to_owned())
+}
+
+pub(crate) fn select_namespace(name: &str) -> Result<()> {
+    let mut storage = StorageService::new(Area::Local).map_err(|error| format_err!("{}", error))?;
+    storage.store(NAMESPACE_KEY, Ok(name.to_owned()));
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
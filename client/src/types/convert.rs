@@ -1,4 +1,4 @@
-use super::{HttpVerb, Recipe, Rule, RuleType};
+use super::{HttpVerb, MatchMode, Recipe, RecipeResponse, Rule, RuleType};
 use anyhow::{format_err, Error, Result};
 use std::convert::TryInto;
 
@@ -7,18 +7,50 @@ impl From<shared::Recipe> for Recipe {
         let shared::Recipe {
             id,
             url,
+            status,
+            headers,
+            content_type,
             payload,
+            templated,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            responses,
+            sequence_mode,
+            delay,
+            fault,
+            file,
             created_at,
             updated_at,
             rules,
         } = r;
         let rules = rules.into_iter().map(Into::into).collect();
-        let payload = payload.to_string();
+        let responses = responses.into_iter().map(Into::into).collect();
+        let scenario = scenario.unwrap_or_default();
+        let required_state = required_state.unwrap_or_default();
+        let new_state = new_state.unwrap_or_default();
+        let payload = payload_to_text(&content_type, payload);
         Self {
             id,
             url,
             rules,
+            status,
+            headers,
+            content_type,
             payload,
+            templated,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            responses,
+            sequence_mode,
+            delay,
+            fault,
+            file,
             created_at,
             updated_at,
         }
@@ -32,7 +64,21 @@ impl TryInto<shared::Recipe> for Recipe {
         let Recipe {
             id,
             url,
+            status,
+            headers,
+            content_type,
             payload,
+            templated,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            responses,
+            sequence_mode,
+            delay,
+            fault,
+            file,
             created_at,
             updated_at,
             rules,
@@ -41,11 +87,43 @@ impl TryInto<shared::Recipe> for Recipe {
             .into_iter()
             .map(TryInto::try_into)
             .collect::<Result<Vec<shared::Rule>>>()?;
-        let payload: serde_json::Value = serde_json::from_str(&payload)?;
+        let responses = responses
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<shared::RecipeResponse>>>()?;
+        let non_empty = |value: String| {
+            if value.trim().is_empty() {
+                None
+            } else {
+                Some(value)
+            }
+        };
+        let scenario = non_empty(scenario);
+        // states only mean something for a named scenario
+        let (required_state, new_state) = if scenario.is_some() {
+            (non_empty(required_state), non_empty(new_state))
+        } else {
+            (None, None)
+        };
+        let payload = text_to_payload(&content_type, payload)?;
         Ok(shared::Recipe {
             id,
             url,
+            status,
+            headers,
+            content_type,
             payload,
+            templated,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            responses,
+            sequence_mode,
+            delay,
+            fault,
+            file,
             created_at,
             updated_at,
             rules,
@@ -53,14 +131,73 @@ impl TryInto<shared::Recipe> for Recipe {
     }
 }
 
+impl From<shared::RecipeResponse> for RecipeResponse {
+    fn from(r: shared::RecipeResponse) -> Self {
+        let shared::RecipeResponse {
+            status,
+            content_type,
+            payload,
+            templated,
+        } = r;
+        let payload = payload_to_text(&content_type, payload);
+        Self {
+            status,
+            content_type,
+            payload,
+            templated,
+        }
+    }
+}
+
+impl TryInto<shared::RecipeResponse> for RecipeResponse {
+    type Error = Error;
+
+    fn try_into(self) -> Result<shared::RecipeResponse> {
+        let RecipeResponse {
+            status,
+            content_type,
+            payload,
+            templated,
+        } = self;
+        let payload = text_to_payload(&content_type, payload)?;
+        Ok(shared::RecipeResponse {
+            status,
+            content_type,
+            payload,
+            templated,
+        })
+    }
+}
+
+/// Payloads are edited as text; JSON is serialized and anything else is already a string.
+fn payload_to_text(content_type: &str, payload: serde_json::Value) -> String {
+    match (shared::PayloadKind::of(content_type), payload) {
+        (shared::PayloadKind::Json, payload) => payload.to_string(),
+        (_, serde_json::Value::String(payload)) => payload,
+        (_, payload) => payload.to_string(),
+    }
+}
+
+fn text_to_payload(content_type: &str, payload: String) -> Result<serde_json::Value> {
+    Ok(match shared::PayloadKind::of(content_type) {
+        shared::PayloadKind::Json => serde_json::from_str(&payload)?,
+        _ => serde_json::Value::String(payload),
+    })
+}
+
 impl From<shared::Rule> for Rule {
     fn from(r: shared::Rule) -> Self {
         use shared::Rule::*;
 
         match r {
-            Authenticated { key_path, .. } => Rule {
+            Authenticated {
+                key_path,
+                algorithms,
+                ..
+            } => Rule {
                 rule_type: Some(RuleType::Authenticated),
                 key_path: Some(key_path),
+                algorithms,
                 ..Rule::default()
             },
             Subject { subject, .. } => Rule {
@@ -73,6 +210,107 @@ impl From<shared::Rule> for Rule {
                 http_method: Some(http_method.into()),
                 ..Rule::default()
             },
+            Header {
+                header_name,
+                match_mode,
+                match_value,
+                ..
+            } => Rule {
+                rule_type: Some(RuleType::Header),
+                header_name: Some(header_name),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Rule::default()
+            },
+            PathParam {
+                param_name,
+                match_mode,
+                match_value,
+                ..
+            } => Rule {
+                rule_type: Some(RuleType::PathParam),
+                param_name: Some(param_name),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Rule::default()
+            },
+            QueryParam {
+                param_name,
+                match_mode,
+                match_value,
+                ..
+            } => Rule {
+                rule_type: Some(RuleType::QueryParam),
+                param_name: Some(param_name),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Rule::default()
+            },
+            Body {
+                body_path,
+                form_field,
+                match_mode,
+                match_value,
+                ..
+            } => Rule {
+                rule_type: Some(RuleType::Body),
+                body_path,
+                form_field,
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Rule::default()
+            },
+            NthCall { nth, .. } => Rule {
+                rule_type: Some(RuleType::NthCall),
+                nth: Some(nth),
+                ..Rule::default()
+            },
+            Claim {
+                claim,
+                match_mode,
+                match_value,
+                ..
+            } => Rule {
+                rule_type: Some(RuleType::Claim),
+                claim: Some(claim),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Rule::default()
+            },
+            Unexpired { .. } => Rule {
+                rule_type: Some(RuleType::Unexpired),
+                ..Rule::default()
+            },
+            BasicAuth {
+                username, password, ..
+            } => Rule {
+                rule_type: Some(RuleType::BasicAuth),
+                username: Some(username),
+                password,
+                ..Rule::default()
+            },
+            ApiKey {
+                key_location,
+                key_name,
+                api_key,
+                ..
+            } => Rule {
+                rule_type: Some(RuleType::ApiKey),
+                key_location: Some(key_location),
+                key_name: Some(key_name),
+                api_key: Some(api_key),
+                ..Rule::default()
+            },
+            ClientCert {
+                subject,
+                fingerprint,
+                ..
+            } => Rule {
+                rule_type: Some(RuleType::ClientCert),
+                subject,
+                fingerprint,
+                ..Rule::default()
+            },
         }
     }
 }
@@ -85,6 +323,9 @@ impl From<shared::HttpVerb> for HttpVerb {
             Post => HttpVerb::Post,
             Put => HttpVerb::Put,
             Delete => HttpVerb::Delete,
+            Patch => HttpVerb::Patch,
+            Head => HttpVerb::Head,
+            Options => HttpVerb::Options,
         }
     }
 }
@@ -97,6 +338,39 @@ impl Into<shared::HttpVerb> for HttpVerb {
             Post => shared::HttpVerb::Post,
             Put => shared::HttpVerb::Put,
             Delete => shared::HttpVerb::Delete,
+            Patch => shared::HttpVerb::Patch,
+            Head => shared::HttpVerb::Head,
+            Options => shared::HttpVerb::Options,
+        }
+    }
+}
+
+impl From<shared::MatchMode> for MatchMode {
+    fn from(m: shared::MatchMode) -> Self {
+        use shared::MatchMode::*;
+        match m {
+            Exact => MatchMode::Exact,
+            Present => MatchMode::Present,
+            Absent => MatchMode::Absent,
+            CaseInsensitive => MatchMode::CaseInsensitive,
+            Regex => MatchMode::Regex,
+            Contains => MatchMode::Contains,
+            AnyOf => MatchMode::AnyOf,
+        }
+    }
+}
+
+impl Into<shared::MatchMode> for MatchMode {
+    fn into(self) -> shared::MatchMode {
+        use MatchMode::*;
+        match self {
+            Exact => shared::MatchMode::Exact,
+            Present => shared::MatchMode::Present,
+            Absent => shared::MatchMode::Absent,
+            CaseInsensitive => shared::MatchMode::CaseInsensitive,
+            Regex => shared::MatchMode::Regex,
+            Contains => shared::MatchMode::Contains,
+            AnyOf => shared::MatchMode::AnyOf,
         }
     }
 }
@@ -111,6 +385,21 @@ impl TryInto<shared::Rule> for Rule {
             key_path,
             subject,
             http_method,
+            header_name,
+            match_mode,
+            match_value,
+            param_name,
+            body_path,
+            form_field,
+            nth,
+            claim,
+            algorithms,
+            username,
+            password,
+            key_location,
+            key_name,
+            api_key,
+            fingerprint,
         } = self;
         if let Some(rule_type) = rule_type {
             use RuleType::*;
@@ -119,6 +408,7 @@ impl TryInto<shared::Rule> for Rule {
                     id,
                     key_path: key_path
                         .ok_or_else(|| format_err!("The field, key_path, must be Some!"))?,
+                    algorithms,
                 },
                 Subject => shared::Rule::Subject {
                     id,
@@ -131,6 +421,75 @@ impl TryInto<shared::Rule> for Rule {
                         .map(Into::into)
                         .ok_or_else(|| format_err!("The field, http_method, must be Some!"))?,
                 },
+                Header => shared::Rule::Header {
+                    id,
+                    header_name: header_name
+                        .ok_or_else(|| format_err!("The field, header_name, must be Some!"))?,
+                    match_mode: match_mode
+                        .map(Into::into)
+                        .ok_or_else(|| format_err!("The field, match_mode, must be Some!"))?,
+                    match_value,
+                },
+                PathParam => shared::Rule::PathParam {
+                    id,
+                    param_name: param_name
+                        .ok_or_else(|| format_err!("The field, param_name, must be Some!"))?,
+                    match_mode: match_mode
+                        .map(Into::into)
+                        .ok_or_else(|| format_err!("The field, match_mode, must be Some!"))?,
+                    match_value,
+                },
+                QueryParam => shared::Rule::QueryParam {
+                    id,
+                    param_name: param_name
+                        .ok_or_else(|| format_err!("The field, param_name, must be Some!"))?,
+                    match_mode: match_mode
+                        .map(Into::into)
+                        .ok_or_else(|| format_err!("The field, match_mode, must be Some!"))?,
+                    match_value,
+                },
+                Body => shared::Rule::Body {
+                    id,
+                    body_path,
+                    form_field,
+                    match_mode: match_mode
+                        .map(Into::into)
+                        .ok_or_else(|| format_err!("The field, match_mode, must be Some!"))?,
+                    match_value,
+                },
+                NthCall => shared::Rule::NthCall {
+                    id,
+                    nth: nth.ok_or_else(|| format_err!("The field, nth, must be Some!"))?,
+                },
+                Claim => shared::Rule::Claim {
+                    id,
+                    claim: claim.ok_or_else(|| format_err!("The field, claim, must be Some!"))?,
+                    match_mode: match_mode
+                        .map(Into::into)
+                        .ok_or_else(|| format_err!("The field, match_mode, must be Some!"))?,
+                    match_value,
+                },
+                Unexpired => shared::Rule::Unexpired { id },
+                BasicAuth => shared::Rule::BasicAuth {
+                    id,
+                    username: username
+                        .ok_or_else(|| format_err!("The field, username, must be Some!"))?,
+                    password,
+                },
+                ApiKey => shared::Rule::ApiKey {
+                    id,
+                    key_location: key_location
+                        .ok_or_else(|| format_err!("The field, key_location, must be Some!"))?,
+                    key_name: key_name
+                        .ok_or_else(|| format_err!("The field, key_name, must be Some!"))?,
+                    api_key: api_key
+                        .ok_or_else(|| format_err!("The field, api_key, must be Some!"))?,
+                },
+                ClientCert => shared::Rule::ClientCert {
+                    id,
+                    subject,
+                    fingerprint,
+                },
             })
         } else {
             Err(format_err!("The field, rule_type, must be Some!"))
@@ -14,6 +14,16 @@ pub(super) enum RuleType {
     Authenticated,
     Subject,
     HttpMethod,
+    Header,
+    PathParam,
+    Body,
+    QueryParam,
+    NthCall,
+    Claim,
+    Unexpired,
+    BasicAuth,
+    ApiKey,
+    ClientCert,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -22,6 +32,9 @@ pub(super) enum HttpVerb {
     Post,
     Put,
     Delete,
+    Patch,
+    Head,
+    Options,
 }
 
 impl Default for HttpVerb {
@@ -38,6 +51,41 @@ impl Display for HttpVerb {
             Post => write!(f, "POST"),
             Put => write!(f, "PUT"),
             Delete => write!(f, "DELETE"),
+            Patch => write!(f, "PATCH"),
+            Head => write!(f, "HEAD"),
+            Options => write!(f, "OPTIONS"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(super) enum MatchMode {
+    Exact,
+    Present,
+    Absent,
+    CaseInsensitive,
+    Regex,
+    Contains,
+    AnyOf,
+}
+
+impl MatchMode {
+    pub(super) fn requires_value(&self) -> bool {
+        !matches!(self, MatchMode::Present | MatchMode::Absent)
+    }
+}
+
+impl Display for MatchMode {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        use MatchMode::*;
+        match self {
+            Exact => write!(f, "exactly matches"),
+            Present => write!(f, "is present"),
+            Absent => write!(f, "is absent"),
+            CaseInsensitive => write!(f, "matches, ignoring case,"),
+            Regex => write!(f, "matches the regular expression"),
+            Contains => write!(f, "contains"),
+            AnyOf => write!(f, "is any of"),
         }
     }
 }
@@ -50,38 +98,190 @@ pub(super) struct Rule {
     pub(super) subject: Option<String>,
     pub(super) key_path: Option<String>,
     pub(super) http_method: Option<HttpVerb>,
+    pub(super) header_name: Option<String>,
+    pub(super) match_mode: Option<MatchMode>,
+    pub(super) match_value: Option<String>,
+    pub(super) param_name: Option<String>,
+    pub(super) body_path: Option<String>,
+    pub(super) form_field: Option<String>,
+    pub(super) nth: Option<u32>,
+    pub(super) claim: Option<String>,
+    pub(super) algorithms: Vec<shared::Algorithm>,
+    pub(super) username: Option<String>,
+    pub(super) password: Option<String>,
+    pub(super) key_location: Option<shared::ApiKeyLocation>,
+    pub(super) key_name: Option<String>,
+    pub(super) api_key: Option<String>,
+    pub(super) fingerprint: Option<String>,
 }
 
-#[derive(Default, Serialize, Deserialize, Debug, Validate, Clone)]
+#[derive(Serialize, Deserialize, Debug, Validate, Clone)]
+#[validate(schema(function = "payload_matches_content_type"))]
 pub(crate) struct Recipe {
     pub(crate) id: Option<Uuid>,
     #[validate(custom(
         function = "url_starts_with_api",
-        message = "The endpoint must be a valid URL that includes a path that starts with \"/api\""
+        message = "The endpoint must be a valid URL, without a query, that includes a path that starts with \"/api\""
     ))]
     pub(crate) url: String,
     #[validate]
     pub(crate) rules: Vec<Rule>,
-    #[validate(custom(function = "payload_is_json", message = "Payload must be valid JSON!"))]
+    #[validate(range(
+        min = 100,
+        max = 999,
+        message = "The status must be an HTTP status code between 100 and 999!"
+    ))]
+    pub(crate) status: u16,
+    #[validate(custom(
+        function = "headers_are_named",
+        message = "Every response header must have a name!"
+    ))]
+    pub(crate) headers: Vec<shared::Header>,
+    pub(crate) content_type: String,
     pub(crate) payload: String,
+    pub(crate) templated: bool,
+    pub(crate) draft: bool,
+    pub(crate) priority: i32,
+    #[validate(custom(
+        function = "scenario_is_url_safe",
+        message = "A scenario name may only have letters, digits, '-', '_' and '.'!"
+    ))]
+    pub(crate) scenario: String,
+    pub(crate) required_state: String,
+    pub(crate) new_state: String,
+    #[validate]
+    pub(crate) responses: Vec<RecipeResponse>,
+    pub(crate) sequence_mode: shared::SequenceMode,
+    pub(crate) delay: Option<shared::Delay>,
+    pub(crate) fault: Option<shared::Fault>,
+    pub(crate) file: Option<String>,
     pub(crate) created_at: Option<NaiveDateTime>,
     pub(crate) updated_at: Option<NaiveDateTime>,
 }
 
-fn payload_is_json(payload: &str) -> Result<(), ValidationError> {
-    if let Err(error) = serde_json::from_str::<serde_json::Value>(payload) {
-        error!("Payload could not be parsed as JSON, {}", error);
-        Err(ValidationError::new("invalid_payload"))
+impl Default for Recipe {
+    fn default() -> Self {
+        Self {
+            id: None,
+            url: String::default(),
+            rules: Vec::new(),
+            status: shared::default_status(),
+            headers: Vec::new(),
+            content_type: shared::default_content_type(),
+            payload: String::default(),
+            templated: false,
+            draft: false,
+            priority: 0,
+            scenario: String::default(),
+            required_state: String::default(),
+            new_state: String::default(),
+            responses: Vec::new(),
+            sequence_mode: shared::SequenceMode::default(),
+            delay: None,
+            fault: None,
+            file: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+/// A response served after the recipe's own, in order, on the calls that match it.
+#[derive(Serialize, Deserialize, Debug, Validate, Clone)]
+#[validate(schema(function = "response_payload_matches_content_type"))]
+pub(crate) struct RecipeResponse {
+    #[validate(range(
+        min = 100,
+        max = 999,
+        message = "The status must be an HTTP status code between 100 and 999!"
+    ))]
+    pub(crate) status: u16,
+    pub(crate) content_type: String,
+    pub(crate) payload: String,
+    pub(crate) templated: bool,
+}
+
+impl Default for RecipeResponse {
+    fn default() -> Self {
+        Self {
+            status: shared::default_status(),
+            content_type: shared::default_content_type(),
+            payload: String::default(),
+            templated: false,
+        }
+    }
+}
+
+fn payload_matches_content_type(r: &Recipe) -> Result<(), ValidationError> {
+    check_payload(&r.content_type, &r.payload, r.templated)
+}
+
+fn response_payload_matches_content_type(r: &RecipeResponse) -> Result<(), ValidationError> {
+    check_payload(&r.content_type, &r.payload, r.templated)
+}
+
+fn check_payload(
+    content_type: &str,
+    payload: &str,
+    templated: bool,
+) -> Result<(), ValidationError> {
+    let invalid = |message: &str| ValidationError {
+        code: "invalid_payload".into(),
+        message: Some(message.to_owned().into()),
+        params: HashMap::new(),
+    };
+    if content_type.parse::<http::HeaderValue>().is_err() || !content_type.contains('/') {
+        return Err(invalid(
+            "The content type must be a valid media type, like \"application/xml\"!",
+        ));
+    }
+    match shared::PayloadKind::of(content_type) {
+        shared::PayloadKind::Json => {
+            if let Err(error) = serde_json::from_str::<serde_json::Value>(payload) {
+                error!("Payload could not be parsed as JSON, {}", error);
+                Err(invalid("Payload must be valid JSON!"))
+            } else {
+                Ok(())
+            }
+        }
+        shared::PayloadKind::Text => Ok(()),
+        shared::PayloadKind::Binary if templated => {
+            Err(invalid("A binary payload cannot be templated!"))
+        }
+        shared::PayloadKind::Binary => {
+            if base64::decode(payload).is_err() {
+                Err(invalid("Choose a file to upload as the payload!"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+fn headers_are_named(headers: &[shared::Header]) -> Result<(), ValidationError> {
+    if headers.iter().any(|header| header.name.trim().is_empty()) {
+        Err(ValidationError::new("unnamed_header"))
     } else {
         Ok(())
     }
 }
 
+fn scenario_is_url_safe(scenario: &str) -> Result<(), ValidationError> {
+    if shared::is_url_safe(scenario) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_scenario"))
+    }
+}
+
 fn url_starts_with_api(url: &str) -> Result<(), ValidationError> {
-    let uri: Uri = url
+    let uri: Uri = sample_url(url)
         .parse()
         .map_err(|_| ValidationError::new("invalid_url"))?;
-    if let Some(path_and_query) = uri.path_and_query() {
+    if uri.query().is_some() {
+        // query parameters are matched by rules rather than the URL
+        Err(ValidationError::new("url_has_query"))
+    } else if let Some(path_and_query) = uri.path_and_query() {
         if !path_and_query.as_str().starts_with("/api") {
             Err(ValidationError::new("path_missing_api"))
         } else {
@@ -92,6 +292,25 @@ fn url_starts_with_api(url: &str) -> Result<(), ValidationError> {
     }
 }
 
+/// Path parameters, like `{id}`, aren't valid in a URI so each is replaced with a placeholder
+/// before the URL is parsed.
+fn sample_url(url: &str) -> String {
+    let mut sample = String::with_capacity(url.len());
+    let mut in_param = false;
+    for c in url.chars() {
+        match c {
+            '{' => in_param = true,
+            '}' if in_param => {
+                in_param = false;
+                sample.push('_');
+            }
+            _ if in_param => (),
+            _ => sample.push(c),
+        }
+    }
+    sample
+}
+
 fn validate_rule(r: &Rule) -> Result<(), ValidationError> {
     use RuleType::*;
 
@@ -128,6 +347,259 @@ fn validate_rule(r: &Rule) -> Result<(), ValidationError> {
             ),
             params: HashMap::new(),
         }),
+        Rule {
+            rule_type: Some(Header),
+            header_name: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_header_rule".into(),
+            message: Some("The header name is required to check a header of a call!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Header),
+            match_mode: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_header_rule".into(),
+            message: Some("Choose how to match the header!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Header),
+            match_mode: Some(match_mode),
+            match_value: None,
+            ..
+        } if match_mode.requires_value() => Err(ValidationError {
+            code: "invalid_header_rule".into(),
+            message: Some("A value is required to match the header against!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(PathParam),
+            param_name: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_path_param_rule".into(),
+            message: Some(
+                "The parameter name is required to check a path parameter of a call!".into(),
+            ),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(PathParam),
+            match_mode: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_path_param_rule".into(),
+            message: Some("Choose how to match the path parameter!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(PathParam),
+            match_mode: Some(match_mode),
+            match_value: None,
+            ..
+        } if match_mode.requires_value() => Err(ValidationError {
+            code: "invalid_path_param_rule".into(),
+            message: Some("A value is required to match the path parameter against!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Body),
+            body_path: Some(_),
+            form_field: Some(_),
+            ..
+        } => Err(ValidationError {
+            code: "invalid_body_rule".into(),
+            message: Some("Match either a JSON path or a form field of the body, not both!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Body),
+            body_path: Some(body_path),
+            ..
+        } if !body_path.starts_with('/') && !body_path.starts_with('$') => Err(ValidationError {
+            code: "invalid_body_rule".into(),
+            message: Some(
+                "The JSON path must be a pointer, like \"/order/id\", or a JSONPath, like \"$.order.id\"!"
+                    .into(),
+            ),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Body),
+            match_mode: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_body_rule".into(),
+            message: Some("Choose how to match the body!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Body),
+            match_mode: Some(match_mode),
+            match_value: None,
+            ..
+        } if match_mode.requires_value() => Err(ValidationError {
+            code: "invalid_body_rule".into(),
+            message: Some("A value is required to match the body against!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(QueryParam),
+            param_name: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_query_param_rule".into(),
+            message: Some(
+                "The parameter name is required to check a query parameter of a call!".into(),
+            ),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(QueryParam),
+            match_mode: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_query_param_rule".into(),
+            message: Some("Choose how to match the query parameter!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(QueryParam),
+            match_mode: Some(match_mode),
+            match_value: None,
+            ..
+        } if match_mode.requires_value() => Err(ValidationError {
+            code: "invalid_query_param_rule".into(),
+            message: Some("A value is required to match the query parameter against!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(NthCall),
+            nth: None,
+            ..
+        }
+        | Rule {
+            rule_type: Some(NthCall),
+            nth: Some(0),
+            ..
+        } => Err(ValidationError {
+            code: "invalid_nth_call_rule".into(),
+            message: Some("The call number to match is required, counting from 1!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Claim),
+            claim: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_claim_rule".into(),
+            message: Some("The name of, or a path to, the claim to check is required!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Claim),
+            match_mode: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_claim_rule".into(),
+            message: Some("Choose how to match the claim!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(Claim),
+            match_mode: Some(match_mode),
+            match_value: None,
+            ..
+        } if match_mode.requires_value() => Err(ValidationError {
+            code: "invalid_claim_rule".into(),
+            message: Some("A value is required to match the claim against!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(BasicAuth),
+            username: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_basic_auth_rule".into(),
+            message: Some("The username is required to check the Basic credentials of a call!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(BasicAuth),
+            username: Some(username),
+            ..
+        } if username.contains(':') => Err(ValidationError {
+            code: "invalid_basic_auth_rule".into(),
+            message: Some("A username of Basic credentials can't have a colon!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(ApiKey),
+            key_location: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_api_key_rule".into(),
+            message: Some("Choose whether the API key is sent in a header or the query!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(ApiKey),
+            key_name: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_api_key_rule".into(),
+            message: Some(
+                "The name of the header or query parameter with the API key is required!".into(),
+            ),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(ApiKey),
+            key_location: Some(shared::ApiKeyLocation::Header),
+            key_name: Some(key_name),
+            ..
+        } if key_name.parse::<http::header::HeaderName>().is_err() => Err(ValidationError {
+            code: "invalid_api_key_rule".into(),
+            message: Some("The header name must be a valid HTTP header name!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(ApiKey),
+            api_key: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_api_key_rule".into(),
+            message: Some("The API key to match is required!".into()),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(ClientCert),
+            subject: None,
+            fingerprint: None,
+            ..
+        } => Err(ValidationError {
+            code: "invalid_client_cert_rule".into(),
+            message: Some(
+                "A subject, a fingerprint or both are required to check the client certificate of a call!"
+                    .into(),
+            ),
+            params: HashMap::new(),
+        }),
+        Rule {
+            rule_type: Some(ClientCert),
+            fingerprint: Some(fingerprint),
+            ..
+        } if !is_fingerprint(fingerprint) => Err(ValidationError {
+            code: "invalid_client_cert_rule".into(),
+            message: Some(
+                "The fingerprint must be a SHA-256 fingerprint of 64 hexadecimal digits, with or without colons!"
+                    .into(),
+            ),
+            params: HashMap::new(),
+        }),
         Rule {
             rule_type: None, ..
         } => Err(ValidationError {
@@ -138,3 +610,9 @@ fn validate_rule(r: &Rule) -> Result<(), ValidationError> {
         _ => Ok(()),
     }
 }
+
+/// Whether the text is a SHA-256 fingerprint, written with or without colons between the bytes.
+fn is_fingerprint(fingerprint: &str) -> bool {
+    let digits: Vec<char> = fingerprint.trim().chars().filter(|c| *c != ':').collect();
+    digits.len() == 64 && digits.iter().all(char::is_ascii_hexdigit)
+}
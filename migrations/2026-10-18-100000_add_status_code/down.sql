@@ -0,0 +1 @@
+alter table recipes drop column status_code;
@@ -0,0 +1 @@
+alter table recipes add column status_code integer not null default 200;
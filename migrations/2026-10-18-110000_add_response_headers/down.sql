@@ -0,0 +1 @@
+drop table response_headers;
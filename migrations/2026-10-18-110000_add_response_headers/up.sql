@@ -0,0 +1,7 @@
+create table response_headers (
+        id uuid primary key default uuid_generate_v4(),
+        recipe_id uuid not null references recipes,
+        position integer not null,
+        name varchar not null,
+        value varchar not null
+);
@@ -0,0 +1,5 @@
+alter table rules drop column match_value;
+alter table rules drop column match_mode;
+alter table rules drop column header_name;
+
+drop type match_mode;
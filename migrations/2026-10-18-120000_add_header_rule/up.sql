@@ -0,0 +1,7 @@
+alter type rule_type add value if not exists 'header';
+
+create type match_mode as enum ('exact', 'present', 'absent', 'case_insensitive', 'regex');
+
+alter table rules add column header_name varchar null;
+alter table rules add column match_mode match_mode null;
+alter table rules add column match_value varchar null;
@@ -0,0 +1,11 @@
+-- postgres cannot drop values from an enum so the type is rebuilt without them, dropping any rules
+-- that use the removed verbs
+delete from rules where http_method in ('patch', 'head', 'options');
+
+alter type http_verb rename to http_verb_old;
+
+create type http_verb as enum ('get', 'post', 'put', 'delete');
+
+alter table rules alter column http_method type http_verb using http_method::text::http_verb;
+
+drop type http_verb_old;
@@ -0,0 +1,3 @@
+alter type http_verb add value if not exists 'patch';
+alter type http_verb add value if not exists 'head';
+alter type http_verb add value if not exists 'options';
@@ -0,0 +1,3 @@
+delete from rules where rule_type = 'path_param';
+
+alter table rules drop column param_name;
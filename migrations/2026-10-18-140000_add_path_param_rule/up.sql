@@ -0,0 +1,3 @@
+alter type rule_type add value if not exists 'path_param';
+
+alter table rules add column param_name varchar null;
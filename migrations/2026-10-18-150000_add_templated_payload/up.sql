@@ -0,0 +1 @@
+alter table recipes add column templated boolean not null default false;
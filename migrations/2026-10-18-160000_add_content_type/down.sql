@@ -0,0 +1,9 @@
+-- payloads had to be JSON before content types so any other payload is kept as a JSON string of
+-- its base64 encoded bytes
+alter table recipes alter column payload type text using
+    case
+        when content_type = 'application/json' then convert_from(payload, 'UTF8')
+        else to_json(encode(payload, 'base64'))::text
+    end;
+
+alter table recipes drop column content_type;
@@ -0,0 +1,3 @@
+alter table recipes add column content_type varchar not null default 'application/json';
+
+alter table recipes alter column payload type bytea using convert_to(payload, 'UTF8');
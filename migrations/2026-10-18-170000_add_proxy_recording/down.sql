@@ -0,0 +1,3 @@
+delete from rules where rule_type = 'body';
+
+alter table recipes drop column draft;
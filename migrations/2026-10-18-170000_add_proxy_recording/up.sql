@@ -0,0 +1,3 @@
+alter type rule_type add value if not exists 'body';
+
+alter table recipes add column draft boolean not null default false;
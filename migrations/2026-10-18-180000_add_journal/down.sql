@@ -0,0 +1,2 @@
+drop table journal_headers;
+drop table journal_entries;
@@ -0,0 +1,22 @@
+create table journal_entries (
+        id uuid primary key default uuid_generate_v4(),
+        method varchar not null,
+        url varchar not null,
+        path varchar not null,
+        body bytea not null,
+        subject varchar,
+        matched boolean not null,
+        recipe_id uuid references recipes on delete set null,
+        status_code integer not null,
+        created_at timestamp not null default now()
+);
+
+create index journal_entries_created_at on journal_entries (created_at);
+
+create table journal_headers (
+        id uuid primary key default uuid_generate_v4(),
+        entry_id uuid not null references journal_entries on delete cascade,
+        position integer not null,
+        name varchar not null,
+        value varchar not null
+);
@@ -0,0 +1,14 @@
+alter table rules drop column form_field;
+alter table rules drop column body_path;
+
+-- postgres cannot drop values from an enum so the type is rebuilt without it, dropping any rules
+-- that use the removed mode
+delete from rules where match_mode = 'contains';
+
+alter type match_mode rename to match_mode_old;
+
+create type match_mode as enum ('exact', 'present', 'absent', 'case_insensitive', 'regex');
+
+alter table rules alter column match_mode type match_mode using match_mode::text::match_mode;
+
+drop type match_mode_old;
@@ -0,0 +1,4 @@
+alter type match_mode add value if not exists 'contains';
+
+alter table rules add column body_path varchar null;
+alter table rules add column form_field varchar null;
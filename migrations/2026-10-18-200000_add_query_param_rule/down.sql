@@ -0,0 +1 @@
+delete from rules where rule_type = 'query_param';
@@ -0,0 +1 @@
+alter type rule_type add value if not exists 'query_param';
@@ -0,0 +1,25 @@
+-- exact query parameter rules are folded back into their recipe's URL; any others are left to be
+-- dropped along with the rule type
+create function pg_temp.url_encode(decoded text) returns text as $$
+    select coalesce(string_agg(
+        case
+            when c ~ '^[A-Za-z0-9._~-]$' then c
+            else upper(regexp_replace(encode(convert_to(c, 'UTF8'), 'hex'), '(..)', '%\1', 'g'))
+        end, '' order by n), '')
+    from regexp_split_to_table(decoded, '') with ordinality as chars(c, n);
+$$ language sql immutable;
+
+update recipes r set url = r.url || '?' || q.query
+from (
+        select recipe_id,
+                string_agg(
+                    pg_temp.url_encode(param_name) || '=' || pg_temp.url_encode(coalesce(match_value, '')),
+                    '&' order by id
+                ) as query
+        from rules
+        where rule_type = 'query_param' and match_mode = 'exact'
+        group by recipe_id
+) q
+where q.recipe_id = r.id;
+
+delete from rules where rule_type = 'query_param' and match_mode = 'exact';
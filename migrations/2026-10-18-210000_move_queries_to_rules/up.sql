@@ -0,0 +1,29 @@
+-- a recipe's URL no longer includes a query, so each parameter of an existing query becomes an
+-- exact query parameter rule; new enum values can't be used in the migration that adds them, so
+-- this runs separately from adding the rule type
+create function pg_temp.url_decode(encoded text) returns text as $$
+    select coalesce(convert_from(decode(string_agg(
+        case
+            when length(m[1]) = 1 then encode(convert_to(m[1], 'UTF8'), 'hex')
+            else substring(m[1] from 2 for 2)
+        end, '' order by n), 'hex'), 'UTF8'), '')
+    from regexp_matches(replace(encoded, '+', ' '), '%[0-9a-fA-F]{2}|.', 'g')
+        with ordinality as matched(m, n);
+$$ language sql immutable;
+
+insert into rules (recipe_id, rule_type, param_name, match_mode, match_value)
+select r.id,
+        'query_param',
+        pg_temp.url_decode(split_part(p.pair, '=', 1)),
+        'exact',
+        case
+            when position('=' in p.pair) > 0
+                then pg_temp.url_decode(substring(p.pair from position('=' in p.pair) + 1))
+            else ''
+        end
+from recipes r,
+        regexp_split_to_table(substring(r.url from position('?' in r.url) + 1), '&') as p(pair)
+where position('?' in r.url) > 0
+        and p.pair <> '';
+
+update recipes set url = split_part(url, '?', 1) where position('?' in url) > 0;
@@ -0,0 +1 @@
+alter table recipes add column priority integer not null default 0;
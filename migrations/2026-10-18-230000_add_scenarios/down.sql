@@ -0,0 +1,5 @@
+alter table recipes drop column new_state;
+alter table recipes drop column required_state;
+alter table recipes drop column scenario;
+
+drop table scenarios;
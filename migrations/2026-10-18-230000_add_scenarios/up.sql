@@ -0,0 +1,9 @@
+create table scenarios (
+        name varchar primary key,
+        state varchar not null,
+        updated_at timestamp not null default now()
+);
+
+alter table recipes add column scenario varchar;
+alter table recipes add column required_state varchar;
+alter table recipes add column new_state varchar;
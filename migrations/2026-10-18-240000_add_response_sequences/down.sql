@@ -0,0 +1,11 @@
+delete from rules where rule_type = 'nth_call';
+
+alter table rules drop column nth;
+
+drop index journal_entries_recipe_id;
+
+drop table recipe_responses;
+
+alter table recipes drop column sequence_mode;
+
+drop type sequence_mode;
@@ -0,0 +1,20 @@
+create type sequence_mode as enum ('cycle', 'stick_on_last', 'fall_through');
+
+alter table recipes add column sequence_mode sequence_mode not null default 'stick_on_last';
+
+create table recipe_responses (
+        id uuid primary key default uuid_generate_v4(),
+        recipe_id uuid not null references recipes on delete cascade,
+        position integer not null,
+        status_code integer not null default 200,
+        content_type varchar not null default 'application/json',
+        payload bytea not null,
+        templated boolean not null default false
+);
+
+-- sequences count how many times their recipe has been served from the journal
+create index journal_entries_recipe_id on journal_entries (recipe_id);
+
+alter type rule_type add value if not exists 'nth_call';
+
+alter table rules add column nth integer;
@@ -0,0 +1,12 @@
+alter table recipes drop column trickle_interval_millis;
+alter table recipes drop column trickle_chunk_bytes;
+alter table recipes drop column fault_type;
+alter table recipes drop column delay_sigma;
+alter table recipes drop column delay_std_dev_millis;
+alter table recipes drop column delay_max_millis;
+alter table recipes drop column delay_millis;
+alter table recipes drop column delay_type;
+
+drop type fault_type;
+
+drop type delay_type;
@@ -0,0 +1,13 @@
+create type delay_type as enum ('fixed', 'uniform', 'normal', 'log_normal');
+
+create type fault_type as enum ('connection_reset', 'partial_then_reset', 'trickle', 'empty_reply');
+
+-- like rules, each type of delay and fault uses the columns it needs and leaves the rest null
+alter table recipes add column delay_type delay_type;
+alter table recipes add column delay_millis integer;
+alter table recipes add column delay_max_millis integer;
+alter table recipes add column delay_std_dev_millis integer;
+alter table recipes add column delay_sigma double precision;
+alter table recipes add column fault_type fault_type;
+alter table recipes add column trickle_chunk_bytes integer;
+alter table recipes add column trickle_interval_millis integer;
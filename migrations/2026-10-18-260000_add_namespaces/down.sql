@@ -0,0 +1,16 @@
+drop index journal_entries_namespace;
+drop index recipes_namespace;
+
+-- only the default namespace survives, since names may repeat across namespaces
+delete from scenarios where namespace <> 'default';
+delete from journal_entries where namespace <> 'default';
+delete from rules where recipe_id in (select id from recipes where namespace <> 'default');
+delete from response_headers where recipe_id in (select id from recipes where namespace <> 'default');
+delete from recipes where namespace <> 'default';
+
+alter table scenarios drop constraint scenarios_pkey;
+alter table scenarios add primary key (name);
+
+alter table scenarios drop column namespace;
+alter table journal_entries drop column namespace;
+alter table recipes drop column namespace;
@@ -0,0 +1,11 @@
+-- everything that already exists belongs to the default namespace
+alter table recipes add column namespace varchar not null default 'default';
+alter table journal_entries add column namespace varchar not null default 'default';
+alter table scenarios add column namespace varchar not null default 'default';
+
+-- a scenario's name only has to be unique within its namespace
+alter table scenarios drop constraint scenarios_pkey;
+alter table scenarios add primary key (namespace, name);
+
+create index recipes_namespace on recipes (namespace);
+create index journal_entries_namespace on journal_entries (namespace);
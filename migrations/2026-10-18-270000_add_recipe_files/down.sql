@@ -0,0 +1 @@
+alter table recipes drop column file;
@@ -0,0 +1,3 @@
+-- the file, relative to the recipe directory, that a recipe was loaded from; recipes created
+-- through the UI or the REST API have none
+alter table recipes add column file varchar;
@@ -0,0 +1,15 @@
+delete from rules where rule_type in ('claim', 'unexpired');
+
+alter table rules drop column claim;
+
+-- postgres cannot drop values from an enum so the type is rebuilt without it, dropping any rules
+-- that use the removed mode
+delete from rules where match_mode = 'any_of';
+
+alter type match_mode rename to match_mode_old;
+
+create type match_mode as enum ('exact', 'present', 'absent', 'case_insensitive', 'regex', 'contains');
+
+alter table rules alter column match_mode type match_mode using match_mode::text::match_mode;
+
+drop type match_mode_old;
@@ -0,0 +1,7 @@
+alter type rule_type add value if not exists 'claim';
+alter type rule_type add value if not exists 'unexpired';
+
+alter type match_mode add value if not exists 'any_of';
+
+-- the name of, or a path to, the token claim a claim rule matches
+alter table rules add column claim varchar;
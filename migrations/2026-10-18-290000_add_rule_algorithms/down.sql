@@ -0,0 +1 @@
+alter table rules drop column algorithms;
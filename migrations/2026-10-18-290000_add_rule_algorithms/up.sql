@@ -0,0 +1,3 @@
+-- the algorithms, by name like 'RS256', that an authenticated rule accepts a token signed with;
+-- none means any algorithm that suits the key
+alter table rules add column algorithms text[] not null default '{}';
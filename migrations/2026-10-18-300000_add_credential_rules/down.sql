@@ -0,0 +1,10 @@
+delete from rules where rule_type in ('basic_auth', 'api_key', 'client_cert');
+
+alter table rules drop column fingerprint;
+alter table rules drop column api_key;
+alter table rules drop column key_name;
+alter table rules drop column key_location;
+alter table rules drop column password;
+alter table rules drop column username;
+
+drop type api_key_location;
@@ -0,0 +1,15 @@
+alter type rule_type add value if not exists 'basic_auth';
+alter type rule_type add value if not exists 'api_key';
+alter type rule_type add value if not exists 'client_cert';
+
+create type api_key_location as enum ('header', 'query');
+
+-- the credentials a basic auth rule expects, any password matching when it is null
+alter table rules add column username varchar;
+alter table rules add column password varchar;
+-- where an api key rule looks for a key, under what name and the key it expects
+alter table rules add column key_location api_key_location;
+alter table rules add column key_name varchar;
+alter table rules add column api_key varchar;
+-- a client cert rule's subject is kept in the subject column along with this fingerprint
+alter table rules add column fingerprint varchar;
@@ -0,0 +1 @@
+drop table served_counts;
@@ -0,0 +1,12 @@
+-- how many times each recipe has been served since its journal was last cleared, claimed by each
+-- call as its recipe is chosen so that calls made at the same time step through a sequence in turn
+create table served_counts (
+        recipe_id uuid primary key references recipes on delete cascade,
+        served bigint not null default 0
+);
+
+-- sequences carry on from where the journal says they are
+insert into served_counts (recipe_id, served)
+        select recipe_id, count(*) from journal_entries
+        where recipe_id is not null
+        group by recipe_id;
@@ -1,10 +1,28 @@
-use anyhow::{format_err, Context, Result};
+use actix_web::http::{HeaderName, Uri};
+use anyhow::{bail, format_err, Context, Result};
 use lazy_static::lazy_static;
 use log::{debug, error};
-use std::{convert::TryFrom, env, path::PathBuf, str::FromStr};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    env, fs,
+    net::IpAddr,
+    path::PathBuf,
+    str::FromStr,
+};
 
 const CLIENT_PATH: &str = "CLIENT_PATH";
 const STATIC_PATH: &str = "STATIC_PATH";
+const PROXY_UPSTREAMS: &str = "PROXY_UPSTREAMS";
+const NAMESPACE_HOSTS: &str = "NAMESPACE_HOSTS";
+const RECIPE_PATH: &str = "RECIPE_PATH";
+const WATCH_RECIPES: &str = "WATCH_RECIPES";
+const STORAGE: &str = "STORAGE";
+const OIDC_CONFIG: &str = "OIDC_CONFIG";
+const CLIENT_CERT_HEADER: &str = "CLIENT_CERT_HEADER";
+const TRUSTED_PROXIES: &str = "TRUSTED_PROXIES";
 
 lazy_static! {
     pub(crate) static ref FAVICON: String = file_from_env_or_default(
@@ -21,12 +39,46 @@ lazy_static! {
     .unwrap_or_else(|error| panic!("{}", error));
     pub(crate) static ref KEY_PATH_KIND: KeyPathKind =
         key_path_kind().unwrap_or_else(|error| panic!("{}", error));
+    pub(crate) static ref UPSTREAMS: HashMap<String, String> = env::var(PROXY_UPSTREAMS)
+        .map(|upstreams| parse_upstreams(&upstreams))
+        .unwrap_or_else(|_| Ok(HashMap::new()))
+        .unwrap_or_else(|error| panic!("{}", error));
+    pub(crate) static ref NAMESPACES: HashMap<String, String> = env::var(NAMESPACE_HOSTS)
+        .map(|hosts| parse_namespace_hosts(&hosts))
+        .unwrap_or_else(|_| Ok(HashMap::new()))
+        .unwrap_or_else(|error| panic!("{}", error));
+    /// The header that the proxy terminating TLS forwards the client certificate of a call in.
+    pub(crate) static ref CLIENT_CERT: HeaderName =
+        client_cert_header(&env_or_default(CLIENT_CERT_HEADER, "X-Client-Cert"))
+            .unwrap_or_else(|error| panic!("{}", error));
+    /// The addresses of the proxies trusted to forward client certificates; without any, no call
+    /// has a client certificate.
+    pub(crate) static ref PROXIES: HashSet<IpAddr> = env::var(TRUSTED_PROXIES)
+        .map(|proxies| parse_trusted_proxies(&proxies))
+        .unwrap_or_else(|_| Ok(HashSet::new()))
+        .unwrap_or_else(|error| panic!("{}", error));
+    pub(crate) static ref PROVIDER: Option<ProviderConfig> = env::var(OIDC_CONFIG)
+        .ok()
+        .map(|path| provider_config(&path))
+        .transpose()
+        .unwrap_or_else(|error| panic!("{}", error));
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum KeyPathKind {
     Directory(PathBuf),
     File(PathBuf),
+    Jwks(KeySetSource),
+}
+
+/// Where a JWKS document is read from, for every call that is checked against it, so that a
+/// stand-in for a real key server can change its keys as a test runs; without a `KEY_PATH` the
+/// keys are those of the built in token issuer.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum KeySetSource {
+    File(PathBuf),
+    Url(String),
+    Issuer,
 }
 
 impl TryFrom<String> for KeyPathKind {
@@ -44,6 +96,10 @@ impl TryFrom<String> for KeyPathKind {
         match key_path_var {
             "directory" | "dir" => Ok(KeyPathKind::Directory(key_path()?)),
             "file" => Ok(KeyPathKind::File(key_path()?)),
+            "jwks" => Ok(KeyPathKind::Jwks(match env::var("KEY_PATH") {
+                Ok(key_path) => key_set_source(&key_path)?,
+                Err(_) => KeySetSource::Issuer,
+            })),
             _ => Err(format_err!(
                 "{} cannot be parsed as a kind of key path!",
                 key_path_var
@@ -52,16 +108,96 @@ impl TryFrom<String> for KeyPathKind {
     }
 }
 
+/// The test clients and users of the mock OAuth2 and OIDC provider, which is only served when they
+/// are configured.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProviderConfig {
+    /// The `iss` of the tokens and the base URL of the provider's endpoints, which defaults to the
+    /// URL the provider is called at.
+    #[serde(default)]
+    pub(crate) issuer: Option<String>,
+    /// Seconds until an access or ID token expires.
+    #[serde(default = "default_expires_in")]
+    pub(crate) expires_in: i64,
+    /// Seconds until a refresh token expires.
+    #[serde(default = "default_refresh_expires_in")]
+    pub(crate) refresh_expires_in: i64,
+    #[serde(default)]
+    pub(crate) clients: Vec<ProviderClient>,
+    #[serde(default)]
+    pub(crate) users: Vec<ProviderUser>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProviderClient {
+    pub(crate) client_id: String,
+    /// A client without a secret is public and only sends its ID.
+    #[serde(default)]
+    pub(crate) client_secret: Option<String>,
+    /// The scopes the client may ask for, and gets when it doesn't ask; any scope may be asked for
+    /// when there are none.
+    #[serde(default)]
+    pub(crate) scopes: Vec<String>,
+    /// Claims added to every token granted to the client.
+    #[serde(default)]
+    pub(crate) claims: Map<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProviderUser {
+    pub(crate) username: String,
+    pub(crate) password: String,
+    /// Claims of the user, like `email` or `roles`, added to their tokens and served as their user
+    /// info; the subject is the username unless there is a `sub` claim.
+    #[serde(default)]
+    pub(crate) claims: Map<String, Value>,
+}
+
+impl ProviderUser {
+    pub(crate) fn subject(&self) -> &str {
+        self.claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .unwrap_or(&self.username)
+    }
+}
+
+fn default_expires_in() -> i64 {
+    60 * 60
+}
+
+fn default_refresh_expires_in() -> i64 {
+    24 * 60 * 60
+}
+
+/// Where the server keeps recipes, the journal and scenarios.
+pub(crate) enum StorageKind {
+    Postgres(String),
+    Memory,
+}
+
 pub(crate) struct ServerConfig {
     pub(crate) bind_address: String,
-    pub(crate) database_url: String,
+    pub(crate) storage: StorageKind,
     pub(crate) client_bundle_path: String,
     pub(crate) static_file_path: String,
+    /// A directory of recipe files to load at startup.
+    pub(crate) recipe_path: Option<PathBuf>,
+    /// Whether to load the recipe files again whenever they change.
+    pub(crate) watch_recipes: bool,
 }
 
 pub(crate) fn init() {
     let _ = *FAVICON;
     let _ = *WASM;
+    let _ = *UPSTREAMS;
+    let _ = *NAMESPACES;
+    let _ = *PROVIDER;
+    let _ = *CLIENT_CERT;
+    let _ = *PROXIES;
 }
 
 pub(crate) fn server_config() -> Result<ServerConfig> {
@@ -72,15 +208,120 @@ pub(crate) fn server_config() -> Result<ServerConfig> {
     let static_file_path =
         path_from_env_or_default(STATIC_PATH, default_path("./static", &["static"]))?;
     let bind_address = format!("{}:{}", host, port);
-    let database_url = env::var("DATABASE_URL").with_context(|| "DATABASE_URL is not set!")?;
+    let storage = storage_kind(
+        &env_or_default(STORAGE, "postgres"),
+        env::var("DATABASE_URL").ok(),
+    )?;
+    let recipe_path = env::var(RECIPE_PATH)
+        .ok()
+        .map(|path| recipe_path(&path))
+        .transpose()?;
+    let watch_recipes = parse_flag(WATCH_RECIPES, &env_or_default(WATCH_RECIPES, "false"))?;
+    if watch_recipes && recipe_path.is_none() {
+        bail!("{} requires {} to be set!", WATCH_RECIPES, RECIPE_PATH)
+    }
     Ok(ServerConfig {
         bind_address,
-        database_url,
+        storage,
         client_bundle_path,
         static_file_path,
+        recipe_path,
+        watch_recipes,
     })
 }
 
+fn storage_kind(kind: &str, database_url: Option<String>) -> Result<StorageKind> {
+    match kind.trim().to_ascii_lowercase().as_str() {
+        "postgres" => database_url
+            .map(StorageKind::Postgres)
+            .ok_or_else(|| format_err!("DATABASE_URL is not set!")),
+        "memory" => Ok(StorageKind::Memory),
+        _ => bail!("{} must be postgres or memory, not {}!", STORAGE, kind),
+    }
+}
+
+fn recipe_path(path: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(shellexpand::tilde(path).as_ref());
+    if !path.is_dir() {
+        bail!(
+            "The path for {}, {}, must be a directory!",
+            RECIPE_PATH,
+            path.display()
+        )
+    }
+    Ok(path)
+}
+
+fn parse_flag(option_name: &str, flag: &str) -> Result<bool> {
+    match flag.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" | "" => Ok(false),
+        _ => bail!("{} must be true or false, not {}!", option_name, flag),
+    }
+}
+
+fn client_cert_header(header_name: &str) -> Result<HeaderName> {
+    HeaderName::from_bytes(header_name.trim().as_bytes()).with_context(|| {
+        format!(
+            "{}, {}, is not a valid header name!",
+            CLIENT_CERT_HEADER, header_name
+        )
+    })
+}
+
+/// Parses a comma separated list of IP addresses.
+fn parse_trusted_proxies(proxies: &str) -> Result<HashSet<IpAddr>> {
+    proxies
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| {
+            proxy.parse().with_context(|| {
+                format!("{} must list IP addresses, not {}!", TRUSTED_PROXIES, proxy)
+            })
+        })
+        .collect()
+}
+
+/// Reads the provider's clients and users from a YAML or JSON file.
+fn provider_config(path: &str) -> Result<ProviderConfig> {
+    let path = PathBuf::from(shellexpand::tilde(path).as_ref());
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("Could not read {}, {}!", OIDC_CONFIG, path.display()))?;
+    parse_provider_config(&text)
+        .with_context(|| format!("{}, {}, is not valid!", OIDC_CONFIG, path.display()))
+}
+
+fn parse_provider_config(text: &str) -> Result<ProviderConfig> {
+    // YAML is a superset of JSON, so either can be read as YAML
+    let mut config: ProviderConfig = serde_yaml::from_str(text)?;
+    config.issuer = config
+        .issuer
+        .map(|issuer| issuer.trim_end_matches('/').to_owned());
+    if config.expires_in <= 0 || config.refresh_expires_in <= 0 {
+        bail!("Tokens must expire a positive number of seconds after they are granted!")
+    }
+    let mut client_ids = HashSet::new();
+    for client in &config.clients {
+        if client.client_id.is_empty() || !client_ids.insert(&client.client_id) {
+            bail!(
+                "Each client must have a distinct client_id, unlike {:?}!",
+                client.client_id
+            )
+        }
+    }
+    let mut subjects = HashSet::new();
+    for user in &config.users {
+        if user.username.is_empty() || !subjects.insert(user.subject()) {
+            bail!(
+                "Each user must have a distinct username and subject, unlike {:?}!",
+                user.username
+            )
+        }
+    }
+    Ok(config)
+}
+
 fn key_path() -> Result<PathBuf> {
     env::var("KEY_PATH")
         .map_err(anyhow::Error::from)
@@ -109,6 +350,23 @@ fn key_path() -> Result<PathBuf> {
         })
 }
 
+/// A JWKS is fetched from an HTTP or HTTPS URL or read from any other path.
+fn key_set_source(key_path: &str) -> Result<KeySetSource> {
+    if key_path.starts_with("http://") || key_path.starts_with("https://") {
+        let uri: Uri = key_path
+            .parse()
+            .with_context(|| format!("The JWKS URL, {}, is not valid!", key_path))?;
+        if uri.host().is_none() {
+            bail!("The JWKS URL, {}, must include a host!", key_path)
+        }
+        Ok(KeySetSource::Url(key_path.to_owned()))
+    } else {
+        Ok(KeySetSource::File(PathBuf::from(
+            shellexpand::tilde(key_path).as_ref(),
+        )))
+    }
+}
+
 fn key_path_kind() -> Result<KeyPathKind> {
     env::var("KEY_PATH_KIND")
         .map_err(anyhow::Error::from)
@@ -120,6 +378,67 @@ fn key_path_kind() -> Result<KeyPathKind> {
         })
 }
 
+/// Parses the hosts to proxy, a comma separated list of pairs, each a host, as it appears in recipe
+/// URLs, and the URL of the upstream to forward its calls to, like
+/// `test.local=http://localhost:9000`.
+fn parse_upstreams(upstreams: &str) -> Result<HashMap<String, String>> {
+    split_pairs(upstreams, PROXY_UPSTREAMS)
+        .map(|pair| {
+            let (host, upstream) = pair?;
+            let uri: Uri = upstream
+                .parse()
+                .with_context(|| format!("The upstream URL, {}, is not valid!", upstream))?;
+            if uri.scheme().is_none() || uri.host().is_none() {
+                bail!(
+                    "The upstream URL, {}, must include a scheme and host!",
+                    upstream
+                )
+            }
+            Ok((host.to_owned(), upstream.trim_end_matches('/').to_owned()))
+        })
+        .collect()
+}
+
+/// Parses the hosts whose calls work in a namespace other than the default, a comma separated list
+/// of pairs, each a host, including any port, and the namespace, like `localhost:9001=team-a`.
+fn parse_namespace_hosts(hosts: &str) -> Result<HashMap<String, String>> {
+    split_pairs(hosts, NAMESPACE_HOSTS)
+        .map(|pair| {
+            let (host, namespace) = pair?;
+            if namespace.is_empty() || !shared::is_url_safe(namespace) {
+                bail!(
+                    "The namespace, {}, for {} may only have letters, digits, '-', '_' and '.'!",
+                    namespace,
+                    host
+                )
+            }
+            Ok((host.to_owned(), namespace.to_owned()))
+        })
+        .collect()
+}
+
+/// Splits a comma separated list of pairs, each a host and a value separated by "=".
+fn split_pairs<'a>(
+    pairs: &'a str,
+    option_name: &'a str,
+) -> impl Iterator<Item = Result<(&'a str, &'a str)>> {
+    pairs
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(move |pair| {
+            let mut parts = pair.splitn(2, '=').map(str::trim);
+            match (parts.next(), parts.next()) {
+                (Some(host), Some(value)) if !host.is_empty() => Ok((host, value)),
+                _ => bail!(
+                    "The pair, {}, in {} must be a host and a value separated by \"=\"!",
+                    pair,
+                    option_name
+                ),
+            }
+        })
+}
+
 fn env_or_default(option_name: &str, default: &str) -> String {
     env::var(option_name).unwrap_or_else(|_| String::from(default))
 }
@@ -183,3 +502,141 @@ fn default_path(under_cargo: &str, subdirs: &[&str]) -> String {
         format!("{}", location.display())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_upstreams() -> Result<()> {
+        let upstreams =
+            parse_upstreams("test.local=http://localhost:9000/, api.test:8080 = https://api.test")?;
+        assert_eq!(
+            Some(&"http://localhost:9000".to_owned()),
+            upstreams.get("test.local")
+        );
+        assert_eq!(
+            Some(&"https://api.test".to_owned()),
+            upstreams.get("api.test:8080")
+        );
+        assert!(parse_upstreams("")?.is_empty());
+        assert!(parse_upstreams("test.local").is_err());
+        assert!(parse_upstreams("test.local=localhost").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_namespace_hosts() -> Result<()> {
+        let hosts = parse_namespace_hosts("localhost:9001=team-a, ci.test = run_42")?;
+        assert_eq!(Some(&"team-a".to_owned()), hosts.get("localhost:9001"));
+        assert_eq!(Some(&"run_42".to_owned()), hosts.get("ci.test"));
+        assert!(parse_namespace_hosts("")?.is_empty());
+        assert!(parse_namespace_hosts("localhost:9001").is_err());
+        assert!(parse_namespace_hosts("localhost:9001=").is_err());
+        assert!(parse_namespace_hosts("localhost:9001=team a").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_flag() -> Result<()> {
+        assert!(parse_flag(WATCH_RECIPES, "true")?);
+        assert!(parse_flag(WATCH_RECIPES, " Yes ")?);
+        assert!(parse_flag(WATCH_RECIPES, "1")?);
+        assert!(!parse_flag(WATCH_RECIPES, "false")?);
+        assert!(!parse_flag(WATCH_RECIPES, "")?);
+        assert!(parse_flag(WATCH_RECIPES, "sometimes").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_kind() -> Result<()> {
+        let database_url = "postgres://localhost/empholite".to_owned();
+        assert!(matches!(
+            storage_kind("postgres", Some(database_url.clone()))?,
+            StorageKind::Postgres(url) if url == database_url
+        ));
+        assert!(matches!(
+            storage_kind(" Memory", None)?,
+            StorageKind::Memory
+        ));
+        assert!(storage_kind("postgres", None).is_err());
+        assert!(storage_kind("sqlite", Some(database_url)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_set_source() -> Result<()> {
+        assert_eq!(
+            KeySetSource::Url("http://localhost:9000/.well-known/jwks.json".to_owned()),
+            key_set_source("http://localhost:9000/.well-known/jwks.json")?
+        );
+        assert_eq!(
+            KeySetSource::File(PathBuf::from("/etc/empholite/jwks.json")),
+            key_set_source("/etc/empholite/jwks.json")?
+        );
+        assert!(key_set_source("https:///jwks.json").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_cert_header() -> Result<()> {
+        assert_eq!(
+            "x-ssl-client-cert",
+            client_cert_header(" X-SSL-Client-Cert")?
+        );
+        assert!(client_cert_header("X Client Cert").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_trusted_proxies() -> Result<()> {
+        let proxies = parse_trusted_proxies("10.0.0.2, ::1")?;
+        assert!(proxies.contains(&"10.0.0.2".parse::<IpAddr>()?));
+        assert!(proxies.contains(&"::1".parse::<IpAddr>()?));
+        assert!(parse_trusted_proxies("")?.is_empty());
+        assert!(parse_trusted_proxies("10.0.0.0/8").is_err());
+        assert!(parse_trusted_proxies("proxy.local").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_provider_config() -> Result<()> {
+        let config = parse_provider_config(
+            r#"
+issuer: http://localhost:8989/
+clients:
+  - client_id: orders
+    client_secret: s3cret
+    scopes: [orders.read]
+  - client_id: spa
+users:
+  - username: alice
+    password: wonderland
+    claims:
+      email: alice@example.com
+  - username: bob
+    password: builder
+    claims:
+      sub: "42"
+"#,
+        )?;
+        assert_eq!(Some("http://localhost:8989".to_owned()), config.issuer);
+        assert_eq!(60 * 60, config.expires_in);
+        assert_eq!(None, config.clients[1].client_secret);
+        assert_eq!("alice", config.users[0].subject());
+        assert_eq!("42", config.users[1].subject());
+
+        let config = parse_provider_config(r#"{"clients": [{"client_id": "orders"}]}"#)?;
+        assert!(config.issuer.is_none());
+        assert!(config.users.is_empty());
+
+        assert!(parse_provider_config("clients: [{client_id: a}, {client_id: a}]").is_err());
+        assert!(parse_provider_config(
+            "users: [{username: a, password: x}, {username: b, password: y, claims: {sub: a}}]"
+        )
+        .is_err());
+        assert!(parse_provider_config("expires_in: 0").is_err());
+        assert!(parse_provider_config("client: []").is_err());
+        Ok(())
+    }
+}
@@ -1,13 +1,20 @@
-use super::db;
+use super::{db, key_set::load_key_set};
 use crate::config::{self, KeyPathKind};
 use actix_web::{error::ErrorInternalServerError, web::Path, HttpResponse, Result};
 use anyhow::{bail, format_err};
 use serde_json::{self, Map, Value};
 use std::{fs, path::PathBuf};
 
+/// The key path that trusts whichever key in the JWKS a token names by its kid.
+const ANY_KEY: &str = "*";
+
+mod namespace;
 mod recipe;
+mod scenario;
 
+pub(crate) use namespace::*;
 pub(crate) use recipe::*;
+pub(crate) use scenario::*;
 
 #[actix_web::get("/ajax/key_path/{tail:.*}")]
 pub(crate) async fn complete_key_path(selected_path: Path<String>) -> Result<HttpResponse> {
@@ -27,6 +34,12 @@ pub(crate) async fn complete_key_path(selected_path: Path<String>) -> Result<Htt
         KeyPathKind::File(ref key_path) => {
             property_completions(selected, &key_path).map_err(ErrorInternalServerError)?
         }
+        KeyPathKind::Jwks(ref source) => {
+            let key_set = load_key_set(source)
+                .await
+                .map_err(ErrorInternalServerError)?;
+            key_set_completions(selected, key_set.kids())
+        }
     };
     Ok(HttpResponse::Ok().json(entries))
 }
@@ -34,10 +47,10 @@ pub(crate) async fn complete_key_path(selected_path: Path<String>) -> Result<Htt
 #[actix_web::get("/ajax/config")]
 pub(crate) async fn get_config() -> Result<HttpResponse> {
     let config = shared::Config {
-        key_path_kind: if let KeyPathKind::Directory(_) = *config::KEY_PATH_KIND {
-            shared::KeyPathKind::Directory
-        } else {
-            shared::KeyPathKind::File
+        key_path_kind: match *config::KEY_PATH_KIND {
+            KeyPathKind::Directory(_) => shared::KeyPathKind::Directory,
+            KeyPathKind::File(_) => shared::KeyPathKind::File,
+            KeyPathKind::Jwks(_) => shared::KeyPathKind::Jwks,
         },
     };
     Ok(HttpResponse::Ok().json(config))
@@ -113,6 +126,22 @@ fn property_completions(
     }
 }
 
+/// Any key in the JWKS, chosen by the token's kid, or one key in particular, by its kid.
+fn key_set_completions(selected: Vec<String>, kids: Vec<String>) -> shared::KeyPathCompletions {
+    let candidates = std::iter::once(ANY_KEY.to_owned())
+        .chain(kids)
+        .map(|component| shared::KeyPathComponent {
+            leaf: true,
+            component,
+        })
+        .collect();
+    shared::KeyPathCompletions {
+        candidates,
+        selected,
+        kind: shared::KeyPathKind::Jwks,
+    }
+}
+
 fn into_selected(selected_path: String) -> Vec<String> {
     let separator = if selected_path.contains('/') {
         '/'
@@ -0,0 +1,14 @@
+use crate::{handlers, DbPool};
+use actix_web::{
+    error::ErrorInternalServerError,
+    web::{self, Data},
+    HttpResponse, Result,
+};
+
+#[actix_web::get("/ajax/namespace/")]
+pub(crate) async fn list_namespaces(db: Data<DbPool>) -> Result<HttpResponse> {
+    let namespaces = web::block(move || handlers::list_namespaces(&db))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(namespaces))
+}
@@ -0,0 +1,30 @@
+use super::db;
+use crate::{
+    handlers::{self, namespace::Namespace},
+    DbPool,
+};
+use actix_web::{
+    error::ErrorInternalServerError,
+    web::{self, Data, Path},
+    HttpResponse, Result,
+};
+
+#[actix_web::get("/ajax/scenario/")]
+pub(crate) async fn list_scenarios(db: Data<DbPool>, namespace: Namespace) -> Result<HttpResponse> {
+    let scenarios = web::block(move || handlers::list_scenarios(&db, &namespace))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(scenarios))
+}
+
+#[actix_web::delete("/ajax/scenario/{name}")]
+pub(crate) async fn reset_scenario(
+    db: Data<DbPool>,
+    namespace: Namespace,
+    path: Path<String>,
+) -> Result<HttpResponse> {
+    web::block(move || db::reset_scenarios(&db, &namespace, Some(&path.into_inner())))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
@@ -1,178 +1,345 @@
 use crate::{
-    models::{NewRecipe, NewRule, Recipe, Rule},
+    models::{
+        JournalCascaded, NewDisruption, NewJournalEntry, NewRecipe, NewRecipeResponse,
+        NewResponseHeader, NewRule, NewScenario, PathParams, Recipe, RecipeCascaded,
+        RecipeResponse, ResponseHeader, Rule, Scenario, UrlTemplate,
+    },
+    storage::Storage,
     DbPool,
 };
-use anyhow::Result;
-use diesel::prelude::*;
+use anyhow::{format_err, Result};
+use log::warn;
+use std::{collections::HashMap, convert::TryFrom};
 use uuid::Uuid;
 
-pub(super) fn load_recipes(db: &DbPool, offset: i64, limit: i64) -> Result<(i64, Vec<Recipe>)> {
-    use crate::schema::recipes::dsl::*;
+pub(super) use crate::storage::ScenarioStep;
 
-    let conn = db.get()?;
+pub(super) fn check(db: &DbPool) -> Result<()> {
+    db.check()
+}
 
-    let total = recipes.count().first::<i64>(&conn)?;
+pub(super) fn load_recipes(
+    db: &DbPool,
+    owner: &str,
+    offset: i64,
+    limit: i64,
+) -> Result<(i64, Vec<Recipe>)> {
+    db.load_recipes(owner, offset, limit)
+}
 
-    let results: Vec<Recipe> = recipes
-        .offset(offset)
-        .limit(limit)
-        .order((url, created_at))
-        .load::<Recipe>(&conn)?;
+pub(super) fn find_recipe(db: &DbPool, owner: &str, to_find: Uuid) -> Result<RecipeCascaded> {
+    let recipe = db.find_recipe(owner, to_find)?;
+    let rules = db.find_rules(recipe.id)?;
+    let headers = find_headers(db, recipe.id)?;
+    let responses = find_responses(db, recipe.id)?;
 
-    Ok((total, results))
+    Ok(RecipeCascaded(recipe, rules, headers, responses))
 }
 
-pub(super) fn find_recipe(db: &DbPool, to_find: Uuid) -> Result<(Recipe, Vec<Rule>)> {
-    use crate::schema::recipes::dsl::*;
+pub(super) fn find_headers(db: &DbPool, parent: Uuid) -> Result<Vec<ResponseHeader>> {
+    db.find_headers(parent)
+}
 
-    let conn = db.get()?;
+pub(super) fn find_responses(db: &DbPool, parent: Uuid) -> Result<Vec<RecipeResponse>> {
+    db.find_responses(parent)
+}
 
-    let recipe = recipes
-        .find(to_find)
-        .first::<Recipe>(&conn)
-        .map_err(anyhow::Error::from)?;
+/// How many responses each recipe has after its own, for those that have any.
+pub(super) fn count_responses(db: &DbPool, parents: &[Uuid]) -> Result<HashMap<Uuid, i64>> {
+    db.count_responses(parents)
+}
 
-    let rules: Vec<(Rule, Recipe)> = Rule::belonging_to(&recipe)
-        .inner_join(recipes)
-        .load::<(Rule, Recipe)>(&conn)?;
-    let rules: Vec<Rule> = rules.into_iter().map(|(rule, _)| rule).collect();
+/// Finds the recipes whose URL template matches the request, along with their rules and the
+/// values captured from the request path; the scheme and host, `base`, narrow the query and the
+/// templates are then matched against the path.
+pub(super) fn find_recipe_by_url(
+    db: &DbPool,
+    owner: &str,
+    base: &str,
+    path: &str,
+) -> Result<Vec<(Recipe, Vec<Rule>, PathParams)>> {
+    let recipes = db.find_served_recipes(owner, base)?;
+
+    // the query only narrows by the scheme and host, here each recipe's template is matched
+    // against the path of the request, keeping the values it captures for the rules
+    let mut recipes: Vec<(Vec<u8>, Recipe, Vec<Rule>, PathParams)> = recipes
+        .into_iter()
+        .filter_map(|(recipe, rules)| {
+            let template = UrlTemplate::try_from(recipe.url.as_str())
+                .map_err(|error| warn!("Skipping recipe, {}: {}", recipe.id, error))
+                .ok()?;
+            if template.base() != base {
+                return None;
+            }
+            let params = template.captures(path)?;
+            Some((template.specificity(), recipe, rules, params))
+        })
+        .collect();
+
+    // sort by priority, then by the specificity of the template, then by the number of rules, all
+    // descending, and finally oldest first so that the order is the same for every call
+    recipes.sort_by(|(a_template, a, a_rules, _), (b_template, b, b_rules, _)| {
+        b.priority
+            .cmp(&a.priority)
+            .then_with(|| b_template.cmp(a_template))
+            .then_with(|| b_rules.len().cmp(&a_rules.len()))
+            .then_with(|| a.created_at.cmp(&b.created_at))
+    });
+    Ok(recipes
+        .into_iter()
+        .map(|(_, recipe, rules, params)| (recipe, rules, params))
+        .collect())
+}
 
-    Ok((recipe, rules))
+/// The URLs of every recipe, other than drafts, for a scheme and host, `base`, to suggest when a
+/// call matches none of them.
+pub(super) fn find_recipe_urls(db: &DbPool, owner: &str, base: &str) -> Result<Vec<String>> {
+    db.find_recipe_urls(owner, base)
 }
 
-pub(super) fn find_recipe_by_url<S: AsRef<str>>(
+/// The recipes with exactly this URL, along with their rules and headers, in the order they are
+/// tried.
+pub(super) fn find_recipes_for_url(
     db: &DbPool,
-    to_find: S,
-) -> Result<Vec<(Recipe, Vec<Rule>)>> {
-    use crate::schema::{recipes, rules};
+    owner: &str,
+    to_find: &str,
+) -> Result<Vec<RecipeCascaded>> {
+    let found = db.find_recipes_with_url(owner, to_find)?;
+
+    let mut found = db.cascade(found)?;
+    // same order they are tried in, the sort is stable so creation time still breaks ties
+    found.sort_by(
+        |RecipeCascaded(a, a_rules, ..), RecipeCascaded(b, b_rules, ..)| {
+            b.priority
+                .cmp(&a.priority)
+                .then_with(|| b_rules.len().cmp(&a_rules.len()))
+        },
+    );
+    Ok(found)
+}
 
-    let conn = db.get()?;
+/// Every recipe in the namespace, along with its rules, headers and responses, in order by URL and
+/// then in the order they are tried, except those loaded from files, which are already saved.
+pub(super) fn export_recipes(db: &DbPool, owner: &str) -> Result<Vec<RecipeCascaded>> {
+    let found = db.export_recipes(owner)?;
 
-    let joined: Vec<(Recipe, Option<Rule>)> = recipes::dsl::recipes
-        .filter(recipes::dsl::url.eq(to_find.as_ref()))
-        .left_join(rules::dsl::rules)
-        .load::<(Recipe, Option<Rule>)>(&conn)?;
+    db.cascade(found)
+}
 
-    // the query returns a denormalized Vec, meaning that while the rule have of each tuple is
-    // distinct, the associated recipe may be repeated; unzip here to re-normalize before returning
-    let (mut recipes, rules): (Vec<_>, Vec<_>) = joined.into_iter().unzip();
+/// The recipes in the namespace with these IDs, along with their rules, headers and responses, in
+/// the same order as the IDs.
+pub(super) fn find_cascaded(db: &DbPool, owner: &str, ids: &[Uuid]) -> Result<Vec<RecipeCascaded>> {
+    let mut found = db.find_recipes(owner, ids)?;
+    found.sort_by_key(|recipe| ids.iter().position(|to_find| *to_find == recipe.id));
 
-    // sort followed by deduplicating yields a Vec of recipes that are unique by ID
-    recipes.sort_by_key(|recipe| recipe.id);
-    recipes.dedup_by_key(|recipe| recipe.id);
+    db.cascade(found)
+}
 
-    // this fold produces the result Vec who members are a tuple containing each recipe and its
-    // associated rules
-    let (mut recipes, _) = recipes
-        .into_iter()
-        // the accumulator is a tuple, the left half is the result Vec containing tuples of recipes
-        // and their related rules, the right half is the leftover unprocessed rules, if any
-        .fold((Vec::new(), rules), move |acc, recipe| {
-            let (mut recipes, rules) = acc;
+pub(super) fn find_recipes(db: &DbPool, owner: &str, ids: &[Uuid]) -> Result<Vec<Recipe>> {
+    db.find_recipes(owner, ids)
+}
 
-            // partitioning on the ID means the left half of the resulting tuple is only rules with
-            // this iterations recipe ID; the right half is the remaining rules which we pass along
-            // in the accumulator
-            let (for_recipe, rules): (Vec<Option<_>>, Vec<Option<_>>) =
-                rules.into_iter().partition(|rule| {
-                    if let Some(rule) = rule {
-                        rule.recipe_id == recipe.id
-                    } else {
-                        false
-                    }
-                });
-            let for_recipe: Vec<Rule> = for_recipe.into_iter().filter_map(|rule| rule).collect();
+/// Gives the recipes descending priorities in the order of `ids`, so the first is tried first.
+pub(super) fn prioritize_recipes(db: &DbPool, owner: &str, ids: &[Uuid]) -> Result<usize> {
+    db.prioritize_recipes(owner, ids)
+}
 
-            // push a new tuple for this iteration and the rules we matched to the recipe
-            recipes.push((recipe, for_recipe));
+/// Runs changes in a single transaction, so that if any of them fails, none of them are kept.
+pub(super) fn in_transaction<T>(
+    db: &DbPool,
+    changes: impl FnOnce(&dyn Storage) -> Result<T>,
+) -> Result<T> {
+    let mut changes = Some(changes);
+    let mut changed = None;
+    db.transaction(&mut |conn| {
+        if let Some(changes) = changes.take() {
+            changed = Some(changes(conn)?);
+        }
+        Ok(())
+    })?;
+    changed.ok_or_else(|| format_err!("The changes were never made!"))
+}
 
-            // return the accumulator to keep working
-            (recipes, rules)
-        });
+/// Creates a recipe with the given ID, as when restoring one, or otherwise a new ID.
+pub(super) fn create_recipe(
+    conn: &dyn Storage,
+    owner: &str,
+    to_id: Option<Uuid>,
+    to_create: NewRecipe,
+    disruption: NewDisruption,
+    from_file: Option<String>,
+) -> Result<Recipe> {
+    conn.create_recipe(owner, to_id, to_create, disruption, from_file)
+}
 
-    // sort by the number of rules
-    recipes.sort_by_key(|(_, rules)| rules.len());
-    // descending so the most specific is evaluated first
-    recipes.reverse();
-    Ok(recipes)
+pub(super) fn create_rules(conn: &dyn Storage, to_create: &[NewRule]) -> Result<Vec<Rule>> {
+    conn.create_rules(to_create)
 }
 
-pub(super) fn create_recipe(db: &DbPool, to_create: NewRecipe) -> Result<Recipe> {
-    use crate::schema::recipes;
+pub(super) fn delete_recipe(conn: &dyn Storage, owner: &str, to_delete: Uuid) -> Result<usize> {
+    // a recipe in another namespace is left alone, along with its rules, headers and responses
+    if !owns_recipe(conn, owner, to_delete)? {
+        return Ok(0);
+    }
+
+    delete_rules(conn, to_delete, &Vec::new())?;
+    replace_headers(conn, to_delete, &Vec::new())?;
+    replace_responses(conn, to_delete, &Vec::new())?;
+
+    conn.delete_recipe(owner, to_delete)
+}
+
+pub(super) fn owns_recipe(conn: &dyn Storage, owner: &str, to_find: Uuid) -> Result<bool> {
+    conn.owns_recipe(owner, to_find)
+}
+
+/// The namespace that owns the recipe with this ID, in case there is one.
+pub(super) fn find_recipe_namespace(conn: &dyn Storage, to_find: Uuid) -> Result<Option<String>> {
+    conn.find_recipe_namespace(to_find)
+}
 
-    let conn = db.get()?;
+/// The file that the recipe in the namespace is loaded from, if it is loaded from one.
+pub(super) fn find_recipe_file(
+    conn: &dyn Storage,
+    owner: &str,
+    to_find: Uuid,
+) -> Result<Option<String>> {
+    conn.find_recipe_file(owner, to_find)
+}
 
-    diesel::insert_into(recipes::table)
-        .values(to_create)
-        .get_result(&conn)
-        .map_err(anyhow::Error::from)
+/// Every recipe loaded from a file, in any namespace, along with its namespace.
+pub(super) fn find_file_recipes(conn: &dyn Storage) -> Result<Vec<(Uuid, String)>> {
+    conn.find_file_recipes()
 }
 
-pub(super) fn create_rules(db: &DbPool, to_create: &[NewRule]) -> Result<Vec<Rule>> {
-    use crate::schema::rules;
+pub(super) fn find_rule_ids(conn: &dyn Storage, parent: Uuid) -> Result<Vec<Uuid>> {
+    conn.find_rule_ids(parent)
+}
 
-    let conn = db.get()?;
+pub(super) fn delete_rules(conn: &dyn Storage, parent: Uuid, to_retain: &[Rule]) -> Result<usize> {
+    conn.delete_rules(parent, to_retain)
+}
 
-    diesel::insert_into(rules::table)
-        .values(to_create)
-        .get_results(&conn)
-        .map_err(anyhow::Error::from)
+pub(super) fn replace_headers(
+    conn: &dyn Storage,
+    parent: Uuid,
+    to_create: &[NewResponseHeader],
+) -> Result<Vec<ResponseHeader>> {
+    conn.replace_headers(parent, to_create)
 }
 
-pub(super) fn delete_recipe(db: &DbPool, to_delete: Uuid) -> Result<usize> {
-    use crate::schema::recipes::dsl::*;
+pub(super) fn replace_responses(
+    conn: &dyn Storage,
+    parent: Uuid,
+    to_create: &[NewRecipeResponse],
+) -> Result<Vec<RecipeResponse>> {
+    conn.replace_responses(parent, to_create)
+}
 
-    delete_rules(db, to_delete, &Vec::new())?;
+pub(super) fn update_rules(conn: &dyn Storage, to_update: Vec<Rule>) -> Result<usize> {
+    conn.update_rules(to_update)
+}
 
-    let conn = db.get()?;
+/// Updates a recipe and, when loading one from a file, the file it is loaded from, in case it has
+/// moved.
+pub(super) fn update_recipe(
+    conn: &dyn Storage,
+    owner: &str,
+    to_update: Uuid,
+    changes: NewRecipe,
+    disruption: NewDisruption,
+    from_file: Option<String>,
+) -> Result<usize> {
+    conn.update_recipe(owner, to_update, changes, disruption, from_file)
+}
 
-    diesel::delete(recipes.filter(id.eq(to_delete)))
-        .execute(&conn)
-        .map_err(anyhow::Error::from)
+pub(super) fn create_journal_entry(
+    db: &DbPool,
+    to_create: NewJournalEntry,
+    headers: Vec<shared::Header>,
+) -> Result<Uuid> {
+    db.create_journal_entry(to_create, headers)
 }
 
-pub(super) fn delete_rules(db: &DbPool, parent: Uuid, to_retain: &[Rule]) -> Result<usize> {
-    use crate::schema::rules::dsl::*;
+/// Loads a page of the journal entries that match the filter, oldest first, along with the total
+/// number of entries that match.
+pub(super) fn find_journal_entries(
+    db: &DbPool,
+    owner: &str,
+    filter: &shared::JournalFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<(i64, Vec<JournalCascaded>)> {
+    db.find_journal_entries(owner, filter, offset, limit)
+}
 
-    let conn = db.get()?;
+pub(super) fn count_journal_entries(
+    db: &DbPool,
+    owner: &str,
+    filter: &shared::JournalFilter,
+) -> Result<i64> {
+    db.count_journal_entries(owner, filter)
+}
 
-    let ids: Vec<Uuid> = to_retain.iter().map(|rule| rule.id).collect();
+/// Deletes every journal entry in the namespace; their headers are deleted along with them, and
+/// sequences start over.
+pub(super) fn clear_journal(db: &DbPool, owner: &str) -> Result<usize> {
+    db.clear_journal(owner)
+}
 
-    diesel::delete(rules.filter(recipe_id.eq(parent).and(id.ne_all(ids))))
-        .execute(&conn)
-        .map_err(anyhow::Error::from)
+/// Counts one more serving of the recipe, giving how many times it had been served since the
+/// journal was last cleared.
+pub(super) fn claim_served(db: &DbPool, to_serve: Uuid) -> Result<i64> {
+    db.claim_served(to_serve)
 }
 
-pub(super) fn update_rules(db: &DbPool, to_update: Vec<Rule>) -> Result<usize> {
-    use crate::schema::rules::dsl::*;
+/// How many calls with the method have been journaled for the path on the scheme and host,
+/// `base`, whatever their query, since the journal was last cleared.
+pub(super) fn count_calls(
+    db: &DbPool,
+    owner: &str,
+    to_match: &str,
+    base: &str,
+    called: &str,
+) -> Result<i64> {
+    db.count_calls(owner, to_match, base, called)
+}
 
-    let conn = db.get()?;
+/// The states of the named scenarios that have left their starting state, by name.
+pub(super) fn find_scenario_states(
+    db: &DbPool,
+    owner: &str,
+    names: &[String],
+) -> Result<HashMap<String, String>> {
+    db.find_scenario_states(owner, names)
+}
 
-    let mut count = 0;
-    for rule in to_update {
-        count += diesel::update(rules.find(rule.id))
-            .set((
-                rule_type.eq(rule.rule_type),
-                subject.eq(rule.subject),
-                key_path.eq(rule.key_path),
-            ))
-            .execute(&conn)?;
-    }
-    Ok(count)
+pub(super) fn set_scenario_state(db: &DbPool, to_change: NewScenario) -> Result<usize> {
+    db.set_scenario_state(to_change)
 }
 
-pub(super) fn update_recipe(
+/// Every scenario named by a recipe, with the states the recipe requires and moves to, along with
+/// the scenarios that have left their starting state.
+pub(super) fn load_scenarios(
     db: &DbPool,
-    to_update: Uuid,
-    changed_url: String,
-    changed_payload: String,
-) -> Result<usize> {
-    use crate::schema::recipes::dsl::*;
+    owner: &str,
+) -> Result<(Vec<ScenarioStep>, Vec<Scenario>)> {
+    db.load_scenarios(owner)
+}
 
-    let conn = db.get()?;
+/// Moves one scenario, or every scenario in the namespace if no name is given, back to its
+/// starting state.
+pub(super) fn reset_scenarios(db: &DbPool, owner: &str, to_reset: Option<&str>) -> Result<usize> {
+    db.reset_scenarios(owner, to_reset)
+}
+
+/// Every namespace that owns a recipe, in order by name, with how many recipes it owns.
+pub(super) fn load_namespaces(db: &DbPool) -> Result<Vec<(String, i64)>> {
+    db.load_namespaces()
+}
 
-    let count = diesel::update(recipes.find(to_update))
-        .set((url.eq(changed_url), payload.eq(changed_payload)))
-        .execute(&conn)?;
-    Ok(count)
+/// Deletes everything the namespace owns, its recipes, along with their rules, headers and
+/// responses, its journal and its scenarios, all at once, returning how many recipes were deleted.
+pub(super) fn delete_namespace(db: &DbPool, owner: &str) -> Result<usize> {
+    db.delete_namespace(owner)
 }
@@ -0,0 +1,156 @@
+use crate::models::{RuleOutcome, UrlTemplate};
+use serde::Serialize;
+use std::{
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+use uuid::Uuid;
+
+/// A call may send this header, with any value, to also get the diagnostics for a miss as JSON in
+/// the `DIAGNOSTICS_HEADER` of the response.
+pub(super) const DEBUG_HEADER: &str = "X-Empholite-Debug";
+pub(super) const DIAGNOSTICS_HEADER: &str = "X-Empholite-Diagnostics";
+
+/// The most similar URLs suggested for a miss.
+const SIMILAR_LIMIT: usize = 3;
+/// URLs that would need more changes than this to match a call aren't worth suggesting.
+const SIMILAR_DISTANCE: usize = 3;
+
+/// Why a call matched no recipe: the recipes whose URL matched but whose rules did not or, if
+/// there were none, the recipes with the most similar URLs.
+#[derive(Debug, Serialize)]
+pub(super) struct Diagnostics {
+    url: String,
+    candidates: Vec<Candidate>,
+    similar: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct Candidate {
+    recipe_id: Uuid,
+    url: String,
+    failed: Vec<RuleOutcome>,
+}
+
+impl Candidate {
+    pub(super) fn new(recipe_id: Uuid, url: String, failed: Vec<RuleOutcome>) -> Self {
+        Self {
+            recipe_id,
+            url,
+            failed,
+        }
+    }
+}
+
+impl Diagnostics {
+    pub(super) fn new(url: String, candidates: Vec<Candidate>) -> Self {
+        Self {
+            url,
+            candidates,
+            similar: Vec::new(),
+        }
+    }
+
+    pub(super) fn has_candidates(&self) -> bool {
+        !self.candidates.is_empty()
+    }
+
+    /// Keeps the recipe URLs closest to the path of the call, closest first.
+    pub(super) fn suggest(&mut self, urls: Vec<String>, path: &str) {
+        let mut similar: Vec<(usize, String)> = urls
+            .into_iter()
+            .filter_map(|url| {
+                let distance = UrlTemplate::try_from(url.as_str()).ok()?.distance(path);
+                if distance <= SIMILAR_DISTANCE {
+                    Some((distance, url))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        similar.sort();
+        self.similar = similar
+            .into_iter()
+            .take(SIMILAR_LIMIT)
+            .map(|(_, url)| url)
+            .collect();
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Could not find a recipe for requested URI, {}", self.url)?;
+        for candidate in &self.candidates {
+            write!(
+                f,
+                "\n\nThe recipe, {}, for {} did not match:",
+                candidate.recipe_id, candidate.url
+            )?;
+            for outcome in &candidate.failed {
+                write!(f, "\n* {}, found {}", outcome.rule, outcome.found)?;
+            }
+        }
+        if !self.similar.is_empty() {
+            write!(f, "\n\nRecipes with similar URLs:")?;
+            for url in &self.similar {
+                write!(f, "\n* {}", url)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let recipe_id = Uuid::new_v4();
+        let diagnostics = Diagnostics::new(
+            "http://test.local/api/users/42".to_owned(),
+            vec![Candidate::new(
+                recipe_id,
+                "http://test.local/api/users/{id}".to_owned(),
+                vec![RuleOutcome {
+                    rule: "HTTP method is POST".to_owned(),
+                    passed: false,
+                    found: "GET".to_owned(),
+                }],
+            )],
+        );
+        assert_eq!(
+            format!(
+                "Could not find a recipe for requested URI, http://test.local/api/users/42\n\n\
+                 The recipe, {}, for http://test.local/api/users/{{id}} did not match:\n\
+                 * HTTP method is POST, found GET",
+                recipe_id
+            ),
+            diagnostics.to_string()
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        let mut diagnostics =
+            Diagnostics::new("http://test.local/api/user/42".to_owned(), Vec::new());
+        assert!(!diagnostics.has_candidates());
+        diagnostics.suggest(
+            vec![
+                "http://test.local/api/orders/{id}/items".to_owned(),
+                "http://test.local/api/users/{id}".to_owned(),
+                "http://test.local/api/reports/daily/summary/all".to_owned(),
+                "http://test.local/api/users/{id}/orders".to_owned(),
+            ],
+            "/api/user/42",
+        );
+        assert_eq!(
+            vec![
+                "http://test.local/api/users/{id}".to_owned(),
+                "http://test.local/api/orders/{id}/items".to_owned(),
+                "http://test.local/api/users/{id}/orders".to_owned(),
+            ],
+            diagnostics.similar
+        );
+    }
+}
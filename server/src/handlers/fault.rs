@@ -0,0 +1,161 @@
+use actix_rt::time::delay_for;
+use actix_web::{
+    dev::{Body, HttpResponseBuilder, SizedStream},
+    error::ErrorServiceUnavailable,
+    web::Bytes,
+    Error, HttpResponse,
+};
+use futures::{
+    future,
+    stream::{self, StreamExt},
+};
+use std::time::Duration;
+
+/// How long to hold the connection open after a partial payload, long enough for it to be sent.
+const RESET_AFTER_MILLIS: u64 = 100;
+
+/// Breaks the response instead of sending the payload whole; the connection is dropped by failing
+/// the payload's stream, since the server closes a connection whose payload fails.
+pub(super) fn break_response(
+    mut response: HttpResponseBuilder,
+    fault: &shared::Fault,
+    payload: Vec<u8>,
+) -> HttpResponse {
+    use shared::Fault::*;
+    match *fault {
+        // the status and headers are only sent along with the first chunk, so failing before it
+        // means nothing at all is sent
+        ConnectionReset => {
+            response.streaming(stream::once(future::ready(Err::<Bytes, _>(reset()))))
+        }
+        PartialThenReset => {
+            let size = payload.len() as u64;
+            let mut payload = Bytes::from(payload);
+            let partial = payload.split_to(payload.len() / 2);
+            // waiting before failing lets the partial payload be sent; the size promises the
+            // whole payload so the client can tell that it was cut short
+            let chunks = stream::once(async { Ok(partial) }).chain(stream::once(async {
+                delay_for(Duration::from_millis(RESET_AFTER_MILLIS)).await;
+                Err(reset())
+            }));
+            response.body(Body::from_message(SizedStream::new(
+                size,
+                chunks.boxed_local(),
+            )))
+        }
+        Trickle {
+            chunk_bytes,
+            interval_millis,
+        } => {
+            let interval = Duration::from_millis(u64::from(interval_millis));
+            let chunks: Vec<Bytes> = payload
+                .chunks(chunk_bytes.max(1) as usize)
+                .map(Bytes::copy_from_slice)
+                .collect();
+            let chunks = stream::iter(chunks.into_iter().enumerate()).then(
+                move |(index, chunk)| async move {
+                    if index > 0 {
+                        delay_for(interval).await;
+                    }
+                    Ok::<_, Error>(chunk)
+                },
+            );
+            response.streaming(chunks.boxed_local())
+        }
+        EmptyReply => response.finish(),
+    }
+}
+
+fn reset() -> Error {
+    ErrorServiceUnavailable("The recipe's fault dropped the connection")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::{
+        dev::{BodySize, MessageBody, ResponseBody},
+        http::StatusCode,
+    };
+    use anyhow::{anyhow, bail, Result};
+    use shared::Fault;
+    use std::time::Instant;
+
+    fn broken(fault: Fault, payload: &[u8]) -> ResponseBody<Body> {
+        let mut response = break_response(
+            HttpResponse::build(StatusCode::OK),
+            &fault,
+            payload.to_vec(),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        response.take_body()
+    }
+
+    /// Reads the next chunk of the body, failing if there isn't one or it's an error.
+    async fn chunk(body: &mut ResponseBody<Body>) -> Result<Bytes> {
+        match body.next().await {
+            Some(chunk) => chunk.map_err(|error| anyhow!("{}", error)),
+            None => bail!("The body ended early!"),
+        }
+    }
+
+    /// Reads the whole body a chunk at a time, failing if any chunk is an error.
+    async fn read(mut body: ResponseBody<Body>) -> Result<Vec<Bytes>> {
+        let mut chunks = vec![];
+        while let Some(chunk) = body.next().await {
+            chunks.push(chunk.map_err(|error| anyhow!("{}", error))?);
+        }
+        Ok(chunks)
+    }
+
+    #[actix_rt::test]
+    async fn test_connection_reset() -> Result<()> {
+        let mut body = broken(Fault::ConnectionReset, b"hello");
+        assert!(body.next().await.expect("a chunk").is_err());
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_partial_then_reset() -> Result<()> {
+        let mut body = broken(Fault::PartialThenReset, b"0123456789");
+        assert_eq!(body.size(), BodySize::Sized(10));
+        assert_eq!(chunk(&mut body).await?, "01234");
+        assert!(body.next().await.expect("a chunk").is_err());
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_trickle() -> Result<()> {
+        let started = Instant::now();
+        let body = broken(
+            Fault::Trickle {
+                chunk_bytes: 4,
+                interval_millis: 20,
+            },
+            b"0123456789",
+        );
+        let chunks = read(body).await?;
+        assert_eq!(chunks, vec!["0123", "4567", "89"]);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+
+        // a chunk size of nothing would never send the payload, so it's sent a byte at a time
+        let body = broken(
+            Fault::Trickle {
+                chunk_bytes: 0,
+                interval_millis: 0,
+            },
+            b"abc",
+        );
+        let chunks = read(body).await?;
+        assert_eq!(chunks, vec!["a", "b", "c"]);
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_empty_reply() -> Result<()> {
+        let mut body = broken(Fault::EmptyReply, b"hello");
+        assert_eq!(body.size(), BodySize::Empty);
+        assert!(body.next().await.is_none());
+        Ok(())
+    }
+}
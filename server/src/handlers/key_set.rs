@@ -0,0 +1,44 @@
+use crate::{
+    config::KeySetSource,
+    models::{issuer_key_set, KeySet},
+};
+use actix_web::{client::Client, web};
+use anyhow::{bail, format_err, Context, Result};
+use std::fs;
+
+/// The largest JWKS accepted from a URL.
+const KEY_SET_LIMIT: usize = 1024 * 1024;
+
+/// Loads the JWKS that tokens are verified against as it is now, so that keys can be added or
+/// rotated while the server runs.
+pub(super) async fn load_key_set(source: &KeySetSource) -> Result<KeySet> {
+    let document = match source {
+        KeySetSource::Issuer => return Ok(issuer_key_set()),
+        KeySetSource::File(path) => {
+            let path = path.clone();
+            web::block(move || fs::read(&path))
+                .await
+                .map_err(|error| format_err!("Could not read the JWKS: {}", error))?
+        }
+        KeySetSource::Url(url) => {
+            let mut response =
+                Client::default().get(url).send().await.map_err(|error| {
+                    format_err!("Could not fetch the JWKS from {}: {}", url, error)
+                })?;
+            if !response.status().is_success() {
+                bail!(
+                    "Fetching the JWKS from {} returned {}!",
+                    url,
+                    response.status()
+                );
+            }
+            response
+                .body()
+                .limit(KEY_SET_LIMIT)
+                .await
+                .map_err(|error| format_err!("Could not fetch the JWKS from {}: {}", url, error))?
+                .to_vec()
+        }
+    };
+    serde_json::from_slice(&document).context("The JWKS is not valid!")
+}
@@ -1,23 +1,77 @@
 pub(crate) mod ajax;
 mod db;
+mod diagnostics;
+mod fault;
+mod key_set;
+pub(crate) mod namespace;
+pub(crate) mod oidc;
+mod proxy;
+pub(crate) mod recipe_files;
 pub(crate) mod rest;
 
-use crate::{models::Recipe, DbPool};
+use self::{
+    db::ScenarioStep,
+    diagnostics::{Candidate, Diagnostics, DEBUG_HEADER, DIAGNOSTICS_HEADER},
+    namespace::Namespace,
+};
+use crate::{
+    config::{self, KeyPathKind},
+    models::{
+        extract_subject, NewJournalEntry, NewRecipeResponse, NewResponseHeader, NewRule,
+        NewScenario, Recipe, RecipeCascaded, RecipeChanges, RequestContext, Rule, RuleType,
+        Scenario, SequenceMode,
+    },
+    storage::Storage,
+    DbPool,
+};
+use actix_rt::time::delay_for;
 use actix_web::{
-    error::ErrorInternalServerError,
-    web::{self, Data},
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    http::StatusCode,
+    web::{self, Bytes, Data},
     HttpRequest, HttpResponse, Result,
 };
-use diesel::prelude::*;
-use log::{debug, trace};
+use anyhow::{bail, format_err};
+use log::{debug, error, trace};
 use serde_json::{json, Value};
-use std::convert::TryInto;
+use shared::SCENARIO_STARTED;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::{TryFrom, TryInto},
+};
+use uuid::Uuid;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const DEFAULT_OFFSET: i64 = 0;
 const DEFAULT_LIMIT: i64 = 25;
 
-pub(crate) async fn serve_recipe(request: HttpRequest, db: Data<DbPool>) -> Result<HttpResponse> {
+pub(crate) async fn serve_recipe(
+    request: HttpRequest,
+    body: Bytes,
+    db: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
+    let served = respond(&request, &body, db.clone(), &namespace).await;
+    let (status, recipe_id) = match &served {
+        Ok((response, recipe_id)) => (response.status(), *recipe_id),
+        Err(error) => (error.as_response_error().status_code(), None),
+    };
+    let (entry, headers) = journal_entry(&request, &body, recipe_id, status, &namespace);
+    // a call should be answered even if it couldn't be journaled
+    if let Err(error) = web::block(move || db::create_journal_entry(&db, entry, headers)).await {
+        error!("Could not journal a call: {}", error);
+    }
+    served.map(|(response, _)| response)
+}
+
+/// Serves the best recipe for the request, along with its ID, or forwards the request if its host
+/// is proxied, or else responds with a 404.
+async fn respond(
+    request: &HttpRequest,
+    body: &Bytes,
+    db: Data<DbPool>,
+    namespace: &Namespace,
+) -> Result<(HttpResponse, Option<Uuid>)> {
     let cx_info = request.connection_info();
     let scheme = cx_info.scheme();
     let host = cx_info.host();
@@ -25,65 +79,278 @@ pub(crate) async fn serve_recipe(request: HttpRequest, db: Data<DbPool>) -> Resu
     trace!("Host {:?}", host);
     let uri = request.uri();
     trace!("URI {:?}", uri);
-    let key = format!(
-        "{}://{}{}",
-        request.connection_info().scheme(),
-        host,
-        uri.path_and_query()
-            .map(|pq| pq.as_str())
-            .unwrap_or_else(|| "")
-    );
+    let key = request_url(request);
     debug!("Recipe key {}", key);
-    let to_find = key.clone();
-    let recipes = web::block(move || db::find_recipe_by_url(&db, to_find))
+    let upstream = config::UPSTREAMS.get(host).cloned();
+    let base = format!("{}://{}", scheme, host);
+    let path = namespace::unprefixed(request, uri.path()).to_owned();
+    let db_pool = db.clone();
+    let (owner, to_find, to_match) = (namespace.clone(), base.clone(), path.clone());
+    // the query isn't part of a recipe's URL, its parameters are matched by rules instead
+    let candidates =
+        web::block(move || db::find_recipe_by_url(&db_pool, &owner, &to_find, &to_match))
+            .await
+            .map_err(ErrorInternalServerError)?;
+    let names: Vec<String> = candidates
+        .iter()
+        .filter_map(|(recipe, _, _)| recipe.scenario.clone())
+        .collect();
+    let states = if names.is_empty() {
+        HashMap::new()
+    } else {
+        let (db_pool, owner) = (db.clone(), namespace.clone());
+        web::block(move || db::find_scenario_states(&db_pool, &owner, &names))
+            .await
+            .map_err(ErrorInternalServerError)?
+    };
+    let ids: Vec<Uuid> = candidates.iter().map(|(recipe, _, _)| recipe.id).collect();
+    let sequenced = if ids.is_empty() {
+        HashMap::new()
+    } else {
+        let db_pool = db.clone();
+        web::block(move || db::count_responses(&db_pool, &ids))
+            .await
+            .map_err(ErrorInternalServerError)?
+    };
+    // calls are journaled after they are answered, so this call follows those already journaled
+    let call_number = if candidates.iter().any(|(_, rules, _)| {
+        rules
+            .iter()
+            .any(|rule| matches!(rule.rule_type, RuleType::NthCall))
+    }) {
+        let (db_pool, owner) = (db.clone(), namespace.clone());
+        let (method, to_count) = (request.method().to_string(), base.clone());
+        let called = path.clone();
+        web::block(move || db::count_calls(&db_pool, &owner, &method, &to_count, &called))
+            .await
+            .map_err(ErrorInternalServerError)?
+            + 1
+    } else {
+        1
+    };
+    // the JWKS is loaded for each call that may need it, so that its keys can change while the
+    // server runs
+    let key_set = match &*config::KEY_PATH_KIND {
+        KeyPathKind::Jwks(source)
+            if candidates.iter().any(|(_, rules, _)| {
+                rules
+                    .iter()
+                    .any(|rule| matches!(rule.rule_type, RuleType::Authenticated))
+            }) =>
+        {
+            Some(
+                key_set::load_key_set(source)
+                    .await
+                    .map_err(ErrorInternalServerError)?,
+            )
+        }
+        _ => None,
+    };
+    // candidates are in order of preference so the first to match is served; the rules of those
+    // that don't match are kept to explain a miss
+    let mut matched = None;
+    let mut misses = Vec::new();
+    for (recipe, rules, path_params) in candidates {
+        let context = RequestContext {
+            request,
+            path_params: &path_params,
+            body,
+            call_number,
+            key_set: key_set.as_ref(),
+        };
+        let scenario_state = recipe
+            .scenario
+            .as_ref()
+            .and_then(|scenario| states.get(scenario))
+            .map(String::as_str);
+        let evaluation = recipe
+            .evaluate_rules(&rules, &context, scenario_state)
+            .map_err(ErrorInternalServerError)?;
+        if !evaluation.matched() {
+            misses.push(Candidate::new(recipe.id, recipe.url, evaluation.failures()));
+            continue;
+        }
+        let sequenced = sequenced.get(&recipe.id).copied().unwrap_or_default();
+        // only a recipe with a sequence to step through, or to fall through, needs its serving
+        // counted; the call claims its step here, before any delay, so calls made at the same
+        // time each get a step of their own
+        if sequenced == 0 && recipe.sequence_mode != SequenceMode::FallThrough {
+            matched = Some((recipe, path_params, 0));
+            break;
+        }
+        let (db_pool, recipe_id) = (db.clone(), recipe.id);
+        let served = web::block(move || db::claim_served(&db_pool, recipe_id))
+            .await
+            .map_err(ErrorInternalServerError)?;
+        if let Some(index) = recipe.next_response(served, sequenced) {
+            matched = Some((recipe, path_params, index));
+            break;
+        }
+        let exhausted = recipe.exhausted(served, sequenced);
+        misses.push(Candidate::new(recipe.id, recipe.url, vec![exhausted]));
+    }
+    if let Some((recipe, path_params, index)) = matched {
+        let recipe_id = recipe.id;
+        let delay = recipe.sample_delay().map_err(ErrorInternalServerError)?;
+        let fault = recipe.fault().map_err(ErrorInternalServerError)?;
+        let transition = match (&recipe.scenario, &recipe.new_state) {
+            (Some(name), Some(state)) => Some(NewScenario {
+                name: name.clone(),
+                state: state.clone(),
+                namespace: recipe.namespace.clone(),
+            }),
+            _ => None,
+        };
+        let (headers, mut responses) = web::block(move || {
+            // serving the recipe moves its scenario along, for the calls that follow
+            if let Some(transition) = transition {
+                db::set_scenario_state(&db, transition)?;
+            }
+            let responses = if index > 0 {
+                db::find_responses(&db, recipe_id)?
+            } else {
+                Vec::new()
+            };
+            db::find_headers(&db, recipe_id).map(|headers| (headers, responses))
+        })
         .await
         .map_err(ErrorInternalServerError)?;
-    let recipes = recipes
-        .into_iter()
-        .map(|(recipe, rules)| recipe.evaluate_rules(&rules, &request))
-        // in order for collect to transpose Vec and Result we need the right hint, here, that
-        // matches the T and E generic arguments returned by the closure in the map in the line
-        // above
-        .collect::<anyhow::Result<Vec<Option<String>>>>()
-        .map_err(ErrorInternalServerError)?
-        // filter map after map_err and ? so that any short circuiting errors bubble out; the
-        // result of the remaining chain calls is a Vec of valid, matching recipes
-        .into_iter()
-        .filter_map(|payload| payload)
-        // due to the extended chaining, the compiler needs more help inferring the final type of
-        // the whole expression
-        .collect::<Vec<String>>();
-    if let Some(payload) = recipes.first() {
-        Ok(HttpResponse::Ok().body(payload))
+        let context = RequestContext {
+            request,
+            path_params: &path_params,
+            body,
+            call_number,
+            key_set: key_set.as_ref(),
+        };
+        // the recipe's own response comes first, then those in its sequence
+        let (status_code, content_type, payload) = match usize::try_from(index - 1)
+            .ok()
+            .filter(|index| *index < responses.len())
+        {
+            Some(index) => {
+                let response = responses.swap_remove(index);
+                let payload = response.render_payload(&context);
+                (response.status_code, response.content_type, payload)
+            }
+            None => {
+                let payload = recipe.render_payload(&context);
+                (recipe.status_code, recipe.content_type, payload)
+            }
+        };
+        let payload = payload.map_err(ErrorInternalServerError)?;
+        let status = u16::try_from(status_code)
+            .map_err(anyhow::Error::from)
+            .and_then(|status| StatusCode::from_u16(status).map_err(anyhow::Error::from))
+            .map_err(ErrorInternalServerError)?;
+        let mut response = HttpResponse::build(status);
+        // a content type in the recipe's headers takes precedence over its content type
+        if !headers
+            .iter()
+            .any(|header| header.name.eq_ignore_ascii_case("content-type"))
+        {
+            response.content_type(content_type.as_str());
+        }
+        for header in headers {
+            // headers are appended, rather than set, so that repeated names are all sent
+            response.header(header.name.as_str(), header.value);
+        }
+        if let Some(delay) = delay {
+            delay_for(delay).await;
+        }
+        let response = match fault {
+            Some(fault) => fault::break_response(response, &fault, payload),
+            None => response.body(payload),
+        };
+        Ok((response, Some(recipe_id)))
+    } else if let Some(upstream) = upstream {
+        let url = format!("{}{}", base, path);
+        proxy::proxy(request, body.clone(), &upstream, url, db, namespace.clone())
+            .await
+            .map(|response| (response, None))
     } else {
-        Ok(HttpResponse::NotFound().body(format!(
-            "Could not find a recipe for requested URI, {}",
-            key
-        )))
+        let mut diagnostics = Diagnostics::new(key, misses);
+        if !diagnostics.has_candidates() {
+            let owner = namespace.clone();
+            let urls = web::block(move || db::find_recipe_urls(&db, &owner, &base))
+                .await
+                .map_err(ErrorInternalServerError)?;
+            diagnostics.suggest(urls, &path);
+        }
+        let mut response = HttpResponse::NotFound();
+        if request.headers().contains_key(DEBUG_HEADER) {
+            let json = serde_json::to_string(&diagnostics).map_err(ErrorInternalServerError)?;
+            response.header(DIAGNOSTICS_HEADER, json);
+        }
+        Ok((response.body(diagnostics.to_string()), None))
     }
 }
 
+/// The full URL of a request, including the scheme and host, which is how recipes are keyed.
+fn request_url(request: &HttpRequest) -> String {
+    let cx_info = request.connection_info();
+    format!(
+        "{}://{}{}",
+        cx_info.scheme(),
+        cx_info.host(),
+        namespace::unprefixed(
+            request,
+            request
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str())
+                .unwrap_or_else(|| "")
+        )
+    )
+}
+
+/// Records a call as it was received; header values that aren't valid text are left out.
+fn journal_entry(
+    request: &HttpRequest,
+    body: &[u8],
+    recipe_id: Option<Uuid>,
+    status: StatusCode,
+    namespace: &str,
+) -> (NewJournalEntry, Vec<shared::Header>) {
+    let headers = request
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            value.to_str().ok().map(|value| shared::Header {
+                name: name.as_str().to_owned(),
+                value: value.to_owned(),
+            })
+        })
+        .collect();
+    let entry = NewJournalEntry {
+        method: request.method().as_str().to_owned(),
+        url: request_url(request),
+        path: namespace::unprefixed(request, request.path()).to_owned(),
+        body: body.to_vec(),
+        subject: extract_subject(request),
+        matched: recipe_id.is_some(),
+        recipe_id,
+        status_code: status.as_u16().into(),
+        namespace: namespace.to_owned(),
+    };
+    (entry, headers)
+}
+
 #[actix_web::get("/health")]
 pub(crate) async fn health_check(db: Data<DbPool>) -> Result<HttpResponse> {
-    let _ = web::block(move || health_query(&db))
+    web::block(move || db::check(&db))
         .await
         .map_err(ErrorInternalServerError)?;
     Ok(HttpResponse::Ok().body(format!("All is well! ({})", VERSION)))
 }
 
-fn health_query(db: &DbPool) -> anyhow::Result<usize> {
-    let conn = db.get()?;
-    diesel::sql_query("select 1 = 1")
-        .execute(&conn)
-        .map_err(anyhow::Error::from)
-}
-
 fn list_recipes_offset_limit(
     db_pool: Data<DbPool>,
+    namespace: &str,
     offset: i64,
     limit: i64,
 ) -> anyhow::Result<Value> {
-    let (total, recipes): (i64, Vec<Recipe>) = db::load_recipes(&db_pool, offset, limit)?;
+    let (total, recipes): (i64, Vec<Recipe>) =
+        db::load_recipes(&db_pool, namespace, offset, limit)?;
     let recipes: Vec<shared::Recipe> = recipes
         .into_iter()
         .map(Recipe::try_into)
@@ -95,3 +362,473 @@ fn list_recipes_offset_limit(
         "recipes": recipes,
     }})
 }
+
+/// Creates a recipe along with its rules, headers and responses, keeping its ID if it has one, as
+/// when restoring an export.
+fn create_cascaded(
+    conn: &dyn Storage,
+    namespace: &str,
+    changes: RecipeChanges,
+) -> anyhow::Result<RecipeCascaded> {
+    let RecipeChanges {
+        id,
+        recipe,
+        disruption,
+        rules,
+        headers,
+        responses,
+        file,
+    } = changes;
+    let recipe = db::create_recipe(conn, namespace, id, recipe, disruption, file)?;
+    let rules: Vec<NewRule> = rules
+        .into_iter()
+        .map(|rule| (recipe.id, rule).into())
+        .collect();
+    let rules = db::create_rules(conn, &rules)?;
+    let headers: Vec<NewResponseHeader> = headers
+        .into_iter()
+        .enumerate()
+        .map(|(position, header)| (recipe.id, position, header).into())
+        .collect();
+    let headers = db::replace_headers(conn, recipe.id, &headers)?;
+    let responses = responses
+        .into_iter()
+        .enumerate()
+        .map(|(position, response)| (recipe.id, position, response).try_into())
+        .collect::<anyhow::Result<Vec<NewRecipeResponse>>>()?;
+    let responses = db::replace_responses(conn, recipe.id, &responses)?;
+    Ok(RecipeCascaded(recipe, rules, headers, responses))
+}
+
+/// Updates a recipe in the namespace along with its rules, headers and responses. Rules that
+/// still have the ID of one of the recipe's rules are updated in place, the recipe's other rules
+/// are removed and the rest are created. A recipe loaded from a file can only be updated by loading
+/// it again.
+fn update_cascaded(
+    conn: &dyn Storage,
+    namespace: &str,
+    id: Uuid,
+    changes: RecipeChanges,
+) -> anyhow::Result<()> {
+    let RecipeChanges {
+        recipe,
+        disruption,
+        rules,
+        headers,
+        responses,
+        file,
+        ..
+    } = changes;
+    if file.is_none() {
+        check_editable(conn, namespace, id)?;
+    }
+    if db::update_recipe(conn, namespace, id, recipe, disruption, file)? != 1 {
+        bail!("Unable to update recipe, {}", id)
+    }
+    let existing = db::find_rule_ids(conn, id)?;
+    let (to_retain, to_create): (Vec<shared::Rule>, Vec<shared::Rule>) = rules
+        .into_iter()
+        .partition(|rule| matches!(rule.id(), Some(rule_id) if existing.contains(&rule_id)));
+    let to_retain = to_retain
+        .into_iter()
+        .map(|rule| (id, rule).try_into())
+        .collect::<anyhow::Result<Vec<Rule>>>()?;
+    let to_create = to_create
+        .into_iter()
+        .map(|rule| (id, rule).into())
+        .collect::<Vec<NewRule>>();
+    let headers = headers
+        .into_iter()
+        .enumerate()
+        .map(|(position, header)| (id, position, header).into())
+        .collect::<Vec<NewResponseHeader>>();
+    let responses = responses
+        .into_iter()
+        .enumerate()
+        .map(|(position, response)| (id, position, response).try_into())
+        .collect::<anyhow::Result<Vec<NewRecipeResponse>>>()?;
+    db::delete_rules(conn, id, &to_retain)?;
+    db::update_rules(conn, to_retain)?;
+    db::create_rules(conn, &to_create)?;
+    db::replace_headers(conn, id, &headers)?;
+    db::replace_responses(conn, id, &responses)?;
+    Ok(())
+}
+
+/// Deletes a recipe in the namespace, unless it is loaded from a file, returning how many recipes
+/// were deleted.
+fn delete_editable(conn: &dyn Storage, namespace: &str, id: Uuid) -> anyhow::Result<usize> {
+    check_editable(conn, namespace, id)?;
+    db::delete_recipe(conn, namespace, id)
+}
+
+fn check_editable(conn: &dyn Storage, namespace: &str, id: Uuid) -> anyhow::Result<()> {
+    if let Some(file) = db::find_recipe_file(conn, namespace, id)? {
+        bail!(
+            "The recipe, {}, is loaded from {} and can only be changed by editing that file!",
+            id,
+            file
+        )
+    }
+    Ok(())
+}
+
+/// Every scenario in the namespace, in order by name, with its current state and the states its
+/// recipes refer to.
+fn list_scenarios(db: &DbPool, namespace: &str) -> anyhow::Result<Vec<shared::Scenario>> {
+    let (steps, current) = db::load_scenarios(db, namespace)?;
+    Ok(collect_scenarios(steps, current))
+}
+
+/// Every namespace that owns a recipe, in order by name, along with the default namespace even if
+/// it owns none.
+fn list_namespaces(db: &DbPool) -> anyhow::Result<Vec<shared::Namespace>> {
+    let mut namespaces: Vec<shared::Namespace> = db::load_namespaces(db)?
+        .into_iter()
+        .map(|(name, recipes)| shared::Namespace { name, recipes })
+        .collect();
+    if let Err(index) = namespaces
+        .binary_search_by(|namespace| namespace.name.as_str().cmp(shared::DEFAULT_NAMESPACE))
+    {
+        namespaces.insert(
+            index,
+            shared::Namespace {
+                name: shared::DEFAULT_NAMESPACE.to_owned(),
+                recipes: 0,
+            },
+        );
+    }
+    Ok(namespaces)
+}
+
+/// Gathers the steps of the recipes, each a scenario with the state required and the state moved
+/// to, into scenarios; those without a current state are in their starting state.
+fn collect_scenarios(steps: Vec<ScenarioStep>, current: Vec<Scenario>) -> Vec<shared::Scenario> {
+    let started = |name: String| shared::Scenario {
+        name,
+        state: SCENARIO_STARTED.to_owned(),
+        states: vec![SCENARIO_STARTED.to_owned()],
+    };
+    let mut collected: BTreeMap<String, shared::Scenario> = BTreeMap::new();
+    for (name, required_state, new_state) in steps {
+        collected
+            .entry(name.clone())
+            .or_insert_with(|| started(name))
+            .states
+            .extend(required_state.into_iter().chain(new_state));
+    }
+    for Scenario { name, state, .. } in current {
+        let scenario = collected
+            .entry(name.clone())
+            .or_insert_with(|| started(name));
+        scenario.states.push(state.clone());
+        scenario.state = state;
+    }
+    collected
+        .into_values()
+        .map(|mut scenario| {
+            // the starting state comes first, then the rest in alphabetical order
+            scenario.states.sort_by(|a, b| {
+                (a != SCENARIO_STARTED)
+                    .cmp(&(b != SCENARIO_STARTED))
+                    .then_with(|| a.cmp(b))
+            });
+            scenario.states.dedup();
+            scenario
+        })
+        .collect()
+}
+
+/// Reorders the recipes for one URL so that they're tried in the order of `ids`, returning every
+/// recipe for the URL in its new order.
+async fn prioritize(
+    db: Data<DbPool>,
+    namespace: Namespace,
+    ids: Vec<Uuid>,
+) -> Result<Vec<shared::Recipe>> {
+    let (db_pool, owner) = (db.clone(), namespace.clone());
+    let to_find = ids.clone();
+    let recipes = web::block(move || db::find_recipes(&db_pool, &owner, &to_find))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let url = validate_priorities(&ids, &recipes).map_err(ErrorBadRequest)?;
+    let recipes = web::block(move || {
+        db::prioritize_recipes(&db, &namespace, &ids)?;
+        db::find_recipes_for_url(&db, &namespace, &url)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    recipes
+        .into_iter()
+        .map(RecipeCascaded::try_into)
+        .collect::<anyhow::Result<_>>()
+        .map_err(ErrorInternalServerError)
+}
+
+/// Checks that every ID is for an existing recipe and that they all share one URL, which is
+/// returned.
+fn validate_priorities(ids: &[Uuid], recipes: &[Recipe]) -> anyhow::Result<String> {
+    if ids.is_empty() {
+        bail!("At least one recipe ID is required to reorder recipes!")
+    }
+    if let Some(missing) = ids
+        .iter()
+        .find(|id| !recipes.iter().any(|recipe| &recipe.id == *id))
+    {
+        bail!("There is no recipe, {}, to reorder!", missing)
+    }
+    if let Some((_, repeated)) = ids
+        .iter()
+        .enumerate()
+        .find(|(index, id)| ids[..*index].contains(id))
+    {
+        bail!("The recipe, {}, can only be listed once!", repeated)
+    }
+    let mut urls = recipes
+        .iter()
+        .filter(|recipe| ids.contains(&recipe.id))
+        .map(|recipe| recipe.url.as_str());
+    let url = urls
+        .next()
+        .ok_or_else(|| format_err!("There are no recipes to reorder!"))?;
+    if urls.any(|other| other != url) {
+        bail!("Only recipes with the same URL can be reordered together!")
+    }
+    Ok(url.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::Memory;
+    use actix_web::{dev::Payload, http::Method, test::TestRequest, FromRequest};
+    use medallion::{DefaultPayload, Header, Token};
+    use std::sync::Arc;
+
+    /// Creates a recipe, in the default namespace, from its JSON as the REST API takes it.
+    fn create(db: &DbPool, recipe: Value) -> anyhow::Result<Uuid> {
+        let recipe: shared::Recipe = serde_json::from_value(recipe)?;
+        let changes: RecipeChanges = recipe.try_into()?;
+        let RecipeCascaded(recipe, ..) = db::in_transaction(db, |conn| {
+            create_cascaded(conn, shared::DEFAULT_NAMESPACE, changes)
+        })?;
+        Ok(recipe.id)
+    }
+
+    /// Responds to the call, giving the status and the ID of the recipe served, if any.
+    async fn call(
+        db: &Data<DbPool>,
+        request: TestRequest,
+    ) -> anyhow::Result<(StatusCode, Option<Uuid>)> {
+        let request = request.header("Host", "test.local").to_http_request();
+        let namespace = Namespace::from_request(&request, &mut Payload::None)
+            .await
+            .map_err(|error| format_err!("{}", error))?;
+        let (response, recipe_id) = respond(&request, &Bytes::new(), db.clone(), &namespace)
+            .await
+            .map_err(|error| format_err!("{}", error))?;
+        Ok((response.status(), recipe_id))
+    }
+
+    #[actix_rt::test]
+    async fn test_respond_to_other_schemes() -> anyhow::Result<()> {
+        let pool: DbPool = Arc::new(Memory::default());
+        let url = "http://test.local/api/login";
+        // recipes that check a bearer token are tried before the one for basic credentials
+        for rule in [
+            json! {{"Authenticated": {"key_path": "public.pem"}}},
+            json! {{"Subject": {"subject": "bob"}}},
+            json! {{"Claim": {"claim": "sub", "match_mode": "Present"}}},
+            json! {{"Unexpired": {}}},
+        ] {
+            create(&pool, json! {{"url": url, "rules": [rule], "payload": {}}})?;
+        }
+        let basic = create(
+            &pool,
+            json! {{"url": url, "rules": [{"BasicAuth": {"username": "bob"}}], "payload": {}}},
+        )?;
+        let db = Data::new(pool);
+
+        let credentials = format!("Basic {}", base64::encode("bob:pw"));
+        let request = TestRequest::with_uri("/api/login").header("Authorization", credentials);
+        assert_eq!((StatusCode::OK, Some(basic)), call(&db, request).await?);
+        // a bearer token that isn't a JWT fails the rules, rather than the call
+        let request =
+            TestRequest::with_uri("/api/login").header("Authorization", "bearer not-a-jwt");
+        assert_eq!((StatusCode::NOT_FOUND, None), call(&db, request).await?);
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_respond_in_sequence() -> anyhow::Result<()> {
+        let pool: DbPool = Arc::new(Memory::default());
+        let jobs = create(
+            &pool,
+            json! {{
+                "url": "http://test.local/api/jobs/1",
+                "status": 202,
+                "payload": {},
+                "responses": [{"status": 201, "payload": {}}, {"status": 200, "payload": {}}],
+                "sequence_mode": "FallThrough",
+                "delay": {"Fixed": {"millis": 50}}
+            }},
+        )?;
+        let db = Data::new(pool);
+
+        // calls made at the same time are each served a step of their own
+        let (first, second, third, fourth) = futures::join!(
+            call(&db, TestRequest::with_uri("/api/jobs/1")),
+            call(&db, TestRequest::with_uri("/api/jobs/1")),
+            call(&db, TestRequest::with_uri("/api/jobs/1")),
+            call(&db, TestRequest::with_uri("/api/jobs/1")),
+        );
+        let mut served = vec![first?, second?, third?, fourth?];
+        served.sort();
+        assert_eq!(
+            vec![
+                (StatusCode::OK, Some(jobs)),
+                (StatusCode::CREATED, Some(jobs)),
+                (StatusCode::ACCEPTED, Some(jobs)),
+                (StatusCode::NOT_FOUND, None),
+            ],
+            served
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_entry() -> anyhow::Result<()> {
+        let payload = DefaultPayload {
+            sub: Some("alice".to_owned()),
+            ..DefaultPayload::default()
+        };
+        let token = Token::<(), ()>::new(Header::default(), payload).sign(b"secret")?;
+        let request = TestRequest::with_uri("/api/orders?page=2")
+            .method(Method::POST)
+            .header("Host", "test.local")
+            .header("Authorization", format!("Bearer {}", token))
+            .to_http_request();
+        let recipe_id = Uuid::new_v4();
+        let (entry, headers) = journal_entry(
+            &request,
+            b"{}",
+            Some(recipe_id),
+            StatusCode::CREATED,
+            shared::DEFAULT_NAMESPACE,
+        );
+        assert_eq!("POST", entry.method);
+        assert_eq!("http://test.local/api/orders?page=2", entry.url);
+        assert_eq!("/api/orders", entry.path);
+        assert_eq!(b"{}".to_vec(), entry.body);
+        assert_eq!(Some("alice".to_owned()), entry.subject);
+        assert!(entry.matched);
+        assert_eq!(Some(recipe_id), entry.recipe_id);
+        assert_eq!(201, entry.status_code);
+        assert!(headers.iter().any(|header| header.name == "host"));
+
+        let (entry, _) = journal_entry(
+            &request,
+            &[],
+            None,
+            StatusCode::NOT_FOUND,
+            shared::DEFAULT_NAMESPACE,
+        );
+        assert!(!entry.matched);
+
+        // the prefix naming the namespace isn't part of the journaled URL
+        let request = TestRequest::with_uri("/ns/team-a/api/orders?page=2")
+            .header("Host", "test.local")
+            .param("namespace", "team-a")
+            .to_http_request();
+        let (entry, _) = journal_entry(&request, &[], None, StatusCode::NOT_FOUND, "team-a");
+        assert_eq!("http://test.local/api/orders?page=2", entry.url);
+        assert_eq!("/api/orders", entry.path);
+        assert_eq!("team-a", entry.namespace);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_priorities() -> anyhow::Result<()> {
+        let now = chrono::NaiveDateTime::from_timestamp(0, 0);
+        let recipe = |url: &str| Recipe {
+            id: Uuid::new_v4(),
+            url: url.to_owned(),
+            payload: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            status_code: 200,
+            templated: false,
+            content_type: shared::default_content_type(),
+            draft: false,
+            priority: 0,
+            scenario: None,
+            required_state: None,
+            new_state: None,
+            sequence_mode: SequenceMode::StickOnLast,
+            delay_type: None,
+            delay_millis: None,
+            delay_max_millis: None,
+            delay_std_dev_millis: None,
+            delay_sigma: None,
+            fault_type: None,
+            trickle_chunk_bytes: None,
+            trickle_interval_millis: None,
+            namespace: shared::DEFAULT_NAMESPACE.to_owned(),
+            file: None,
+        };
+        let recipes = vec![
+            recipe("http://test.local/api/orders"),
+            recipe("http://test.local/api/orders"),
+            recipe("http://test.local/api/users"),
+        ];
+        assert_eq!(
+            "http://test.local/api/orders",
+            validate_priorities(&[recipes[1].id, recipes[0].id], &recipes)?
+        );
+        assert!(validate_priorities(&[recipes[0].id, recipes[2].id], &recipes).is_err());
+        assert!(validate_priorities(&[recipes[0].id, recipes[0].id], &recipes).is_err());
+        assert!(validate_priorities(&[recipes[0].id, Uuid::new_v4()], &recipes).is_err());
+        assert!(validate_priorities(&[], &recipes).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_scenarios() {
+        let now = chrono::NaiveDateTime::from_timestamp(0, 0);
+        let steps = vec![
+            (
+                "approval".to_owned(),
+                Some("Pending".to_owned()),
+                Some("Approved".to_owned()),
+            ),
+            ("approval".to_owned(), None, Some("Pending".to_owned())),
+            ("login".to_owned(), Some(SCENARIO_STARTED.to_owned()), None),
+        ];
+        let current = vec![Scenario {
+            name: "approval".to_owned(),
+            state: "Pending".to_owned(),
+            updated_at: now,
+            namespace: shared::DEFAULT_NAMESPACE.to_owned(),
+        }];
+        let scenarios = collect_scenarios(steps, current);
+        assert_eq!(
+            vec![
+                shared::Scenario {
+                    name: "approval".to_owned(),
+                    state: "Pending".to_owned(),
+                    states: vec![
+                        SCENARIO_STARTED.to_owned(),
+                        "Approved".to_owned(),
+                        "Pending".to_owned(),
+                    ],
+                },
+                shared::Scenario {
+                    name: "login".to_owned(),
+                    state: SCENARIO_STARTED.to_owned(),
+                    states: vec![SCENARIO_STARTED.to_owned()],
+                },
+            ],
+            scenarios
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::config;
+use actix_web::{dev::Payload, error::ErrorBadRequest, Error, FromRequest, HttpRequest};
+use anyhow::{bail, Context};
+use futures::future::{ready, Ready};
+use std::ops::Deref;
+
+/// The prefix of a path that names the namespace a call works in, ahead of the usual path.
+pub(crate) const NAMESPACE_SCOPE: &str = "/ns/{namespace}";
+
+/// The namespace a call works in, named by, in order, the prefix of its path, its namespace header,
+/// or the namespace mapped to its host, and otherwise the default namespace.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Namespace(String);
+
+impl Namespace {
+    fn of(request: &HttpRequest) -> anyhow::Result<Self> {
+        let name = if let Some(name) = request.match_info().get("namespace") {
+            name.to_owned()
+        } else if let Some(name) = request.headers().get(shared::NAMESPACE_HEADER) {
+            name.to_str()
+                .with_context(|| format!("The {} header must be text!", shared::NAMESPACE_HEADER))?
+                .trim()
+                .to_owned()
+        } else if let Some(name) = config::NAMESPACES.get(request.connection_info().host()) {
+            name.clone()
+        } else {
+            shared::DEFAULT_NAMESPACE.to_owned()
+        };
+        if name.is_empty() || !shared::is_url_safe(&name) {
+            bail!(
+                "The namespace, {}, may only have letters, digits, '-', '_' and '.'!",
+                name
+            )
+        }
+        Ok(Self(name))
+    }
+}
+
+impl Deref for Namespace {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromRequest for Namespace {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+    type Config = ();
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::of(request).map_err(ErrorBadRequest))
+    }
+}
+
+/// A path, or path and query, of a call without the prefix that named its namespace, if it had
+/// one, which is how the path appears in recipe URLs.
+pub(super) fn unprefixed<'a>(request: &HttpRequest, path: &'a str) -> &'a str {
+    request
+        .match_info()
+        .get("namespace")
+        .and_then(|name| path.strip_prefix(&format!("/ns/{}", name)))
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_namespace() -> anyhow::Result<()> {
+        let request = TestRequest::with_uri("/api/orders").to_http_request();
+        assert_eq!(shared::DEFAULT_NAMESPACE, &*Namespace::of(&request)?);
+        let request = TestRequest::with_uri("/api/orders")
+            .header(shared::NAMESPACE_HEADER, "team-a")
+            .to_http_request();
+        assert_eq!("team-a", &*Namespace::of(&request)?);
+        let request = TestRequest::with_uri("/ns/run_42/api/orders?page=2")
+            .header(shared::NAMESPACE_HEADER, "team-a")
+            .param("namespace", "run_42")
+            .to_http_request();
+        assert_eq!("run_42", &*Namespace::of(&request)?);
+        assert_eq!(
+            "/api/orders?page=2",
+            unprefixed(&request, "/ns/run_42/api/orders?page=2")
+        );
+        let request = TestRequest::with_uri("/api/orders")
+            .header(shared::NAMESPACE_HEADER, "team a")
+            .to_http_request();
+        assert!(Namespace::of(&request).is_err());
+        Ok(())
+    }
+}
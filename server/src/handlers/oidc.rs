@@ -0,0 +1,142 @@
+use crate::{
+    config::{self, ProviderConfig},
+    models::{discovery, grant, introspect, user_info, IntrospectionForm, OAuthError, TokenForm},
+};
+use actix_web::{
+    error::{BlockingError, ErrorInternalServerError},
+    http::header::{CACHE_CONTROL, WWW_AUTHENTICATE},
+    web::{self, get, post, ServiceConfig},
+    HttpRequest, HttpResponse, Result,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::fmt::Debug;
+
+/// The endpoints of the mock OAuth2 and OIDC provider, which are only served when it has been
+/// configured with test clients and users.
+pub(crate) fn configure(config: &mut ServiceConfig) {
+    if config::PROVIDER.is_none() {
+        return;
+    }
+    config
+        .route(
+            "/.well-known/openid-configuration",
+            get().to(openid_configuration),
+        )
+        .route("/oauth/token", post().to(token))
+        .route("/oauth/introspect", post().to(introspect_token))
+        .route("/oauth/userinfo", get().to(userinfo))
+        .route("/oauth/userinfo", post().to(userinfo));
+}
+
+async fn openid_configuration(request: HttpRequest) -> Result<HttpResponse> {
+    let provider = provider()?;
+    Ok(HttpResponse::Ok().json(discovery(provider, &issuer(provider, &request))))
+}
+
+async fn token(request: HttpRequest, form: web::Form<TokenForm>) -> Result<HttpResponse> {
+    let provider = provider()?;
+    let issuer = issuer(provider, &request);
+    let authorization = authorization(&request);
+    let granted = web::block(move || {
+        grant(
+            provider,
+            &issuer,
+            authorization.as_deref(),
+            form.into_inner(),
+        )
+    })
+    .await;
+    respond(granted)
+}
+
+async fn introspect_token(
+    request: HttpRequest,
+    form: web::Form<IntrospectionForm>,
+) -> Result<HttpResponse> {
+    let provider = provider()?;
+    let authorization = authorization(&request);
+    let introspected =
+        web::block(move || introspect(provider, authorization.as_deref(), form.into_inner())).await;
+    respond(introspected)
+}
+
+async fn userinfo(request: HttpRequest) -> Result<HttpResponse> {
+    let provider = provider()?;
+    let authorization = authorization(&request);
+    let info = web::block(move || user_info(provider, authorization.as_deref())).await;
+    respond(info)
+}
+
+fn provider() -> Result<&'static ProviderConfig> {
+    config::PROVIDER
+        .as_ref()
+        .ok_or_else(|| ErrorInternalServerError("The OIDC provider is not configured!"))
+}
+
+/// The configured issuer or else the URL the provider was called at.
+fn issuer(provider: &ProviderConfig, request: &HttpRequest) -> String {
+    provider.issuer.clone().unwrap_or_else(|| {
+        let cx_info = request.connection_info();
+        format!("{}://{}", cx_info.scheme(), cx_info.host())
+    })
+}
+
+fn authorization(request: &HttpRequest) -> Option<String> {
+    request
+        .headers()
+        .get("Authorization")
+        .and_then(|authorization| authorization.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+/// Responds with the result, never to be cached, or with the error as RFC 6749 and RFC 6750
+/// describe, asking for credentials when a client or token wasn't accepted.
+fn respond<T: Serialize + Debug>(
+    result: std::result::Result<T, BlockingError<OAuthError>>,
+) -> Result<HttpResponse> {
+    let error = match result {
+        Ok(body) => {
+            return Ok(HttpResponse::Ok()
+                .header(CACHE_CONTROL, "no-store")
+                .json(body))
+        }
+        Err(BlockingError::Error(error)) => error,
+        Err(BlockingError::Canceled) => {
+            return Err(ErrorInternalServerError(
+                "The call to the provider was canceled!",
+            ))
+        }
+    };
+    let mut response = match error.error {
+        "invalid_client" => {
+            let mut response = HttpResponse::Unauthorized();
+            response.header(WWW_AUTHENTICATE, "Basic realm=\"empholite\"");
+            response
+        }
+        "invalid_token" => {
+            let mut response = HttpResponse::Unauthorized();
+            response.header(
+                WWW_AUTHENTICATE,
+                format!(
+                    "Bearer error=\"invalid_token\", error_description=\"{}\"",
+                    error.description
+                ),
+            );
+            response
+        }
+        "insufficient_scope" => {
+            let mut response = HttpResponse::Forbidden();
+            response.header(
+                WWW_AUTHENTICATE,
+                "Bearer error=\"insufficient_scope\", scope=\"openid\"",
+            );
+            response
+        }
+        "server_error" => HttpResponse::InternalServerError(),
+        _ => HttpResponse::BadRequest(),
+    };
+    Ok(response
+        .header(CACHE_CONTROL, "no-store")
+        .json(json! {{"error": error.error, "error_description": error.description}}))
+}
@@ -0,0 +1,485 @@
+use super::{
+    db,
+    namespace::{self, Namespace},
+};
+use crate::{
+    models::{urlencoded_fields, RecipeCascaded, RecipeChanges},
+    DbPool,
+};
+use actix_web::{
+    client::Client,
+    error::ErrorBadGateway,
+    http::{HeaderValue, Method, StatusCode},
+    web::{self, Bytes, Data},
+    HttpRequest, HttpResponse, Result,
+};
+use anyhow::format_err;
+use log::{debug, error};
+use serde_json::Value;
+use shared::{HttpVerb, MatchMode, PayloadKind};
+use std::convert::TryInto;
+use uuid::Uuid;
+
+/// The largest response body accepted from an upstream.
+const RESPONSE_LIMIT: usize = 10 * 1024 * 1024;
+
+/// Headers that describe a single connection, or how a body was sent over it, rather than the
+/// call itself, so they are neither forwarded nor recorded.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "content-encoding",
+    "content-length",
+    "host",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Request headers that are forwarded but that vary too much from one call to the next to make
+/// useful rules for a recording.
+const UNRECORDED: &[&str] = &[
+    "accept-encoding",
+    "authorization",
+    "cookie",
+    "date",
+    "user-agent",
+    "x-forwarded-for",
+    "x-request-id",
+];
+
+/// What an upstream sent back for a forwarded call.
+#[derive(Debug)]
+pub(super) struct Forwarded {
+    status: StatusCode,
+    headers: Vec<(String, HeaderValue)>,
+    body: Bytes,
+}
+
+/// Forwards a call that matched no recipe to the upstream for its host, records the exchange as a
+/// draft recipe, and responds with whatever the upstream sent back.
+pub(super) async fn proxy(
+    request: &HttpRequest,
+    body: Bytes,
+    upstream: &str,
+    url: String,
+    db: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
+    let forwarded = forward(upstream, request, body.clone())
+        .await
+        .map_err(ErrorBadGateway)?;
+    let recording = record(url, request, &body, &forwarded);
+    // the caller should get the upstream's response even if it couldn't be recorded
+    match web::block(move || save(&db, &namespace, recording)).await {
+        Ok(id) => debug!("Recorded draft recipe, {}", id),
+        Err(error) => error!("Could not record a draft recipe: {}", error),
+    }
+    let mut response = HttpResponse::build(forwarded.status);
+    for (name, value) in &forwarded.headers {
+        response.header(name.as_str(), value.clone());
+    }
+    Ok(response.body(forwarded.body))
+}
+
+pub(super) async fn forward(
+    upstream: &str,
+    request: &HttpRequest,
+    body: Bytes,
+) -> anyhow::Result<Forwarded> {
+    let url = format!(
+        "{}{}",
+        upstream,
+        namespace::unprefixed(
+            request,
+            request
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str())
+                .unwrap_or_else(|| "/")
+        )
+    );
+    debug!("Forwarding to {}", url);
+    let mut to_send = Client::default().request(request.method().clone(), &url);
+    for (name, value) in request.headers() {
+        if !is_hop_by_hop(name.as_str()) {
+            to_send = to_send.header(name.as_str(), value.clone());
+        }
+    }
+    let mut response = to_send
+        .send_body(body)
+        .await
+        .map_err(|error| format_err!("Could not forward the call to {}: {}", url, error))?;
+    let headers = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !is_hop_by_hop(name.as_str()))
+        .map(|(name, value)| (name.as_str().to_owned(), value.clone()))
+        .collect();
+    let body = response
+        .body()
+        .limit(RESPONSE_LIMIT)
+        .await
+        .map_err(|error| format_err!("Could not read the response from {}: {}", url, error))?;
+    Ok(Forwarded {
+        status: response.status(),
+        headers,
+        body,
+    })
+}
+
+/// Builds a draft recipe from a forwarded call, with rules for its method, query parameters, most of
+/// its headers and its body, so that it only replays for calls like the one recorded; the URL has
+/// no query since parameters are matched by the rules.
+pub(super) fn record(
+    url: String,
+    request: &HttpRequest,
+    body: &[u8],
+    forwarded: &Forwarded,
+) -> shared::Recipe {
+    let mut rules = Vec::new();
+    if let Some(http_method) = as_verb(request.method()) {
+        rules.push(shared::Rule::HttpMethod {
+            id: None,
+            http_method,
+        });
+    }
+    for (param_name, value) in urlencoded_fields(request.query_string()) {
+        rules.push(shared::Rule::QueryParam {
+            id: None,
+            param_name,
+            match_mode: MatchMode::Exact,
+            match_value: Some(value),
+        });
+    }
+    for (name, value) in request.headers() {
+        let name = name.as_str();
+        if is_hop_by_hop(name) || UNRECORDED.contains(&name) {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            rules.push(shared::Rule::Header {
+                id: None,
+                header_name: name.to_owned(),
+                match_mode: MatchMode::Exact,
+                match_value: Some(value.to_owned()),
+            });
+        }
+    }
+    if !body.is_empty() {
+        if let Ok(body) = std::str::from_utf8(body) {
+            rules.push(shared::Rule::Body {
+                id: None,
+                body_path: None,
+                form_field: None,
+                match_mode: MatchMode::Exact,
+                match_value: Some(body.to_owned()),
+            });
+        }
+    }
+    let mut headers = Vec::new();
+    let mut content_type = None;
+    for (name, value) in &forwarded.headers {
+        // a value that isn't text is passed on to the caller, but can't be recorded
+        let value = match value.to_str() {
+            Ok(value) => value.to_owned(),
+            Err(_) => continue,
+        };
+        if name.eq_ignore_ascii_case("content-type") && content_type.is_none() {
+            content_type = Some(value);
+        } else {
+            headers.push(shared::Header {
+                name: name.clone(),
+                value,
+            });
+        }
+    }
+    let (content_type, payload) =
+        recorded_payload(content_type.as_deref(), &forwarded.body, &mut headers);
+    shared::Recipe {
+        id: None,
+        url,
+        rules,
+        status: forwarded.status.as_u16(),
+        headers,
+        content_type,
+        payload,
+        responses: Vec::new(),
+        sequence_mode: shared::SequenceMode::default(),
+        delay: None,
+        fault: None,
+        templated: false,
+        draft: true,
+        priority: 0,
+        scenario: None,
+        required_state: None,
+        new_state: None,
+        file: None,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+/// Converts a response body to a payload of the kind its content type calls for; a body that
+/// doesn't fit is kept as binary and its original content type is recorded as a header so that
+/// replaying it sends the same bytes and type.
+fn recorded_payload(
+    content_type: Option<&str>,
+    body: &[u8],
+    headers: &mut Vec<shared::Header>,
+) -> (String, Value) {
+    let declared = content_type.unwrap_or("application/octet-stream");
+    let payload = match PayloadKind::of(declared) {
+        PayloadKind::Json => serde_json::from_slice(body).ok(),
+        PayloadKind::Text => std::str::from_utf8(body).ok().map(Value::from),
+        PayloadKind::Binary => Some(Value::from(base64::encode(body))),
+    };
+    if let Some(payload) = payload {
+        (declared.to_owned(), payload)
+    } else {
+        headers.push(shared::Header {
+            name: "Content-Type".to_owned(),
+            value: declared.to_owned(),
+        });
+        (
+            "application/octet-stream".to_owned(),
+            Value::from(base64::encode(body)),
+        )
+    }
+}
+
+/// Saves a recording as a draft recipe; a draft recorded before for the same call, with the same
+/// URL and rules, the method among them, is updated with the upstream's latest response instead,
+/// so that proxying a call again doesn't add another.
+fn save(db: &DbPool, namespace: &str, recording: shared::Recipe) -> anyhow::Result<Uuid> {
+    let recorded = rule_keys(&recording.rules);
+    let mut recorded_before = None;
+    for RecipeCascaded(recipe, rules, ..) in
+        db::find_recipes_for_url(db, namespace, &recording.url)?
+    {
+        let rules = rules
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<anyhow::Result<Vec<shared::Rule>>>()?;
+        if recipe.draft && rule_keys(&rules) == recorded {
+            recorded_before = Some(recipe.id);
+            break;
+        }
+    }
+    let changes: RecipeChanges = recording.try_into()?;
+    db::in_transaction(db, |conn| match recorded_before {
+        Some(id) => super::update_cascaded(conn, namespace, id, changes).map(|_| id),
+        None => super::create_cascaded(conn, namespace, changes)
+            .map(|RecipeCascaded(recipe, ..)| recipe.id),
+    })
+}
+
+/// The rules as JSON without their IDs, in order, so that the rules of two recipes can be compared
+/// whatever order they were saved in.
+fn rule_keys(rules: &[shared::Rule]) -> Vec<String> {
+    let mut keys: Vec<String> = rules
+        .iter()
+        .map(|rule| {
+            let mut key = serde_json::to_value(rule).unwrap_or_default();
+            // a rule is an object with one property, naming its type, for the rule's fields
+            if let Some(fields) = key
+                .as_object_mut()
+                .and_then(|tagged| tagged.values_mut().next())
+                .and_then(Value::as_object_mut)
+            {
+                fields.remove("id");
+            }
+            key.to_string()
+        })
+        .collect();
+    keys.sort();
+    keys
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP.iter().any(|hop| name.eq_ignore_ascii_case(hop))
+}
+
+fn as_verb(method: &Method) -> Option<HttpVerb> {
+    Some(match *method {
+        Method::GET => HttpVerb::Get,
+        Method::POST => HttpVerb::Post,
+        Method::PUT => HttpVerb::Put,
+        Method::DELETE => HttpVerb::Delete,
+        Method::PATCH => HttpVerb::Patch,
+        Method::HEAD => HttpVerb::Head,
+        Method::OPTIONS => HttpVerb::Options,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::Memory;
+    use actix_web::{dev::Payload, test, App, FromRequest};
+    use anyhow::Result;
+    use std::sync::Arc;
+
+    async fn stub() -> HttpResponse {
+        HttpResponse::Created()
+            .content_type("application/json")
+            .header("X-Stub", "yes")
+            .body(r#"{"id": 42}"#)
+    }
+
+    async fn raw_stub() -> HttpResponse {
+        HttpResponse::Ok()
+            .header("X-Raw", &b"caf\xe9"[..])
+            .header("X-Stub", "yes")
+            .finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_forward_and_record() -> Result<()> {
+        let upstream = test::start(|| App::new().route("/api/users", web::post().to(stub)));
+        let request = test::TestRequest::post()
+            .uri("/api/users?team=a")
+            .header("Accept", "application/json")
+            .header("Authorization", "Bearer abc")
+            .to_http_request();
+        let body = Bytes::from_static(br#"{"name": "Alice"}"#);
+        let forwarded = forward(
+            &format!("http://{}", upstream.addr()),
+            &request,
+            body.clone(),
+        )
+        .await?;
+        assert_eq!(StatusCode::CREATED, forwarded.status);
+        assert!(forwarded
+            .headers
+            .iter()
+            .any(|(name, value)| name == "x-stub" && value == "yes"));
+
+        let recording = record(
+            "http://test.local/api/users".to_owned(),
+            &request,
+            &body,
+            &forwarded,
+        );
+        assert!(recording.draft);
+        assert_eq!(201, recording.status);
+        assert_eq!("application/json", recording.content_type);
+        assert_eq!(serde_json::json! {{"id": 42}}, recording.payload);
+        assert!(matches!(
+            recording.rules.as_slice(),
+            [
+                shared::Rule::HttpMethod {
+                    http_method: HttpVerb::Post,
+                    ..
+                },
+                shared::Rule::QueryParam { param_name, match_value: Some(team), .. },
+                shared::Rule::Header { header_name, .. },
+                shared::Rule::Body { match_value: Some(_), .. },
+            ] if param_name == "team" && team == "a" && header_name == "accept"
+        ));
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_header_not_text() -> Result<()> {
+        let upstream = test::start(|| App::new().route("/api/users", web::get().to(raw_stub)));
+        let request = test::TestRequest::get().uri("/api/users").to_http_request();
+        let forwarded = forward(
+            &format!("http://{}", upstream.addr()),
+            &request,
+            Bytes::new(),
+        )
+        .await?;
+        // the caller gets the header as it was sent, but it can't be recorded
+        assert!(forwarded
+            .headers
+            .iter()
+            .any(|(name, value)| name == "x-raw" && value.as_bytes() == b"caf\xe9"));
+        let recording = record(
+            "http://test.local/api/users".to_owned(),
+            &request,
+            &[],
+            &forwarded,
+        );
+        let names: Vec<&str> = recording
+            .headers
+            .iter()
+            .map(|header| header.name.as_str())
+            .collect();
+        assert!(names.contains(&"x-stub"));
+        assert!(!names.contains(&"x-raw"));
+        Ok(())
+    }
+
+    /// Proxies a call to the upstream, giving the status it responded with.
+    async fn call(pool: &DbPool, upstream: &str, uri: &str) -> Result<StatusCode> {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .header("Host", "test.local")
+            .header("Accept", "application/json")
+            .to_http_request();
+        let namespace = Namespace::from_request(&request, &mut Payload::None)
+            .await
+            .map_err(|error| format_err!("{}", error))?;
+        let response = proxy(
+            &request,
+            Bytes::from_static(br#"{"name": "Alice"}"#),
+            upstream,
+            "http://test.local/api/users".to_owned(),
+            Data::new(pool.clone()),
+            namespace,
+        )
+        .await
+        .map_err(|error| format_err!("{}", error))?;
+        Ok(response.status())
+    }
+
+    #[actix_rt::test]
+    async fn test_record_once() -> Result<()> {
+        let upstream = test::start(|| App::new().route("/api/users", web::post().to(stub)));
+        let upstream = format!("http://{}", upstream.addr());
+        let pool: DbPool = Arc::new(Memory::default());
+        let drafts = || {
+            db::find_recipes_for_url(
+                &pool,
+                shared::DEFAULT_NAMESPACE,
+                "http://test.local/api/users",
+            )
+            .map(|drafts| drafts.len())
+        };
+
+        assert_eq!(
+            StatusCode::CREATED,
+            call(&pool, &upstream, "/api/users?team=a").await?
+        );
+        assert_eq!(
+            StatusCode::CREATED,
+            call(&pool, &upstream, "/api/users?team=a").await?
+        );
+        assert_eq!(1, drafts()?);
+        // a call that differs is recorded on its own
+        call(&pool, &upstream, "/api/users?team=b").await?;
+        assert_eq!(2, drafts()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recorded_payload() {
+        let mut headers = Vec::new();
+        let (content_type, payload) =
+            recorded_payload(Some("application/json"), b"not json", &mut headers);
+        assert_eq!("application/octet-stream", content_type);
+        assert_eq!(Value::from(base64::encode(b"not json")), payload);
+        assert_eq!(
+            vec![shared::Header {
+                name: "Content-Type".to_owned(),
+                value: "application/json".to_owned(),
+            }],
+            headers
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use super::{db, rest::validate_change};
+use crate::{models::RecipeChanges, storage::Storage, DbPool};
+use anyhow::{bail, format_err, Context, Result};
+use log::{error, info};
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// How long to wait for changes to files to settle before loading them again, since saving one
+/// file often means several events.
+const WATCH_DELAY_MILLIS: u64 = 500;
+
+/// A recipe read from a file, along with the namespace it belongs to, named by the directory the
+/// file is in.
+struct FileRecipe {
+    id: Uuid,
+    namespace: String,
+    changes: RecipeChanges,
+}
+
+/// Loads every recipe file in the directory, replacing the recipes loaded from files before. If
+/// any of them is invalid, none of them are loaded. Returns how many recipes were loaded.
+pub(crate) fn load(db: &DbPool, dir: &Path) -> Result<usize> {
+    let recipes = read_dir(dir, dir)?;
+    let count = recipes.len();
+    db::in_transaction(db, |conn| replace(conn, recipes))?;
+    Ok(count)
+}
+
+/// Loads the recipe files again whenever any of them changes, until the server stops.
+pub(crate) fn watch(db: DbPool, dir: PathBuf) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::watcher(sender, Duration::from_millis(WATCH_DELAY_MILLIS))?;
+    watcher.watch(&dir, RecursiveMode::Recursive)?;
+    thread::spawn(move || {
+        // the watcher stops watching once dropped, so it has to live as long as this thread
+        let _watcher = watcher;
+        for event in receiver {
+            match event {
+                DebouncedEvent::NoticeWrite(_)
+                | DebouncedEvent::NoticeRemove(_)
+                | DebouncedEvent::Chmod(_) => (),
+                DebouncedEvent::Error(error, path) => {
+                    error!("Error watching recipe files, {:?}: {}", path, error)
+                }
+                _ => match load(&db, &dir) {
+                    Ok(count) => info!("Loaded {} recipes from {}", count, dir.display()),
+                    Err(error) => error!(
+                        "Kept the recipes loaded before, since loading {} failed: {:#}",
+                        dir.display(),
+                        error
+                    ),
+                },
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Updates the recipes loaded before that are still in the files, creates the new ones and deletes
+/// those that are no longer in any file.
+fn replace(conn: &dyn Storage, recipes: Vec<FileRecipe>) -> Result<()> {
+    let mut stale: HashMap<Uuid, String> = db::find_file_recipes(conn)?.into_iter().collect();
+    for FileRecipe {
+        id,
+        namespace,
+        changes,
+    } in recipes
+    {
+        let file = changes.file.clone().unwrap_or_default();
+        match (db::find_recipe_namespace(conn, id)?, stale.remove(&id)) {
+            (Some(owner), Some(_)) if owner == namespace => {
+                super::update_cascaded(conn, &namespace, id, changes)?;
+            }
+            (Some(owner), Some(_)) => {
+                // the file moved to another namespace's directory
+                db::delete_recipe(conn, &owner, id)?;
+                super::create_cascaded(conn, &namespace, changes)?;
+            }
+            (Some(_), None) => bail!(
+                "A recipe in {} has the ID, {}, which is already used by another recipe!",
+                file,
+                id
+            ),
+            (None, _) => {
+                super::create_cascaded(conn, &namespace, changes)?;
+            }
+        }
+    }
+    for (id, owner) in stale {
+        db::delete_recipe(conn, &owner, id)?;
+    }
+    Ok(())
+}
+
+/// Reads the recipe files in a directory and those under it, in order by name so that loading the
+/// same files always gives the same result.
+fn read_dir(root: &Path, dir: &Path) -> Result<Vec<FileRecipe>> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("Could not read the recipe directory, {}!", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+    paths.sort();
+    let mut recipes = Vec::new();
+    for path in paths {
+        let visible = matches!(
+            path.file_name().and_then(|name| name.to_str()),
+            Some(name) if !name.starts_with('.')
+        );
+        if !visible {
+            continue;
+        }
+        if path.is_dir() {
+            recipes.extend(read_dir(root, &path)?);
+        } else if format_of(&path).is_some() {
+            recipes.extend(read_file(root, &path)?);
+        }
+    }
+    Ok(recipes)
+}
+
+#[derive(Debug, PartialEq)]
+enum Format {
+    Json,
+    Yaml,
+}
+
+/// Files are read by their extension, and any file without one of these is ignored, like the
+/// backups an editor leaves.
+fn format_of(path: &Path) -> Option<Format> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "json" => Some(Format::Json),
+        "yaml" | "yml" => Some(Format::Yaml),
+        _ => None,
+    }
+}
+
+/// Reads a file holding either one recipe or an array of them, in the same shape as the body of a
+/// call to create a recipe through the REST API.
+fn read_file(root: &Path, path: &Path) -> Result<Vec<FileRecipe>> {
+    let (namespace, file) = locate(root, path)?;
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Could not read the recipe file, {}!", file))?;
+    let value: Value = match format_of(path) {
+        Some(Format::Json) => serde_json::from_str(&text)
+            .with_context(|| format!("Could not parse {} as JSON!", file))?,
+        Some(Format::Yaml) => serde_yaml::from_str(&text)
+            .with_context(|| format!("Could not parse {} as YAML!", file))?,
+        None => bail!("{} is not a JSON or YAML file!", file),
+    };
+    let values = match value {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let recipe = validate_change(value, "load")
+                .map_err(|error| format_err!("Recipe {} in {}: {}", index + 1, file, error))?;
+            // a recipe without an ID gets the same one every time its file is loaded
+            let id = recipe.id.unwrap_or_else(|| {
+                let name = format!("empholite:recipe-file/{}#{}", file, index);
+                Uuid::new_v5(&Uuid::NAMESPACE_URL, name.as_bytes())
+            });
+            let mut changes: RecipeChanges = recipe.try_into()?;
+            changes.id = Some(id);
+            changes.file = Some(file.clone());
+            Ok(FileRecipe {
+                id,
+                namespace: namespace.clone(),
+                changes,
+            })
+        })
+        .collect()
+}
+
+/// The namespace that a file's recipes belong to, named by the directory under the root that
+/// holds the file, or the default namespace for files in the root itself, and the path of the
+/// file relative to the root.
+fn locate(root: &Path, path: &Path) -> Result<(String, String)> {
+    let relative = path.strip_prefix(root)?;
+    let parts = relative
+        .iter()
+        .map(|part| {
+            part.to_str()
+                .map(str::to_owned)
+                .ok_or_else(|| format_err!("The path, {}, must be UTF-8!", relative.display()))
+        })
+        .collect::<Result<Vec<String>>>()?;
+    let namespace = if parts.len() > 1 {
+        parts[0].clone()
+    } else {
+        shared::DEFAULT_NAMESPACE.to_owned()
+    };
+    if !shared::is_url_safe(&namespace) {
+        bail!(
+            "The directory, {}, names a namespace so it may only have letters, digits, '-', '_' and '.'!",
+            namespace
+        )
+    }
+    Ok((namespace, parts.join("/")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_dir() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("empholite-recipes-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("team-a"))?;
+        fs::write(
+            root.join("orders.json"),
+            r#"{"url": "http://test.local/api/orders", "payload": {"orders": []}}"#,
+        )?;
+        fs::write(
+            root.join("team-a").join("users.yaml"),
+            "- url: http://test.local/api/users\n  payload: []\n- url: http://test.local/api/users/{id}\n  status: 404\n  payload: ''\n",
+        )?;
+        fs::write(root.join("notes.txt"), "not a recipe")?;
+        fs::write(root.join(".orders.json.swp"), "not a recipe either")?;
+        let recipes = read_dir(&root, &root);
+        let again = read_dir(&root, &root);
+        fs::write(
+            root.join("invalid.yml"),
+            "url: http://test.local/api/broken\n",
+        )?;
+        let invalid = read_dir(&root, &root);
+        fs::remove_dir_all(&root)?;
+
+        let recipes = recipes?;
+        assert_eq!(3, recipes.len());
+        assert_eq!(shared::DEFAULT_NAMESPACE, recipes[0].namespace);
+        assert_eq!(Some("orders.json".to_owned()), recipes[0].changes.file);
+        assert_eq!("team-a", recipes[2].namespace);
+        assert_eq!(
+            Some("team-a/users.yaml".to_owned()),
+            recipes[2].changes.file
+        );
+        assert_eq!(404, recipes[2].changes.recipe.status_code);
+        let ids: Vec<Uuid> = recipes.iter().map(|recipe| recipe.id).collect();
+        let ids_again: Vec<Uuid> = again?.iter().map(|recipe| recipe.id).collect();
+        assert_eq!(ids, ids_again);
+        assert_ne!(ids[1], ids[2]);
+        let error = format!("{:#}", invalid.err().expect("A recipe without a payload"));
+        assert!(error.contains("invalid.yml"), "{}", error);
+        Ok(())
+    }
+}
@@ -1,23 +1,60 @@
-use super::db;
+use super::{db, key_set::load_key_set, namespace::Namespace};
 use crate::{
-    models::{HttpVerb, NewRecipe, NewRule, RecipeCascaded, Rule, RuleType},
+    config::{self, KeyPathKind},
+    models::{
+        claim_pointer, issue, issuer_key_set, json_pointer, normalize_fingerprint,
+        payload_to_bytes, validate_template, ApiKeyLocation, HttpVerb, JournalCascaded, MatchMode,
+        RecipeCascaded, RecipeChanges, RuleType, UrlTemplate,
+    },
+    storage::Storage,
     DbPool,
 };
 use actix_web::{
-    error::{ErrorBadRequest, ErrorInternalServerError},
-    http::Uri,
-    web::{self, Bytes, Data, Path},
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound},
+    http::{
+        header::{HeaderName, HeaderValue},
+        StatusCode, Uri,
+    },
+    web::{self, Bytes, Data, Path, Query},
     HttpResponse, Result,
 };
 use anyhow::{bail, format_err, Context};
-use serde_json::Value;
-use std::convert::TryInto;
+use mime::Mime;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shared::{Algorithm, PayloadKind};
+use std::convert::{TryFrom, TryInto};
 use uuid::Uuid;
 
+const NDJSON: &str = "application/x-ndjson";
+
+#[derive(Deserialize)]
+pub(crate) struct Export {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Json,
+    Ndjson,
+}
+
 #[actix_web::get("/api/v1/recipe")]
-pub(crate) async fn list_recipes(db_pool: Data<DbPool>) -> Result<HttpResponse> {
+pub(crate) async fn list_recipes(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
     let json = web::block(move || {
-        super::list_recipes_offset_limit(db_pool, super::DEFAULT_OFFSET, super::DEFAULT_LIMIT)
+        super::list_recipes_offset_limit(
+            db_pool,
+            &namespace,
+            super::DEFAULT_OFFSET,
+            super::DEFAULT_LIMIT,
+        )
     })
     .await
     .map_err(ErrorInternalServerError)?;
@@ -27,10 +64,16 @@ pub(crate) async fn list_recipes(db_pool: Data<DbPool>) -> Result<HttpResponse>
 #[actix_web::get("/api/v1/recipe/offset/{offset}")]
 pub(crate) async fn list_recipes_page(
     db_pool: Data<DbPool>,
+    namespace: Namespace,
     offset: Path<i64>,
 ) -> Result<HttpResponse> {
     let json = web::block(move || {
-        super::list_recipes_offset_limit(db_pool, offset.into_inner(), super::DEFAULT_LIMIT)
+        super::list_recipes_offset_limit(
+            db_pool,
+            &namespace,
+            offset.into_inner(),
+            super::DEFAULT_LIMIT,
+        )
     })
     .await
     .map_err(ErrorInternalServerError)?;
@@ -38,107 +81,534 @@ pub(crate) async fn list_recipes_page(
 }
 
 #[actix_web::get("/api/v1/recipe/{id}")]
-pub(crate) async fn get_recipe(path: Path<Uuid>, db: Data<DbPool>) -> Result<HttpResponse> {
-    let (recipe, rules) = web::block(move || db::find_recipe(&db, path.into_inner()))
-        .await
-        .map_err(ErrorInternalServerError)?;
-    let body: shared::Recipe = RecipeCascaded(recipe, rules)
-        .try_into()
-        .map_err(ErrorInternalServerError)?;
+pub(crate) async fn get_recipe(
+    path: Path<Uuid>,
+    db: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
+    let body: shared::Recipe =
+        web::block(move || db::find_recipe(&db, &namespace, path.into_inner()))
+            .await
+            .map_err(ErrorInternalServerError)?
+            .try_into()
+            .map_err(ErrorInternalServerError)?;
     Ok(HttpResponse::Ok().json(body))
 }
 
 #[actix_web::post("/api/v1/recipe")]
-pub(crate) async fn create_recipe(db_pool: Data<DbPool>, recipe: Bytes) -> Result<HttpResponse> {
+pub(crate) async fn create_recipe(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    recipe: Bytes,
+) -> Result<HttpResponse> {
     let recipe: Value = serde_json::from_slice(&recipe)
         .with_context(|| "Could not parse the post body as JSON!")
         .map_err(ErrorBadRequest)?;
-    let shared::Recipe {
-        url,
-        payload,
-        rules,
-        ..
-    } = validate_post(recipe).map_err(ErrorBadRequest)?;
-    let payload = serde_json::to_string(&payload).map_err(ErrorInternalServerError)?;
-    let (recipe, rules) = {
-        let to_create = NewRecipe { url, payload };
-        web::block(move || {
-            db::create_recipe(&db_pool, to_create).and_then(|recipe| {
-                let to_create: Vec<NewRule> = rules
-                    .into_iter()
-                    .map(|rule| (recipe.id, rule).into())
-                    .collect();
-                db::create_rules(&db_pool, &to_create).map(|rules| (recipe, rules))
-            })
+    let mut changes: RecipeChanges = validate_post(recipe)
+        .and_then(TryInto::try_into)
+        .map_err(ErrorBadRequest)?;
+    // a new recipe always gets a new ID
+    changes.id = None;
+    let cascaded = web::block(move || {
+        db::in_transaction(&db_pool, |conn| {
+            super::create_cascaded(conn, &namespace, changes)
         })
-        .await
-        .map_err(ErrorInternalServerError)?
-    };
-    let created: shared::Recipe = RecipeCascaded(recipe, rules)
-        .try_into()
-        .map_err(ErrorInternalServerError)?;
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    let created: shared::Recipe = cascaded.try_into().map_err(ErrorInternalServerError)?;
     Ok(HttpResponse::Ok().json(created))
 }
 
+/// Creates every recipe in the body at once; if any of them is invalid or cannot be created, none
+/// of them are, and the response lists what is wrong.
+#[actix_web::post("/api/v1/recipe/bulk")]
+pub(crate) async fn create_recipes(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    recipes: Bytes,
+) -> Result<HttpResponse> {
+    let changes = match validate_bulk(&recipes, validate_post).map_err(ErrorBadRequest)? {
+        Ok(changes) => changes,
+        Err(errors) => return Ok(HttpResponse::BadRequest().json(errors)),
+    };
+    let created = web::block(move || {
+        let created = in_bulk(&db_pool, changes, |conn, mut changes| {
+            changes.id = None;
+            super::create_cascaded(conn, &namespace, changes)
+                .map(|RecipeCascaded(recipe, ..)| recipe.id)
+        })?;
+        find_bulk(&db_pool, &namespace, created)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    bulk_response(created)
+}
+
+/// Updates every recipe in the body that has the ID of a recipe in the namespace and creates the
+/// rest, all at once, so that an export can be restored over the recipes it was taken from, or
+/// into another namespace or server. Created recipes keep their IDs unless another namespace
+/// already uses them.
+#[actix_web::put("/api/v1/recipe/bulk")]
+pub(crate) async fn upsert_recipes(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    recipes: Bytes,
+) -> Result<HttpResponse> {
+    let changes = match validate_bulk(&recipes, validate_upsert).map_err(ErrorBadRequest)? {
+        Ok(changes) => changes,
+        Err(errors) => return Ok(HttpResponse::BadRequest().json(errors)),
+    };
+    let upserted = web::block(move || {
+        let upserted = in_bulk(&db_pool, changes, |conn, mut changes| {
+            let owner = match changes.id {
+                Some(id) => db::find_recipe_namespace(conn, id)?,
+                None => None,
+            };
+            if let (Some(id), Some(owner)) = (changes.id, owner) {
+                if owner == *namespace {
+                    return super::update_cascaded(conn, &namespace, id, changes).map(|_| id);
+                }
+                // copying recipes from another namespace, so they need IDs of their own
+                changes.id = None;
+            }
+            super::create_cascaded(conn, &namespace, changes)
+                .map(|RecipeCascaded(recipe, ..)| recipe.id)
+        })?;
+        find_bulk(&db_pool, &namespace, upserted)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    bulk_response(upserted)
+}
+
+/// Reorders the recipes for one URL; the body is an array of their IDs, the first to be tried
+/// first.
+#[actix_web::put("/api/v1/recipe/priority")]
+pub(crate) async fn prioritize_recipes(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    ids: Bytes,
+) -> Result<HttpResponse> {
+    let ids: Vec<Uuid> = serde_json::from_slice(&ids)
+        .with_context(|| "The body must be a JSON array of recipe IDs!")
+        .map_err(ErrorBadRequest)?;
+    let recipes = super::prioritize(db_pool, namespace, ids).await?;
+    Ok(HttpResponse::Ok().json(recipes))
+}
+
 #[actix_web::put("/api/v1/recipe")]
-pub(crate) async fn update_recipe(db_pool: Data<DbPool>, recipe: Bytes) -> Result<HttpResponse> {
+pub(crate) async fn update_recipe(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    recipe: Bytes,
+) -> Result<HttpResponse> {
     let recipe: Value = serde_json::from_slice(&recipe)
         .with_context(|| "Could not parse the post body as JSON!")
         .map_err(ErrorBadRequest)?;
-    let shared::Recipe {
-        id,
-        url,
-        payload,
-        rules,
-        ..
-    } = validate_put(recipe).map_err(ErrorBadRequest)?;
-    let id = id
+    let changes: RecipeChanges = validate_put(recipe)
+        .and_then(TryInto::try_into)
+        .map_err(ErrorBadRequest)?;
+    let id = changes
+        .id
         .ok_or_else(|| format_err!("Must specify Id when udpating a recipe!"))
         .map_err(ErrorBadRequest)?;
-    let payload = serde_json::to_string(&payload)?;
-    let (recipe, rules) = {
-        use shared::Rule::*;
-        web::block(move || {
-            let count = db::update_recipe(&db_pool, id, url, payload)?;
-            if count == 1 {
-                let (to_retain, to_create): (Vec<shared::Rule>, Vec<shared::Rule>) =
-                    rules.into_iter().partition(|rule| match rule {
-                        Authenticated { id, .. } | Subject { id, .. } | HttpMethod { id, .. } => {
-                            id.is_some()
-                        }
-                    });
-                let to_retain = to_retain
-                    .into_iter()
-                    .map(|rule| (id, rule).try_into())
-                    .collect::<anyhow::Result<Vec<Rule>>>()?;
-                let to_create = to_create
-                    .into_iter()
-                    .map(|rule| (id, rule).into())
-                    .collect::<Vec<NewRule>>();
-                db::delete_rules(&db_pool, id, &to_retain)?;
-                db::update_rules(&db_pool, to_retain)?;
-                db::create_rules(&db_pool, &to_create)?;
-                db::find_recipe(&db_pool, id)
-            } else {
-                bail!("Unable to update recipe, {}", id)
+    let cascaded = web::block(move || {
+        db::in_transaction(&db_pool, |conn| {
+            super::update_cascaded(conn, &namespace, id, changes)
+        })?;
+        db::find_recipe(&db_pool, &namespace, id)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    let updated: shared::Recipe = cascaded.try_into().map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+#[actix_web::delete("/api/v1/recipe/{id}")]
+pub(crate) async fn delete_recipe(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    path: Path<Uuid>,
+) -> Result<HttpResponse> {
+    let to_delete = path.into_inner();
+    web::block(move || {
+        db::in_transaction(&db_pool, |conn| {
+            super::delete_editable(conn, &namespace, to_delete)
+        })
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().body(format!("Deleted recipe, {}", to_delete)))
+}
+
+/// Deletes every recipe whose ID is in the body at once; if any of them is not in the namespace,
+/// none of them are deleted.
+#[actix_web::delete("/api/v1/recipe/bulk")]
+pub(crate) async fn delete_recipes(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    ids: Bytes,
+) -> Result<HttpResponse> {
+    let ids: Vec<Uuid> = serde_json::from_slice(&ids)
+        .with_context(|| "The body must be a JSON array of recipe IDs!")
+        .map_err(ErrorBadRequest)?;
+    let deleted = web::block(move || {
+        in_bulk(&db_pool, ids, |conn, to_delete| {
+            if super::delete_editable(conn, &namespace, to_delete)? == 0 {
+                bail!("There is no recipe, {}, to delete!", to_delete)
             }
+            Ok(to_delete)
         })
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    match deleted {
+        Ok(deleted) => Ok(HttpResponse::Ok().body(format!("Deleted {} recipes", deleted.len()))),
+        Err(failed) => Ok(HttpResponse::BadRequest().json(vec![failed])),
+    }
+}
+
+/// Every recipe in the namespace, with its rules, headers and responses, to snapshot into version
+/// control and restore with `PUT /api/v1/recipe/bulk`. Timestamps are left out so that an
+/// unchanged recipe exports the same each time.
+#[actix_web::get("/api/v1/recipe/export")]
+pub(crate) async fn export_recipes(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    export: Query<Export>,
+) -> Result<HttpResponse> {
+    let recipes = web::block(move || db::export_recipes(&db_pool, &namespace))
         .await
         .map_err(ErrorInternalServerError)?
-    };
-    let created: shared::Recipe = RecipeCascaded(recipe, rules)
-        .try_into()
+        .into_iter()
+        .map(|cascaded| {
+            let recipe: shared::Recipe = cascaded.try_into()?;
+            Ok(shared::Recipe {
+                created_at: None,
+                updated_at: None,
+                ..recipe
+            })
+        })
+        .collect::<anyhow::Result<Vec<shared::Recipe>>>()
         .map_err(ErrorInternalServerError)?;
-    Ok(HttpResponse::Ok().json(created))
+    match export.format {
+        ExportFormat::Json => {
+            let body = serde_json::to_string_pretty(&recipes).map_err(ErrorInternalServerError)?;
+            Ok(HttpResponse::Ok()
+                .content_type(mime::APPLICATION_JSON.as_ref())
+                .body(body))
+        }
+        ExportFormat::Ndjson => {
+            let mut body = String::new();
+            for recipe in recipes {
+                body.push_str(&serde_json::to_string(&recipe).map_err(ErrorInternalServerError)?);
+                body.push('\n');
+            }
+            Ok(HttpResponse::Ok().content_type(NDJSON).body(body))
+        }
+    }
 }
 
-#[actix_web::delete("/api/v1/recipe/{id}")]
-pub(crate) async fn delete_recipe(db_pool: Data<DbPool>, path: Path<Uuid>) -> Result<HttpResponse> {
-    let to_delete = path.into_inner();
-    web::block(move || db::delete_recipe(&db_pool, to_delete))
+#[actix_web::get("/api/v1/journal")]
+pub(crate) async fn list_journal(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    filter: Query<shared::JournalFilter>,
+) -> Result<HttpResponse> {
+    let page = web::block(move || {
+        list_journal_offset_limit(
+            &db_pool,
+            &namespace,
+            &filter,
+            super::DEFAULT_OFFSET,
+            super::DEFAULT_LIMIT,
+        )
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[actix_web::get("/api/v1/journal/offset/{offset}")]
+pub(crate) async fn list_journal_page(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    offset: Path<i64>,
+    filter: Query<shared::JournalFilter>,
+) -> Result<HttpResponse> {
+    let page = web::block(move || {
+        list_journal_offset_limit(
+            &db_pool,
+            &namespace,
+            &filter,
+            offset.into_inner(),
+            super::DEFAULT_LIMIT,
+        )
+    })
+    .await
+    .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[actix_web::get("/api/v1/journal/count")]
+pub(crate) async fn count_journal(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    filter: Query<shared::JournalFilter>,
+) -> Result<HttpResponse> {
+    let count = web::block(move || db::count_journal_entries(&db_pool, &namespace, &filter))
         .await
         .map_err(ErrorInternalServerError)?;
-    Ok(HttpResponse::Ok().body(format!("Deleted recipe, {}", to_delete)))
+    Ok(HttpResponse::Ok().json(json! {{ "count": count }}))
+}
+
+#[actix_web::delete("/api/v1/journal")]
+pub(crate) async fn clear_journal(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
+    let count = web::block(move || db::clear_journal(&db_pool, &namespace))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().body(format!("Deleted {} journal entries", count)))
+}
+
+#[actix_web::get("/api/v1/scenario")]
+pub(crate) async fn list_scenarios(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
+    let scenarios = web::block(move || super::list_scenarios(&db_pool, &namespace))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(scenarios))
+}
+
+#[actix_web::get("/api/v1/scenario/{name}")]
+pub(crate) async fn get_scenario(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    path: Path<String>,
+) -> Result<HttpResponse> {
+    let to_find = path.into_inner();
+    let scenarios = web::block(move || super::list_scenarios(&db_pool, &namespace))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let scenario = scenarios
+        .into_iter()
+        .find(|scenario| scenario.name == to_find)
+        .ok_or_else(|| ErrorNotFound(format!("There is no scenario named {}!", to_find)))?;
+    Ok(HttpResponse::Ok().json(scenario))
+}
+
+#[actix_web::delete("/api/v1/scenario/{name}")]
+pub(crate) async fn reset_scenario(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+    path: Path<String>,
+) -> Result<HttpResponse> {
+    let to_reset = path.into_inner();
+    let name = to_reset.clone();
+    web::block(move || db::reset_scenarios(&db_pool, &namespace, Some(&name)))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().body(format!(
+        "Reset scenario, {}, to {}",
+        to_reset,
+        shared::SCENARIO_STARTED
+    )))
+}
+
+#[actix_web::delete("/api/v1/scenario")]
+pub(crate) async fn reset_scenarios(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
+    let count = web::block(move || db::reset_scenarios(&db_pool, &namespace, None))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().body(format!(
+        "Reset {} scenarios to {}",
+        count,
+        shared::SCENARIO_STARTED
+    )))
+}
+
+#[actix_web::get("/api/v1/namespace")]
+pub(crate) async fn list_namespaces(db_pool: Data<DbPool>) -> Result<HttpResponse> {
+    let namespaces = web::block(move || super::list_namespaces(&db_pool))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(namespaces))
+}
+
+/// Deletes everything a namespace owns, for tearing down after a test run.
+#[actix_web::delete("/api/v1/namespace/{namespace}")]
+pub(crate) async fn delete_namespace(
+    db_pool: Data<DbPool>,
+    namespace: Namespace,
+) -> Result<HttpResponse> {
+    let to_delete = namespace.clone();
+    let count = web::block(move || db::delete_namespace(&db_pool, &to_delete))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().body(format!(
+        "Deleted namespace, {}, and its {} recipes",
+        &*namespace, count
+    )))
+}
+
+/// Mints a signed token with the claims, expiry, key and `kid` the body asks for, so that tests
+/// can make calls that an Authenticated rule trusts.
+#[actix_web::post("/api/v1/token")]
+pub(crate) async fn issue_token(body: Bytes) -> Result<HttpResponse> {
+    let request: shared::TokenRequest = if body.is_empty() {
+        shared::TokenRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .with_context(|| "Could not parse the post body as a token request!")
+            .map_err(ErrorBadRequest)?
+    };
+    // a key from a JWKS is chosen from the document as it is now
+    let key_set = match (&request.key_path, &*config::KEY_PATH_KIND) {
+        (Some(_), KeyPathKind::Jwks(source)) => Some(
+            load_key_set(source)
+                .await
+                .map_err(ErrorInternalServerError)?,
+        ),
+        _ => None,
+    };
+    let issued = web::block(move || issue(request, key_set.as_ref()))
+        .await
+        .map_err(ErrorBadRequest)?;
+    Ok(HttpResponse::Ok().json(issued))
+}
+
+/// The JWKS that publishes the keys the issuer signs tokens with when no key path is given.
+#[actix_web::get("/api/v1/jwks")]
+pub(crate) async fn get_jwks() -> Result<HttpResponse> {
+    let key_set = web::block(|| Ok::<_, anyhow::Error>(issuer_key_set()))
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(key_set))
+}
+
+fn list_journal_offset_limit(
+    db_pool: &DbPool,
+    namespace: &str,
+    filter: &shared::JournalFilter,
+    offset: i64,
+    limit: i64,
+) -> anyhow::Result<shared::JournalPage> {
+    let (total, entries) = db::find_journal_entries(db_pool, namespace, filter, offset, limit)?;
+    let entries = entries
+        .into_iter()
+        .map(JournalCascaded::try_into)
+        .collect::<anyhow::Result<Vec<shared::JournalEntry>>>()?;
+    Ok(shared::JournalPage {
+        total,
+        offset,
+        limit,
+        entries,
+    })
+}
+
+/// Applies a change to each item, in order, in one transaction, stopping at the first that fails
+/// and reporting it, in which case none of the changes are kept.
+fn in_bulk<I, T>(
+    db_pool: &DbPool,
+    items: Vec<I>,
+    mut change: impl FnMut(&dyn Storage, I) -> anyhow::Result<T>,
+) -> anyhow::Result<Result<Vec<T>, shared::BulkError>> {
+    let mut failed = None;
+    let changed = db::in_transaction(db_pool, |conn| {
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                change(conn, item).map_err(|error| {
+                    failed = Some(shared::BulkError {
+                        index,
+                        error: format!("{}", error),
+                    });
+                    error
+                })
+            })
+            .collect()
+    });
+    match failed {
+        Some(failed) => Ok(Err(failed)),
+        None => changed.map(Ok),
+    }
+}
+
+/// Loads the recipes saved by a bulk change, once it has been kept, in the same order as the
+/// change.
+fn find_bulk(
+    db_pool: &DbPool,
+    namespace: &str,
+    saved: Result<Vec<Uuid>, shared::BulkError>,
+) -> anyhow::Result<Result<Vec<RecipeCascaded>, shared::BulkError>> {
+    match saved {
+        Ok(ids) => db::find_cascaded(db_pool, namespace, &ids).map(Ok),
+        Err(failed) => Ok(Err(failed)),
+    }
+}
+
+fn bulk_response(saved: Result<Vec<RecipeCascaded>, shared::BulkError>) -> Result<HttpResponse> {
+    match saved {
+        Ok(saved) => {
+            let saved = saved
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<anyhow::Result<Vec<shared::Recipe>>>()
+                .map_err(ErrorInternalServerError)?;
+            Ok(HttpResponse::Ok().json(saved))
+        }
+        Err(failed) => Ok(HttpResponse::BadRequest().json(vec![failed])),
+    }
+}
+
+/// Validates each recipe in the body of a bulk change, either a JSON array or newline delimited
+/// JSON, returning what is wrong with every invalid one, if any are.
+fn validate_bulk(
+    body: &[u8],
+    validate: fn(Value) -> anyhow::Result<shared::Recipe>,
+) -> anyhow::Result<Result<Vec<RecipeChanges>, Vec<shared::BulkError>>> {
+    let items = parse_bulk(body)?;
+    let mut changes = Vec::with_capacity(items.len());
+    let mut errors = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        match validate(item).and_then(TryInto::try_into) {
+            Ok(item) => changes.push(item),
+            Err(error) => errors.push(shared::BulkError {
+                index,
+                error: format!("{}", error),
+            }),
+        }
+    }
+    Ok(if errors.is_empty() {
+        Ok(changes)
+    } else {
+        Err(errors)
+    })
+}
+
+fn parse_bulk(body: &[u8]) -> anyhow::Result<Vec<Value>> {
+    let body = std::str::from_utf8(body).with_context(|| "The body must be UTF-8 text!")?;
+    if body.trim_start().starts_with('[') {
+        return serde_json::from_str(body)
+            .with_context(|| "Could not parse the body as a JSON array of recipes!");
+    }
+    body.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Could not parse line {} as JSON!", number + 1))
+        })
+        .collect()
 }
 
 fn validate_post(post: Value) -> anyhow::Result<shared::Recipe> {
@@ -151,28 +621,232 @@ fn validate_put(put: Value) -> anyhow::Result<shared::Recipe> {
     validate_change(put, "update")
 }
 
-fn validate_change(value: Value, action: &str) -> anyhow::Result<shared::Recipe> {
+/// A recipe in a bulk upsert is updated if it has the ID of an existing recipe and created
+/// otherwise, so its ID is optional.
+fn validate_upsert(upsert: Value) -> anyhow::Result<shared::Recipe> {
+    validate_change(upsert, "save")
+}
+
+pub(super) fn validate_change(value: Value, action: &str) -> anyhow::Result<shared::Recipe> {
     let endpoint = value
         .get("url")
         .and_then(Value::as_str)
         .ok_or_else(|| format_err!("You must specify a URL in order to {} a recipe!", action))?;
     validate_url(endpoint)?;
+    if let Some(status) = value.get("status") {
+        validate_status(status)?;
+    }
+    if let Some(headers) = value.get("headers") {
+        let headers = headers
+            .as_array()
+            .ok_or_else(|| format_err!("Headers property must be an array of JSON objects!"))?;
+        validate_headers(&headers)?;
+    }
     if let Some(rules) = value.get("rules") {
         let rules = rules
             .as_array()
             .ok_or_else(|| format_err!("Rules property must be an array of JSON objects!"))?;
         validate_rules(&rules)?;
     }
-    value.get("payload").ok_or_else(|| {
+    let content_type = if let Some(content_type) = value.get("content_type") {
+        let content_type = content_type
+            .as_str()
+            .ok_or_else(|| format_err!("The content_type property must be a string!"))?;
+        validate_content_type(content_type)?;
+        content_type.to_owned()
+    } else {
+        shared::default_content_type()
+    };
+    let payload = value.get("payload").ok_or_else(|| {
         format_err!(
             "You must include a payload in order to {} a recipe!",
             action
         )
     })?;
+    payload_to_bytes(&content_type, payload.clone())?;
+    if let Some(draft) = value.get("draft") {
+        draft
+            .as_bool()
+            .ok_or_else(|| format_err!("The draft property must be true or false!"))?;
+    }
+    if let Some(templated) = value.get("templated") {
+        validate_templated(templated, &content_type, payload)?;
+    }
+    validate_scenario(&value)?;
+    if let Some(responses) = value.get("responses") {
+        let responses = responses
+            .as_array()
+            .ok_or_else(|| format_err!("Responses property must be an array of JSON objects!"))?;
+        validate_responses(&responses)?;
+    }
+    if let Some(sequence_mode) = value.get("sequence_mode") {
+        validate_sequence_mode(sequence_mode)?;
+    }
+    match value.get("delay") {
+        None | Some(Value::Null) => (),
+        Some(delay) => validate_delay(delay)?,
+    }
+    match value.get("fault") {
+        None | Some(Value::Null) => (),
+        Some(fault) => validate_fault(fault)?,
+    }
     serde_json::from_value(value).map_err(anyhow::Error::from)
 }
 
+fn validate_templated(
+    templated: &Value,
+    content_type: &str,
+    payload: &Value,
+) -> anyhow::Result<()> {
+    let templated = templated
+        .as_bool()
+        .ok_or_else(|| format_err!("The templated property must be true or false!"))?;
+    if templated && PayloadKind::of(content_type) == PayloadKind::Binary {
+        bail!(
+            "The payload for content type, {}, is binary and cannot be templated!",
+            content_type
+        )
+    }
+    if templated {
+        validate_template(payload)?;
+    }
+    Ok(())
+}
+
+fn validate_responses(responses: &[Value]) -> anyhow::Result<()> {
+    responses.iter().try_for_each(validate_response)
+}
+
+/// A response in a recipe's sequence has the same properties as the recipe's own response.
+fn validate_response(response: &Value) -> anyhow::Result<()> {
+    if !response.is_object() {
+        bail!("A response must be a JSON object!")
+    }
+    if let Some(status) = response.get("status") {
+        validate_status(status)?;
+    }
+    let content_type = if let Some(content_type) = response.get("content_type") {
+        let content_type = content_type
+            .as_str()
+            .ok_or_else(|| format_err!("The content_type property must be a string!"))?;
+        validate_content_type(content_type)?;
+        content_type.to_owned()
+    } else {
+        shared::default_content_type()
+    };
+    let payload = response
+        .get("payload")
+        .ok_or_else(|| format_err!("A response must include a payload!"))?;
+    payload_to_bytes(&content_type, payload.clone())?;
+    if let Some(templated) = response.get("templated") {
+        validate_templated(templated, &content_type, payload)?;
+    }
+    Ok(())
+}
+
+fn validate_sequence_mode(sequence_mode: &Value) -> anyhow::Result<()> {
+    match sequence_mode.as_str() {
+        Some("Cycle") | Some("StickOnLast") | Some("FallThrough") => Ok(()),
+        _ => bail!(
+            "The sequence_mode property must be one of \"Cycle\", \"StickOnLast\", or \"FallThrough\"!"
+        ),
+    }
+}
+
+/// A recipe that requires or changes a state has to name the scenario that the state belongs to.
+fn validate_scenario(value: &Value) -> anyhow::Result<()> {
+    let text = |name: &str| -> anyhow::Result<Option<&str>> {
+        match value.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(text)) if !text.trim().is_empty() => Ok(Some(text.as_str())),
+            Some(_) => bail!("The {} property must be a non-empty string!", name),
+        }
+    };
+    let scenario = text("scenario")?;
+    let required_state = text("required_state")?;
+    let new_state = text("new_state")?;
+    match scenario {
+        Some(scenario) if !shared::is_url_safe(scenario) => bail!(
+            "The scenario, {}, may only have letters, digits, '-', '_' and '.'!",
+            scenario
+        ),
+        None if required_state.is_some() || new_state.is_some() => {
+            bail!("A recipe that requires or changes a state must name its scenario!")
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+fn validate_delay(delay: &Value) -> anyhow::Result<()> {
+    let delay: shared::Delay = serde_json::from_value(delay.clone()).with_context(|| {
+        "The delay property must be an object with one property, \"Fixed\", \"Uniform\", \"Normal\", or \"LogNormal\", whose value has the delay's settings!"
+    })?;
+    let millis = |millis: u32, name: &str| -> anyhow::Result<()> {
+        if i32::try_from(millis).is_err() {
+            bail!("The delay's {}, {}, is too long!", name, millis)
+        }
+        Ok(())
+    };
+    use shared::Delay::*;
+    match delay {
+        Fixed { millis: fixed } => millis(fixed, "millis"),
+        Uniform {
+            min_millis,
+            max_millis,
+        } => {
+            millis(min_millis, "min_millis")?;
+            millis(max_millis, "max_millis")?;
+            if min_millis > max_millis {
+                bail!("The delay's min_millis must not be more than its max_millis!")
+            }
+            Ok(())
+        }
+        Normal {
+            mean_millis,
+            std_dev_millis,
+        } => {
+            millis(mean_millis, "mean_millis")?;
+            millis(std_dev_millis, "std_dev_millis")
+        }
+        LogNormal {
+            median_millis,
+            sigma,
+        } => {
+            millis(median_millis, "median_millis")?;
+            if !sigma.is_finite() || sigma < 0.0 {
+                bail!("The delay's sigma must be a number that is zero or more!")
+            }
+            Ok(())
+        }
+    }
+}
+
+fn validate_fault(fault: &Value) -> anyhow::Result<()> {
+    let fault: shared::Fault = serde_json::from_value(fault.clone()).with_context(|| {
+        "The fault property must be one of \"ConnectionReset\", \"PartialThenReset\", or \"EmptyReply\", or an object with the property, \"Trickle\", whose value has its settings!"
+    })?;
+    if let shared::Fault::Trickle {
+        chunk_bytes,
+        interval_millis,
+    } = fault
+    {
+        if chunk_bytes == 0 || i32::try_from(chunk_bytes).is_err() {
+            bail!("The trickle's chunk_bytes must be a positive integer!")
+        }
+        if i32::try_from(interval_millis).is_err() {
+            bail!(
+                "The trickle's interval_millis, {}, is too long!",
+                interval_millis
+            )
+        }
+    }
+    Ok(())
+}
+
 fn validate_url(endpoint: &str) -> anyhow::Result<()> {
+    // path parameters aren't valid in a URI, so the checks below use a sample of the template
+    let endpoint = UrlTemplate::try_from(endpoint)?.sample();
     let endpoint = endpoint
         .parse::<Uri>()
         .with_context(|| format_err!("Could not parse the URL field, {}, as a URL!", endpoint))?;
@@ -192,6 +866,42 @@ fn validate_url(endpoint: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn validate_content_type(content_type: &str) -> anyhow::Result<()> {
+    content_type
+        .parse::<Mime>()
+        .with_context(|| format!("The content type, {}, is not valid!", content_type))?;
+    Ok(())
+}
+
+fn validate_status(status: &Value) -> anyhow::Result<()> {
+    let status = status
+        .as_u64()
+        .ok_or_else(|| format_err!("The status property must be a positive integer!"))?;
+    let status = u16::try_from(status)
+        .with_context(|| format!("The status, {}, is not a valid HTTP status code!", status))?;
+    StatusCode::from_u16(status)
+        .with_context(|| format!("The status, {}, is not a valid HTTP status code!", status))?;
+    Ok(())
+}
+
+fn validate_headers(headers: &[Value]) -> anyhow::Result<()> {
+    headers.iter().try_for_each(validate_header)
+}
+
+fn validate_header(header: &Value) -> anyhow::Result<()> {
+    let name = header.get("name").and_then(Value::as_str).ok_or_else(|| {
+        format_err!("A header must have a property, \"name\", with a string value!")
+    })?;
+    HeaderName::from_bytes(name.as_bytes())
+        .with_context(|| format!("The header name, {}, is not valid!", name))?;
+    let value = header.get("value").and_then(Value::as_str).ok_or_else(|| {
+        format_err!("A header must have a property, \"value\", with a string value!")
+    })?;
+    HeaderValue::from_str(value)
+        .with_context(|| format!("The value for header, {}, is not valid!", name))?;
+    Ok(())
+}
+
 fn validate_rules(rules: &[Value]) -> anyhow::Result<()> {
     rules
         .iter()
@@ -205,7 +915,7 @@ fn validate_rule(rule: &Value) -> anyhow::Result<()> {
         .as_object()
         .ok_or_else(|| format_err!("Rule must be a JSON object!"))?;
     if rule.len() != 1 {
-        bail!("Rule JSON can only have one property, whose name must match a rule type, e.g. \"Authenticated\", \"Subject\", or \"HttpMethod\"")
+        bail!("Rule JSON can only have one property, whose name must match a rule type, e.g. \"Authenticated\", \"Subject\", \"HttpMethod\", \"Header\", \"PathParam\", \"Body\", \"QueryParam\", \"NthCall\", \"Claim\", \"Unexpired\", \"BasicAuth\", \"ApiKey\", or \"ClientCert\"")
     }
     let rule_type = rule
         .keys()
@@ -224,11 +934,42 @@ fn validate_rule(rule: &Value) -> anyhow::Result<()> {
         Authenticated => validate_authenticated(&rule),
         Subject => validate_subject(&rule),
         HttpMethod => validate_http_method(&rule),
+        Header => validate_header_rule(&rule),
+        PathParam => validate_path_param_rule(&rule),
+        Body => validate_body_rule(&rule),
+        QueryParam => validate_query_param_rule(&rule),
+        NthCall => validate_nth_call_rule(&rule),
+        Claim => validate_claim_rule(&rule),
+        Unexpired => Ok(()),
+        BasicAuth => validate_basic_auth_rule(&rule),
+        ApiKey => validate_api_key_rule(&rule),
+        ClientCert => validate_client_cert_rule(&rule),
     }
 }
 
 fn validate_authenticated(rule: &Value) -> anyhow::Result<()> {
     rule.get("key_path").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"Authenticated\", must have a property, \"key_path\", in its body with a string value!"))?;
+    if let Some(algorithms) = rule.get("algorithms") {
+        let algorithms = algorithms.as_array().ok_or_else(|| {
+            format_err!("The value of the property, \"algorithms\", must be an array!")
+        })?;
+        for algorithm in algorithms {
+            let algorithm = algorithm
+                .as_str()
+                .ok_or_else(|| format_err!("Each of the \"algorithms\" must be a string!"))?;
+            Algorithm::named(algorithm).ok_or_else(|| {
+                format_err!(
+                    "The algorithm, {}, is not one of {}!",
+                    algorithm,
+                    Algorithm::ALL
+                        .iter()
+                        .map(|algorithm| algorithm.name())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )
+            })?;
+        }
+    }
     Ok(())
 }
 
@@ -248,6 +989,140 @@ fn validate_http_method(rule: &Value) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn validate_header_rule(rule: &Value) -> anyhow::Result<()> {
+    let header_name = rule.get("header_name").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"Header\", must have a property, \"header_name\", in its body with a string value!"))?;
+    HeaderName::from_bytes(header_name.as_bytes())
+        .with_context(|| format!("The header name, {}, is not valid!", header_name))?;
+    validate_match(rule, "Header")
+}
+
+fn validate_path_param_rule(rule: &Value) -> anyhow::Result<()> {
+    rule.get("param_name").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"PathParam\", must have a property, \"param_name\", in its body with a string value!"))?;
+    validate_match(rule, "PathParam")
+}
+
+fn validate_query_param_rule(rule: &Value) -> anyhow::Result<()> {
+    rule.get("param_name").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"QueryParam\", must have a property, \"param_name\", in its body with a string value!"))?;
+    validate_match(rule, "QueryParam")
+}
+
+fn validate_nth_call_rule(rule: &Value) -> anyhow::Result<()> {
+    rule.get("nth")
+        .and_then(Value::as_u64)
+        .filter(|nth| *nth > 0 && i32::try_from(*nth).is_ok())
+        .ok_or_else(|| format_err!("The rule type, \"NthCall\", must have a property, \"nth\", in its body with a positive integer value!"))?;
+    Ok(())
+}
+
+fn validate_claim_rule(rule: &Value) -> anyhow::Result<()> {
+    let claim = rule.get("claim").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"Claim\", must have a property, \"claim\", in its body with a string value!"))?;
+    claim_pointer(claim)?;
+    validate_match(rule, "Claim")
+}
+
+fn validate_basic_auth_rule(rule: &Value) -> anyhow::Result<()> {
+    let username = rule.get("username").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"BasicAuth\", must have a property, \"username\", in its body with a string value!"))?;
+    // the username ends at the first colon of basic credentials, so it can't have one itself
+    if username.contains(':') {
+        bail!("The username, {}, cannot have a colon!", username)
+    }
+    match rule.get("password") {
+        None | Some(Value::Null) | Some(Value::String(_)) => Ok(()),
+        Some(_) => bail!("The value of the property, \"password\", must be a string!"),
+    }
+}
+
+fn validate_api_key_rule(rule: &Value) -> anyhow::Result<()> {
+    let key_location = rule.get("key_location").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"ApiKey\", must have a property, \"key_location\", in its body with a string value!"))?;
+    let key_location: ApiKeyLocation = key_location.try_into()?;
+    let key_name = rule.get("key_name").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"ApiKey\", must have a property, \"key_name\", in its body with a string value!"))?;
+    if key_location == ApiKeyLocation::Header {
+        HeaderName::from_bytes(key_name.as_bytes())
+            .with_context(|| format!("The header name, {}, is not valid!", key_name))?;
+    } else if key_name.is_empty() {
+        bail!("The name of the query parameter with the API key is required!")
+    }
+    rule.get("api_key")
+        .and_then(Value::as_str)
+        .filter(|api_key| !api_key.is_empty())
+        .ok_or_else(|| format_err!("The rule type, \"ApiKey\", must have a property, \"api_key\", in its body with a string value that isn't empty!"))?;
+    Ok(())
+}
+
+/// A client certificate rule matches the subject, the fingerprint or both, so it must have at
+/// least one of them.
+fn validate_client_cert_rule(rule: &Value) -> anyhow::Result<()> {
+    let property = |name: &str| -> anyhow::Result<Option<&str>> {
+        match rule.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value)),
+            Some(_) => bail!(
+                "The property, \"{}\", of the rule type, \"ClientCert\", must have a string value!",
+                name
+            ),
+        }
+    };
+    match (property("subject")?, property("fingerprint")?) {
+        (None, None) => bail!("The rule type, \"ClientCert\", must have a property, \"subject\", \"fingerprint\" or both, in its body!"),
+        (_, Some(fingerprint)) => {
+            let normalized = normalize_fingerprint(fingerprint);
+            if normalized.len() != 64 || !normalized.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!(
+                    "The fingerprint, {}, must be a SHA-256 fingerprint in hexadecimal!",
+                    fingerprint
+                )
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A body rule matches the whole body unless it has either a path into a JSON body or the name of
+/// a form field, but not both.
+fn validate_body_rule(rule: &Value) -> anyhow::Result<()> {
+    let target = |name: &str| -> anyhow::Result<Option<&str>> {
+        match rule.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value)),
+            Some(_) => bail!(
+                "The property, \"{}\", of the rule type, \"Body\", must have a string value!",
+                name
+            ),
+        }
+    };
+    match (target("body_path")?, target("form_field")?) {
+        (Some(_), Some(_)) => bail!("The rule type, \"Body\", can match either a \"body_path\" or a \"form_field\", but not both!"),
+        (Some(body_path), None) => {
+            json_pointer(body_path)?;
+        }
+        (None, Some("")) => bail!("The property, \"form_field\", of the rule type, \"Body\", must name a field!"),
+        _ => (),
+    }
+    validate_match(rule, "Body")
+}
+
+/// Checks the match mode and match value shared by rules that compare a value from the request.
+fn validate_match(rule: &Value, rule_type: &str) -> anyhow::Result<()> {
+    let match_mode = rule.get("match_mode").and_then(Value::as_str).ok_or_else(|| format_err!("The rule type, \"{}\", must have a property, \"match_mode\", in its body with a string value!", rule_type))?;
+    let match_mode: MatchMode = match_mode.try_into()?;
+    let match_value = rule.get("match_value").and_then(Value::as_str);
+    match (match_mode, match_value) {
+        (MatchMode::Present, _) | (MatchMode::Absent, _) => Ok(()),
+        (_, None) => bail!("The rule type, \"{}\", must have a property, \"match_value\", with a string value for the match mode!", rule_type),
+        (MatchMode::Regex, Some(match_value)) => {
+            Regex::new(match_value).with_context(|| {
+                format!(
+                    "The match value, {}, is not a valid regular expression!",
+                    match_value
+                )
+            })?;
+            Ok(())
+        }
+        (_, Some(_)) => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -266,59 +1141,254 @@ mod test {
                 },
                 {
                     "HttpMethod":{"http_method":"Get"}
+                },
+                {
+                    "Header":{"header_name":"X-Foo","match_mode":"Exact","match_value":"bar"}
+                },
+                {
+                    "Header":{"header_name":"X-Bar","match_mode":"Absent"}
                 }
             ],
             "payload": {
-                "foo": "bar"
+                "foo": "bar"
+            }
+        }};
+
+        validate_post(json)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_rules() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "rules": [],
+            "payload": {
+                "foo": "bar"
+            }
+        }};
+
+        validate_post(json)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_rules() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "payload": {
+                "foo": "bar"
+            }
+        }};
+
+        validate_post(json)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_payload() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "rules": []
+        }};
+
+        if let Err(error) = validate_post(json) {
+            assert!(
+                error.to_string().contains("payload"),
+                "Error should have been about missing payload!"
+            );
+            Ok(())
+        } else {
+            bail!("Validation should failed due to missing payload!")
+        }
+    }
+
+    #[test]
+    fn test_status() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "status": 503,
+            "payload": {
+                "foo": "bar"
+            }
+        }};
+
+        let recipe = validate_post(json)?;
+        assert_eq!(503, recipe.status);
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_status() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "payload": {
+                "foo": "bar"
+            }
+        }};
+
+        let recipe = validate_post(json)?;
+        assert_eq!(200, recipe.status);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_status() -> anyhow::Result<()> {
+        for status in &[json!(42), json!(1000), json!("201"), json!(-1)] {
+            if let Err(error) = validate_status(status) {
+                assert!(
+                    error.to_string().contains("status"),
+                    "Error should have been about invalid status! ({})",
+                    error
+                );
+            } else {
+                bail!(
+                    "Validation should have failed due to invalid status, {}",
+                    status
+                )
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_headers() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "status": 201,
+            "headers": [
+                {"name": "Location", "value": "http://test.local/api/rest/1"},
+                {"name": "Set-Cookie", "value": "a=1"},
+                {"name": "Set-Cookie", "value": "b=2"}
+            ],
+            "payload": {
+                "foo": "bar"
+            }
+        }};
+
+        let recipe = validate_post(json)?;
+        assert_eq!(3, recipe.headers.len());
+        assert_eq!("Set-Cookie", recipe.headers[2].name);
+        assert_eq!("b=2", recipe.headers[2].value);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_header() -> anyhow::Result<()> {
+        if let Err(error) = validate_header(&json! {{
+            "name": "Not A Header",
+            "value": "foo"
+        }}) {
+            assert!(
+                error.to_string().contains("header name"),
+                "Error should have been about invalid header name! ({})",
+                error
+            );
+        } else {
+            bail!("Validation should have failed due to invalid header name")
+        }
+        if let Err(error) = validate_header(&json! {{
+            "name": "X-Foo"
+        }}) {
+            assert!(
+                error.to_string().contains("value"),
+                "Error should have been about missing header value! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to missing header value")
+        }
+    }
+
+    #[test]
+    fn test_templated() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/users/{id}",
+            "templated": true,
+            "payload": {
+                "id": "{{path.id}}"
             }
         }};
-
-        validate_post(json)?;
+        let recipe = validate_post(json)?;
+        assert!(recipe.templated);
         Ok(())
     }
 
     #[test]
-    fn test_empty_rules() -> anyhow::Result<()> {
+    fn test_invalid_template() -> anyhow::Result<()> {
         let json = json! {{
-            "url": "http://test.local/api/rest",
-            "rules": [],
+            "url": "http://test.local/api/users/{id}",
+            "templated": true,
             "payload": {
-                "foo": "bar"
+                "id": "{{session.id}}"
             }
         }};
+        if let Err(error) = validate_post(json) {
+            assert!(
+                error.to_string().contains("placeholder"),
+                "Error should have been about an invalid placeholder! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to an invalid placeholder")
+        }
+    }
 
-        validate_post(json)?;
+    #[test]
+    fn test_text_payload() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "content_type": "application/xml",
+            "payload": "<foo>bar</foo>"
+        }};
+        let recipe = validate_post(json)?;
+        assert_eq!("application/xml", recipe.content_type);
         Ok(())
     }
 
     #[test]
-    fn test_no_rules() -> anyhow::Result<()> {
+    fn test_binary_payload() -> anyhow::Result<()> {
         let json = json! {{
             "url": "http://test.local/api/rest",
-            "payload": {
-                "foo": "bar"
-            }
+            "content_type": "image/png",
+            "payload": "iVBORw0KGgo="
         }};
-
         validate_post(json)?;
-        Ok(())
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "content_type": "image/png",
+            "payload": "not base64!"
+        }};
+        if let Err(error) = validate_post(json) {
+            assert!(
+                error.to_string().contains("base64"),
+                "Error should have been about base64 encoding! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to a payload that isn't base64")
+        }
     }
 
     #[test]
-    fn test_missing_payload() -> anyhow::Result<()> {
+    fn test_invalid_content_type() -> anyhow::Result<()> {
         let json = json! {{
             "url": "http://test.local/api/rest",
-            "rules": []
+            "content_type": "not a content type",
+            "payload": "foo"
         }};
-
         if let Err(error) = validate_post(json) {
             assert!(
-                error.to_string().contains("payload"),
-                "Error should have been about missing payload!"
+                error.to_string().contains("content type"),
+                "Error should have been about the content type! ({})",
+                error
             );
             Ok(())
         } else {
-            bail!("Validation should failed due to missing payload!")
+            bail!("Validation should have failed due to an invalid content type")
         }
     }
 
@@ -348,6 +1418,35 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_url_with_query() -> anyhow::Result<()> {
+        if let Err(error) = validate_url("http://test.local/api/orders?status=open") {
+            assert!(
+                error.to_string().contains("QueryParam"),
+                "Error should have been about the query! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to the query")
+        }
+    }
+
+    #[test]
+    fn test_url_template() -> anyhow::Result<()> {
+        validate_url("http://test.local/api/users/{id}/orders/*")?;
+        if let Err(error) = validate_url("http://test.local/api/users/user-{id}") {
+            assert!(
+                error.to_string().contains("parameter"),
+                "Error should have been about invalid path parameter! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to invalid path parameter")
+        }
+    }
+
     #[test]
     fn test_invalid_rule_type() -> anyhow::Result<()> {
         if let Err(error) = validate_rule(&json! {{
@@ -379,6 +1478,27 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_auth_algorithms() -> anyhow::Result<()> {
+        validate_rule(&json! {{"Authenticated": {
+            "key_path":"*",
+            "algorithms":["RS256", "ES256"]
+        }}})?;
+        if let Err(error) = validate_authenticated(&json! {{
+            "key_path":"*",
+            "algorithms":["RS256", "none"]
+        }}) {
+            assert!(
+                error.to_string().contains("none"),
+                "Error should have been about an unknown algorithm! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to an unknown algorithm")
+        }
+    }
+
     #[test]
     fn test_subject_no_subject() -> anyhow::Result<()> {
         if let Err(error) = validate_subject(&json! {{
@@ -426,4 +1546,470 @@ mod test {
             bail!("Validation should have failed due to invalid http method")
         }
     }
+
+    #[test]
+    fn test_header_rule_no_name() -> anyhow::Result<()> {
+        if let Err(error) = validate_header_rule(&json! {{
+            "match_mode":"Present"
+        }}) {
+            assert!(
+                error.to_string().contains("header_name"),
+                "Error should have been about missing header name! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to missing header name")
+        }
+    }
+
+    #[test]
+    fn test_header_rule_no_value() -> anyhow::Result<()> {
+        if let Err(error) = validate_header_rule(&json! {{
+            "header_name":"X-Foo",
+            "match_mode":"CaseInsensitive"
+        }}) {
+            assert!(
+                error.to_string().contains("match_value"),
+                "Error should have been about missing match value! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to missing match value")
+        }
+    }
+
+    #[test]
+    fn test_header_rule_invalid_mode() -> anyhow::Result<()> {
+        if let Err(error) = validate_header_rule(&json! {{
+            "header_name":"X-Foo",
+            "match_mode":"Sometimes",
+            "match_value":"bar"
+        }}) {
+            assert!(
+                error.to_string().contains("match mode"),
+                "Error should have been about invalid match mode! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to invalid match mode")
+        }
+    }
+
+    #[test]
+    fn test_header_rule_invalid_regex() -> anyhow::Result<()> {
+        if let Err(error) = validate_header_rule(&json! {{
+            "header_name":"X-Foo",
+            "match_mode":"Regex",
+            "match_value":"(unclosed"
+        }}) {
+            assert!(
+                error.to_string().contains("regular expression"),
+                "Error should have been about invalid regular expression! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to invalid regular expression")
+        }
+    }
+
+    #[test]
+    fn test_path_param_rule_no_name() -> anyhow::Result<()> {
+        if let Err(error) = validate_path_param_rule(&json! {{
+            "match_mode":"Exact",
+            "match_value":"42"
+        }}) {
+            assert!(
+                error.to_string().contains("param_name"),
+                "Error should have been about missing parameter name! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to missing parameter name")
+        }
+    }
+
+    #[test]
+    fn test_query_param_rule_no_name() -> anyhow::Result<()> {
+        if let Err(error) = validate_query_param_rule(&json! {{
+            "match_mode":"Exact",
+            "match_value":"open"
+        }}) {
+            assert!(
+                error.to_string().contains("param_name"),
+                "Error should have been about missing parameter name! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to missing parameter name")
+        }
+    }
+
+    #[test]
+    fn test_body_rule() -> anyhow::Result<()> {
+        validate_body_rule(&json! {{
+            "match_mode":"Contains",
+            "match_value":"Alice"
+        }})?;
+        validate_body_rule(&json! {{
+            "body_path":"$.order.items[0].sku",
+            "match_mode":"Exact",
+            "match_value":"42"
+        }})?;
+        validate_body_rule(&json! {{
+            "form_field":"name",
+            "match_mode":"Present"
+        }})?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_body_rule_invalid_path() -> anyhow::Result<()> {
+        if let Err(error) = validate_body_rule(&json! {{
+            "body_path":"order.id",
+            "match_mode":"Exact",
+            "match_value":"42"
+        }}) {
+            assert!(
+                error.to_string().contains("JSON pointer"),
+                "Error should have been about an invalid body path! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to an invalid body path")
+        }
+    }
+
+    #[test]
+    fn test_claim_rule() -> anyhow::Result<()> {
+        validate_rule(&json! {{"Claim": {
+            "claim":"roles",
+            "match_mode":"AnyOf",
+            "match_value":"admin,support"
+        }}})?;
+        validate_rule(&json! {{"Claim": {
+            "claim":"$.tenant.id",
+            "match_mode":"Exact",
+            "match_value":"42"
+        }}})?;
+        validate_rule(&json! {{"Unexpired": {}}})?;
+        if let Err(error) = validate_claim_rule(&json! {{
+            "claim":"$.roles[*]",
+            "match_mode":"Present"
+        }}) {
+            assert!(
+                error.to_string().contains("JSON pointer"),
+                "Error should have been about an invalid claim path! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to an invalid claim path")
+        }
+    }
+
+    #[test]
+    fn test_credential_rules() -> anyhow::Result<()> {
+        validate_rule(&json! {{"BasicAuth": {"username":"alice"}}})?;
+        validate_rule(&json! {{"BasicAuth": {"username":"alice","password":"wonderland"}}})?;
+        validate_rule(&json! {{"ApiKey": {
+            "key_location":"Header",
+            "key_name":"X-Api-Key",
+            "api_key":"k-123"
+        }}})?;
+        validate_rule(&json! {{"ApiKey": {
+            "key_location":"Query",
+            "key_name":"api_key",
+            "api_key":"k-123"
+        }}})?;
+        validate_rule(&json! {{"ClientCert": {"subject":"CN=alice,O=Acme"}}})?;
+        validate_rule(&json! {{"ClientCert": {
+            "fingerprint":"AB:".repeat(31) + "AB"
+        }}})?;
+        assert!(validate_basic_auth_rule(&json! {{"username":"al:ice"}}).is_err());
+        assert!(validate_api_key_rule(&json! {{
+            "key_location":"Cookie",
+            "key_name":"key",
+            "api_key":"k-123"
+        }})
+        .is_err());
+        assert!(validate_api_key_rule(&json! {{
+            "key_location":"Header",
+            "key_name":"X Api Key",
+            "api_key":"k-123"
+        }})
+        .is_err());
+        assert!(validate_client_cert_rule(&json! {{}}).is_err());
+        if let Err(error) = validate_client_cert_rule(&json! {{"fingerprint":"abc"}}) {
+            assert!(
+                error.to_string().contains("SHA-256"),
+                "Error should have been about an invalid fingerprint! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to an invalid fingerprint")
+        }
+    }
+
+    #[test]
+    fn test_body_rule_path_and_field() -> anyhow::Result<()> {
+        if let Err(error) = validate_body_rule(&json! {{
+            "body_path":"/name",
+            "form_field":"name",
+            "match_mode":"Present"
+        }}) {
+            assert!(
+                error.to_string().contains("not both"),
+                "Error should have been about having both a path and a field! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to having both a path and a field")
+        }
+    }
+
+    #[test]
+    fn test_scenario() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/orders/1",
+            "scenario": "approval",
+            "required_state": "Approved",
+            "payload": {"status": "approved"}
+        }};
+
+        let recipe = validate_post(json)?;
+        assert_eq!(Some("approval"), recipe.scenario.as_deref());
+        assert_eq!(Some("Approved"), recipe.required_state.as_deref());
+        assert_eq!(None, recipe.new_state);
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_without_scenario() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/orders/1/approve",
+            "new_state": "Approved",
+            "payload": {}
+        }};
+
+        if let Err(error) = validate_post(json) {
+            assert!(
+                error.to_string().contains("scenario"),
+                "Error should have been about the missing scenario! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to a state without a scenario")
+        }
+    }
+
+    #[test]
+    fn test_invalid_scenario() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/orders/1",
+            "scenario": "order approval",
+            "payload": {}
+        }};
+
+        if let Err(error) = validate_post(json) {
+            assert!(
+                error.to_string().contains("letters, digits"),
+                "Error should have been about the scenario name! ({})",
+                error
+            );
+            Ok(())
+        } else {
+            bail!("Validation should have failed due to a scenario name with a space")
+        }
+    }
+
+    #[test]
+    fn test_responses() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "payload": {"status": "pending"},
+            "responses": [
+                {"payload": {"status": "done"}},
+                {"status": 503, "content_type": "text/plain", "payload": "down"}
+            ],
+            "sequence_mode": "Cycle",
+            "rules": [
+                {"NthCall": {"nth": 3}}
+            ]
+        }};
+
+        let recipe = validate_post(json)?;
+        assert_eq!(2, recipe.responses.len());
+        assert_eq!(200, recipe.responses[0].status);
+        assert_eq!(503, recipe.responses[1].status);
+        assert_eq!(shared::SequenceMode::Cycle, recipe.sequence_mode);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_responses() -> anyhow::Result<()> {
+        let invalid = vec![
+            (json!({"responses": [{"status": 200}]}), "payload"),
+            (
+                json!({"responses": [{"status": 1000, "payload": {}}]}),
+                "status",
+            ),
+            (
+                json!({"responses": [{"content_type": "application/octet-stream", "payload": "AA==", "templated": true}]}),
+                "binary",
+            ),
+            (json!({"sequence_mode": "Random"}), "sequence_mode"),
+            (
+                json!({"rules": [{"NthCall": {"nth": 0}}]}),
+                "positive integer",
+            ),
+        ];
+        for (properties, expected) in invalid {
+            let mut json = json! {{
+                "url": "http://test.local/api/rest",
+                "payload": {"foo": "bar"}
+            }};
+            if let (Value::Object(json), Value::Object(properties)) =
+                (&mut json, properties.clone())
+            {
+                json.extend(properties);
+            }
+            match validate_post(json) {
+                Err(error) => assert!(
+                    error.to_string().contains(expected),
+                    "Error should have been about the {}! ({})",
+                    expected,
+                    error
+                ),
+                Ok(_) => bail!("Validation should have failed for {}", properties),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_disruption() -> anyhow::Result<()> {
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "payload": {"foo": "bar"},
+            "delay": {"LogNormal": {"median_millis": 200, "sigma": 0.5}},
+            "fault": {"Trickle": {"chunk_bytes": 4, "interval_millis": 100}}
+        }};
+
+        let recipe = validate_post(json)?;
+        assert_eq!(
+            Some(shared::Delay::LogNormal {
+                median_millis: 200,
+                sigma: 0.5
+            }),
+            recipe.delay
+        );
+        let json = json! {{
+            "url": "http://test.local/api/rest",
+            "payload": {"foo": "bar"},
+            "fault": "ConnectionReset"
+        }};
+        assert_eq!(
+            Some(shared::Fault::ConnectionReset),
+            validate_post(json)?.fault
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_disruption() -> anyhow::Result<()> {
+        let invalid = vec![
+            (
+                json!({"delay": {"Fixed": {"millis": -1}}}),
+                "delay property",
+            ),
+            (
+                json!({"delay": {"Uniform": {"min_millis": 200, "max_millis": 100}}}),
+                "min_millis",
+            ),
+            (
+                json!({"delay": {"Fixed": {"millis": 3_000_000_000u32}}}),
+                "too long",
+            ),
+            (
+                json!({"delay": {"LogNormal": {"median_millis": 100, "sigma": -1.0}}}),
+                "sigma",
+            ),
+            (json!({"fault": "Timeout"}), "fault property"),
+            (
+                json!({"fault": {"Trickle": {"chunk_bytes": 0, "interval_millis": 100}}}),
+                "chunk_bytes",
+            ),
+        ];
+        for (properties, expected) in invalid {
+            let mut json = json! {{
+                "url": "http://test.local/api/rest",
+                "payload": {"foo": "bar"}
+            }};
+            if let (Value::Object(json), Value::Object(properties)) =
+                (&mut json, properties.clone())
+            {
+                json.extend(properties);
+            }
+            match validate_post(json) {
+                Err(error) => assert!(
+                    error.to_string().contains(expected),
+                    "Error should have been about the {}! ({})",
+                    expected,
+                    error
+                ),
+                Ok(_) => bail!("Validation should have failed for {}", properties),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk() -> anyhow::Result<()> {
+        let array = br#"[
+            {"url": "http://test.local/api/orders", "payload": {"orders": []}},
+            {"url": "http://test.local/api/users", "status": 404, "payload": ""}
+        ]"#;
+        let ndjson = br#"{"url": "http://test.local/api/orders", "payload": {"orders": []}}
+
+{"id": "5d3c1c2e-9b1a-4a7e-8d0c-1a2b3c4d5e6f", "url": "http://test.local/api/users", "status": 404, "payload": ""}
+"#;
+        for body in &[&array[..], &ndjson[..]] {
+            let changes = validate_bulk(body, validate_upsert)?
+                .map_err(|errors| format_err!("Unexpected errors, {:?}", errors))?;
+            assert_eq!(2, changes.len());
+            assert_eq!("http://test.local/api/users", changes[1].recipe.url);
+            assert_eq!(404, changes[1].recipe.status_code);
+        }
+        assert!(validate_bulk(b"[{\"url\": ", validate_post).is_err());
+        assert!(validate_bulk(b"{}\n{\"url\": ", validate_post).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_bulk() -> anyhow::Result<()> {
+        let body = br#"[
+            {"url": "http://test.local/api/orders", "payload": {}},
+            {"url": "//test.local/api/orders", "payload": {}},
+            {"url": "http://test.local/api/orders"}
+        ]"#;
+        let errors = validate_bulk(body, validate_post)?
+            .err()
+            .ok_or_else(|| format_err!("The second and third recipes should be invalid!"))?;
+        assert_eq!(
+            vec![1, 2],
+            errors.iter().map(|e| e.index).collect::<Vec<_>>()
+        );
+        assert!(errors[1].error.contains("payload"));
+        Ok(())
+    }
 }
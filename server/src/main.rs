@@ -7,25 +7,29 @@ mod config;
 mod handlers;
 mod models;
 mod schema;
+mod storage;
 
 use actix_files::{Files, NamedFile};
 use actix_web::{
+    http::Method,
     middleware,
-    web::{delete, get, post, put, Data},
+    web::{delete, get, head, method, patch, post, put, scope, Data, ServiceConfig},
     App, HttpServer, Result,
 };
 use chrono::Utc;
-use diesel::{
-    r2d2::{ConnectionManager, Pool},
-    PgConnection,
-};
 use dotenv::dotenv;
 use env_logger::Builder;
+use handlers::namespace::NAMESPACE_SCOPE;
 use log::{info, LevelFilter};
 use serde_json::json;
-use std::{collections::HashMap, env, io::prelude::*, sync::Mutex};
+use std::{
+    collections::HashMap,
+    env,
+    io::prelude::*,
+    sync::{Arc, Mutex},
+};
 
-type DbPool = Pool<ConnectionManager<PgConnection>>;
+type DbPool = Arc<dyn storage::Storage + Send + Sync>;
 
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
@@ -37,14 +41,30 @@ async fn main() -> std::io::Result<()> {
 
     let config::ServerConfig {
         bind_address,
-        database_url,
+        storage,
         client_bundle_path,
         static_file_path,
+        recipe_path,
+        watch_recipes,
     } = config::server_config().unwrap_or_else(|error| panic!("{}", error));
 
-    let manager: ConnectionManager<PgConnection> = ConnectionManager::new(database_url);
-    let pool = Pool::new(manager)
-        .map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error))?;
+    let pool: DbPool = match storage {
+        config::StorageKind::Postgres(database_url) => {
+            Arc::new(storage::Postgres::connect(&database_url).map_err(startup_error)?)
+        }
+        config::StorageKind::Memory => {
+            info!("Keeping recipes, the journal and scenarios in memory until the server stops");
+            Arc::new(storage::Memory::default())
+        }
+    };
+
+    if let Some(recipe_path) = recipe_path {
+        let count = handlers::recipe_files::load(&pool, &recipe_path).map_err(startup_error)?;
+        info!("Loaded {} recipes from {}", count, recipe_path.display());
+        if watch_recipes {
+            handlers::recipe_files::watch(pool.clone(), recipe_path).map_err(startup_error)?;
+        }
+    }
 
     info!("Starting server, listening at {}", bind_address);
 
@@ -56,23 +76,25 @@ async fn main() -> std::io::Result<()> {
             .route("/favicon", get().to(favicon))
             .route("/favicon.ico", get().to(favicon))
             .route("/pkg/client_bg.wasm", get().to(wasm))
-            .service(handlers::rest::get_recipe)
-            .service(handlers::rest::list_recipes_page)
-            .service(handlers::rest::list_recipes)
-            .service(handlers::rest::create_recipe)
-            .service(handlers::rest::update_recipe)
-            .service(handlers::rest::delete_recipe)
+            .service(handlers::rest::list_namespaces)
+            .service(handlers::rest::delete_namespace)
+            .service(handlers::rest::issue_token)
+            .service(handlers::rest::get_jwks)
+            .configure(handlers::oidc::configure)
+            .service(scope(NAMESPACE_SCOPE).configure(namespaced))
+            .configure(namespaced)
+            .service(handlers::ajax::list_namespaces)
+            .service(handlers::ajax::list_candidates)
+            .service(handlers::ajax::prioritize_recipes)
             .service(handlers::ajax::get_recipe)
             .service(handlers::ajax::list_recipes_page)
             .service(handlers::ajax::list_recipes)
             .service(handlers::ajax::upsert_recipe)
             .service(handlers::ajax::delete_recipe)
+            .service(handlers::ajax::list_scenarios)
+            .service(handlers::ajax::reset_scenario)
             .service(handlers::ajax::complete_key_path)
             .service(handlers::ajax::get_config)
-            .route("/api{tail:.*}", get().to(handlers::serve_recipe))
-            .route("/api{tail:.*}", post().to(handlers::serve_recipe))
-            .route("/api{tail:.*}", put().to(handlers::serve_recipe))
-            .route("/api{tail:.*}", delete().to(handlers::serve_recipe))
             .service(handlers::health_check)
             .service(Files::new("/client", &client_bundle_path))
             .service(Files::new("/add{tail:.*}", &static_file_path).index_file("index.html"))
@@ -85,6 +107,46 @@ async fn main() -> std::io::Result<()> {
     .await
 }
 
+/// Stops the server from starting, with the error and what caused it.
+fn startup_error(error: anyhow::Error) -> std::io::Error {
+    std::io::Error::other(format!("{:#}", error))
+}
+
+/// The REST API and mock endpoints, which work in the namespace named by each call, so they are
+/// served both as is and under a prefix naming the namespace.
+fn namespaced(config: &mut ServiceConfig) {
+    config
+        .service(handlers::rest::export_recipes)
+        .service(handlers::rest::create_recipes)
+        .service(handlers::rest::upsert_recipes)
+        .service(handlers::rest::delete_recipes)
+        .service(handlers::rest::get_recipe)
+        .service(handlers::rest::list_recipes_page)
+        .service(handlers::rest::list_recipes)
+        .service(handlers::rest::create_recipe)
+        .service(handlers::rest::prioritize_recipes)
+        .service(handlers::rest::update_recipe)
+        .service(handlers::rest::delete_recipe)
+        .service(handlers::rest::count_journal)
+        .service(handlers::rest::list_journal_page)
+        .service(handlers::rest::list_journal)
+        .service(handlers::rest::clear_journal)
+        .service(handlers::rest::list_scenarios)
+        .service(handlers::rest::get_scenario)
+        .service(handlers::rest::reset_scenario)
+        .service(handlers::rest::reset_scenarios)
+        .route("/api{tail:.*}", get().to(handlers::serve_recipe))
+        .route("/api{tail:.*}", post().to(handlers::serve_recipe))
+        .route("/api{tail:.*}", put().to(handlers::serve_recipe))
+        .route("/api{tail:.*}", delete().to(handlers::serve_recipe))
+        .route("/api{tail:.*}", patch().to(handlers::serve_recipe))
+        .route("/api{tail:.*}", head().to(handlers::serve_recipe))
+        .route(
+            "/api{tail:.*}",
+            method(Method::OPTIONS).to(handlers::serve_recipe),
+        );
+}
+
 const ENABLE_JSON_LOGGING: &str = "JSON_LOGGING";
 
 fn bootstrap() {
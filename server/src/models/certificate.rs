@@ -0,0 +1,190 @@
+use anyhow::{bail, Context, Result};
+use openssl::{hash::MessageDigest, nid::Nid, x509::X509};
+
+/// What a client certificate rule can match of the certificate that the proxy terminating TLS
+/// forwards with a call.
+pub(crate) struct ClientCertificate {
+    /// The subject as RFC 4514 writes it, most specific first, like `CN=alice,O=Acme`.
+    pub(crate) subject: String,
+    pub(crate) common_name: Option<String>,
+    /// The SHA-256 fingerprint as upper case hexadecimal with colons between the bytes.
+    pub(crate) fingerprint: String,
+}
+
+impl ClientCertificate {
+    /// Reads a forwarded certificate, either in PEM format, URL encoded or not, as nginx and Envoy
+    /// forward it, or as base64 encoded DER.
+    pub(crate) fn parse(forwarded: &str) -> Result<Self> {
+        let forwarded = forwarded.trim().trim_matches('"');
+        let decoded = percent_decode(forwarded)?;
+        let certificate = if decoded.starts_with(b"-----BEGIN") {
+            X509::from_pem(&decoded)
+        } else {
+            let der = base64::decode(&decoded)
+                .context("The client certificate is neither PEM nor base64 encoded DER!")?;
+            X509::from_der(&der)
+        }
+        .context("The client certificate is not valid!")?;
+        let mut subject = Vec::new();
+        let mut common_name = None;
+        for entry in certificate.subject_name().entries() {
+            // RFC 5280 has names written as UTF8String, or PrintableString, which is a subset
+            let value = String::from_utf8(entry.data().as_slice().to_vec())
+                .context("The client certificate's subject is not UTF-8 text!")?;
+            let nid = entry.object().nid();
+            if nid == Nid::COMMONNAME {
+                common_name = Some(value.clone());
+            }
+            let name = nid
+                .short_name()
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|_| entry.object().to_string());
+            subject.push(format!("{}={}", name, escape(&value)));
+        }
+        // RFC 4514 writes the most specific part first, the reverse of the certificate's order
+        subject.reverse();
+        let fingerprint = certificate
+            .digest(MessageDigest::sha256())?
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<String>>()
+            .join(":");
+        Ok(Self {
+            subject: subject.join(","),
+            common_name,
+            fingerprint,
+        })
+    }
+
+    /// Whether the subject is this certificate's, as RFC 4514 writes it, or its common name.
+    pub(crate) fn has_subject(&self, subject: &str) -> bool {
+        self.subject == subject || self.common_name.as_deref() == Some(subject)
+    }
+
+    pub(crate) fn has_fingerprint(&self, fingerprint: &str) -> bool {
+        normalize_fingerprint(&self.fingerprint) == normalize_fingerprint(fingerprint)
+    }
+}
+
+/// A fingerprint as lower case hexadecimal without colons, so that fingerprints written either
+/// way compare equal.
+pub(crate) fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .trim()
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Escapes the characters of a value in a distinguished name that RFC 4514 requires to be.
+fn escape(value: &str) -> String {
+    let last = value.chars().count().saturating_sub(1);
+    let mut escaped = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        let leading = i == 0 && (c == ' ' || c == '#');
+        let trailing = i == last && c == ' ';
+        if leading || trailing || matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Decodes the `%XX` escapes in URL encoded text, leaving anything else, like `+`, as it is, since
+/// a base64 encoded certificate may have them.
+fn percent_decode(encoded: &str) -> Result<Vec<u8>> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match hex {
+                Some(byte) => decoded.push(byte),
+                None => bail!("The client certificate is not correctly URL encoded!"),
+            }
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        ec::{EcGroup, EcKey},
+        pkey::PKey,
+        x509::X509Name,
+    };
+
+    /// A self signed certificate for alice, in PEM format.
+    pub(crate) fn certificate_pem() -> Result<String> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+        let mut name = X509Name::builder()?;
+        name.append_entry_by_nid(Nid::COUNTRYNAME, "US")?;
+        name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "Acme, Inc.")?;
+        name.append_entry_by_nid(Nid::COMMONNAME, "alice")?;
+        let name = name.build();
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        let serial = BigNum::from_u32(1)?.to_asn1_integer()?;
+        builder.set_serial_number(&serial)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        let not_before = Asn1Time::days_from_now(0)?;
+        builder.set_not_before(&not_before)?;
+        let not_after = Asn1Time::days_from_now(1)?;
+        builder.set_not_after(&not_after)?;
+        builder.sign(&key, MessageDigest::sha256())?;
+        Ok(String::from_utf8(builder.build().to_pem()?)?)
+    }
+
+    fn url_encode(text: &str) -> String {
+        text.bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("%{:02X}", byte),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let pem = certificate_pem()?;
+        let certificate = ClientCertificate::parse(&url_encode(&pem))?;
+        assert_eq!("CN=alice,O=Acme\\, Inc.,C=US", certificate.subject);
+        assert!(certificate.has_subject("alice"));
+        assert!(certificate.has_subject("CN=alice,O=Acme\\, Inc.,C=US"));
+        assert!(!certificate.has_subject("CN=alice"));
+
+        let der = X509::from_pem(pem.as_bytes())?.to_der()?;
+        let from_der = ClientCertificate::parse(&base64::encode(&der))?;
+        assert_eq!(certificate.fingerprint, from_der.fingerprint);
+        let expected = openssl::sha::sha256(&der);
+        let hex: String = expected
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert!(certificate.has_fingerprint(&hex));
+        assert!(certificate.has_fingerprint(&certificate.fingerprint.to_lowercase()));
+
+        assert!(ClientCertificate::parse("%ZZ").is_err());
+        assert!(ClientCertificate::parse("not a certificate").is_err());
+        Ok(())
+    }
+}
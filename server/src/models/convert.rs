@@ -1,6 +1,12 @@
-use super::{HttpVerb, NewRule, Recipe, RecipeCascaded, Rule, RuleType};
+use super::{
+    ApiKeyLocation, DelayType, FaultType, HttpVerb, JournalCascaded, JournalEntry, JournalHeader,
+    MatchMode, NewDisruption, NewJournalHeader, NewRecipe, NewRecipeResponse, NewResponseHeader,
+    NewRule, Recipe, RecipeCascaded, RecipeChanges, RecipeResponse, ResponseHeader, Rule, RuleType,
+    SequenceMode,
+};
 use anyhow::{bail, format_err, Error, Result};
 use serde_json::Value;
+use shared::PayloadKind;
 use std::convert::{TryFrom, TryInto};
 use uuid::Uuid;
 
@@ -8,21 +14,48 @@ impl TryInto<shared::Recipe> for Recipe {
     type Error = Error;
 
     fn try_into(self) -> Result<shared::Recipe> {
+        let delay = self.delay()?;
+        let fault = self.fault()?;
         let Recipe {
             url,
             payload,
             id,
             created_at,
             updated_at,
+            status_code,
+            templated,
+            content_type,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            sequence_mode,
+            file,
+            ..
         } = self;
         let id = Some(id);
-        let payload = serde_json::from_str(&payload)?;
+        let status = u16::try_from(status_code)?;
+        let payload = payload_from_bytes(&content_type, payload)?;
+        let sequence_mode = sequence_mode.into();
         let created_at = Some(created_at);
         let updated_at = Some(updated_at);
         Ok(shared::Recipe {
             id,
             url,
+            status,
+            content_type,
             payload,
+            templated,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            sequence_mode,
+            delay,
+            fault,
+            file,
             created_at,
             updated_at,
             ..shared::Recipe::default()
@@ -34,33 +67,400 @@ impl TryInto<shared::Recipe> for RecipeCascaded {
     type Error = Error;
 
     fn try_into(self) -> Result<shared::Recipe> {
+        let delay = self.0.delay()?;
+        let fault = self.0.fault()?;
         let Recipe {
             id,
             url,
             payload,
             created_at,
             updated_at,
+            status_code,
+            templated,
+            content_type,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            sequence_mode,
+            file,
+            ..
         } = self.0;
         let rules = self
             .1
             .into_iter()
             .map(TryInto::try_into)
             .collect::<Result<Vec<shared::Rule>>>()?;
+        let mut headers = self.2;
+        headers.sort_by_key(|header| header.position);
+        let headers = headers.into_iter().map(Into::into).collect();
+        let mut responses = self.3;
+        responses.sort_by_key(|response| response.position);
+        let responses = responses
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<shared::RecipeResponse>>>()?;
+        let sequence_mode = sequence_mode.into();
         let id = Some(id);
-        let payload: Value = serde_json::from_str(&payload)?;
+        let status = u16::try_from(status_code)?;
+        let payload = payload_from_bytes(&content_type, payload)?;
         let created_at = Some(created_at);
         let updated_at = Some(updated_at);
         Ok(shared::Recipe {
             id,
             url,
             rules,
+            status,
+            headers,
+            content_type,
             payload,
+            responses,
+            sequence_mode,
+            delay,
+            fault,
+            templated,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            file,
             created_at,
             updated_at,
         })
     }
 }
 
+/// Payloads are stored as bytes; JSON payloads are stored as their text, text payloads as is, and
+/// binary payloads are decoded from base64.
+pub(crate) fn payload_to_bytes(content_type: &str, payload: Value) -> Result<Vec<u8>> {
+    match PayloadKind::of(content_type) {
+        PayloadKind::Json => serde_json::to_vec(&payload).map_err(Error::from),
+        PayloadKind::Text => match payload {
+            Value::String(text) => Ok(text.into_bytes()),
+            _ => bail!(
+                "The payload for content type, {}, must be a string!",
+                content_type
+            ),
+        },
+        PayloadKind::Binary => match payload {
+            Value::String(encoded) => base64::decode(&encoded).map_err(|error| {
+                format_err!(
+                    "The payload for content type, {}, must be base64 encoded! ({})",
+                    content_type,
+                    error
+                )
+            }),
+            _ => bail!(
+                "The payload for content type, {}, must be a base64 encoded string!",
+                content_type
+            ),
+        },
+    }
+}
+
+fn payload_from_bytes(content_type: &str, payload: Vec<u8>) -> Result<Value> {
+    Ok(match PayloadKind::of(content_type) {
+        PayloadKind::Json => serde_json::from_slice(&payload)?,
+        PayloadKind::Text => Value::String(String::from_utf8(payload)?),
+        PayloadKind::Binary => Value::String(base64::encode(&payload)),
+    })
+}
+
+impl TryInto<shared::RecipeResponse> for RecipeResponse {
+    type Error = Error;
+
+    fn try_into(self) -> Result<shared::RecipeResponse> {
+        let RecipeResponse {
+            status_code,
+            content_type,
+            payload,
+            templated,
+            ..
+        } = self;
+        Ok(shared::RecipeResponse {
+            status: u16::try_from(status_code)?,
+            payload: payload_from_bytes(&content_type, payload)?,
+            content_type,
+            templated,
+        })
+    }
+}
+
+impl TryFrom<(Uuid, usize, shared::RecipeResponse)> for NewRecipeResponse {
+    type Error = Error;
+
+    fn try_from(t: (Uuid, usize, shared::RecipeResponse)) -> Result<Self> {
+        let (
+            recipe_id,
+            position,
+            shared::RecipeResponse {
+                status,
+                content_type,
+                payload,
+                templated,
+            },
+        ) = t;
+        Ok(Self {
+            recipe_id,
+            position: position as i32,
+            status_code: status.into(),
+            payload: payload_to_bytes(&content_type, payload)?,
+            content_type,
+            templated,
+        })
+    }
+}
+
+impl Recipe {
+    /// The recipe's delay, gathered from the columns its type uses.
+    pub(crate) fn delay(&self) -> Result<Option<shared::Delay>> {
+        Ok(match self.delay_type {
+            None => None,
+            Some(DelayType::Fixed) => Some(shared::Delay::Fixed {
+                millis: unsigned_column(self.delay_millis, "delay_millis")?,
+            }),
+            Some(DelayType::Uniform) => Some(shared::Delay::Uniform {
+                min_millis: unsigned_column(self.delay_millis, "delay_millis")?,
+                max_millis: unsigned_column(self.delay_max_millis, "delay_max_millis")?,
+            }),
+            Some(DelayType::Normal) => Some(shared::Delay::Normal {
+                mean_millis: unsigned_column(self.delay_millis, "delay_millis")?,
+                std_dev_millis: unsigned_column(self.delay_std_dev_millis, "delay_std_dev_millis")?,
+            }),
+            Some(DelayType::LogNormal) => Some(shared::Delay::LogNormal {
+                median_millis: unsigned_column(self.delay_millis, "delay_millis")?,
+                sigma: self
+                    .delay_sigma
+                    .ok_or_else(|| format_err!("The column, delay_sigma, must be set!"))?,
+            }),
+        })
+    }
+
+    /// The recipe's fault, gathered from the columns its type uses.
+    pub(crate) fn fault(&self) -> Result<Option<shared::Fault>> {
+        Ok(match self.fault_type {
+            None => None,
+            Some(FaultType::ConnectionReset) => Some(shared::Fault::ConnectionReset),
+            Some(FaultType::PartialThenReset) => Some(shared::Fault::PartialThenReset),
+            Some(FaultType::Trickle) => Some(shared::Fault::Trickle {
+                chunk_bytes: unsigned_column(self.trickle_chunk_bytes, "trickle_chunk_bytes")?,
+                interval_millis: unsigned_column(
+                    self.trickle_interval_millis,
+                    "trickle_interval_millis",
+                )?,
+            }),
+            Some(FaultType::EmptyReply) => Some(shared::Fault::EmptyReply),
+        })
+    }
+}
+
+/// A column that the type of a delay or fault requires, which is never negative.
+impl TryFrom<shared::Recipe> for RecipeChanges {
+    type Error = Error;
+
+    fn try_from(recipe: shared::Recipe) -> Result<Self> {
+        let shared::Recipe {
+            id,
+            url,
+            payload,
+            status,
+            headers,
+            rules,
+            templated,
+            content_type,
+            draft,
+            priority,
+            scenario,
+            required_state,
+            new_state,
+            responses,
+            sequence_mode,
+            delay,
+            fault,
+            ..
+        } = recipe;
+        let payload = payload_to_bytes(&content_type, payload)?;
+        Ok(Self {
+            id,
+            recipe: NewRecipe {
+                url,
+                payload,
+                status_code: status.into(),
+                templated,
+                content_type,
+                draft,
+                priority,
+                scenario,
+                required_state,
+                new_state,
+                sequence_mode: sequence_mode.into(),
+            },
+            disruption: (delay, fault).into(),
+            rules,
+            headers,
+            responses,
+            // only loading the file can set this, never a change through the UI or the REST API
+            file: None,
+        })
+    }
+}
+
+fn unsigned_column(value: Option<i32>, name: &str) -> Result<u32> {
+    let value = value.ok_or_else(|| format_err!("The column, {}, must be set!", name))?;
+    u32::try_from(value).map_err(Error::from)
+}
+
+impl From<(Option<shared::Delay>, Option<shared::Fault>)> for NewDisruption {
+    fn from(t: (Option<shared::Delay>, Option<shared::Fault>)) -> Self {
+        let (delay, fault) = t;
+        let mut disruption = NewDisruption::default();
+        match delay {
+            None => (),
+            Some(shared::Delay::Fixed { millis }) => {
+                disruption.delay_type = Some(DelayType::Fixed);
+                disruption.delay_millis = Some(millis as i32);
+            }
+            Some(shared::Delay::Uniform {
+                min_millis,
+                max_millis,
+            }) => {
+                disruption.delay_type = Some(DelayType::Uniform);
+                disruption.delay_millis = Some(min_millis as i32);
+                disruption.delay_max_millis = Some(max_millis as i32);
+            }
+            Some(shared::Delay::Normal {
+                mean_millis,
+                std_dev_millis,
+            }) => {
+                disruption.delay_type = Some(DelayType::Normal);
+                disruption.delay_millis = Some(mean_millis as i32);
+                disruption.delay_std_dev_millis = Some(std_dev_millis as i32);
+            }
+            Some(shared::Delay::LogNormal {
+                median_millis,
+                sigma,
+            }) => {
+                disruption.delay_type = Some(DelayType::LogNormal);
+                disruption.delay_millis = Some(median_millis as i32);
+                disruption.delay_sigma = Some(sigma);
+            }
+        }
+        match fault {
+            None => (),
+            Some(shared::Fault::ConnectionReset) => {
+                disruption.fault_type = Some(FaultType::ConnectionReset)
+            }
+            Some(shared::Fault::PartialThenReset) => {
+                disruption.fault_type = Some(FaultType::PartialThenReset)
+            }
+            Some(shared::Fault::Trickle {
+                chunk_bytes,
+                interval_millis,
+            }) => {
+                disruption.fault_type = Some(FaultType::Trickle);
+                disruption.trickle_chunk_bytes = Some(chunk_bytes as i32);
+                disruption.trickle_interval_millis = Some(interval_millis as i32);
+            }
+            Some(shared::Fault::EmptyReply) => disruption.fault_type = Some(FaultType::EmptyReply),
+        }
+        disruption
+    }
+}
+
+impl From<shared::SequenceMode> for SequenceMode {
+    fn from(m: shared::SequenceMode) -> Self {
+        use shared::SequenceMode::*;
+        match m {
+            Cycle => SequenceMode::Cycle,
+            StickOnLast => SequenceMode::StickOnLast,
+            FallThrough => SequenceMode::FallThrough,
+        }
+    }
+}
+
+impl From<SequenceMode> for shared::SequenceMode {
+    fn from(m: SequenceMode) -> Self {
+        use SequenceMode::*;
+        match m {
+            Cycle => shared::SequenceMode::Cycle,
+            StickOnLast => shared::SequenceMode::StickOnLast,
+            FallThrough => shared::SequenceMode::FallThrough,
+        }
+    }
+}
+
+impl Into<shared::Header> for ResponseHeader {
+    fn into(self) -> shared::Header {
+        let ResponseHeader { name, value, .. } = self;
+        shared::Header { name, value }
+    }
+}
+
+impl From<(Uuid, usize, shared::Header)> for NewResponseHeader {
+    fn from(t: (Uuid, usize, shared::Header)) -> Self {
+        let (recipe_id, position, shared::Header { name, value }) = t;
+        let position = position as i32;
+        Self {
+            recipe_id,
+            position,
+            name,
+            value,
+        }
+    }
+}
+
+impl TryInto<shared::JournalEntry> for JournalCascaded {
+    type Error = Error;
+
+    fn try_into(self) -> Result<shared::JournalEntry> {
+        let JournalCascaded(
+            JournalEntry {
+                id,
+                method,
+                url,
+                body,
+                subject,
+                matched,
+                recipe_id,
+                status_code,
+                created_at,
+                ..
+            },
+            headers,
+        ) = self;
+        Ok(shared::JournalEntry {
+            id,
+            method,
+            url,
+            headers: headers
+                .into_iter()
+                .map(|JournalHeader { name, value, .. }| shared::Header { name, value })
+                .collect(),
+            body: String::from_utf8_lossy(&body).into_owned(),
+            subject,
+            matched,
+            recipe_id,
+            status: u16::try_from(status_code)?,
+            created_at,
+        })
+    }
+}
+
+impl From<(Uuid, usize, shared::Header)> for NewJournalHeader {
+    fn from(t: (Uuid, usize, shared::Header)) -> Self {
+        let (entry_id, position, shared::Header { name, value }) = t;
+        let position = position as i32;
+        Self {
+            entry_id,
+            position,
+            name,
+            value,
+        }
+    }
+}
+
 impl TryInto<shared::Rule> for Rule {
     type Error = Error;
 
@@ -71,6 +471,21 @@ impl TryInto<shared::Rule> for Rule {
             key_path,
             subject,
             http_method,
+            header_name,
+            match_mode,
+            match_value,
+            param_name,
+            body_path,
+            form_field,
+            nth,
+            claim,
+            algorithms,
+            username,
+            password,
+            key_location,
+            key_name,
+            api_key,
+            fingerprint,
             id,
             ..
         } = self;
@@ -79,6 +494,13 @@ impl TryInto<shared::Rule> for Rule {
             Authenticated => shared::Rule::Authenticated {
                 id,
                 key_path: key_path.ok_or_else(|| format_err!("Field, key_path, must be Some!"))?,
+                algorithms: algorithms
+                    .iter()
+                    .map(|name| {
+                        shared::Algorithm::named(name)
+                            .ok_or_else(|| format_err!("{} is not a valid algorithm!", name))
+                    })
+                    .collect::<Result<Vec<shared::Algorithm>>>()?,
             },
             Subject => shared::Rule::Subject {
                 id,
@@ -90,6 +512,73 @@ impl TryInto<shared::Rule> for Rule {
                     .map(Into::into)
                     .ok_or_else(|| format_err!("Field, http_method, must be Some!"))?,
             },
+            Header => shared::Rule::Header {
+                id,
+                header_name: header_name
+                    .ok_or_else(|| format_err!("Field, header_name, must be Some!"))?,
+                match_mode: match_mode
+                    .map(Into::into)
+                    .ok_or_else(|| format_err!("Field, match_mode, must be Some!"))?,
+                match_value,
+            },
+            PathParam => shared::Rule::PathParam {
+                id,
+                param_name: param_name
+                    .ok_or_else(|| format_err!("Field, param_name, must be Some!"))?,
+                match_mode: match_mode
+                    .map(Into::into)
+                    .ok_or_else(|| format_err!("Field, match_mode, must be Some!"))?,
+                match_value,
+            },
+            QueryParam => shared::Rule::QueryParam {
+                id,
+                param_name: param_name
+                    .ok_or_else(|| format_err!("Field, param_name, must be Some!"))?,
+                match_mode: match_mode
+                    .map(Into::into)
+                    .ok_or_else(|| format_err!("Field, match_mode, must be Some!"))?,
+                match_value,
+            },
+            Body => shared::Rule::Body {
+                id,
+                body_path,
+                form_field,
+                match_mode: match_mode
+                    .map(Into::into)
+                    .ok_or_else(|| format_err!("Field, match_mode, must be Some!"))?,
+                match_value,
+            },
+            NthCall => shared::Rule::NthCall {
+                id,
+                nth: u32::try_from(nth.ok_or_else(|| format_err!("Field, nth, must be Some!"))?)?,
+            },
+            Claim => shared::Rule::Claim {
+                id,
+                claim: claim.ok_or_else(|| format_err!("Field, claim, must be Some!"))?,
+                match_mode: match_mode
+                    .map(Into::into)
+                    .ok_or_else(|| format_err!("Field, match_mode, must be Some!"))?,
+                match_value,
+            },
+            Unexpired => shared::Rule::Unexpired { id },
+            BasicAuth => shared::Rule::BasicAuth {
+                id,
+                username: username.ok_or_else(|| format_err!("Field, username, must be Some!"))?,
+                password,
+            },
+            ApiKey => shared::Rule::ApiKey {
+                id,
+                key_location: key_location
+                    .map(Into::into)
+                    .ok_or_else(|| format_err!("Field, key_location, must be Some!"))?,
+                key_name: key_name.ok_or_else(|| format_err!("Field, key_name, must be Some!"))?,
+                api_key: api_key.ok_or_else(|| format_err!("Field, api_key, must be Some!"))?,
+            },
+            ClientCert => shared::Rule::ClientCert {
+                id,
+                subject,
+                fingerprint,
+            },
         })
     }
 }
@@ -97,42 +586,52 @@ impl TryInto<shared::Rule> for Rule {
 impl TryFrom<(Uuid, shared::Rule)> for Rule {
     type Error = Error;
     fn try_from(t: (Uuid, shared::Rule)) -> Result<Self> {
-        use shared::Rule::*;
         let (recipe_id, r) = t;
-        Ok(match r {
-            Authenticated { id, key_path } => {
-                let id = id.ok_or_else(|| format_err!("Rule must have an ID!"))?;
-                Self {
-                    id,
-                    recipe_id,
-                    rule_type: RuleType::Authenticated,
-                    subject: None,
-                    key_path: Some(key_path),
-                    http_method: None,
-                }
-            }
-            Subject { id, subject } => {
-                let id = id.ok_or_else(|| format_err!("Rule must have an ID!"))?;
-                Self {
-                    id,
-                    recipe_id,
-                    rule_type: RuleType::Subject,
-                    subject: Some(subject),
-                    key_path: None,
-                    http_method: None,
-                }
-            }
-            HttpMethod { id, http_method } => {
-                let id = id.ok_or_else(|| format_err!("Rule must have an ID!"))?;
-                Self {
-                    id,
-                    recipe_id,
-                    rule_type: RuleType::HttpMethod,
-                    subject: None,
-                    key_path: None,
-                    http_method: Some(http_method.into()),
-                }
-            }
+        let id = r.id().ok_or_else(|| format_err!("Rule must have an ID!"))?;
+        let NewRule {
+            rule_type,
+            key_path,
+            subject,
+            http_method,
+            header_name,
+            match_mode,
+            match_value,
+            param_name,
+            body_path,
+            form_field,
+            nth,
+            claim,
+            algorithms,
+            username,
+            password,
+            key_location,
+            key_name,
+            api_key,
+            fingerprint,
+            ..
+        } = (recipe_id, r).into();
+        Ok(Self {
+            id,
+            recipe_id,
+            rule_type,
+            key_path,
+            subject,
+            http_method,
+            header_name,
+            match_mode,
+            match_value,
+            param_name,
+            body_path,
+            form_field,
+            nth,
+            claim,
+            algorithms,
+            username,
+            password,
+            key_location,
+            key_name,
+            api_key,
+            fingerprint,
         })
     }
 }
@@ -142,27 +641,133 @@ impl From<(Uuid, shared::Rule)> for NewRule {
         let (recipe_id, r) = t;
         use shared::Rule::*;
         match r {
-            Authenticated { key_path, .. } => Self {
-                recipe_id,
-                rule_type: RuleType::Authenticated,
-                subject: None,
+            Authenticated {
+                key_path,
+                algorithms,
+                ..
+            } => Self {
                 key_path: Some(key_path),
-                http_method: None,
+                algorithms: algorithms
+                    .iter()
+                    .map(|algorithm| algorithm.name().to_owned())
+                    .collect(),
+                ..Self::of_type(recipe_id, RuleType::Authenticated)
             },
             Subject { subject, .. } => Self {
-                recipe_id,
-                rule_type: RuleType::Subject,
                 subject: Some(subject),
-                key_path: None,
-                http_method: None,
+                ..Self::of_type(recipe_id, RuleType::Subject)
             },
             HttpMethod { http_method, .. } => Self {
-                recipe_id,
-                rule_type: RuleType::HttpMethod,
-                subject: None,
-                key_path: None,
                 http_method: Some(http_method.into()),
+                ..Self::of_type(recipe_id, RuleType::HttpMethod)
+            },
+            Header {
+                header_name,
+                match_mode,
+                match_value,
+                ..
+            } => Self {
+                header_name: Some(header_name),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Self::of_type(recipe_id, RuleType::Header)
+            },
+            PathParam {
+                param_name,
+                match_mode,
+                match_value,
+                ..
+            } => Self {
+                param_name: Some(param_name),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Self::of_type(recipe_id, RuleType::PathParam)
             },
+            Body {
+                body_path,
+                form_field,
+                match_mode,
+                match_value,
+                ..
+            } => Self {
+                body_path,
+                form_field,
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Self::of_type(recipe_id, RuleType::Body)
+            },
+            QueryParam {
+                param_name,
+                match_mode,
+                match_value,
+                ..
+            } => Self {
+                param_name: Some(param_name),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Self::of_type(recipe_id, RuleType::QueryParam)
+            },
+            NthCall { nth, .. } => Self {
+                nth: Some(nth as i32),
+                ..Self::of_type(recipe_id, RuleType::NthCall)
+            },
+            Claim {
+                claim,
+                match_mode,
+                match_value,
+                ..
+            } => Self {
+                claim: Some(claim),
+                match_mode: Some(match_mode.into()),
+                match_value,
+                ..Self::of_type(recipe_id, RuleType::Claim)
+            },
+            Unexpired { .. } => Self::of_type(recipe_id, RuleType::Unexpired),
+            BasicAuth {
+                username, password, ..
+            } => Self {
+                username: Some(username),
+                password,
+                ..Self::of_type(recipe_id, RuleType::BasicAuth)
+            },
+            ApiKey {
+                key_location,
+                key_name,
+                api_key,
+                ..
+            } => Self {
+                key_location: Some(key_location.into()),
+                key_name: Some(key_name),
+                api_key: Some(api_key),
+                ..Self::of_type(recipe_id, RuleType::ApiKey)
+            },
+            ClientCert {
+                subject,
+                fingerprint,
+                ..
+            } => Self {
+                subject,
+                fingerprint,
+                ..Self::of_type(recipe_id, RuleType::ClientCert)
+            },
+        }
+    }
+}
+
+impl From<shared::ApiKeyLocation> for ApiKeyLocation {
+    fn from(location: shared::ApiKeyLocation) -> Self {
+        match location {
+            shared::ApiKeyLocation::Header => ApiKeyLocation::Header,
+            shared::ApiKeyLocation::Query => ApiKeyLocation::Query,
+        }
+    }
+}
+
+impl From<ApiKeyLocation> for shared::ApiKeyLocation {
+    fn from(location: ApiKeyLocation) -> Self {
+        match location {
+            ApiKeyLocation::Header => shared::ApiKeyLocation::Header,
+            ApiKeyLocation::Query => shared::ApiKeyLocation::Query,
         }
     }
 }
@@ -175,6 +780,9 @@ impl From<shared::HttpVerb> for HttpVerb {
             Post => HttpVerb::Post,
             Put => HttpVerb::Put,
             Delete => HttpVerb::Delete,
+            Patch => HttpVerb::Patch,
+            Head => HttpVerb::Head,
+            Options => HttpVerb::Options,
         }
     }
 }
@@ -187,6 +795,9 @@ impl Into<shared::HttpVerb> for HttpVerb {
             Post => shared::HttpVerb::Post,
             Put => shared::HttpVerb::Put,
             Delete => shared::HttpVerb::Delete,
+            Patch => shared::HttpVerb::Patch,
+            Head => shared::HttpVerb::Head,
+            Options => shared::HttpVerb::Options,
         }
     }
 }
@@ -201,6 +812,9 @@ impl TryFrom<&str> for HttpVerb {
             "Post" => Ok(Post),
             "Put" => Ok(Put),
             "Delete" => Ok(Delete),
+            "Patch" => Ok(Patch),
+            "Head" => Ok(Head),
+            "Options" => Ok(Options),
             _ => bail!(
                 "{} is not a valid HTTP verb! For conversion from strings, case matters.",
                 s
@@ -209,6 +823,72 @@ impl TryFrom<&str> for HttpVerb {
     }
 }
 
+impl From<shared::MatchMode> for MatchMode {
+    fn from(m: shared::MatchMode) -> Self {
+        use shared::MatchMode::*;
+        match m {
+            Exact => MatchMode::Exact,
+            Present => MatchMode::Present,
+            Absent => MatchMode::Absent,
+            CaseInsensitive => MatchMode::CaseInsensitive,
+            Regex => MatchMode::Regex,
+            Contains => MatchMode::Contains,
+            AnyOf => MatchMode::AnyOf,
+        }
+    }
+}
+
+impl Into<shared::MatchMode> for MatchMode {
+    fn into(self) -> shared::MatchMode {
+        use MatchMode::*;
+        match self {
+            Exact => shared::MatchMode::Exact,
+            Present => shared::MatchMode::Present,
+            Absent => shared::MatchMode::Absent,
+            CaseInsensitive => shared::MatchMode::CaseInsensitive,
+            Regex => shared::MatchMode::Regex,
+            Contains => shared::MatchMode::Contains,
+            AnyOf => shared::MatchMode::AnyOf,
+        }
+    }
+}
+
+impl TryFrom<&str> for ApiKeyLocation {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "Header" => Ok(ApiKeyLocation::Header),
+            "Query" => Ok(ApiKeyLocation::Query),
+            _ => bail!(
+                "{} is not a valid API key location, either Header or Query! For conversion from strings, case matters.",
+                s
+            ),
+        }
+    }
+}
+
+impl TryFrom<&str> for MatchMode {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        use MatchMode::*;
+        match s {
+            "Exact" => Ok(Exact),
+            "Present" => Ok(Present),
+            "Absent" => Ok(Absent),
+            "CaseInsensitive" => Ok(CaseInsensitive),
+            "Regex" => Ok(Regex),
+            "Contains" => Ok(Contains),
+            "AnyOf" => Ok(AnyOf),
+            _ => bail!(
+                "{} is not a valid match mode! For conversion from strings, case matters.",
+                s
+            ),
+        }
+    }
+}
+
 impl TryFrom<&str> for RuleType {
     type Error = Error;
 
@@ -218,6 +898,16 @@ impl TryFrom<&str> for RuleType {
             "Authenticated" => Ok(Authenticated),
             "Subject" => Ok(Subject),
             "HttpMethod" => Ok(HttpMethod),
+            "Header" => Ok(Header),
+            "PathParam" => Ok(PathParam),
+            "Body" => Ok(Body),
+            "QueryParam" => Ok(QueryParam),
+            "NthCall" => Ok(NthCall),
+            "Claim" => Ok(Claim),
+            "Unexpired" => Ok(Unexpired),
+            "BasicAuth" => Ok(BasicAuth),
+            "ApiKey" => Ok(ApiKey),
+            "ClientCert" => Ok(ClientCert),
             _ => bail!("{} is not a valid rule type!", s),
         }
     }
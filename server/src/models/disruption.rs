@@ -0,0 +1,101 @@
+use super::Recipe;
+use anyhow::Result;
+use rand::Rng;
+use std::time::Duration;
+
+/// The longest delay that can be saved, so the longest that can be drawn.
+const MAX_DELAY_MILLIS: f64 = i32::MAX as f64;
+
+impl Recipe {
+    /// How long to wait before serving the recipe, drawn afresh for each call from its delay.
+    pub(crate) fn sample_delay(&self) -> Result<Option<Duration>> {
+        Ok(self
+            .delay()?
+            .map(|delay| sample(&delay, &mut rand::thread_rng())))
+    }
+}
+
+fn sample<R: Rng>(delay: &shared::Delay, rng: &mut R) -> Duration {
+    use shared::Delay::*;
+    let millis = match *delay {
+        Fixed { millis } => f64::from(millis),
+        Uniform {
+            min_millis,
+            max_millis,
+        } => rng.gen_range(
+            u64::from(min_millis),
+            u64::from(max_millis.max(min_millis)) + 1,
+        ) as f64,
+        Normal {
+            mean_millis,
+            std_dev_millis,
+        } => f64::from(mean_millis) + f64::from(std_dev_millis) * standard_normal(rng),
+        LogNormal {
+            median_millis,
+            sigma,
+        } => f64::from(median_millis) * (sigma * standard_normal(rng)).exp(),
+    };
+    // a draw below zero is no delay, and one beyond the longest delay is cut short
+    Duration::from_millis(millis.clamp(0.0, MAX_DELAY_MILLIS) as u64)
+}
+
+/// Draws from the standard normal distribution with the Box-Muller transform.
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    // the logarithm needs a value above zero, and `gen` may return zero but never one
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn samples(delay: shared::Delay) -> Vec<u128> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut samples: Vec<u128> = (0..1001)
+            .map(|_| sample(&delay, &mut rng).as_millis())
+            .collect();
+        samples.sort_unstable();
+        samples
+    }
+
+    #[test]
+    fn test_fixed() {
+        let samples = samples(shared::Delay::Fixed { millis: 250 });
+        assert!(samples.iter().all(|millis| *millis == 250));
+    }
+
+    #[test]
+    fn test_uniform() {
+        let samples = samples(shared::Delay::Uniform {
+            min_millis: 100,
+            max_millis: 200,
+        });
+        assert_eq!(Some(&100), samples.first());
+        assert_eq!(Some(&200), samples.last());
+    }
+
+    #[test]
+    fn test_normal() {
+        let samples = samples(shared::Delay::Normal {
+            mean_millis: 100,
+            std_dev_millis: 80,
+        });
+        // the draws below zero are no delay at all
+        assert_eq!(Some(&0), samples.first());
+        assert!((90..110).contains(&samples[500]), "{}", samples[500]);
+    }
+
+    #[test]
+    fn test_log_normal() {
+        let samples = samples(shared::Delay::LogNormal {
+            median_millis: 100,
+            sigma: 1.0,
+        });
+        assert!((90..110).contains(&samples[500]), "{}", samples[500]);
+        // the tail above the median is longer than the one below it
+        assert!(samples[1000] - samples[500] > samples[500] - samples[0]);
+    }
+}
@@ -1,117 +1,1640 @@
-use super::{Recipe, Rule, RuleType};
+use super::{
+    certificate::ClientCertificate,
+    jwt::{read_key, Key, KeySet, SignedToken, TokenHeader},
+    ApiKeyLocation, HttpVerb, MatchMode, PathParams, Recipe, Rule, RuleType, SequenceMode,
+};
 use crate::config::{self, KeyPathKind};
-use actix_web::HttpRequest;
-use anyhow::{format_err, Context, Result};
+use actix_web::{http::Method, web::Query, HttpRequest};
+use anyhow::{bail, format_err, Context, Result};
+use chrono::Utc;
 use log::debug;
-use medallion::{DefaultPayload, DefaultToken};
+use regex::Regex as Pattern;
+use serde::Serialize;
 use serde_json::Value;
-use std::{fs, path::PathBuf, str::FromStr};
+use shared::{Algorithm, SCENARIO_STARTED};
+use std::{collections::HashSet, net::IpAddr};
+
+/// The request being served along with its body, what was captured from its path by the
+/// recipe's URL template and which call it is, counting from one, with its method to its URL,
+/// and the JWKS that tokens are verified against, when `KEY_PATH_KIND` is "jwks".
+pub(crate) struct RequestContext<'a> {
+    pub(crate) request: &'a HttpRequest,
+    pub(crate) path_params: &'a PathParams,
+    pub(crate) body: &'a [u8],
+    pub(crate) call_number: i64,
+    pub(crate) key_set: Option<&'a KeySet>,
+}
+
+/// How a single rule fared against a request, described for diagnosing why a recipe didn't match.
+#[derive(Debug, Serialize)]
+pub(crate) struct RuleOutcome {
+    pub(crate) rule: String,
+    pub(crate) passed: bool,
+    pub(crate) found: String,
+}
+
+/// The outcome of every rule of a recipe, in order; the recipe matches if they all passed.
+#[derive(Debug)]
+pub(crate) struct Evaluation(pub(crate) Vec<RuleOutcome>);
+
+impl Evaluation {
+    pub(crate) fn matched(&self) -> bool {
+        self.0.iter().all(|outcome| outcome.passed)
+    }
+
+    pub(crate) fn failures(self) -> Vec<RuleOutcome> {
+        self.0
+            .into_iter()
+            .filter(|outcome| !outcome.passed)
+            .collect()
+    }
+}
 
 impl Recipe {
+    /// Checks the state the recipe requires of its scenario, given the scenario's current state,
+    /// if it has left its starting state, and then every rule.
     pub(crate) fn evaluate_rules(
         &self,
         rules: &[Rule],
-        request: &HttpRequest,
-    ) -> Result<Option<String>> {
+        context: &RequestContext,
+        scenario_state: Option<&str>,
+    ) -> Result<Evaluation> {
         debug!("Evaluating rules for {}", self.url);
-        let rules = rules
-            .iter()
-            .map(|rule| rule.eval(request))
+        let scenario = self.check_scenario(scenario_state).map(Ok);
+        scenario
+            .into_iter()
+            .chain(rules.iter().map(|rule| rule.check(context)))
             .inspect(|result| debug!("Result {:?}", result))
-            .collect::<Result<Vec<bool>>>()?;
-        if rules.iter().all(|rule| *rule) {
-            Ok(Some(self.payload.clone()))
-        } else {
-            Ok(None)
+            .collect::<Result<Vec<RuleOutcome>>>()
+            .map(Evaluation)
+    }
+
+    /// Which response to serve given how many times the recipe has been served, 0 being the
+    /// recipe's own and the rest its sequence of `sequenced` responses, in order; a sequence that
+    /// falls through has nothing left to serve once each response has been served.
+    pub(crate) fn next_response(&self, served: i64, sequenced: i64) -> Option<i64> {
+        let total = sequenced + 1;
+        match self.sequence_mode {
+            SequenceMode::Cycle => Some(served % total),
+            SequenceMode::StickOnLast => Some(served.min(sequenced)),
+            SequenceMode::FallThrough if served < total => Some(served),
+            SequenceMode::FallThrough => None,
+        }
+    }
+
+    /// Explains why a recipe whose rules matched wasn't served, its sequence having fallen
+    /// through.
+    pub(crate) fn exhausted(&self, served: i64, sequenced: i64) -> RuleOutcome {
+        RuleOutcome {
+            rule: format!(
+                "Sequence of {} responses, falling through once each is served",
+                sequenced + 1
+            ),
+            passed: false,
+            found: format!("served {} times", served),
         }
     }
+
+    fn check_scenario(&self, scenario_state: Option<&str>) -> Option<RuleOutcome> {
+        let (scenario, required_state) = match (&self.scenario, &self.required_state) {
+            (Some(scenario), Some(required_state)) => (scenario, required_state),
+            _ => return None,
+        };
+        let state = scenario_state.unwrap_or(SCENARIO_STARTED);
+        Some(RuleOutcome {
+            rule: format!("Scenario, {}, is in state, {}", scenario, required_state),
+            passed: state == required_state,
+            found: format!("state, {}", state),
+        })
+    }
 }
 
+/// Whether a rule passed along with a description of what it found in the request.
+type Check = (bool, String);
+
 impl Rule {
-    fn eval(&self, request: &HttpRequest) -> Result<bool> {
+    fn check(&self, context: &RequestContext) -> Result<RuleOutcome> {
         debug!("Evaluating {:?}", self);
         use RuleType::*;
 
-        match &self.rule_type {
-            Authenticated => self.is_authenticated(request),
+        let request = context.request;
+        let (passed, found) = match &self.rule_type {
+            Authenticated => self.is_authenticated(context),
             Subject => self.is_authorized(request),
             HttpMethod => self.is_method(request),
-        }
+            Header => self.is_header_match(request),
+            PathParam => self.is_path_param_match(context.path_params),
+            Body => self.is_body_match(context.body),
+            QueryParam => self.is_query_param_match(request),
+            NthCall => self.is_nth_call(context.call_number),
+            Claim => self.is_claim_match(request),
+            Unexpired => is_unexpired(request),
+            BasicAuth => self.is_basic_auth(request),
+            ApiKey => self.is_api_key(request),
+            ClientCert => self.is_client_cert(request),
+        }?;
+        Ok(RuleOutcome {
+            rule: self.describe(),
+            passed,
+            found,
+        })
     }
 
-    fn is_authenticated(&self, request: &HttpRequest) -> Result<bool> {
-        if let Some(token) = extract_auth_token(request)? {
-            debug!("Verifying token {:?}", token);
-            let key = match config::KEY_PATH_KIND.clone() {
-                KeyPathKind::Directory(mut key_path) => {
-                    debug!("KEY PATH={}", key_path.display());
-                    let db_key_path = self
-                        .key_path
-                        .as_ref()
-                        .ok_or_else(|| format_err!("Key path was not set!"))?;
-                    let db_key_path = PathBuf::from_str(db_key_path.trim_start_matches('/'))
-                        .with_context(|| format!("Could not parse {} as a path!", db_key_path))?;
-                    key_path.push(db_key_path);
-                    debug!("After adding from DB, KEY PATH={}", key_path.display());
-                    debug!("Loading key {:?}", key_path.display());
-                    fs::read(key_path)?
+    /// A description of what the rule expects, for diagnostics.
+    fn describe(&self) -> String {
+        use RuleType::*;
+
+        let or_unset = |value: &Option<String>| value.clone().unwrap_or_default();
+        let expects = || {
+            self.match_mode
+                .as_ref()
+                .map(|match_mode| match_mode.describe(self.match_value.as_deref()))
+                .unwrap_or_default()
+        };
+        match &self.rule_type {
+            Authenticated if self.algorithms.is_empty() => format!(
+                "Authenticated by verifying the JWT with the key, {}",
+                or_unset(&self.key_path)
+            ),
+            Authenticated => format!(
+                "Authenticated by verifying the JWT with the key, {}, signed with one of {}",
+                or_unset(&self.key_path),
+                self.algorithms.join(", ")
+            ),
+            Subject => format!("Subject claim is {}", or_unset(&self.subject)),
+            HttpMethod => format!(
+                "HTTP method is {}",
+                self.http_method
+                    .as_ref()
+                    .map(|http_method| http_method.as_method().to_string())
+                    .unwrap_or_default()
+            ),
+            Header => format!("Header, {}, {}", or_unset(&self.header_name), expects()),
+            PathParam => format!(
+                "Path parameter, {}, {}",
+                or_unset(&self.param_name),
+                expects()
+            ),
+            Body => match (&self.body_path, &self.form_field) {
+                (Some(body_path), _) => format!("Body at {} {}", body_path, expects()),
+                (_, Some(form_field)) => format!("Form field, {}, {}", form_field, expects()),
+                _ => format!("Body {}", expects()),
+            },
+            QueryParam => format!(
+                "Query parameter, {}, {}",
+                or_unset(&self.param_name),
+                expects()
+            ),
+            NthCall => format!("Call number {}", self.nth.unwrap_or_default()),
+            Claim => format!("Claim, {}, {}", or_unset(&self.claim), expects()),
+            Unexpired => "Token is before its exp and not before its nbf".to_owned(),
+            BasicAuth => format!(
+                "Basic credentials for {} with {}",
+                or_unset(&self.username),
+                if self.password.is_some() {
+                    "the password"
+                } else {
+                    "any password"
                 }
-                KeyPathKind::File(key_path) => {
-                    let db_key_path = self
-                        .key_path
-                        .as_ref()
-                        .ok_or_else(|| format_err!("Key path was not set!"))?;
-                    debug!("Key reference from database, {}", db_key_path);
-                    let key_ref: Vec<String> =
-                        db_key_path.split('.').map(ToOwned::to_owned).collect();
-                    debug!("Split key ref {:?}", key_ref);
-                    debug!("Opening file, {}", key_path.display());
-                    let key_data: Value = serde_json::from_reader(fs::File::open(&key_path)?)?;
-                    let key = key_ref.iter().fold(Ok(&key_data), |key_data, key_ref| {
-                        key_data.and_then(|key_data| {
-                            debug!("Key data {:?}", key_data);
-                            key_data.get(key_ref).ok_or_else(|| {
-                                format_err!("Failed to find property, {}, in key data.", key_ref)
-                            })
-                        })
-                    })?;
-                    let key = key.as_str().ok_or_else(|| {
-                        format_err!("Key data in the JSON key file wasn't a string!")
-                    })?;
-                    debug!("Found key, {}", key);
-                    key.as_bytes().to_owned()
+            ),
+            ApiKey => match self.key_location {
+                Some(ApiKeyLocation::Query) => {
+                    format!(
+                        "API key in the query parameter, {}",
+                        or_unset(&self.key_name)
+                    )
                 }
-            };
-            token.verify(&key).map_err(anyhow::Error::from)
-        } else {
-            Ok(false)
+                _ => format!("API key in the header, {}", or_unset(&self.key_name)),
+            },
+            ClientCert => match (&self.subject, &self.fingerprint) {
+                (Some(subject), Some(fingerprint)) => format!(
+                    "Client certificate with the subject, {}, and the fingerprint, {}",
+                    subject, fingerprint
+                ),
+                (Some(subject), None) => {
+                    format!("Client certificate with the subject, {}", subject)
+                }
+                (None, fingerprint) => format!(
+                    "Client certificate with the fingerprint, {}",
+                    or_unset(fingerprint)
+                ),
+            },
+        }
+    }
+
+    fn is_nth_call(&self, call_number: i64) -> Result<Check> {
+        let nth = self
+            .nth
+            .ok_or_else(|| format_err!("Call number was not set!"))?;
+        Ok((
+            i64::from(nth) == call_number,
+            format!("call {}", call_number),
+        ))
+    }
+
+    fn is_authenticated(&self, context: &RequestContext) -> Result<Check> {
+        let token = match bearer_token(context.request) {
+            Some(token) => token,
+            None => return Ok((false, NO_TOKEN.to_owned())),
+        };
+        debug!("Verifying token {:?}", token);
+        let token = match SignedToken::parse(&token) {
+            Ok(token) => token,
+            Err(error) => return Ok((false, unreadable_token(&error))),
+        };
+        let algorithm = match Algorithm::named(&token.header.alg) {
+            Some(algorithm) => algorithm,
+            None => {
+                return Ok((
+                    false,
+                    format!(
+                        "a JWT signed with {}, which isn't supported",
+                        token.header.alg
+                    ),
+                ))
+            }
+        };
+        if !self.algorithms.is_empty()
+            && !self
+                .algorithms
+                .iter()
+                .any(|allowed| allowed == algorithm.name())
+        {
+            return Ok((false, format!("a JWT signed with {}", algorithm.name())));
         }
+        let key = match self.load_key(context, &token.header, algorithm)? {
+            Ok(key) => key,
+            Err(found) => return Ok((false, found)),
+        };
+        if !key.suits(algorithm) {
+            return Ok((
+                false,
+                format!(
+                    "a JWT signed with {}, which the key can't verify",
+                    algorithm.name()
+                ),
+            ));
+        }
+        let verified = key.verify(algorithm, token.signed, &token.signature)?;
+        Ok((
+            verified,
+            if verified {
+                "a verified JWT".to_owned()
+            } else {
+                "a JWT that could not be verified".to_owned()
+            },
+        ))
+    }
+
+    /// The key the rule trusts to verify a token, from `KEY_PATH` as `KEY_PATH_KIND` says, or
+    /// why no key the rule trusts can verify the token, for a JWKS.
+    fn load_key(
+        &self,
+        context: &RequestContext,
+        header: &TokenHeader,
+        algorithm: Algorithm,
+    ) -> Result<std::result::Result<Key, String>> {
+        let db_key_path = self
+            .key_path
+            .as_ref()
+            .ok_or_else(|| format_err!("Key path was not set!"))?;
+        let key = match &*config::KEY_PATH_KIND {
+            KeyPathKind::Jwks(_) => {
+                let key_set = context
+                    .key_set
+                    .ok_or_else(|| format_err!("The JWKS was not loaded!"))?;
+                let jwk = match key_set.choose(db_key_path, header.kid.as_deref()) {
+                    Ok(jwk) => jwk,
+                    Err(found) => return Ok(Err(found)),
+                };
+                if !jwk.allows(algorithm) {
+                    return Ok(Err(format!(
+                        "a JWT signed with {}, which its key isn't for",
+                        algorithm.name()
+                    )));
+                }
+                jwk.to_key()?
+            }
+            kind => Key::from_pem(&read_key(kind, db_key_path)?)?,
+        };
+        Ok(Ok(key))
+    }
+
+    fn is_authorized(&self, request: &HttpRequest) -> Result<Check> {
+        let claims = match bearer_claims(request) {
+            Ok(claims) => claims,
+            Err(found) => return Ok((false, found)),
+        };
+        let subject = claims.get("sub").and_then(Value::as_str);
+        debug!("Authorizing subject {:?}", subject);
+        let found = describe_found(&subject.iter().collect::<Vec<&&str>>());
+        Ok((subject == self.subject.as_deref(), found))
+    }
+
+    fn is_claim_match(&self, request: &HttpRequest) -> Result<Check> {
+        let claim = self
+            .claim
+            .as_ref()
+            .ok_or_else(|| format_err!("Claim was not set!"))?;
+        let pointer = claim_pointer(claim)?;
+        let claims = match bearer_claims(request) {
+            Ok(claims) => claims,
+            Err(found) => return Ok((false, found)),
+        };
+        let found = claims
+            .pointer(&pointer)
+            .cloned()
+            .map(json_as_text)
+            .unwrap_or_default();
+        debug!("Found values for claim, {}, {:?}", claim, found);
+        self.match_mode
+            .as_ref()
+            .ok_or_else(|| format_err!("Match mode was not set!"))?
+            .test(self.match_value.as_deref(), &found)
+            .map(|passed| (passed, describe_found(&found)))
+    }
+
+    fn is_basic_auth(&self, request: &HttpRequest) -> Result<Check> {
+        let username = self
+            .username
+            .as_ref()
+            .ok_or_else(|| format_err!("Username was not set!"))?;
+        let authorization = request
+            .headers()
+            .get("Authorization")
+            .and_then(|authorization| authorization.to_str().ok());
+        let (found, password) = match authorization.and_then(basic_credentials) {
+            Some(credentials) => credentials,
+            None => return Ok((false, "no basic credentials".to_owned())),
+        };
+        debug!("Found basic credentials for {}", found);
+        let passed = &found == username
+            && self
+                .password
+                .as_ref()
+                .map(|expected| expected == &password)
+                .unwrap_or(true);
+        // the password is never described, even when it is wrong
+        Ok((passed, format!("basic credentials for {}", found)))
     }
 
-    fn is_authorized(&self, request: &HttpRequest) -> Result<bool> {
-        if let Some(token) = extract_auth_token(request)? {
-            debug!("Authorizing token {:?}", token);
-            Ok(token.payload.sub == self.subject)
+    fn is_api_key(&self, request: &HttpRequest) -> Result<Check> {
+        let key_name = self
+            .key_name
+            .as_ref()
+            .ok_or_else(|| format_err!("API key name was not set!"))?;
+        let api_key = self
+            .api_key
+            .as_ref()
+            .ok_or_else(|| format_err!("API key was not set!"))?;
+        let found = match self.key_location {
+            Some(ApiKeyLocation::Header) => request
+                .headers()
+                .get_all(key_name.as_str())
+                .filter_map(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+                .collect(),
+            Some(ApiKeyLocation::Query) => field_values(request.query_string(), key_name),
+            None => bail!("API key location was not set!"),
+        };
+        debug!("Found API keys named {}, {:?}", key_name, found);
+        Ok((
+            found.iter().any(|found| found == api_key),
+            describe_found(&found),
+        ))
+    }
+
+    fn is_client_cert(&self, request: &HttpRequest) -> Result<Check> {
+        self.is_client_cert_from(request, &config::PROXIES)
+    }
+
+    /// Only reads a certificate forwarded by one of the proxies trusted to, since any other caller
+    /// could send the header with a certificate of its choosing.
+    fn is_client_cert_from(
+        &self,
+        request: &HttpRequest,
+        proxies: &HashSet<IpAddr>,
+    ) -> Result<Check> {
+        let trusted = request
+            .peer_addr()
+            .map(|peer| proxies.contains(&peer.ip()))
+            .unwrap_or(false);
+        let forwarded = match request.headers().get(&*config::CLIENT_CERT) {
+            Some(forwarded) if trusted => forwarded,
+            Some(_) => {
+                return Ok((
+                    false,
+                    "a client certificate from a caller that isn't a trusted proxy".to_owned(),
+                ))
+            }
+            None => return Ok((false, "no client certificate".to_owned())),
+        };
+        let certificate = match forwarded
+            .to_str()
+            .map_err(anyhow::Error::from)
+            .and_then(ClientCertificate::parse)
+        {
+            Ok(certificate) => certificate,
+            Err(error) => {
+                return Ok((
+                    false,
+                    format!("a client certificate that couldn't be read, {}", error),
+                ))
+            }
+        };
+        debug!("Found client certificate for {}", certificate.subject);
+        let passed = self
+            .subject
+            .as_ref()
+            .map(|subject| certificate.has_subject(subject))
+            .unwrap_or(true)
+            && self
+                .fingerprint
+                .as_ref()
+                .map(|fingerprint| certificate.has_fingerprint(fingerprint))
+                .unwrap_or(true);
+        Ok((
+            passed,
+            format!(
+                "the subject, {}, and the fingerprint, {}",
+                certificate.subject, certificate.fingerprint
+            ),
+        ))
+    }
+
+    fn is_method(&self, request: &HttpRequest) -> Result<Check> {
+        let http_method = self
+            .http_method
+            .as_ref()
+            .ok_or_else(|| format_err!("HTTP method was not set!"))?;
+        debug!("Comparing {:?} to {}", http_method, request.method());
+        Ok((
+            http_method.as_method() == *request.method(),
+            request.method().to_string(),
+        ))
+    }
+
+    fn is_header_match(&self, request: &HttpRequest) -> Result<Check> {
+        let header_name = self
+            .header_name
+            .as_ref()
+            .ok_or_else(|| format_err!("Header name was not set!"))?;
+        // a value that isn't text is still there, but only matches as far as it can be read
+        let found: Vec<String> = request
+            .headers()
+            .get_all(header_name.as_str())
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+            .collect();
+        debug!("Found values for header, {}, {:?}", header_name, found);
+        self.match_mode
+            .as_ref()
+            .ok_or_else(|| format_err!("Match mode was not set!"))?
+            .test(self.match_value.as_deref(), &found)
+            .map(|passed| (passed, describe_found(&found)))
+    }
+
+    fn is_body_match(&self, body: &[u8]) -> Result<Check> {
+        // an empty body is treated as missing, so that it is absent rather than present and empty
+        let found: Vec<String> = if body.is_empty() {
+            Vec::new()
+        } else if let Some(body_path) = self.body_path.as_ref() {
+            let pointer = json_pointer(body_path)?;
+            // a body that isn't JSON has nothing at any path, the same as a path that isn't found
+            serde_json::from_slice::<Value>(body)
+                .ok()
+                .and_then(|body| body.pointer(&pointer).cloned())
+                .map(json_as_text)
+                .unwrap_or_default()
+        } else if let Some(form_field) = self.form_field.as_ref() {
+            field_values(&String::from_utf8_lossy(body), form_field)
         } else {
-            Ok(false)
+            vec![String::from_utf8_lossy(body).into_owned()]
+        };
+        debug!("Found values for body, {:?}", found);
+        self.match_mode
+            .as_ref()
+            .ok_or_else(|| format_err!("Match mode was not set!"))?
+            .test(self.match_value.as_deref(), &found)
+            .map(|passed| (passed, describe_found(&found)))
+    }
+
+    fn is_query_param_match(&self, request: &HttpRequest) -> Result<Check> {
+        let param_name = self
+            .param_name
+            .as_ref()
+            .ok_or_else(|| format_err!("Query parameter name was not set!"))?;
+        // parameters are parsed so that their order and encoding don't matter
+        let found = field_values(request.query_string(), param_name);
+        debug!(
+            "Found values for query parameter, {}, {:?}",
+            param_name, found
+        );
+        self.match_mode
+            .as_ref()
+            .ok_or_else(|| format_err!("Match mode was not set!"))?
+            .test(self.match_value.as_deref(), &found)
+            .map(|passed| (passed, describe_found(&found)))
+    }
+
+    fn is_path_param_match(&self, path_params: &PathParams) -> Result<Check> {
+        let param_name = self
+            .param_name
+            .as_ref()
+            .ok_or_else(|| format_err!("Path parameter name was not set!"))?;
+        let found: Vec<&String> = path_params.get(param_name).into_iter().collect();
+        debug!(
+            "Found value for path parameter, {}, {:?}",
+            param_name, found
+        );
+        self.match_mode
+            .as_ref()
+            .ok_or_else(|| format_err!("Match mode was not set!"))?
+            .test(self.match_value.as_deref(), &found)
+            .map(|passed| (passed, describe_found(&found)))
+    }
+}
+
+impl HttpVerb {
+    fn as_method(&self) -> Method {
+        use HttpVerb::*;
+        match self {
+            Get => Method::GET,
+            Post => Method::POST,
+            Put => Method::PUT,
+            Delete => Method::DELETE,
+            Patch => Method::PATCH,
+            Head => Method::HEAD,
+            Options => Method::OPTIONS,
+        }
+    }
+}
+
+impl MatchMode {
+    fn describe(&self, expected: Option<&str>) -> String {
+        use MatchMode::*;
+        let expected = expected.unwrap_or_default();
+        match self {
+            Exact => format!("exactly matches \"{}\"", expected),
+            Present => "is present".to_owned(),
+            Absent => "is absent".to_owned(),
+            CaseInsensitive => format!("matches \"{}\", ignoring case", expected),
+            Regex => format!("matches the regular expression \"{}\"", expected),
+            Contains => format!("contains \"{}\"", expected),
+            AnyOf => format!("is any of \"{}\"", expected),
         }
     }
 
-    fn is_method(&self, _request: &HttpRequest) -> Result<bool> {
-        todo!("Evaluate method rule")
+    /// Tests all of the values found in a request, which may be none, against the value a rule
+    /// expects; the rule passes if any of the found values match.
+    pub(crate) fn test<S: AsRef<str>>(&self, expected: Option<&str>, found: &[S]) -> Result<bool> {
+        use MatchMode::*;
+        let expected = || expected.ok_or_else(|| format_err!("Match value was not set!"));
+        Ok(match self {
+            Present => !found.is_empty(),
+            Absent => found.is_empty(),
+            Exact => {
+                let expected = expected()?;
+                found.iter().any(|found| found.as_ref() == expected)
+            }
+            CaseInsensitive => {
+                let expected = expected()?;
+                found
+                    .iter()
+                    .any(|found| found.as_ref().to_lowercase() == expected.to_lowercase())
+            }
+            Regex => {
+                let expected = Pattern::new(expected()?)?;
+                found.iter().any(|found| expected.is_match(found.as_ref()))
+            }
+            Contains => {
+                let expected = expected()?;
+                found.iter().any(|found| found.as_ref().contains(expected))
+            }
+            AnyOf => {
+                let expected: Vec<&str> = expected()?.split(',').map(str::trim).collect();
+                found.iter().any(|found| expected.contains(&found.as_ref()))
+            }
+        })
     }
 }
 
-fn extract_auth_token(request: &HttpRequest) -> Result<Option<DefaultToken<DefaultPayload>>> {
-    let auth = request.headers().get("Authorization");
-    if let Some(auth) = auth {
-        let auth = auth.to_str()?;
-        let auth = auth.trim_start_matches("Bearer").trim();
-        let auth = auth.split('.').take(3).collect::<Vec<&str>>().join(".");
-        DefaultToken::parse(&auth)
-            .map(Some)
-            .map_err(anyhow::Error::from)
+const NO_TOKEN: &str = "no bearer token";
+
+/// Checks the times a token is valid between, if it has them, against the time of the request;
+/// a token is valid until, but not at, its `exp` and from its `nbf` on.
+fn is_unexpired(request: &HttpRequest) -> Result<Check> {
+    let claims = match bearer_claims(request) {
+        Ok(claims) => claims,
+        Err(found) => return Ok((false, found)),
+    };
+    let now = Utc::now().timestamp() as f64;
+    // the claims come from the caller, so one that isn't a time fails the rule, not the call
+    let time = |name: &str| match claims.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(time) => time.as_f64().map(Some).ok_or_else(|| {
+            format!(
+                "{} of {}, not a number of seconds since the epoch",
+                name,
+                describe_found(&json_as_text(time.clone()))
+            )
+        }),
+    };
+    let (expires, not_before) = match (time("exp"), time("nbf")) {
+        (Ok(expires), Ok(not_before)) => (expires, not_before),
+        (Err(found), _) | (_, Err(found)) => return Ok((false, found)),
+    };
+    let unexpired = match expires {
+        Some(expires) => now < expires,
+        None => true,
+    };
+    let started = match not_before {
+        Some(not_before) => not_before <= now,
+        None => true,
+    };
+    let describe = |name: &str, time: Option<f64>| {
+        time.map(|time| format!("{} {}", name, time))
+            .unwrap_or_else(|| format!("no {}", name))
+    };
+    Ok((
+        unexpired && started,
+        format!(
+            "{} and {} at {}",
+            describe("exp", expires),
+            describe("nbf", not_before),
+            now
+        ),
+    ))
+}
+
+/// The longest value, in characters, quoted in a description of what a rule found.
+const FOUND_LIMIT: usize = 200;
+
+fn describe_found<S: AsRef<str>>(found: &[S]) -> String {
+    if found.is_empty() {
+        "nothing".to_owned()
+    } else {
+        found
+            .iter()
+            .map(|found| {
+                let found = found.as_ref();
+                if found.chars().count() > FOUND_LIMIT {
+                    format!(
+                        "\"{}...\"",
+                        found.chars().take(FOUND_LIMIT).collect::<String>()
+                    )
+                } else {
+                    format!("\"{}\"", found)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Converts the claim of a claim rule to a JSON pointer into the claims of a token; a claim is
+/// either the name of a top level claim, like `scope`, or a path like that of a body rule.
+pub(crate) fn claim_pointer(claim: &str) -> Result<String> {
+    if claim.is_empty() {
+        bail!("The claim must be the name of a claim or a path to one!");
+    }
+    if claim.starts_with('/') || claim.starts_with('$') {
+        json_pointer(claim).map_err(|_| {
+            format_err!(
+                "The claim, {}, must be the name of a claim, a JSON pointer, like \"/tenant/id\", or a JSONPath of names and indices, like \"$.tenant.id\"!",
+                claim
+            )
+        })
     } else {
-        Ok(None)
+        Ok(format!("/{}", claim.replace('~', "~0").replace('/', "~1")))
+    }
+}
+
+/// Converts the path of a body rule to a JSON pointer; a path is either already a pointer, like
+/// `/order/items/0`, or a JSONPath of names and indices, like `$.order.items[0]` or
+/// `$['order']['items'][0]`, since wildcards and filters can't be expressed as a pointer.
+pub(crate) fn json_pointer(path: &str) -> Result<String> {
+    if path.is_empty() || path.starts_with('/') {
+        return Ok(path.to_owned());
+    }
+    let invalid = || {
+        format_err!(
+            "The body path, {}, must be a JSON pointer, like \"/order/id\", or a JSONPath of names and indices, like \"$.order.items[0]\"!",
+            path
+        )
+    };
+    let mut rest = path.strip_prefix('$').ok_or_else(invalid)?;
+    let mut pointer = String::with_capacity(path.len());
+    while !rest.is_empty() {
+        let (token, remaining) = if let Some(after) = rest.strip_prefix("['") {
+            after.split_once("']").ok_or_else(invalid)?
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let (index, remaining) = after.split_once(']').ok_or_else(invalid)?;
+            index.parse::<usize>().map_err(|_| invalid())?;
+            (index, remaining)
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(&['.', '['][..]).unwrap_or(after.len());
+            after.split_at(end)
+        } else {
+            return Err(invalid());
+        };
+        if token.is_empty() || token == "*" {
+            return Err(invalid());
+        }
+        pointer.push('/');
+        pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+        rest = remaining;
+    }
+    Ok(pointer)
+}
+
+/// The name and value of each field of URL encoded text, like a query or form body, in order and
+/// decoded; text that can't be decoded has no fields.
+pub(crate) fn urlencoded_fields(encoded: &str) -> Vec<(String, String)> {
+    Query::<Vec<(String, String)>>::from_query(encoded)
+        .map(Query::into_inner)
+        .unwrap_or_default()
+}
+
+/// The username and password of HTTP Basic credentials in an Authorization header, if that is what
+/// it has.
+pub(crate) fn basic_credentials(authorization: &str) -> Option<(String, String)> {
+    let mut parts = authorization.trim().splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(credentials)) if scheme.eq_ignore_ascii_case("Basic") => {
+            let credentials = base64::decode(credentials.trim()).ok()?;
+            let credentials = String::from_utf8(credentials).ok()?;
+            let (username, password) = credentials.split_once(':')?;
+            Some((username.to_owned(), password.to_owned()))
+        }
+        _ => None,
+    }
+}
+
+/// Every value of a field of URL encoded text, since a field may be repeated.
+fn field_values(encoded: &str, name: &str) -> Vec<String> {
+    urlencoded_fields(encoded)
+        .into_iter()
+        .filter(|(field, _)| field == name)
+        .map(|(_, value)| value)
+        .collect()
+}
+
+/// The text of a JSON value found in a body; strings are matched without their quotes and each
+/// element of an array is matched on its own.
+fn json_as_text(value: Value) -> Vec<String> {
+    match value {
+        Value::String(value) => vec![value],
+        Value::Array(values) => values.into_iter().flat_map(json_as_text).collect(),
+        value => vec![value.to_string()],
+    }
+}
+
+/// The bearer token from the request, if any, for verifying it; credentials of any other scheme,
+/// like Basic, aren't a bearer token.
+fn bearer_token(request: &HttpRequest) -> Option<String> {
+    let auth = request.headers().get("Authorization")?.to_str().ok()?;
+    let (scheme, token) = auth.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("Bearer") {
+        return None;
+    }
+    Some(
+        token
+            .trim()
+            .split('.')
+            .take(3)
+            .collect::<Vec<&str>>()
+            .join("."),
+    )
+}
+
+/// The claims of the request's bearer token, or else what was found instead, for a rule that
+/// checks them to fail with.
+fn bearer_claims(request: &HttpRequest) -> std::result::Result<Value, String> {
+    match extract_claims(request) {
+        Ok(Some(claims)) => Ok(claims),
+        Ok(None) => Err(NO_TOKEN.to_owned()),
+        Err(error) => Err(unreadable_token(&error)),
+    }
+}
+
+fn unreadable_token(error: &anyhow::Error) -> String {
+    format!("a bearer token that couldn't be read, {}", error)
+}
+
+/// Every claim of the request's bearer token, registered and custom, as JSON; the claims are
+/// decoded as they are, so that those the token parser can't handle, like an array of audiences,
+/// can still be matched.
+pub(super) fn extract_claims(request: &HttpRequest) -> Result<Option<Value>> {
+    let token = match bearer_token(request) {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+    let claims = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| format_err!("The bearer token is not a JWT!"))?;
+    let claims = base64::decode_config(claims.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+        .context("The claims of the bearer token are not valid base64!")?;
+    serde_json::from_slice(&claims)
+        .map(Some)
+        .context("The claims of the bearer token are not valid JSON!")
+}
+
+/// The subject claim of the request's bearer token, if it has one that can be parsed.
+pub(crate) fn extract_subject(request: &HttpRequest) -> Option<String> {
+    extract_claims(request)
+        .ok()
+        .flatten()
+        .and_then(|claims| claims.get("sub")?.as_str().map(ToOwned::to_owned))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::{http::HeaderValue, test::TestRequest};
+    use serde_json::json;
+    use std::convert::TryFrom;
+    use uuid::Uuid;
+
+    fn method_rule(http_method: shared::HttpVerb) -> Result<Rule> {
+        let id = Some(Uuid::new_v4());
+        Rule::try_from((Uuid::new_v4(), shared::Rule::HttpMethod { id, http_method }))
+    }
+
+    fn path_param_rule(match_mode: shared::MatchMode, match_value: Option<&str>) -> Result<Rule> {
+        let id = Some(Uuid::new_v4());
+        Rule::try_from((
+            Uuid::new_v4(),
+            shared::Rule::PathParam {
+                id,
+                param_name: "id".to_owned(),
+                match_mode,
+                match_value: match_value.map(ToOwned::to_owned),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_method() -> Result<()> {
+        let path_params = PathParams::new();
+        let request = TestRequest::default()
+            .method(Method::PATCH)
+            .to_http_request();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            method_rule(shared::HttpVerb::Patch)?
+                .check(&context)?
+                .passed
+        );
+        assert!(!method_rule(shared::HttpVerb::Put)?.check(&context)?.passed);
+
+        let request = TestRequest::default()
+            .method(Method::OPTIONS)
+            .to_http_request();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            method_rule(shared::HttpVerb::Options)?
+                .check(&context)?
+                .passed
+        );
+        assert!(!method_rule(shared::HttpVerb::Get)?.check(&context)?.passed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_param() -> Result<()> {
+        let request = TestRequest::default().to_http_request();
+        let mut path_params = PathParams::new();
+        path_params.insert("id".to_owned(), "42".to_owned());
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            path_param_rule(shared::MatchMode::Exact, Some("42"))?
+                .check(&context)?
+                .passed
+        );
+        assert!(
+            !path_param_rule(shared::MatchMode::Exact, Some("7"))?
+                .check(&context)?
+                .passed
+        );
+        assert!(
+            path_param_rule(shared::MatchMode::Regex, Some("^[0-9]+$"))?
+                .check(&context)?
+                .passed
+        );
+        assert!(
+            path_param_rule(shared::MatchMode::Present, None)?
+                .check(&context)?
+                .passed
+        );
+
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            path_param_rule(shared::MatchMode::Absent, None)?
+                .check(&context)?
+                .passed
+        );
+        Ok(())
+    }
+
+    fn body_rule(
+        body_path: Option<&str>,
+        form_field: Option<&str>,
+        match_mode: shared::MatchMode,
+        match_value: Option<&str>,
+    ) -> Result<Rule> {
+        Rule::try_from((
+            Uuid::new_v4(),
+            shared::Rule::Body {
+                id: Some(Uuid::new_v4()),
+                body_path: body_path.map(ToOwned::to_owned),
+                form_field: form_field.map(ToOwned::to_owned),
+                match_mode,
+                match_value: match_value.map(ToOwned::to_owned),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_body() -> Result<()> {
+        let body_rule = |match_mode, match_value| body_rule(None, None, match_mode, match_value);
+        let request = TestRequest::default().to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: b"{\"name\":\"Alice\"}",
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            body_rule(shared::MatchMode::Exact, Some("{\"name\":\"Alice\"}"))?
+                .check(&context)?
+                .passed
+        );
+        assert!(
+            body_rule(shared::MatchMode::Regex, Some("Alice"))?
+                .check(&context)?
+                .passed
+        );
+        assert!(
+            !body_rule(shared::MatchMode::Absent, None)?
+                .check(&context)?
+                .passed
+        );
+
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            body_rule(shared::MatchMode::Absent, None)?
+                .check(&context)?
+                .passed
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_body_path() -> Result<()> {
+        let request = TestRequest::default().to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body:
+                b"{\"order\":{\"id\":42,\"customer\":\"Alice Smith\",\"tags\":[\"rush\",\"gift\"]}}",
+            call_number: 1,
+            key_set: None,
+        };
+        let passes = |body_path, match_mode, match_value| -> Result<bool> {
+            Ok(body_rule(Some(body_path), None, match_mode, match_value)?
+                .check(&context)?
+                .passed)
+        };
+        assert!(passes("/order/id", shared::MatchMode::Exact, Some("42"))?);
+        assert!(passes("$.order.id", shared::MatchMode::Exact, Some("42"))?);
+        assert!(passes(
+            "$.order.customer",
+            shared::MatchMode::Exact,
+            Some("Alice Smith")
+        )?);
+        assert!(passes(
+            "$['order']['customer']",
+            shared::MatchMode::Contains,
+            Some("Smith")
+        )?);
+        assert!(passes(
+            "/order/customer",
+            shared::MatchMode::Regex,
+            Some("^Alice")
+        )?);
+        assert!(passes(
+            "$.order.tags",
+            shared::MatchMode::Exact,
+            Some("gift")
+        )?);
+        assert!(passes(
+            "$.order.tags[0]",
+            shared::MatchMode::Exact,
+            Some("rush")
+        )?);
+        assert!(!passes(
+            "$.order.tags[1]",
+            shared::MatchMode::Exact,
+            Some("rush")
+        )?);
+        assert!(passes("$.order.total", shared::MatchMode::Absent, None)?);
+
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: b"not json",
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            body_rule(Some("/order/id"), None, shared::MatchMode::Absent, None)?
+                .check(&context)?
+                .passed
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_form_field() -> Result<()> {
+        let request = TestRequest::default().to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: b"name=Alice+Smith&role=admin&role=owner",
+            call_number: 1,
+            key_set: None,
+        };
+        let passes = |form_field, match_mode, match_value| -> Result<bool> {
+            Ok(body_rule(None, Some(form_field), match_mode, match_value)?
+                .check(&context)?
+                .passed)
+        };
+        assert!(passes(
+            "name",
+            shared::MatchMode::Exact,
+            Some("Alice Smith")
+        )?);
+        assert!(passes("role", shared::MatchMode::Exact, Some("owner"))?);
+        assert!(!passes("role", shared::MatchMode::Exact, Some("guest"))?);
+        assert!(passes("email", shared::MatchMode::Absent, None)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_param() -> Result<()> {
+        let query_param_rule = |match_mode, match_value: Option<&str>| {
+            Rule::try_from((
+                Uuid::new_v4(),
+                shared::Rule::QueryParam {
+                    id: Some(Uuid::new_v4()),
+                    param_name: "status".to_owned(),
+                    match_mode,
+                    match_value: match_value.map(ToOwned::to_owned),
+                },
+            ))
+        };
+        let path_params = PathParams::new();
+        // the order of the parameters doesn't matter, nor does how they are encoded
+        for uri in &[
+            "/api/orders?status=open&page=1",
+            "/api/orders?page=1&status=open",
+            "/api/orders?page=1&status=%6Fpen",
+        ] {
+            let request = TestRequest::with_uri(uri).to_http_request();
+            let context = RequestContext {
+                request: &request,
+                path_params: &path_params,
+                body: &[],
+                call_number: 1,
+                key_set: None,
+            };
+            assert!(
+                query_param_rule(shared::MatchMode::Exact, Some("open"))?
+                    .check(&context)?
+                    .passed
+            );
+            assert!(
+                query_param_rule(shared::MatchMode::Regex, Some("^op"))?
+                    .check(&context)?
+                    .passed
+            );
+            assert!(
+                query_param_rule(shared::MatchMode::Present, None)?
+                    .check(&context)?
+                    .passed
+            );
+        }
+
+        let request = TestRequest::with_uri("/api/orders?page=1").to_http_request();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(
+            query_param_rule(shared::MatchMode::Absent, None)?
+                .check(&context)?
+                .passed
+        );
+        assert!(
+            !query_param_rule(shared::MatchMode::Exact, Some("open"))?
+                .check(&context)?
+                .passed
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scenario() -> Result<()> {
+        let now = chrono::NaiveDateTime::from_timestamp(0, 0);
+        let recipe = Recipe {
+            id: Uuid::new_v4(),
+            url: "http://test.local/api/orders/1".to_owned(),
+            payload: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            status_code: 200,
+            templated: false,
+            content_type: shared::default_content_type(),
+            draft: false,
+            priority: 0,
+            scenario: Some("approval".to_owned()),
+            required_state: Some(SCENARIO_STARTED.to_owned()),
+            new_state: Some("Pending".to_owned()),
+            sequence_mode: SequenceMode::StickOnLast,
+            delay_type: None,
+            delay_millis: None,
+            delay_max_millis: None,
+            delay_std_dev_millis: None,
+            delay_sigma: None,
+            fault_type: None,
+            trickle_chunk_bytes: None,
+            trickle_interval_millis: None,
+            namespace: shared::DEFAULT_NAMESPACE.to_owned(),
+            file: None,
+        };
+        let path_params = PathParams::new();
+        let request = TestRequest::default().to_http_request();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        assert!(recipe.evaluate_rules(&[], &context, None)?.matched());
+        assert!(recipe
+            .evaluate_rules(&[], &context, Some(SCENARIO_STARTED))?
+            .matched());
+        let evaluation = recipe.evaluate_rules(&[], &context, Some("Pending"))?;
+        assert!(!evaluation.matched());
+        assert_eq!("state, Pending", evaluation.failures()[0].found);
+
+        let recipe = Recipe {
+            required_state: None,
+            ..recipe
+        };
+        assert!(recipe
+            .evaluate_rules(&[], &context, Some("Pending"))?
+            .matched());
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_response() {
+        let now = chrono::NaiveDateTime::from_timestamp(0, 0);
+        let recipe = Recipe {
+            id: Uuid::new_v4(),
+            url: "http://test.local/api/orders/1".to_owned(),
+            payload: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            status_code: 200,
+            templated: false,
+            content_type: shared::default_content_type(),
+            draft: false,
+            priority: 0,
+            scenario: None,
+            required_state: None,
+            new_state: None,
+            sequence_mode: SequenceMode::StickOnLast,
+            delay_type: None,
+            delay_millis: None,
+            delay_max_millis: None,
+            delay_std_dev_millis: None,
+            delay_sigma: None,
+            fault_type: None,
+            trickle_chunk_bytes: None,
+            trickle_interval_millis: None,
+            namespace: shared::DEFAULT_NAMESPACE.to_owned(),
+            file: None,
+        };
+        let served = |recipe: &Recipe| -> Vec<Option<i64>> {
+            (0..5)
+                .map(|served| recipe.next_response(served, 2))
+                .collect()
+        };
+        assert_eq!(
+            vec![Some(0), Some(1), Some(2), Some(2), Some(2)],
+            served(&recipe)
+        );
+        let recipe = Recipe {
+            sequence_mode: SequenceMode::Cycle,
+            ..recipe
+        };
+        assert_eq!(
+            vec![Some(0), Some(1), Some(2), Some(0), Some(1)],
+            served(&recipe)
+        );
+        let recipe = Recipe {
+            sequence_mode: SequenceMode::FallThrough,
+            ..recipe
+        };
+        assert_eq!(vec![Some(0), Some(1), Some(2), None, None], served(&recipe));
+        assert_eq!(Some(0), recipe.next_response(0, 0));
+        assert_eq!(None, recipe.next_response(1, 0));
+    }
+
+    #[test]
+    fn test_nth_call() -> Result<()> {
+        let rule = Rule::try_from((
+            Uuid::new_v4(),
+            shared::Rule::NthCall {
+                id: Some(Uuid::new_v4()),
+                nth: 2,
+            },
+        ))?;
+        let path_params = PathParams::new();
+        let request = TestRequest::default().to_http_request();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        let outcome = rule.check(&context)?;
+        assert!(!outcome.passed);
+        assert_eq!("call 1", outcome.found);
+        let context = RequestContext {
+            call_number: 2,
+            ..context
+        };
+        assert!(rule.check(&context)?.passed);
+        Ok(())
+    }
+
+    /// An unsigned bearer token with the given claims, since claims are matched without verifying
+    /// the token.
+    fn bearer(claims: Value) -> String {
+        let claims = base64::encode_config(claims.to_string(), base64::URL_SAFE_NO_PAD);
+        format!("Bearer e30.{}.signature", claims)
+    }
+
+    fn claim_rule(claim: &str, match_mode: shared::MatchMode, match_value: &str) -> Result<Rule> {
+        Rule::try_from((
+            Uuid::new_v4(),
+            shared::Rule::Claim {
+                id: Some(Uuid::new_v4()),
+                claim: claim.to_owned(),
+                match_mode,
+                match_value: Some(match_value.to_owned()),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_claim() -> Result<()> {
+        let claims = json!({
+            "iss": "https://issuer.local",
+            "aud": ["orders", "billing"],
+            "scope": "orders:read orders:write",
+            "roles": ["admin", "support"],
+            "tenant": {"id": 42, "region": "eu"},
+        });
+        let request = TestRequest::default()
+            .header("Authorization", bearer(claims))
+            .to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        let passes = |claim: &str, match_mode, match_value: &str| -> Result<bool> {
+            Ok(claim_rule(claim, match_mode, match_value)?
+                .check(&context)?
+                .passed)
+        };
+        assert!(passes(
+            "iss",
+            shared::MatchMode::Exact,
+            "https://issuer.local"
+        )?);
+        assert!(passes("aud", shared::MatchMode::Exact, "billing")?);
+        assert!(!passes("aud", shared::MatchMode::Exact, "shipping")?);
+        assert!(passes(
+            "scope",
+            shared::MatchMode::Contains,
+            "orders:write"
+        )?);
+        assert!(passes("roles", shared::MatchMode::AnyOf, "owner, admin")?);
+        assert!(!passes("roles", shared::MatchMode::AnyOf, "owner,auditor")?);
+        assert!(passes("/tenant/id", shared::MatchMode::Exact, "42")?);
+        assert!(passes(
+            "$.tenant.region",
+            shared::MatchMode::Regex,
+            "^(eu|us)$"
+        )?);
+        assert!(!passes("$.tenant.plan", shared::MatchMode::Regex, ".*")?);
+
+        let outcome = claim_rule("roles", shared::MatchMode::Exact, "owner")?.check(&context)?;
+        assert_eq!("\"admin\", \"support\"", outcome.found);
+
+        let request = TestRequest::default().to_http_request();
+        let outcome = claim_rule("iss", shared::MatchMode::Exact, "https://issuer.local")?.check(
+            &RequestContext {
+                request: &request,
+                ..context
+            },
+        )?;
+        assert!(!outcome.passed);
+        assert_eq!(NO_TOKEN, outcome.found);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unexpired() -> Result<()> {
+        let rule = Rule::try_from((
+            Uuid::new_v4(),
+            shared::Rule::Unexpired {
+                id: Some(Uuid::new_v4()),
+            },
+        ))?;
+        let now = Utc::now().timestamp();
+        let passes = |claims: Value| -> Result<bool> {
+            let request = TestRequest::default()
+                .header("Authorization", bearer(claims))
+                .to_http_request();
+            let path_params = PathParams::new();
+            let context = RequestContext {
+                request: &request,
+                path_params: &path_params,
+                body: &[],
+                call_number: 1,
+                key_set: None,
+            };
+            Ok(rule.check(&context)?.passed)
+        };
+        assert!(passes(json!({"sub": "alice"}))?);
+        assert!(passes(json!({"exp": now + 60, "nbf": now - 60}))?);
+        assert!(!passes(json!({"exp": now - 60}))?);
+        assert!(!passes(json!({"nbf": now + 60}))?);
+        assert!(passes(json!({"exp": (now + 60) as f64 + 0.5}))?);
+        assert!(!passes(json!({"exp": "tomorrow"}))?);
+        assert!(!passes(json!({"exp": now + 60, "nbf": "yesterday"}))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_not_text() -> Result<()> {
+        let request = TestRequest::default()
+            .header("X-Team", HeaderValue::from_bytes(b"caf\xe9")?)
+            .to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        let passes = |match_mode, match_value: Option<&str>| -> Result<bool> {
+            let rule = Rule::try_from((
+                Uuid::new_v4(),
+                shared::Rule::Header {
+                    id: Some(Uuid::new_v4()),
+                    header_name: "X-Team".to_owned(),
+                    match_mode,
+                    match_value: match_value.map(str::to_owned),
+                },
+            ))?;
+            Ok(rule.check(&context)?.passed)
+        };
+        assert!(!passes(shared::MatchMode::Exact, Some("café"))?);
+        assert!(passes(shared::MatchMode::Present, None)?);
+        Ok(())
+    }
+
+    /// Whether the rule passes for a GET of the URI with the headers.
+    fn passes_for(rule: shared::Rule, uri: &str, headers: &[(&str, &str)]) -> Result<bool> {
+        let rule = Rule::try_from((Uuid::new_v4(), rule))?;
+        let mut request = TestRequest::with_uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        Ok(rule.check(&context)?.passed)
+    }
+
+    #[test]
+    fn test_basic_auth() -> Result<()> {
+        let rule = |password: Option<&str>| shared::Rule::BasicAuth {
+            id: Some(Uuid::new_v4()),
+            username: "alice".to_owned(),
+            password: password.map(ToOwned::to_owned),
+        };
+        let basic = format!("Basic {}", base64::encode("alice:wonderland"));
+        let credentials = [("Authorization", basic.as_str())];
+        assert!(passes_for(rule(Some("wonderland")), "/", &credentials)?);
+        assert!(passes_for(rule(None), "/", &credentials)?);
+        assert!(!passes_for(rule(Some("looking-glass")), "/", &credentials)?);
+        assert!(!passes_for(rule(None), "/", &[])?);
+        assert!(!passes_for(
+            rule(None),
+            "/",
+            &[("Authorization", "Bearer e30.e30.c2ln")]
+        )?);
+
+        // a password may have colons, but a username can't
+        assert_eq!(
+            Some(("bob".to_owned(), "a:b".to_owned())),
+            basic_credentials(&format!("basic {}", base64::encode("bob:a:b")))
+        );
+        assert_eq!(None, basic_credentials("Basic not-base64!"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_api_key() -> Result<()> {
+        let rule = |key_location: shared::ApiKeyLocation, key_name: &str| shared::Rule::ApiKey {
+            id: Some(Uuid::new_v4()),
+            key_location,
+            key_name: key_name.to_owned(),
+            api_key: "k-123".to_owned(),
+        };
+        let header = || rule(shared::ApiKeyLocation::Header, "X-Api-Key");
+        assert!(passes_for(header(), "/", &[("x-api-key", "k-123")])?);
+        assert!(!passes_for(header(), "/", &[("X-Api-Key", "k-456")])?);
+        assert!(!passes_for(header(), "/?X-Api-Key=k-123", &[])?);
+        let query = || rule(shared::ApiKeyLocation::Query, "api_key");
+        assert!(passes_for(query(), "/?page=2&api_key=k-123", &[])?);
+        assert!(!passes_for(query(), "/?api_key=k-456", &[])?);
+        assert!(!passes_for(query(), "/", &[("api_key", "k-123")])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_cert() -> Result<()> {
+        let pem = super::super::certificate::test::certificate_pem()?;
+        let forwarded = pem.replace('\n', "%0A").replace('+', "%2B");
+        let fingerprint = ClientCertificate::parse(&forwarded)?.fingerprint;
+        let rule = |subject: Option<&str>, fingerprint: Option<&str>| shared::Rule::ClientCert {
+            id: Some(Uuid::new_v4()),
+            subject: subject.map(ToOwned::to_owned),
+            fingerprint: fingerprint.map(ToOwned::to_owned),
+        };
+        let proxies: HashSet<IpAddr> = vec!["10.0.0.2".parse()?].into_iter().collect();
+        // whether the rule passes for a call from the peer, forwarding the certificate, if any
+        let passes = |rule: shared::Rule, peer: &str, forwarded: Option<&str>| -> Result<bool> {
+            let rule = Rule::try_from((Uuid::new_v4(), rule))?;
+            let mut request = TestRequest::default().peer_addr(peer.parse()?);
+            if let Some(forwarded) = forwarded {
+                request = request.header(config::CLIENT_CERT.as_str(), forwarded);
+            }
+            Ok(rule
+                .is_client_cert_from(&request.to_http_request(), &proxies)?
+                .0)
+        };
+        let proxy = "10.0.0.2:443";
+        assert!(passes(rule(Some("alice"), None), proxy, Some(&forwarded))?);
+        assert!(passes(
+            rule(Some("alice"), Some(&fingerprint.to_lowercase())),
+            proxy,
+            Some(&forwarded)
+        )?);
+        assert!(!passes(rule(Some("bob"), None), proxy, Some(&forwarded))?);
+        assert!(!passes(
+            rule(None, Some(&"00".repeat(32))),
+            proxy,
+            Some(&forwarded)
+        )?);
+        assert!(!passes(rule(Some("alice"), None), proxy, None)?);
+        assert!(!passes(rule(Some("alice"), None), proxy, Some("garbage"))?);
+        // any other caller could claim whatever certificate it likes
+        assert!(!passes(
+            rule(Some("alice"), None),
+            "10.0.0.3:443",
+            Some(&forwarded)
+        )?);
+        assert!(!passes_for(
+            rule(Some("alice"), None),
+            "/",
+            &[(config::CLIENT_CERT.as_str(), forwarded.as_str())]
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_claim_pointer() -> Result<()> {
+        assert_eq!("/scope", claim_pointer("scope")?);
+        assert_eq!(
+            "/https:~1~1example.com~1roles",
+            claim_pointer("https://example.com/roles")?
+        );
+        assert_eq!("/tenant/id", claim_pointer("/tenant/id")?);
+        assert_eq!("/tenant/id", claim_pointer("$.tenant.id")?);
+        assert!(claim_pointer("").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_pointer() -> Result<()> {
+        assert_eq!("/order/id", json_pointer("/order/id")?);
+        assert_eq!("", json_pointer("$")?);
+        assert_eq!("/order/items/0/sku", json_pointer("$.order.items[0].sku")?);
+        assert_eq!("/a~1b/c~0d", json_pointer("$['a/b']['c~d']")?);
+        assert!(json_pointer("order.id").is_err());
+        assert!(json_pointer("$.order.*").is_err());
+        assert!(json_pointer("$..id").is_err());
+        assert!(json_pointer("$.items[first]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_presence() -> Result<()> {
+        let none: &[&str] = &[];
+        assert!(MatchMode::Present.test(None, &["foo"])?);
+        assert!(!MatchMode::Present.test(None, none)?);
+        assert!(MatchMode::Absent.test(None, none)?);
+        assert!(!MatchMode::Absent.test(None, &["foo"])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_value() -> Result<()> {
+        assert!(MatchMode::Exact.test(Some("foo"), &["bar", "foo"])?);
+        assert!(!MatchMode::Exact.test(Some("foo"), &["Foo"])?);
+        assert!(MatchMode::CaseInsensitive.test(Some("foo"), &["Foo"])?);
+        assert!(MatchMode::Regex.test(Some("^application/.*json$"), &["application/vnd+json"])?);
+        assert!(!MatchMode::Regex.test(Some("^text/"), &["application/json"])?);
+        assert!(MatchMode::Contains.test(Some("json"), &["application/json"])?);
+        assert!(!MatchMode::Contains.test(Some("JSON"), &["application/json"])?);
+        assert!(MatchMode::AnyOf.test(Some("read, write"), &["admin", "write"])?);
+        assert!(!MatchMode::AnyOf.test(Some("read,write"), &["read write"])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_missing_value() {
+        assert!(MatchMode::Exact.test(None, &["foo"]).is_err());
     }
 }
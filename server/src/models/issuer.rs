@@ -0,0 +1,237 @@
+use super::jwt::{self, Jwk, Key, KeySet, SignedToken, SigningKey};
+use crate::config::{self, KeyPathKind, KeySetSource};
+use anyhow::{bail, format_err, Result};
+use chrono::Utc;
+use lazy_static::lazy_static;
+use openssl::{
+    ec::{EcGroup, EcKey},
+    nid::Nid,
+    pkey::PKey,
+    rsa::Rsa,
+};
+use serde_json::{Map, Value};
+use shared::{Algorithm, IssuedToken, TokenRequest};
+
+/// How long a minted token lasts, in seconds, unless the request says otherwise.
+const DEFAULT_EXPIRES_IN: i64 = 60 * 60;
+
+/// One of the issuer's own keys along with the JWK that publishes it.
+struct IssuerKey {
+    jwk: Jwk,
+    key: SigningKey,
+}
+
+lazy_static! {
+    /// An RSA key and an EC key on P-256, generated when first needed and lost when the server
+    /// stops, so that tokens can be minted without keys of one's own.
+    static ref ISSUER_KEYS: Vec<IssuerKey> = generate()
+        .unwrap_or_else(|error| panic!("Could not generate the issuer's keys: {}", error));
+}
+
+fn generate() -> Result<Vec<IssuerKey>> {
+    let rsa = PKey::from_rsa(Rsa::generate(2048)?)?;
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let ec = PKey::from_ec_key(EcKey::generate(&group)?)?;
+    vec![rsa, ec]
+        .into_iter()
+        .map(|key| {
+            Ok(IssuerKey {
+                jwk: Jwk::public(&key)?,
+                key: SigningKey::Private(key),
+            })
+        })
+        .collect()
+}
+
+/// The JWKS that publishes the issuer's own keys.
+pub(crate) fn issuer_key_set() -> KeySet {
+    KeySet {
+        keys: ISSUER_KEYS.iter().map(|key| key.jwk.clone()).collect(),
+    }
+}
+
+/// Mints a token as the request asks, signed with one of the issuer's own keys or with the key
+/// at the request's key path; a key from a JWKS other than the issuer's own is chosen from the key
+/// set, which must be loaded for such a request.
+pub(crate) fn issue(request: TokenRequest, key_set: Option<&KeySet>) -> Result<IssuedToken> {
+    let TokenRequest {
+        claims: chosen,
+        expires_in,
+        key_path,
+        kid,
+        algorithm,
+    } = request;
+    let (key, key_kid) = match key_path {
+        Some(key_path) => signing_key(&key_path, key_set)?,
+        None => issuer_key(algorithm)?,
+    };
+    let algorithm = match algorithm {
+        Some(algorithm) => algorithm,
+        None => key.default_algorithm()?,
+    };
+    let now = Utc::now().timestamp();
+    let mut claims = Map::new();
+    claims.insert("iat".to_owned(), Value::from(now));
+    claims.insert(
+        "exp".to_owned(),
+        Value::from(now + expires_in.unwrap_or(DEFAULT_EXPIRES_IN)),
+    );
+    claims.extend(chosen);
+    let expires_at = claims.get("exp").and_then(Value::as_i64);
+    let kid = kid.or(key_kid);
+    let token = jwt::mint(&Value::Object(claims), &key, algorithm, kid.as_deref())?;
+    Ok(IssuedToken {
+        token,
+        kid,
+        algorithm,
+        expires_at,
+    })
+}
+
+/// The claims of a token signed by one of the issuer's own keys, whether or not it has expired, or
+/// `None` if it wasn't.
+pub(crate) fn verify_issued(token: &str) -> Result<Option<Map<String, Value>>> {
+    let token = match SignedToken::parse(token) {
+        Ok(token) => token,
+        Err(_) => return Ok(None),
+    };
+    let algorithm = match Algorithm::named(&token.header.alg) {
+        Some(algorithm) => algorithm,
+        None => return Ok(None),
+    };
+    let issuer_key = ISSUER_KEYS
+        .iter()
+        .find(|issuer_key| issuer_key.jwk.kid.is_some() && issuer_key.jwk.kid == token.header.kid);
+    let key = match issuer_key {
+        Some(issuer_key) => issuer_key.jwk.to_key()?,
+        None => return Ok(None),
+    };
+    if !key.suits(algorithm) || !key.verify(algorithm, token.signed, &token.signature)? {
+        return Ok(None);
+    }
+    match token.claims() {
+        Ok(Value::Object(claims)) => Ok(Some(claims)),
+        _ => Ok(None),
+    }
+}
+
+/// The issuer's own key that signs with the algorithm, the RSA key unless one is chosen, and its
+/// `kid`.
+fn issuer_key(algorithm: Option<Algorithm>) -> Result<(SigningKey, Option<String>)> {
+    let algorithm = algorithm.unwrap_or(Algorithm::RS256);
+    for issuer_key in ISSUER_KEYS.iter() {
+        if issuer_key.key.suits(algorithm)? {
+            return Ok((issuer_key.key.clone(), issuer_key.jwk.kid.clone()));
+        }
+    }
+    bail!(
+        "None of the issuer's own keys can sign a JWT with {}; give the key path of a key that can!",
+        algorithm.name()
+    )
+}
+
+/// The key at the key path, found the same way as the key of an Authenticated rule, and the
+/// `kid` that names it, for a key in a JWKS.
+fn signing_key(key_path: &str, key_set: Option<&KeySet>) -> Result<(SigningKey, Option<String>)> {
+    match &*config::KEY_PATH_KIND {
+        KeyPathKind::Jwks(KeySetSource::Issuer) => ISSUER_KEYS
+            .iter()
+            .find(|issuer_key| issuer_key.jwk.kid.as_deref() == Some(key_path))
+            .map(|issuer_key| (issuer_key.key.clone(), issuer_key.jwk.kid.clone()))
+            .ok_or_else(|| format_err!("The issuer has no key, {}!", key_path)),
+        KeyPathKind::Jwks(_) => {
+            let key_set = key_set.ok_or_else(|| format_err!("The JWKS was not loaded!"))?;
+            let jwk = key_set
+                .choose(key_path, None)
+                .map_err(|_| format_err!("The key, {}, isn't in the JWKS!", key_path))?;
+            match jwk.to_key()? {
+                Key::Secret(secret) => Ok((SigningKey::Secret(secret), jwk.kid.clone())),
+                Key::Public(_) => bail!(
+                    "The key, {}, in the JWKS is a public key, which cannot sign a JWT!",
+                    key_path
+                ),
+            }
+        }
+        kind => Ok((SigningKey::from_pem(&jwt::read_key(kind, key_path)?)?, None)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::jwt::SignedToken;
+    use serde_json::json;
+
+    fn verify(issued: &IssuedToken) -> Result<Value> {
+        let token = SignedToken::parse(&issued.token)?;
+        let key = issuer_key_set()
+            .choose("*", token.header.kid.as_deref())
+            .map_err(|found| format_err!(found))?
+            .to_key()?;
+        assert!(key.verify(issued.algorithm, token.signed, &token.signature)?);
+        let claims = issued.token.split('.').nth(1).unwrap_or_default();
+        Ok(serde_json::from_slice(&base64::decode_config(
+            claims,
+            base64::URL_SAFE_NO_PAD,
+        )?)?)
+    }
+
+    #[test]
+    fn test_issue() -> Result<()> {
+        let issued = issue(TokenRequest::default(), None)?;
+        assert_eq!(Algorithm::RS256, issued.algorithm);
+        let claims = verify(&issued)?;
+        assert_eq!(
+            claims["exp"].as_i64(),
+            claims["iat"].as_i64().map(|iat| iat + DEFAULT_EXPIRES_IN)
+        );
+        assert_eq!(issued.expires_at, claims["exp"].as_i64());
+
+        let mut chosen = Map::new();
+        chosen.insert("sub".to_owned(), json!("alice"));
+        chosen.insert("aud".to_owned(), json!(["orders", "users"]));
+        let issued = issue(
+            TokenRequest {
+                claims: chosen,
+                expires_in: Some(-60),
+                algorithm: Some(Algorithm::ES256),
+                ..TokenRequest::default()
+            },
+            None,
+        )?;
+        let claims = verify(&issued)?;
+        assert_eq!("alice", claims["sub"]);
+        assert_eq!(json!(["orders", "users"]), claims["aud"]);
+        // an expired token is still the issuer's
+        assert_eq!(
+            Some(&json!("alice")),
+            verify_issued(&issued.token)?
+                .as_ref()
+                .and_then(|claims| claims.get("sub"))
+        );
+        assert!(issued.expires_at < Some(Utc::now().timestamp()));
+
+        // a chosen kid names the key in the header, even if it isn't the issuer's
+        let issued = issue(
+            TokenRequest {
+                kid: Some("other".to_owned()),
+                ..TokenRequest::default()
+            },
+            None,
+        )?;
+        assert_eq!(Some("other".to_owned()), issued.kid);
+        assert!(verify(&issued).is_err());
+        assert!(verify_issued(&issued.token)?.is_none());
+
+        // the issuer has no secret to sign with
+        assert!(issue(
+            TokenRequest {
+                algorithm: Some(Algorithm::HS256),
+                ..TokenRequest::default()
+            },
+            None
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,786 @@
+use crate::config::KeyPathKind;
+use anyhow::{bail, format_err, Context, Result};
+use base64::URL_SAFE_NO_PAD;
+use log::debug;
+use openssl::{
+    bn::{BigNum, BigNumContext, BigNumRef},
+    ec::{EcGroup, EcKey},
+    ecdsa::EcdsaSig,
+    hash::MessageDigest,
+    memcmp,
+    nid::Nid,
+    pkey::{Id, PKey, Private, Public},
+    rsa::{Padding, Rsa},
+    sha::sha256,
+    sign::{RsaPssSaltlen, Signer, Verifier},
+    x509::X509,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use shared::Algorithm;
+use std::{fs, path::PathBuf, str::FromStr};
+
+/// The curves that ECDSA keys may be on, with the algorithm for each, its name in a JWK and the
+/// size of a coordinate on it, which is also the size of each half of a signature.
+const CURVES: [(Algorithm, Nid, &str, usize); 3] = [
+    (Algorithm::ES256, Nid::X9_62_PRIME256V1, "P-256", 32),
+    (Algorithm::ES384, Nid::SECP384R1, "P-384", 48),
+    (Algorithm::ES512, Nid::SECP521R1, "P-521", 66),
+];
+
+/// The parts of a JWT's header that choose how it is verified.
+#[derive(Deserialize, Debug)]
+pub(crate) struct TokenHeader {
+    pub(crate) alg: String,
+    #[serde(default)]
+    pub(crate) kid: Option<String>,
+}
+
+/// A JWT split into its header, the text its signature covers and the signature itself.
+pub(crate) struct SignedToken<'a> {
+    pub(crate) header: TokenHeader,
+    pub(crate) signed: &'a str,
+    pub(crate) signature: Vec<u8>,
+}
+
+impl<'a> SignedToken<'a> {
+    pub(crate) fn parse(token: &'a str) -> Result<Self> {
+        let mut parts = token.split('.');
+        let (header, claims, signature) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(header), Some(claims), Some(signature)) => (header, claims, signature),
+            _ => bail!("The bearer token is not a JWT!"),
+        };
+        // the signature covers the header and the claims, as they were encoded
+        let signed = &token[..header.len() + 1 + claims.len()];
+        let header = decode(header).context("The header of the bearer token is not valid!")?;
+        let header = serde_json::from_slice(&header)
+            .context("The header of the bearer token is not valid JSON!")?;
+        let signature =
+            decode(signature).context("The signature of the bearer token is not valid!")?;
+        Ok(Self {
+            header,
+            signed,
+            signature,
+        })
+    }
+
+    /// The claims the signature covers, as JSON.
+    pub(crate) fn claims(&self) -> Result<Value> {
+        let claims = self
+            .signed
+            .split_once('.')
+            .map(|(_, claims)| claims)
+            .unwrap_or_default();
+        let claims = decode(claims).context("The claims of the bearer token are not valid!")?;
+        serde_json::from_slice(&claims)
+            .context("The claims of the bearer token are not valid JSON!")
+    }
+}
+
+/// A key that tokens are verified with, either the public half of a key pair or a secret shared
+/// with whatever signs them.
+pub(crate) enum Key {
+    Public(PKey<Public>),
+    Secret(Vec<u8>),
+}
+
+impl Key {
+    /// A public key or certificate in PEM format; anything else is taken to be a secret.
+    pub(crate) fn from_pem(key: &[u8]) -> Result<Self> {
+        if !key.starts_with(b"-----BEGIN") {
+            return Ok(Key::Secret(key.to_owned()));
+        }
+        PKey::public_key_from_pem(key)
+            .or_else(|_| Rsa::public_key_from_pem_pkcs1(key).and_then(PKey::from_rsa))
+            .or_else(|_| X509::from_pem(key).and_then(|certificate| certificate.public_key()))
+            .map(Key::Public)
+            .context("The key is not a public key or certificate in PEM format!")
+    }
+
+    /// Whether a token signed with the algorithm can be verified with this kind of key, so that a
+    /// public key is never used as a secret, or the other way around.
+    pub(crate) fn suits(&self, algorithm: Algorithm) -> bool {
+        use Algorithm::*;
+        match (self, algorithm) {
+            (Key::Secret(_), HS256) | (Key::Secret(_), HS384) | (Key::Secret(_), HS512) => true,
+            (Key::Public(key), RS256)
+            | (Key::Public(key), RS384)
+            | (Key::Public(key), RS512)
+            | (Key::Public(key), PS256)
+            | (Key::Public(key), PS384)
+            | (Key::Public(key), PS512) => key.id() == Id::RSA,
+            (Key::Public(key), ES256) | (Key::Public(key), ES384) | (Key::Public(key), ES512) => {
+                key.ec_key()
+                    .ok()
+                    .and_then(|key| key.group().curve_name())
+                    .map(|curve| Some(curve) == curve_for(algorithm).map(|(nid, _)| nid))
+                    .unwrap_or_default()
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn verify(
+        &self,
+        algorithm: Algorithm,
+        signed: &str,
+        signature: &[u8],
+    ) -> Result<bool> {
+        use Algorithm::*;
+        let digest = digest_for(algorithm);
+        match (self, algorithm) {
+            (Key::Secret(secret), HS256)
+            | (Key::Secret(secret), HS384)
+            | (Key::Secret(secret), HS512) => {
+                let secret = PKey::hmac(secret)?;
+                let mut signer = Signer::new(digest, &secret)?;
+                signer.update(signed.as_bytes())?;
+                let expected = signer.sign_to_vec()?;
+                // the comparison takes the same time whatever the signature, but only for
+                // signatures of the same length
+                Ok(expected.len() == signature.len() && memcmp::eq(&expected, signature))
+            }
+            (Key::Public(key), RS256) | (Key::Public(key), RS384) | (Key::Public(key), RS512) => {
+                let mut verifier = Verifier::new(digest, key)?;
+                verifier.update(signed.as_bytes())?;
+                Ok(verifier.verify(signature).unwrap_or_default())
+            }
+            (Key::Public(key), PS256) | (Key::Public(key), PS384) | (Key::Public(key), PS512) => {
+                let mut verifier = Verifier::new(digest, key)?;
+                verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+                verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+                verifier.set_rsa_mgf1_md(digest)?;
+                verifier.update(signed.as_bytes())?;
+                Ok(verifier.verify(signature).unwrap_or_default())
+            }
+            (Key::Public(key), ES256) | (Key::Public(key), ES384) | (Key::Public(key), ES512) => {
+                // a JWS signature is the two halves of an ECDSA signature side by side, rather
+                // than the DER that openssl expects
+                let half = curve_for(algorithm)
+                    .map(|(_, size)| size)
+                    .unwrap_or_default();
+                if signature.len() != half * 2 {
+                    return Ok(false);
+                }
+                let signature = EcdsaSig::from_private_components(
+                    BigNum::from_slice(&signature[..half])?,
+                    BigNum::from_slice(&signature[half..])?,
+                )?
+                .to_der()?;
+                let mut verifier = Verifier::new(digest, key)?;
+                verifier.update(signed.as_bytes())?;
+                Ok(verifier.verify(&signature).unwrap_or_default())
+            }
+            _ => bail!(
+                "A key of this kind cannot verify a JWT signed with {}!",
+                algorithm.name()
+            ),
+        }
+    }
+}
+
+/// A key that tokens are signed with, either the private half of a key pair or a secret shared
+/// with whatever verifies them.
+#[derive(Clone)]
+pub(crate) enum SigningKey {
+    Private(PKey<Private>),
+    Secret(Vec<u8>),
+}
+
+impl SigningKey {
+    /// A private key in PEM format; anything else is taken to be a secret.
+    pub(crate) fn from_pem(key: &[u8]) -> Result<Self> {
+        if !key.starts_with(b"-----BEGIN") {
+            return Ok(SigningKey::Secret(key.to_owned()));
+        }
+        PKey::private_key_from_pem(key)
+            .map(SigningKey::Private)
+            .context("The key is not a private key in PEM format!")
+    }
+
+    /// Whether the key can sign a token with the algorithm, the same as whether the key that
+    /// verifies what it signs can verify such a token.
+    pub(crate) fn suits(&self, algorithm: Algorithm) -> Result<bool> {
+        let key = match self {
+            SigningKey::Private(key) => {
+                Key::Public(PKey::public_key_from_der(&key.public_key_to_der()?)?)
+            }
+            SigningKey::Secret(secret) => Key::Secret(secret.clone()),
+        };
+        Ok(key.suits(algorithm))
+    }
+
+    /// The algorithm to sign with when none is chosen, the SHA-256 form for the kind of key, or
+    /// the one for the key's curve.
+    pub(crate) fn default_algorithm(&self) -> Result<Algorithm> {
+        for algorithm in &[
+            Algorithm::HS256,
+            Algorithm::RS256,
+            Algorithm::ES256,
+            Algorithm::ES384,
+            Algorithm::ES512,
+        ] {
+            if self.suits(*algorithm)? {
+                return Ok(*algorithm);
+            }
+        }
+        bail!("The key cannot sign a JWT with any supported algorithm!")
+    }
+
+    fn sign(&self, algorithm: Algorithm, signed: &str) -> Result<Vec<u8>> {
+        use Algorithm::*;
+        let digest = digest_for(algorithm);
+        match (self, algorithm) {
+            (SigningKey::Secret(secret), HS256)
+            | (SigningKey::Secret(secret), HS384)
+            | (SigningKey::Secret(secret), HS512) => {
+                let secret = PKey::hmac(secret)?;
+                let mut signer = Signer::new(digest, &secret)?;
+                signer.update(signed.as_bytes())?;
+                Ok(signer.sign_to_vec()?)
+            }
+            (SigningKey::Private(key), RS256)
+            | (SigningKey::Private(key), RS384)
+            | (SigningKey::Private(key), RS512) => {
+                let mut signer = Signer::new(digest, key)?;
+                signer.update(signed.as_bytes())?;
+                Ok(signer.sign_to_vec()?)
+            }
+            (SigningKey::Private(key), PS256)
+            | (SigningKey::Private(key), PS384)
+            | (SigningKey::Private(key), PS512) => {
+                let mut signer = Signer::new(digest, key)?;
+                signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+                signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+                signer.set_rsa_mgf1_md(digest)?;
+                signer.update(signed.as_bytes())?;
+                Ok(signer.sign_to_vec()?)
+            }
+            (SigningKey::Private(key), ES256)
+            | (SigningKey::Private(key), ES384)
+            | (SigningKey::Private(key), ES512) => {
+                let half = curve_for(algorithm)
+                    .map(|(_, size)| size)
+                    .unwrap_or_default();
+                let mut signer = Signer::new(digest, key)?;
+                signer.update(signed.as_bytes())?;
+                let signature = EcdsaSig::from_der(&signer.sign_to_vec()?)?;
+                let mut raw = padded(signature.r(), half);
+                raw.extend(padded(signature.s(), half));
+                Ok(raw)
+            }
+            _ => bail!(
+                "A key of this kind cannot sign a JWT with {}!",
+                algorithm.name()
+            ),
+        }
+    }
+}
+
+/// Mints a JWT with the claims, signed with the key and the algorithm, and naming the key in its
+/// header with the `kid`, if there is one.
+pub(crate) fn mint(
+    claims: &Value,
+    key: &SigningKey,
+    algorithm: Algorithm,
+    kid: Option<&str>,
+) -> Result<String> {
+    if !key.suits(algorithm)? {
+        bail!("The key cannot sign a JWT with {}!", algorithm.name());
+    }
+    let mut header = json! {{"alg": algorithm.name(), "typ": "JWT"}};
+    if let Some(kid) = kid {
+        header["kid"] = Value::from(kid);
+    }
+    let signed = format!(
+        "{}.{}",
+        encode(header.to_string().as_bytes()),
+        encode(claims.to_string().as_bytes())
+    );
+    let signature = key.sign(algorithm, &signed)?;
+    Ok(format!("{}.{}", signed, encode(&signature)))
+}
+
+/// A JWKS document, whose keys are chosen by the `kid` of each token.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct KeySet {
+    pub(crate) keys: Vec<Jwk>,
+}
+
+/// A key in a JWKS; only the members for its type, `kty`, are set.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct Jwk {
+    kty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alg: Option<String>,
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    usage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    n: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    e: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crv: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    y: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    k: Option<String>,
+}
+
+impl KeySet {
+    /// Chooses the key for a token with the given `kid` that a rule trusts, either the key with a
+    /// `kid` of its own or any key in the set, for `*`; if the token can't be verified by any key
+    /// the rule trusts, why not is described instead.
+    pub(crate) fn choose(
+        &self,
+        trusted: &str,
+        kid: Option<&str>,
+    ) -> std::result::Result<&Jwk, String> {
+        let signing = || {
+            self.keys
+                .iter()
+                .filter(|key| matches!(key.usage.as_deref(), None | Some("sig")))
+        };
+        let named = |kid: &str| signing().find(|key| key.kid.as_deref() == Some(kid));
+        match (trusted, kid) {
+            ("*", Some(kid)) => named(kid).ok_or_else(|| {
+                format!(
+                    "a JWT signed with the key, {}, which isn't in the JWKS",
+                    kid
+                )
+            }),
+            (trusted, Some(kid)) if trusted != kid => Err(format!(
+                "a JWT signed with the key, {}, rather than {}",
+                kid, trusted
+            )),
+            ("*", None) => {
+                let mut keys = signing();
+                match (keys.next(), keys.next()) {
+                    (Some(key), None) => Ok(key),
+                    _ => Err("a JWT without a kid to choose a key from the JWKS".to_owned()),
+                }
+            }
+            (trusted, _) => named(trusted)
+                .ok_or_else(|| format!("a JWT, but the key, {}, isn't in the JWKS", trusted)),
+        }
+    }
+
+    /// The `kid` of each key in the set, for choosing which one a rule trusts.
+    pub(crate) fn kids(&self) -> Vec<String> {
+        self.keys.iter().filter_map(|key| key.kid.clone()).collect()
+    }
+}
+
+impl Jwk {
+    /// The public half of a key pair, for publishing in a JWKS, named by its thumbprint as RFC
+    /// 7638 describes so that a new key never has the `kid` of an old one.
+    pub(crate) fn public(key: &PKey<Private>) -> Result<Self> {
+        let mut jwk = match key.id() {
+            Id::RSA => {
+                let rsa = key.rsa()?;
+                Jwk {
+                    kty: "RSA".to_owned(),
+                    n: Some(encode(&rsa.n().to_vec())),
+                    e: Some(encode(&rsa.e().to_vec())),
+                    ..Jwk::default()
+                }
+            }
+            Id::EC => {
+                let ec = key.ec_key()?;
+                let (crv, size) = ec
+                    .group()
+                    .curve_name()
+                    .and_then(|nid| CURVES.iter().find(|(_, curve, _, _)| *curve == nid))
+                    .map(|(_, _, crv, size)| (*crv, *size))
+                    .ok_or_else(|| format_err!("The key is not on a supported curve!"))?;
+                let mut x = BigNum::new()?;
+                let mut y = BigNum::new()?;
+                let mut context = BigNumContext::new()?;
+                ec.public_key()
+                    .affine_coordinates_gfp(ec.group(), &mut x, &mut y, &mut context)?;
+                Jwk {
+                    kty: "EC".to_owned(),
+                    crv: Some(crv.to_owned()),
+                    x: Some(encode(&padded(&x, size))),
+                    y: Some(encode(&padded(&y, size))),
+                    ..Jwk::default()
+                }
+            }
+            _ => bail!("Only RSA and EC keys can be published in a JWKS!"),
+        };
+        // the required members, in order and without whitespace, as the thumbprint requires
+        let members = match (&jwk.n, &jwk.e, &jwk.crv, &jwk.x, &jwk.y) {
+            (Some(n), Some(e), _, _, _) => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n),
+            (_, _, Some(crv), Some(x), Some(y)) => {
+                format!(r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#, crv, x, y)
+            }
+            _ => bail!("The key is missing members needed for its thumbprint!"),
+        };
+        jwk.kid = Some(encode(&sha256(members.as_bytes())));
+        jwk.usage = Some("sig".to_owned());
+        Ok(jwk)
+    }
+
+    /// Whether the key may verify a token signed with the algorithm, if the key names the only
+    /// algorithm it is for.
+    pub(crate) fn allows(&self, algorithm: Algorithm) -> bool {
+        match self.alg.as_deref() {
+            Some(alg) => alg == algorithm.name(),
+            None => true,
+        }
+    }
+
+    pub(crate) fn to_key(&self) -> Result<Key> {
+        let member = |name: &str, value: &Option<String>| -> Result<Vec<u8>> {
+            let value = value.as_ref().ok_or_else(|| {
+                format_err!("A key of type, {}, must have a member, {}!", self.kty, name)
+            })?;
+            decode(value).with_context(|| format!("The member, {}, of a key is not valid!", name))
+        };
+        match self.kty.as_str() {
+            "RSA" => {
+                let rsa = Rsa::from_public_components(
+                    BigNum::from_slice(&member("n", &self.n)?)?,
+                    BigNum::from_slice(&member("e", &self.e)?)?,
+                )?;
+                Ok(Key::Public(PKey::from_rsa(rsa)?))
+            }
+            "EC" => {
+                let crv = self.crv.as_deref().unwrap_or("missing");
+                let curve = CURVES
+                    .iter()
+                    .find(|(_, _, name, _)| *name == crv)
+                    .map(|(_, curve, _, _)| *curve)
+                    .ok_or_else(|| format_err!("The curve, {}, is not supported!", crv))?;
+                let group = EcGroup::from_curve_name(curve)?;
+                let x = BigNum::from_slice(&member("x", &self.x)?)?;
+                let y = BigNum::from_slice(&member("y", &self.y)?)?;
+                let key = EcKey::from_public_key_affine_coordinates(&group, &x, &y)?;
+                Ok(Key::Public(PKey::from_ec_key(key)?))
+            }
+            "oct" => Ok(Key::Secret(member("k", &self.k)?)),
+            kty => bail!("The key type, {}, is not supported!", kty),
+        }
+    }
+}
+
+/// Reads a key, or a secret, from `KEY_PATH`, either the file at the relative path, for a
+/// directory, or the string at the dotted property path, for a JSON file.
+pub(super) fn read_key(kind: &KeyPathKind, key_path: &str) -> Result<Vec<u8>> {
+    match kind {
+        KeyPathKind::Directory(directory) => {
+            debug!("KEY PATH={}", directory.display());
+            let relative = PathBuf::from_str(key_path.trim_start_matches('/'))
+                .with_context(|| format!("Could not parse {} as a path!", key_path))?;
+            let full_path = directory.join(relative);
+            debug!("Loading key {:?}", full_path.display());
+            Ok(fs::read(full_path)?)
+        }
+        KeyPathKind::File(file) => {
+            debug!("Key reference, {}", key_path);
+            let key_ref: Vec<&str> = key_path.split('.').collect();
+            debug!("Opening file, {}", file.display());
+            let key_data: Value = serde_json::from_reader(fs::File::open(file)?)?;
+            let key = key_ref.iter().try_fold(&key_data, |key_data, key_ref| {
+                key_data.get(key_ref).ok_or_else(|| {
+                    format_err!("Failed to find property, {}, in key data.", key_ref)
+                })
+            })?;
+            let key = key
+                .as_str()
+                .ok_or_else(|| format_err!("Key data in the JSON key file wasn't a string!"))?;
+            Ok(key.as_bytes().to_owned())
+        }
+        KeyPathKind::Jwks(_) => bail!("A key in a JWKS is chosen by its kid, not read by path!"),
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, URL_SAFE_NO_PAD)
+}
+
+fn decode(encoded: &str) -> Result<Vec<u8>> {
+    base64::decode_config(encoded.trim_end_matches('='), URL_SAFE_NO_PAD).map_err(Into::into)
+}
+
+fn digest_for(algorithm: Algorithm) -> MessageDigest {
+    use Algorithm::*;
+    match algorithm {
+        HS256 | RS256 | PS256 | ES256 => MessageDigest::sha256(),
+        HS384 | RS384 | PS384 | ES384 => MessageDigest::sha384(),
+        HS512 | RS512 | PS512 | ES512 => MessageDigest::sha512(),
+    }
+}
+
+/// The curve for an ECDSA algorithm and the size of a coordinate on it.
+fn curve_for(algorithm: Algorithm) -> Option<(Nid, usize)> {
+    CURVES
+        .iter()
+        .find(|(curve_algorithm, _, _, _)| *curve_algorithm == algorithm)
+        .map(|(_, curve, _, size)| (*curve, *size))
+}
+
+/// A number as big endian bytes, with leading zeroes to make up the length, as a JWK or a JWS
+/// signature has its numbers.
+fn padded(number: &BigNumRef, length: usize) -> Vec<u8> {
+    let bytes = number.to_vec();
+    let mut padded = vec![0; length.saturating_sub(bytes.len())];
+    padded.extend(bytes);
+    padded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token(
+        alg: &str,
+        kid: Option<&str>,
+        sign: impl FnOnce(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<String> {
+        let header = json! {{"alg": alg, "typ": "JWT", "kid": kid}};
+        let signed = format!(
+            "{}.{}",
+            encode(header.to_string().as_bytes()),
+            encode(br#"{"sub":"alice"}"#)
+        );
+        let signature = sign(signed.as_bytes())?;
+        Ok(format!("{}.{}", signed, encode(&signature)))
+    }
+
+    fn sign(key: &PKey<Private>, digest: MessageDigest, signed: &[u8]) -> Result<Vec<u8>> {
+        let mut signer = Signer::new(digest, key)?;
+        signer.update(signed)?;
+        Ok(signer.sign_to_vec()?)
+    }
+
+    fn sign_pss(key: &PKey<Private>, digest: MessageDigest, signed: &[u8]) -> Result<Vec<u8>> {
+        let mut signer = Signer::new(digest, key)?;
+        signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+        signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+        signer.set_rsa_mgf1_md(digest)?;
+        signer.update(signed)?;
+        Ok(signer.sign_to_vec()?)
+    }
+
+    /// Signs the way JWS does with ECDSA, the two halves of the signature side by side.
+    fn sign_es256(key: &PKey<Private>, signed: &[u8]) -> Result<Vec<u8>> {
+        let signature = EcdsaSig::from_der(&sign(key, MessageDigest::sha256(), signed)?)?;
+        let mut raw = padded(signature.r(), 32);
+        raw.extend(padded(signature.s(), 32));
+        Ok(raw)
+    }
+
+    fn verify(token: &str, key: &Key) -> Result<bool> {
+        let token = SignedToken::parse(token)?;
+        let algorithm = Algorithm::named(&token.header.alg)
+            .ok_or_else(|| format_err!("Unknown algorithm, {}", token.header.alg))?;
+        Ok(key.suits(algorithm) && key.verify(algorithm, token.signed, &token.signature)?)
+    }
+
+    fn public(key: &PKey<Private>) -> Result<Key> {
+        Key::from_pem(&key.public_key_to_pem()?)
+    }
+
+    fn rsa_key() -> Result<PKey<Private>> {
+        Ok(PKey::from_rsa(Rsa::generate(2048)?)?)
+    }
+
+    fn ec_key() -> Result<PKey<Private>> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+    }
+
+    #[test]
+    fn test_rsa() -> Result<()> {
+        let private = rsa_key()?;
+        let key = public(&private)?;
+        let rs256 = token("RS256", None, |signed| {
+            sign(&private, MessageDigest::sha256(), signed)
+        })?;
+        assert!(verify(&rs256, &key)?);
+        let ps384 = token("PS384", None, |signed| {
+            sign_pss(&private, MessageDigest::sha384(), signed)
+        })?;
+        assert!(verify(&ps384, &key)?);
+        // signed with PSS padding but claiming the PKCS1 padding of RS384
+        let rs384 = token("RS384", None, |signed| {
+            sign_pss(&private, MessageDigest::sha384(), signed)
+        })?;
+        assert!(!verify(&rs384, &key)?);
+        assert!(!verify(&rs256, &public(&rsa_key()?)?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ec() -> Result<()> {
+        let private = ec_key()?;
+        let key = public(&private)?;
+        let es256 = token("ES256", None, |signed| sign_es256(&private, signed))?;
+        assert!(verify(&es256, &key)?);
+        assert!(!verify(&es256, &public(&ec_key()?)?)?);
+        // the key is on the curve for ES256 alone
+        assert!(!key.suits(Algorithm::ES384));
+        assert!(!key.suits(Algorithm::RS256));
+        let der = token("ES256", None, |signed| {
+            sign(&private, MessageDigest::sha256(), signed)
+        })?;
+        assert!(!verify(&der, &key)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hmac() -> Result<()> {
+        let secret = PKey::hmac(b"secret")?;
+        let key = Key::from_pem(b"secret")?;
+        let hs256 = token("HS256", None, |signed| {
+            sign(&secret, MessageDigest::sha256(), signed)
+        })?;
+        assert!(verify(&hs256, &key)?);
+        assert!(!verify(&hs256, &Key::from_pem(b"other")?)?);
+        // a signature of a different length fails rather than panicking
+        let short = token("HS256", None, |_| Ok(b"short".to_vec()))?;
+        assert!(!verify(&short, &key)?);
+        // a public key is never used as a secret
+        let public_key = public(&rsa_key()?)?;
+        assert!(!public_key.suits(Algorithm::HS256));
+        assert!(!key.suits(Algorithm::RS256));
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_set() -> Result<()> {
+        let rsa = rsa_key()?;
+        let ec = ec_key()?;
+        let (x, y) = {
+            let ec = ec.ec_key()?;
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            let mut context = openssl::bn::BigNumContext::new()?;
+            ec.public_key()
+                .affine_coordinates_gfp(ec.group(), &mut x, &mut y, &mut context)?;
+            (x.to_vec(), y.to_vec())
+        };
+        let key_set: KeySet = serde_json::from_value(json! {{"keys": [
+            {
+                "kty": "RSA",
+                "kid": "rsa",
+                "use": "sig",
+                "n": encode(&rsa.rsa()?.n().to_vec()),
+                "e": encode(&rsa.rsa()?.e().to_vec()),
+            },
+            {
+                "kty": "EC",
+                "kid": "ec",
+                "alg": "ES256",
+                "crv": "P-256",
+                "x": encode(&x),
+                "y": encode(&y),
+            },
+            {"kty": "oct", "kid": "oct", "k": encode(b"secret")},
+            {"kty": "RSA", "kid": "encryption", "use": "enc", "n": "AQAB", "e": "AQAB"},
+        ]}})?;
+        assert_eq!(vec!["rsa", "ec", "oct", "encryption"], key_set.kids());
+
+        let rs256 = token("RS256", Some("rsa"), |signed| {
+            sign(&rsa, MessageDigest::sha256(), signed)
+        })?;
+        let jwk = key_set
+            .choose("*", Some("rsa"))
+            .map_err(|found| format_err!(found))?;
+        assert!(verify(&rs256, &jwk.to_key()?)?);
+        let es256 = token("ES256", Some("ec"), |signed| sign_es256(&ec, signed))?;
+        let jwk = key_set
+            .choose("ec", Some("ec"))
+            .map_err(|found| format_err!(found))?;
+        assert!(jwk.allows(Algorithm::ES256));
+        assert!(!jwk.allows(Algorithm::RS256));
+        assert!(verify(&es256, &jwk.to_key()?)?);
+        let hs256 = token("HS256", None, |signed| {
+            sign(&PKey::hmac(b"secret")?, MessageDigest::sha256(), signed)
+        })?;
+        let jwk = key_set
+            .choose("oct", None)
+            .map_err(|found| format_err!(found))?;
+        assert!(verify(&hs256, &jwk.to_key()?)?);
+
+        assert_eq!(
+            Some("a JWT signed with the key, ec, rather than rsa".to_owned()),
+            key_set.choose("rsa", Some("ec")).err()
+        );
+        assert!(key_set.choose("*", Some("unknown")).is_err());
+        assert!(key_set.choose("*", Some("encryption")).is_err());
+        assert!(key_set.choose("*", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mint() -> Result<()> {
+        let claims = json! {{"sub": "alice"}};
+        let rsa = rsa_key()?;
+        let ec = ec_key()?;
+        let cases = vec![
+            (
+                SigningKey::Private(rsa.clone()),
+                public(&rsa)?,
+                Algorithm::RS384,
+            ),
+            (
+                SigningKey::Private(rsa.clone()),
+                public(&rsa)?,
+                Algorithm::PS256,
+            ),
+            (
+                SigningKey::Private(ec.clone()),
+                public(&ec)?,
+                Algorithm::ES256,
+            ),
+            (
+                SigningKey::from_pem(b"secret")?,
+                Key::from_pem(b"secret")?,
+                Algorithm::HS512,
+            ),
+        ];
+        for (signing_key, key, algorithm) in cases {
+            let token = mint(&claims, &signing_key, algorithm, Some("kid"))?;
+            assert!(verify(&token, &key)?, "{} should verify", algorithm.name());
+            assert_eq!(
+                Some("kid"),
+                SignedToken::parse(&token)?.header.kid.as_deref()
+            );
+        }
+        assert_eq!(
+            Algorithm::ES256,
+            SigningKey::Private(ec.clone()).default_algorithm()?
+        );
+        assert!(mint(&claims, &SigningKey::Private(ec), Algorithm::RS256, None).is_err());
+        assert!(mint(&claims, &SigningKey::Private(rsa), Algorithm::HS256, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_jwk() -> Result<()> {
+        for private in [rsa_key()?, ec_key()?].iter() {
+            let jwk = Jwk::public(private)?;
+            // the thumbprint depends on the key alone
+            assert_eq!(jwk.kid, Jwk::public(private)?.kid);
+            let signing_key = SigningKey::Private(private.clone());
+            let algorithm = signing_key.default_algorithm()?;
+            let token = mint(&json! {{}}, &signing_key, algorithm, jwk.kid.as_deref())?;
+            assert!(verify(&token, &jwk.to_key()?)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(SignedToken::parse("e30.e30").is_err());
+        assert!(SignedToken::parse("notjson.e30.c2ln").is_err());
+        // a header without an algorithm
+        assert!(SignedToken::parse("e30.e30.c2ln").is_err());
+    }
+}
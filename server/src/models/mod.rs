@@ -1,48 +1,187 @@
+mod certificate;
 mod convert;
+mod disruption;
 mod eval;
+mod issuer;
+mod jwt;
+mod oidc;
+mod template;
+mod url_template;
 
-use crate::schema::{recipes, rules};
+pub(crate) use certificate::normalize_fingerprint;
+pub(crate) use convert::payload_to_bytes;
+pub(crate) use eval::{
+    claim_pointer, extract_subject, json_pointer, urlencoded_fields, RequestContext, RuleOutcome,
+};
+pub(crate) use issuer::{issue, issuer_key_set};
+pub(crate) use jwt::KeySet;
+pub(crate) use oidc::{
+    discovery, grant, introspect, user_info, IntrospectionForm, OAuthError, TokenForm,
+};
+pub(crate) use template::validate_template;
+pub(crate) use url_template::{PathParams, UrlTemplate};
+
+use crate::schema::{
+    journal_entries, journal_headers, recipe_responses, recipes, response_headers, rules, scenarios,
+};
 use chrono::NaiveDateTime;
-use diesel::{Associations, Identifiable, Insertable, Queryable};
+use diesel::{AsChangeset, Associations, Identifiable, Insertable, Queryable};
 use diesel_derive_enum::DbEnum;
 use serde::Deserialize;
 use uuid::Uuid;
 
-#[derive(Queryable, Identifiable, Associations)]
+#[derive(Queryable, Identifiable, Associations, Clone)]
 pub(crate) struct Recipe {
     pub(crate) id: Uuid,
     pub(crate) url: String,
-    pub(crate) payload: String,
+    pub(crate) payload: Vec<u8>,
     pub(crate) created_at: NaiveDateTime,
     pub(crate) updated_at: NaiveDateTime,
+    pub(crate) status_code: i32,
+    pub(crate) templated: bool,
+    pub(crate) content_type: String,
+    pub(crate) draft: bool,
+    pub(crate) priority: i32,
+    pub(crate) scenario: Option<String>,
+    pub(crate) required_state: Option<String>,
+    pub(crate) new_state: Option<String>,
+    pub(crate) sequence_mode: SequenceMode,
+    pub(crate) delay_type: Option<DelayType>,
+    pub(crate) delay_millis: Option<i32>,
+    pub(crate) delay_max_millis: Option<i32>,
+    pub(crate) delay_std_dev_millis: Option<i32>,
+    pub(crate) delay_sigma: Option<f64>,
+    pub(crate) fault_type: Option<FaultType>,
+    pub(crate) trickle_chunk_bytes: Option<i32>,
+    pub(crate) trickle_interval_millis: Option<i32>,
+    pub(crate) namespace: String,
+    pub(crate) file: Option<String>,
 }
 
-pub(crate) struct RecipeCascaded(pub(crate) Recipe, pub(crate) Vec<Rule>);
+pub(crate) struct RecipeCascaded(
+    pub(crate) Recipe,
+    pub(crate) Vec<Rule>,
+    pub(crate) Vec<ResponseHeader>,
+    pub(crate) Vec<RecipeResponse>,
+);
 
-#[derive(Insertable)]
+#[derive(Insertable, AsChangeset)]
 #[table_name = "recipes"]
+#[changeset_options(treat_none_as_null = "true")]
 pub(crate) struct NewRecipe {
     pub(crate) url: String,
-    pub(crate) payload: String,
+    pub(crate) payload: Vec<u8>,
+    pub(crate) status_code: i32,
+    pub(crate) templated: bool,
+    pub(crate) content_type: String,
+    pub(crate) draft: bool,
+    pub(crate) priority: i32,
+    pub(crate) scenario: Option<String>,
+    pub(crate) required_state: Option<String>,
+    pub(crate) new_state: Option<String>,
+    pub(crate) sequence_mode: SequenceMode,
+}
+
+/// The delay and fault of a recipe, saved along with the rest of it; each type of delay or fault
+/// only uses some of the columns and the rest are cleared.
+#[derive(Insertable, AsChangeset, Default)]
+#[table_name = "recipes"]
+#[changeset_options(treat_none_as_null = "true")]
+pub(crate) struct NewDisruption {
+    pub(crate) delay_type: Option<DelayType>,
+    pub(crate) delay_millis: Option<i32>,
+    pub(crate) delay_max_millis: Option<i32>,
+    pub(crate) delay_std_dev_millis: Option<i32>,
+    pub(crate) delay_sigma: Option<f64>,
+    pub(crate) fault_type: Option<FaultType>,
+    pub(crate) trickle_chunk_bytes: Option<i32>,
+    pub(crate) trickle_interval_millis: Option<i32>,
+}
+
+/// A recipe from the shared model, split into the columns to save and the rules, headers and
+/// responses that can only be saved once the recipe has an ID.
+pub(crate) struct RecipeChanges {
+    pub(crate) id: Option<Uuid>,
+    pub(crate) recipe: NewRecipe,
+    pub(crate) disruption: NewDisruption,
+    pub(crate) rules: Vec<shared::Rule>,
+    pub(crate) headers: Vec<shared::Header>,
+    pub(crate) responses: Vec<shared::RecipeResponse>,
+    /// The file the recipe is loaded from, which is the only way to change it.
+    pub(crate) file: Option<String>,
 }
 
-#[derive(DbEnum, Deserialize, Debug)]
+#[derive(DbEnum, Deserialize, Debug, Clone)]
 pub(crate) enum RuleType {
     Authenticated,
     Subject,
     HttpMethod,
+    Header,
+    PathParam,
+    Body,
+    QueryParam,
+    NthCall,
+    Claim,
+    Unexpired,
+    BasicAuth,
+    ApiKey,
+    ClientCert,
 }
 
-#[derive(DbEnum, Deserialize, Debug)]
+#[derive(DbEnum, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ApiKeyLocation {
+    Header,
+    Query,
+}
+
+#[derive(DbEnum, Deserialize, Debug, Clone)]
 pub(crate) enum HttpVerb {
     Get,
     Post,
     Put,
     Delete,
+    Patch,
+    Head,
+    Options,
+}
+
+#[derive(DbEnum, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) enum MatchMode {
+    Exact,
+    Present,
+    Absent,
+    CaseInsensitive,
+    Regex,
+    Contains,
+    AnyOf,
+}
+
+#[derive(DbEnum, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SequenceMode {
+    Cycle,
+    StickOnLast,
+    FallThrough,
+}
+
+#[derive(DbEnum, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DelayType {
+    Fixed,
+    Uniform,
+    Normal,
+    LogNormal,
 }
 
-#[derive(Queryable, Identifiable, Associations, Debug)]
+#[derive(DbEnum, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FaultType {
+    ConnectionReset,
+    PartialThenReset,
+    Trickle,
+    EmptyReply,
+}
+
+#[derive(Queryable, Identifiable, Associations, AsChangeset, Debug, Clone)]
 #[belongs_to(Recipe)]
+#[changeset_options(treat_none_as_null = "true")]
 pub(crate) struct Rule {
     pub(crate) id: Uuid,
     pub(crate) recipe_id: Uuid,
@@ -50,6 +189,21 @@ pub(crate) struct Rule {
     pub(crate) key_path: Option<String>,
     pub(crate) subject: Option<String>,
     pub(crate) http_method: Option<HttpVerb>,
+    pub(crate) header_name: Option<String>,
+    pub(crate) match_mode: Option<MatchMode>,
+    pub(crate) match_value: Option<String>,
+    pub(crate) param_name: Option<String>,
+    pub(crate) body_path: Option<String>,
+    pub(crate) form_field: Option<String>,
+    pub(crate) nth: Option<i32>,
+    pub(crate) claim: Option<String>,
+    pub(crate) algorithms: Vec<String>,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) key_location: Option<ApiKeyLocation>,
+    pub(crate) key_name: Option<String>,
+    pub(crate) api_key: Option<String>,
+    pub(crate) fingerprint: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -60,4 +214,159 @@ pub(crate) struct NewRule {
     pub(crate) key_path: Option<String>,
     pub(crate) subject: Option<String>,
     pub(crate) http_method: Option<HttpVerb>,
+    pub(crate) header_name: Option<String>,
+    pub(crate) match_mode: Option<MatchMode>,
+    pub(crate) match_value: Option<String>,
+    pub(crate) param_name: Option<String>,
+    pub(crate) body_path: Option<String>,
+    pub(crate) form_field: Option<String>,
+    pub(crate) nth: Option<i32>,
+    pub(crate) claim: Option<String>,
+    pub(crate) algorithms: Vec<String>,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) key_location: Option<ApiKeyLocation>,
+    pub(crate) key_name: Option<String>,
+    pub(crate) api_key: Option<String>,
+    pub(crate) fingerprint: Option<String>,
+}
+
+impl NewRule {
+    /// A rule with only its type set; each kind of rule fills in the columns it uses.
+    pub(crate) fn of_type(recipe_id: Uuid, rule_type: RuleType) -> Self {
+        Self {
+            recipe_id,
+            rule_type,
+            key_path: None,
+            subject: None,
+            http_method: None,
+            header_name: None,
+            match_mode: None,
+            match_value: None,
+            param_name: None,
+            body_path: None,
+            form_field: None,
+            nth: None,
+            claim: None,
+            algorithms: Vec::new(),
+            username: None,
+            password: None,
+            key_location: None,
+            key_name: None,
+            api_key: None,
+            fingerprint: None,
+        }
+    }
+}
+
+/// A response served after the recipe's own, in order by position.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Recipe)]
+pub(crate) struct RecipeResponse {
+    pub(crate) id: Uuid,
+    pub(crate) recipe_id: Uuid,
+    pub(crate) position: i32,
+    pub(crate) status_code: i32,
+    pub(crate) content_type: String,
+    pub(crate) payload: Vec<u8>,
+    pub(crate) templated: bool,
+}
+
+#[derive(Insertable)]
+#[table_name = "recipe_responses"]
+pub(crate) struct NewRecipeResponse {
+    pub(crate) recipe_id: Uuid,
+    pub(crate) position: i32,
+    pub(crate) status_code: i32,
+    pub(crate) content_type: String,
+    pub(crate) payload: Vec<u8>,
+    pub(crate) templated: bool,
+}
+
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Recipe)]
+pub(crate) struct ResponseHeader {
+    pub(crate) id: Uuid,
+    pub(crate) recipe_id: Uuid,
+    pub(crate) position: i32,
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "response_headers"]
+pub(crate) struct NewResponseHeader {
+    pub(crate) recipe_id: Uuid,
+    pub(crate) position: i32,
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name = "journal_entries"]
+pub(crate) struct JournalEntry {
+    pub(crate) id: Uuid,
+    pub(crate) method: String,
+    pub(crate) url: String,
+    pub(crate) path: String,
+    pub(crate) body: Vec<u8>,
+    pub(crate) subject: Option<String>,
+    pub(crate) matched: bool,
+    pub(crate) recipe_id: Option<Uuid>,
+    pub(crate) status_code: i32,
+    pub(crate) created_at: NaiveDateTime,
+    pub(crate) namespace: String,
+}
+
+pub(crate) struct JournalCascaded(pub(crate) JournalEntry, pub(crate) Vec<JournalHeader>);
+
+#[derive(Insertable, Debug)]
+#[table_name = "journal_entries"]
+pub(crate) struct NewJournalEntry {
+    pub(crate) method: String,
+    pub(crate) url: String,
+    pub(crate) path: String,
+    pub(crate) body: Vec<u8>,
+    pub(crate) subject: Option<String>,
+    pub(crate) matched: bool,
+    pub(crate) recipe_id: Option<Uuid>,
+    pub(crate) status_code: i32,
+    pub(crate) namespace: String,
+}
+
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(JournalEntry, foreign_key = "entry_id")]
+pub(crate) struct JournalHeader {
+    pub(crate) id: Uuid,
+    pub(crate) entry_id: Uuid,
+    pub(crate) position: i32,
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "journal_headers"]
+pub(crate) struct NewJournalHeader {
+    pub(crate) entry_id: Uuid,
+    pub(crate) position: i32,
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+/// The current state of a scenario that has moved since it was last reset; a scenario without a
+/// row is in its starting state.
+#[derive(Queryable, Debug, Clone)]
+pub(crate) struct Scenario {
+    pub(crate) name: String,
+    pub(crate) state: String,
+    pub(crate) updated_at: NaiveDateTime,
+    pub(crate) namespace: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "scenarios"]
+pub(crate) struct NewScenario {
+    pub(crate) name: String,
+    pub(crate) state: String,
+    pub(crate) namespace: String,
 }
@@ -0,0 +1,726 @@
+use super::{
+    eval::basic_credentials,
+    issuer::{issue, verify_issued},
+};
+use crate::config::{ProviderClient, ProviderConfig, ProviderUser};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use shared::TokenRequest;
+
+const ACCESS: &str = "access";
+const REFRESH: &str = "refresh";
+const OPENID: &str = "openid";
+/// The private claim that tells access and refresh tokens apart, so that neither can stand in for
+/// the other, nor an ID token for either.
+const TOKEN_USE: &str = "token_use";
+
+/// Why a call to the provider failed, as one of the error codes of RFC 6749 and RFC 6750 along
+/// with a description.
+#[derive(Debug)]
+pub(crate) struct OAuthError {
+    pub(crate) error: &'static str,
+    pub(crate) description: String,
+}
+
+impl OAuthError {
+    fn new(error: &'static str, description: impl Into<String>) -> Self {
+        Self {
+            error,
+            description: description.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for OAuthError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::new("server_error", format!("{:#}", error))
+    }
+}
+
+type Result<T> = std::result::Result<T, OAuthError>;
+
+/// The fields of a form posted to the token endpoint, any of which the grant type may need.
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct TokenForm {
+    pub(crate) grant_type: Option<String>,
+    pub(crate) scope: Option<String>,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) refresh_token: Option<String>,
+    pub(crate) client_id: Option<String>,
+    pub(crate) client_secret: Option<String>,
+}
+
+/// The fields of a form posted to the introspection endpoint.
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct IntrospectionForm {
+    pub(crate) token: Option<String>,
+    pub(crate) client_id: Option<String>,
+    pub(crate) client_secret: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct TokenResponse {
+    pub(crate) access_token: String,
+    pub(crate) token_type: &'static str,
+    pub(crate) expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) id_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) scope: Option<String>,
+}
+
+/// The provider's metadata, as OpenID Connect Discovery describes, with its endpoints under the
+/// issuer.
+pub(crate) fn discovery(provider: &ProviderConfig, issuer: &str) -> Value {
+    let mut scopes: Vec<&str> = provider
+        .clients
+        .iter()
+        .flat_map(|client| client.scopes.iter().map(String::as_str))
+        .chain(std::iter::once(OPENID))
+        .collect();
+    scopes.sort_unstable();
+    scopes.dedup();
+    let auth_methods = ["client_secret_basic", "client_secret_post", "none"];
+    json! {{
+        "issuer": issuer,
+        "token_endpoint": format!("{}/oauth/token", issuer),
+        "introspection_endpoint": format!("{}/oauth/introspect", issuer),
+        "userinfo_endpoint": format!("{}/oauth/userinfo", issuer),
+        "jwks_uri": format!("{}/api/v1/jwks", issuer),
+        "grant_types_supported": ["client_credentials", "password", "refresh_token"],
+        "token_endpoint_auth_methods_supported": auth_methods,
+        "introspection_endpoint_auth_methods_supported": auth_methods,
+        "scopes_supported": scopes,
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["RS256"],
+    }}
+}
+
+/// Grants tokens to the client that calls the token endpoint, for itself, for one of the users, or
+/// in exchange for a refresh token granted before.
+pub(crate) fn grant(
+    provider: &ProviderConfig,
+    issuer: &str,
+    authorization: Option<&str>,
+    form: TokenForm,
+) -> Result<TokenResponse> {
+    let client = authenticate(
+        provider,
+        authorization,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )?;
+    let grant_type = form
+        .grant_type
+        .as_deref()
+        .ok_or_else(|| OAuthError::new("invalid_request", "The grant_type is missing!"))?;
+    match grant_type {
+        "client_credentials" => {
+            let scopes = requested_scopes(client, form.scope.as_deref())?;
+            let access_token = mint(
+                access_claims(issuer, client, None, &scopes),
+                provider.expires_in,
+            )?;
+            Ok(TokenResponse {
+                access_token,
+                token_type: "Bearer",
+                expires_in: provider.expires_in,
+                refresh_token: None,
+                id_token: None,
+                scope: joined(&scopes),
+            })
+        }
+        "password" => {
+            let username = required(form.username.as_deref(), "username")?;
+            let password = required(form.password.as_deref(), "password")?;
+            let user = provider
+                .users
+                .iter()
+                .find(|user| user.username == username && user.password == password)
+                .ok_or_else(|| {
+                    OAuthError::new("invalid_grant", "The username or password is wrong!")
+                })?;
+            let scopes = requested_scopes(client, form.scope.as_deref())?;
+            for_user(provider, issuer, client, user, &scopes)
+        }
+        "refresh_token" => {
+            let refresh_token = required(form.refresh_token.as_deref(), "refresh_token")?;
+            let claims = verify_issued(refresh_token)?
+                .filter(|claims| is_active(claims, REFRESH))
+                .ok_or_else(|| {
+                    OAuthError::new("invalid_grant", "The refresh token is not valid!")
+                })?;
+            if claims.get("client_id").and_then(Value::as_str) != Some(&client.client_id) {
+                return Err(OAuthError::new(
+                    "invalid_grant",
+                    "The refresh token was granted to another client!",
+                ));
+            }
+            let user = claims
+                .get("sub")
+                .and_then(Value::as_str)
+                .and_then(|subject| user_for(provider, subject))
+                .ok_or_else(|| {
+                    OAuthError::new("invalid_grant", "The refresh token's user is unknown!")
+                })?;
+            let granted = scopes_of(&claims);
+            let scopes = match form.scope.as_deref() {
+                Some(scope) => {
+                    let scopes = split(scope);
+                    if let Some(scope) = scopes.iter().find(|scope| !granted.contains(scope)) {
+                        return Err(OAuthError::new(
+                            "invalid_scope",
+                            format!("The scope, {}, was not granted before!", scope),
+                        ));
+                    }
+                    scopes
+                }
+                None => granted,
+            };
+            for_user(provider, issuer, client, user, &scopes)
+        }
+        _ => Err(OAuthError::new(
+            "unsupported_grant_type",
+            format!("The grant_type, {}, is not supported!", grant_type),
+        )),
+    }
+}
+
+/// Describes a token to the client that calls the introspection endpoint, as RFC 7662 does: its
+/// claims if it is an access or refresh token granted by the provider that hasn't expired, or
+/// else only that it isn't active.
+pub(crate) fn introspect(
+    provider: &ProviderConfig,
+    authorization: Option<&str>,
+    form: IntrospectionForm,
+) -> Result<Value> {
+    authenticate(
+        provider,
+        authorization,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )?;
+    let token = required(form.token.as_deref(), "token")?;
+    let mut claims = match verify_issued(token)?
+        .filter(|claims| is_active(claims, ACCESS) || is_active(claims, REFRESH))
+    {
+        Some(claims) => claims,
+        None => return Ok(json! {{"active": false}}),
+    };
+    let token_type = match claims.remove(TOKEN_USE).as_ref().and_then(Value::as_str) {
+        Some(ACCESS) => "Bearer",
+        _ => "refresh_token",
+    };
+    let username = claims
+        .get("sub")
+        .and_then(Value::as_str)
+        .and_then(|subject| user_for(provider, subject))
+        .map(|user| user.username.clone());
+    claims.insert("active".to_owned(), json!(true));
+    claims.insert("token_type".to_owned(), json!(token_type));
+    if let Some(username) = username {
+        claims.insert("username".to_owned(), json!(username));
+    }
+    Ok(Value::Object(claims))
+}
+
+/// The claims of the user that an access token with the `openid` scope was granted for, as the
+/// user info endpoint serves them.
+pub(crate) fn user_info(provider: &ProviderConfig, authorization: Option<&str>) -> Result<Value> {
+    let token = authorization
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or_else(|| OAuthError::new("invalid_request", "The access token is missing!"))?;
+    let claims = verify_issued(token)?
+        .filter(|claims| is_active(claims, ACCESS))
+        .ok_or_else(|| OAuthError::new("invalid_token", "The access token is not valid!"))?;
+    if !scopes_of(&claims).iter().any(|scope| scope == OPENID) {
+        return Err(OAuthError::new(
+            "insufficient_scope",
+            "The access token was not granted the openid scope!",
+        ));
+    }
+    let user = claims
+        .get("sub")
+        .and_then(Value::as_str)
+        .and_then(|subject| user_for(provider, subject))
+        .ok_or_else(|| {
+            OAuthError::new(
+                "invalid_token",
+                "The access token was not granted to a user!",
+            )
+        })?;
+    let mut info = user.claims.clone();
+    info.insert("sub".to_owned(), json!(user.subject()));
+    Ok(Value::Object(info))
+}
+
+/// The client that calls the provider, named either by HTTP Basic credentials or by the fields of
+/// the form; a public client, one without a secret, only names itself.
+fn authenticate<'a>(
+    provider: &'a ProviderConfig,
+    authorization: Option<&str>,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<&'a ProviderClient> {
+    let basic = authorization.and_then(basic_credentials);
+    let (client_id, client_secret) = match &basic {
+        Some((client_id, client_secret)) => (client_id.as_str(), Some(client_secret.as_str())),
+        None => (
+            client_id.ok_or_else(|| {
+                OAuthError::new("invalid_client", "The client did not authenticate!")
+            })?,
+            client_secret,
+        ),
+    };
+    provider
+        .clients
+        .iter()
+        .find(|client| {
+            client.client_id == client_id
+                && client.client_secret.as_deref().unwrap_or_default()
+                    == client_secret.unwrap_or_default()
+        })
+        .ok_or_else(|| {
+            OAuthError::new(
+                "invalid_client",
+                "The client is unknown or its secret is wrong!",
+            )
+        })
+}
+
+/// The scopes a client asks for, which must be among those it may ask for, or all of those when it
+/// doesn't ask; the `openid` scope may always be asked for.
+fn requested_scopes(client: &ProviderClient, scope: Option<&str>) -> Result<Vec<String>> {
+    let scopes = match scope {
+        Some(scope) => split(scope),
+        None => return Ok(client.scopes.clone()),
+    };
+    if client.scopes.is_empty() {
+        return Ok(scopes);
+    }
+    match scopes
+        .iter()
+        .find(|scope| *scope != OPENID && !client.scopes.contains(scope))
+    {
+        Some(scope) => Err(OAuthError::new(
+            "invalid_scope",
+            format!(
+                "The client, {}, may not ask for the scope, {}!",
+                client.client_id, scope
+            ),
+        )),
+        None => Ok(scopes),
+    }
+}
+
+/// The access and refresh tokens granted to a client for a user, and an ID token when the client
+/// asks for the `openid` scope.
+fn for_user(
+    provider: &ProviderConfig,
+    issuer: &str,
+    client: &ProviderClient,
+    user: &ProviderUser,
+    scopes: &[String],
+) -> Result<TokenResponse> {
+    let access_token = mint(
+        access_claims(issuer, client, Some(user), scopes),
+        provider.expires_in,
+    )?;
+    let mut refresh_claims = base_claims(issuer, client, user.subject(), scopes);
+    refresh_claims.insert(TOKEN_USE.to_owned(), json!(REFRESH));
+    let refresh_token = mint(refresh_claims, provider.refresh_expires_in)?;
+    let id_token = if scopes.iter().any(|scope| scope == OPENID) {
+        let mut id_claims = user.claims.clone();
+        id_claims.insert("iss".to_owned(), json!(issuer));
+        id_claims.insert("sub".to_owned(), json!(user.subject()));
+        id_claims.insert("aud".to_owned(), json!(client.client_id));
+        Some(mint(id_claims, provider.expires_in)?)
+    } else {
+        None
+    };
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: provider.expires_in,
+        refresh_token: Some(refresh_token),
+        id_token,
+        scope: joined(scopes),
+    })
+}
+
+/// The claims of an access token, those of the client and then of the user, if there is one, and
+/// then those the provider sets, which neither can replace.
+fn access_claims(
+    issuer: &str,
+    client: &ProviderClient,
+    user: Option<&ProviderUser>,
+    scopes: &[String],
+) -> Map<String, Value> {
+    let mut claims = client.claims.clone();
+    let subject = match user {
+        Some(user) => {
+            claims.extend(user.claims.clone());
+            user.subject()
+        }
+        None => &client.client_id,
+    };
+    claims.extend(base_claims(issuer, client, subject, scopes));
+    claims.insert(TOKEN_USE.to_owned(), json!(ACCESS));
+    claims
+}
+
+fn base_claims(
+    issuer: &str,
+    client: &ProviderClient,
+    subject: &str,
+    scopes: &[String],
+) -> Map<String, Value> {
+    let mut claims = Map::new();
+    claims.insert("iss".to_owned(), json!(issuer));
+    claims.insert("sub".to_owned(), json!(subject));
+    claims.insert("aud".to_owned(), json!(client.client_id));
+    claims.insert("client_id".to_owned(), json!(client.client_id));
+    if let Some(scope) = joined(scopes) {
+        claims.insert("scope".to_owned(), json!(scope));
+    }
+    claims
+}
+
+fn mint(claims: Map<String, Value>, expires_in: i64) -> Result<String> {
+    let issued = issue(
+        TokenRequest {
+            claims,
+            expires_in: Some(expires_in),
+            ..TokenRequest::default()
+        },
+        None,
+    )?;
+    Ok(issued.token)
+}
+
+/// Whether the claims are of a token of the given use that hasn't expired.
+fn is_active(claims: &Map<String, Value>, token_use: &str) -> bool {
+    claims.get(TOKEN_USE).and_then(Value::as_str) == Some(token_use)
+        && claims
+            .get("exp")
+            .and_then(Value::as_i64)
+            .map(|exp| Utc::now().timestamp() < exp)
+            .unwrap_or_default()
+}
+
+fn user_for<'a>(provider: &'a ProviderConfig, subject: &str) -> Option<&'a ProviderUser> {
+    provider.users.iter().find(|user| user.subject() == subject)
+}
+
+fn required<'a>(value: Option<&'a str>, name: &str) -> Result<&'a str> {
+    value.ok_or_else(|| OAuthError::new("invalid_request", format!("The {} is missing!", name)))
+}
+
+fn scopes_of(claims: &Map<String, Value>) -> Vec<String> {
+    claims
+        .get("scope")
+        .and_then(Value::as_str)
+        .map(split)
+        .unwrap_or_default()
+}
+
+fn split(scope: &str) -> Vec<String> {
+    scope.split_whitespace().map(ToOwned::to_owned).collect()
+}
+
+fn joined(scopes: &[String]) -> Option<String> {
+    if scopes.is_empty() {
+        None
+    } else {
+        Some(scopes.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ISSUER: &str = "http://localhost:8989";
+
+    fn provider() -> ProviderConfig {
+        serde_yaml::from_str(
+            r#"
+clients:
+  - client_id: orders
+    client_secret: s3cret
+    scopes: [orders.read, orders.write]
+    claims:
+      tenant: acme
+  - client_id: spa
+users:
+  - username: alice
+    password: wonderland
+    claims:
+      email: alice@example.com
+      roles: [admin]
+"#,
+        )
+        .expect("The test provider is valid")
+    }
+
+    fn form(grant_type: &str) -> TokenForm {
+        TokenForm {
+            grant_type: Some(grant_type.to_owned()),
+            client_id: Some("orders".to_owned()),
+            client_secret: Some("s3cret".to_owned()),
+            ..TokenForm::default()
+        }
+    }
+
+    fn claims(token: &str) -> Map<String, Value> {
+        verify_issued(token)
+            .ok()
+            .flatten()
+            .expect("The token was issued by the provider")
+    }
+
+    fn error<T: std::fmt::Debug>(result: Result<T>) -> &'static str {
+        result.expect_err("The call failed").error
+    }
+
+    #[test]
+    fn test_client_credentials() -> Result<()> {
+        let provider = provider();
+        let basic = format!("Basic {}", base64::encode("orders:s3cret"));
+        let granted = grant(
+            &provider,
+            ISSUER,
+            Some(&basic),
+            TokenForm {
+                grant_type: Some("client_credentials".to_owned()),
+                scope: Some("orders.read".to_owned()),
+                ..TokenForm::default()
+            },
+        )?;
+        assert!(granted.refresh_token.is_none());
+        assert_eq!(Some("orders.read".to_owned()), granted.scope);
+        let access = claims(&granted.access_token);
+        assert_eq!("orders", access["sub"]);
+        assert_eq!(ISSUER, access["iss"]);
+        assert_eq!("acme", access["tenant"]);
+
+        // every scope the client may ask for, when it doesn't ask
+        let granted = grant(&provider, ISSUER, None, form("client_credentials"))?;
+        assert_eq!(Some("orders.read orders.write".to_owned()), granted.scope);
+
+        let wrong_secret = format!("Basic {}", base64::encode("orders:wrong"));
+        assert_eq!(
+            "invalid_client",
+            error(grant(
+                &provider,
+                ISSUER,
+                Some(&wrong_secret),
+                form("client_credentials")
+            ))
+        );
+        assert_eq!(
+            "invalid_scope",
+            error(grant(
+                &provider,
+                ISSUER,
+                None,
+                TokenForm {
+                    scope: Some("users.read".to_owned()),
+                    ..form("client_credentials")
+                }
+            ))
+        );
+        assert_eq!(
+            "unsupported_grant_type",
+            error(grant(&provider, ISSUER, None, form("implicit")))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_and_refresh() -> Result<()> {
+        let provider = provider();
+        let password = TokenForm {
+            username: Some("alice".to_owned()),
+            password: Some("wonderland".to_owned()),
+            scope: Some("openid orders.read".to_owned()),
+            ..form("password")
+        };
+        let granted = grant(&provider, ISSUER, None, password)?;
+        let access = claims(&granted.access_token);
+        assert_eq!("alice", access["sub"]);
+        assert_eq!(json!(["admin"]), access["roles"]);
+        let id = claims(granted.id_token.as_deref().unwrap_or_default());
+        assert_eq!("orders", id["aud"]);
+        assert_eq!("alice@example.com", id["email"]);
+        // an ID token is not an access token
+        assert_eq!(
+            "invalid_token",
+            error(user_info(
+                &provider,
+                Some(&format!("Bearer {}", granted.id_token.unwrap_or_default()))
+            ))
+        );
+        let info = user_info(&provider, Some(&format!("Bearer {}", granted.access_token)))?;
+        assert_eq!(
+            json! {{"sub": "alice", "email": "alice@example.com", "roles": ["admin"]}},
+            info
+        );
+
+        let refresh_token = granted.refresh_token.unwrap_or_default();
+        let refreshed = grant(
+            &provider,
+            ISSUER,
+            None,
+            TokenForm {
+                refresh_token: Some(refresh_token.clone()),
+                scope: Some("orders.read".to_owned()),
+                ..form("refresh_token")
+            },
+        )?;
+        assert!(refreshed.id_token.is_none());
+        assert_eq!("alice", claims(&refreshed.access_token)["sub"]);
+        // a refresh token can't widen the scope, stand in for an access token or be used by
+        // another client
+        assert_eq!(
+            "invalid_scope",
+            error(grant(
+                &provider,
+                ISSUER,
+                None,
+                TokenForm {
+                    refresh_token: Some(refresh_token.clone()),
+                    scope: Some("orders.write".to_owned()),
+                    ..form("refresh_token")
+                }
+            ))
+        );
+        assert_eq!(
+            "invalid_token",
+            error(user_info(
+                &provider,
+                Some(&format!("Bearer {}", refresh_token))
+            ))
+        );
+        assert_eq!(
+            "invalid_grant",
+            error(grant(
+                &provider,
+                ISSUER,
+                None,
+                TokenForm {
+                    grant_type: Some("refresh_token".to_owned()),
+                    client_id: Some("spa".to_owned()),
+                    refresh_token: Some(refresh_token),
+                    ..TokenForm::default()
+                }
+            ))
+        );
+
+        assert_eq!(
+            "invalid_grant",
+            error(grant(
+                &provider,
+                ISSUER,
+                None,
+                TokenForm {
+                    username: Some("alice".to_owned()),
+                    password: Some("looking-glass".to_owned()),
+                    ..form("password")
+                }
+            ))
+        );
+        // without the openid scope there is neither an ID token nor user info
+        let granted = grant(
+            &provider,
+            ISSUER,
+            None,
+            TokenForm {
+                grant_type: Some("password".to_owned()),
+                client_id: Some("spa".to_owned()),
+                username: Some("alice".to_owned()),
+                password: Some("wonderland".to_owned()),
+                ..TokenForm::default()
+            },
+        )?;
+        assert!(granted.id_token.is_none());
+        assert_eq!(
+            "insufficient_scope",
+            error(user_info(
+                &provider,
+                Some(&format!("Bearer {}", granted.access_token))
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_introspect() -> Result<()> {
+        let provider = provider();
+        let granted = grant(
+            &provider,
+            ISSUER,
+            None,
+            TokenForm {
+                username: Some("alice".to_owned()),
+                password: Some("wonderland".to_owned()),
+                ..form("password")
+            },
+        )?;
+        let introspect_token = |token: &str| {
+            introspect(
+                &provider,
+                None,
+                IntrospectionForm {
+                    token: Some(token.to_owned()),
+                    client_id: Some("spa".to_owned()),
+                    client_secret: None,
+                },
+            )
+        };
+        let access = introspect_token(&granted.access_token)?;
+        assert_eq!(json!(true), access["active"]);
+        assert_eq!("Bearer", access["token_type"]);
+        assert_eq!("alice", access["username"]);
+        assert_eq!("orders.read orders.write", access["scope"]);
+        assert!(access.get(TOKEN_USE).is_none());
+        let refresh = introspect_token(granted.refresh_token.as_deref().unwrap_or_default())?;
+        assert_eq!("refresh_token", refresh["token_type"]);
+
+        let expired = mint(access_claims(ISSUER, &provider.clients[0], None, &[]), -60)?;
+        assert_eq!(json! {{"active": false}}, introspect_token(&expired)?);
+        assert_eq!(json! {{"active": false}}, introspect_token("not.a.token")?);
+
+        assert_eq!(
+            "invalid_client",
+            error(introspect(
+                &provider,
+                None,
+                IntrospectionForm {
+                    token: Some(granted.access_token),
+                    ..IntrospectionForm::default()
+                }
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_discovery() {
+        let metadata = discovery(&provider(), ISSUER);
+        assert_eq!(
+            "http://localhost:8989/oauth/token",
+            metadata["token_endpoint"]
+        );
+        assert_eq!("http://localhost:8989/api/v1/jwks", metadata["jwks_uri"]);
+        assert_eq!(
+            json!(["openid", "orders.read", "orders.write"]),
+            metadata["scopes_supported"]
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use super::{eval, Recipe, RecipeResponse, RequestContext};
+use actix_web::web::Query;
+use anyhow::{bail, format_err, Result};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use log::debug;
+use regex::Regex;
+use serde_json::Value;
+use shared::PayloadKind;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex =
+        Regex::new(r"\{\{\s*([^{}]*?)\s*\}\}").expect("Placeholder pattern should compile!");
+}
+
+impl Recipe {
+    /// The payload to serve for a request; a templated payload has its placeholders replaced with
+    /// values from the request, otherwise the payload is served as is.
+    pub(crate) fn render_payload(&self, context: &RequestContext) -> Result<Vec<u8>> {
+        render_payload(&self.content_type, self.templated, &self.payload, context)
+    }
+}
+
+impl RecipeResponse {
+    /// The payload to serve for a request, rendered the same way as a recipe's own payload.
+    pub(crate) fn render_payload(&self, context: &RequestContext) -> Result<Vec<u8>> {
+        render_payload(&self.content_type, self.templated, &self.payload, context)
+    }
+}
+
+fn render_payload(
+    content_type: &str,
+    templated: bool,
+    payload: &[u8],
+    context: &RequestContext,
+) -> Result<Vec<u8>> {
+    if !templated {
+        return Ok(payload.to_vec());
+    }
+    match PayloadKind::of(content_type) {
+        PayloadKind::Json => {
+            let payload: Value = serde_json::from_slice(payload)?;
+            let sources = Sources::from_context(context);
+            let payload = render_value(payload, &sources)?;
+            serde_json::to_vec(&payload).map_err(anyhow::Error::from)
+        }
+        PayloadKind::Text => {
+            let payload = std::str::from_utf8(payload)?;
+            let sources = Sources::from_context(context);
+            render_string(payload, &sources).map(String::into_bytes)
+        }
+        PayloadKind::Binary => Ok(payload.to_vec()),
+    }
+}
+
+/// Checks that every placeholder in a payload refers to a supported source, so mistakes are
+/// caught when a recipe is saved rather than when it is served.
+pub(crate) fn validate_template(payload: &Value) -> Result<()> {
+    match payload {
+        Value::String(text) => PLACEHOLDER
+            .captures_iter(text)
+            .try_for_each(|captures| parse_expression(&captures[1]).map(|_| ())),
+        Value::Array(values) => values.iter().try_for_each(validate_template),
+        Value::Object(values) => values.values().try_for_each(validate_template),
+        _ => Ok(()),
+    }
+}
+
+/// A placeholder parsed into the source of its value and, for most sources, the name to look up.
+#[derive(Debug, PartialEq)]
+enum Expression<'a> {
+    Path(&'a str),
+    Query(&'a str),
+    Header(&'a str),
+    Claim(&'a str),
+    Body(&'a str),
+    NewUuid,
+    Now,
+    Timestamp,
+}
+
+fn parse_expression(expression: &str) -> Result<Expression<'_>> {
+    use Expression::*;
+    match expression {
+        "uuid" => return Ok(NewUuid),
+        "now" => return Ok(Now),
+        "timestamp" => return Ok(Timestamp),
+        "body" => return Ok(Body("")),
+        _ => (),
+    }
+    let separator = expression.find('.').ok_or_else(|| {
+        format_err!(
+            "The template placeholder, {{{{{}}}}}, is not supported!",
+            expression
+        )
+    })?;
+    let (source, name) = (&expression[..separator], &expression[separator + 1..]);
+    if name.is_empty() {
+        bail!(
+            "The template placeholder, {{{{{}}}}}, must name a value after the \".\"!",
+            expression
+        )
+    }
+    Ok(match source {
+        "path" => Path(name),
+        "query" => Query(name),
+        "header" => Header(name),
+        "claim" => Claim(name),
+        "body" if name.starts_with('/') => Body(name),
+        "body" => bail!(
+            "The template placeholder, {{{{{}}}}}, must use a JSON pointer, like \"body./user/id\"!",
+            expression
+        ),
+        _ => bail!(
+            "The template placeholder, {{{{{}}}}}, must start with \"path\", \"query\", \"header\", \"claim\", or \"body\"!",
+            expression
+        ),
+    })
+}
+
+/// Everything a placeholder may refer to, gathered once per request.
+struct Sources<'a> {
+    context: &'a RequestContext<'a>,
+    query: HashMap<String, String>,
+    claims: Option<Value>,
+    body: Option<Value>,
+    now: DateTime<Utc>,
+}
+
+impl<'a> Sources<'a> {
+    fn from_context(context: &'a RequestContext<'a>) -> Self {
+        let query = Query::<HashMap<String, String>>::from_query(context.request.query_string())
+            .map(Query::into_inner)
+            .unwrap_or_default();
+        // like a body that isn't JSON, a token whose claims can't be read simply has no claims
+        let claims = eval::extract_claims(context.request).ok().flatten();
+        let body = serde_json::from_slice(context.body).ok();
+        Self {
+            context,
+            query,
+            claims,
+            body,
+            now: Utc::now(),
+        }
+    }
+
+    fn resolve(&self, expression: &str) -> Result<Option<Value>> {
+        use Expression::*;
+        let value = match parse_expression(expression)? {
+            Path(name) => self.context.path_params.get(name).cloned().map(Value::from),
+            Query(name) => self.query.get(name).cloned().map(Value::from),
+            Header(name) => self
+                .context
+                .request
+                .headers()
+                .get(name)
+                // a value that isn't text is treated as missing, falling back to any default
+                .and_then(|value| value.to_str().ok())
+                .map(Value::from),
+            Claim(name) => self
+                .claims
+                .as_ref()
+                .and_then(|claims| claims.get(name))
+                .cloned(),
+            Body(pointer) => self
+                .body
+                .as_ref()
+                .and_then(|body| body.pointer(pointer))
+                .cloned(),
+            NewUuid => Some(Value::from(Uuid::new_v4().to_string())),
+            Now => Some(Value::from(self.now.to_rfc3339())),
+            Timestamp => Some(Value::from(self.now.timestamp())),
+        };
+        debug!("Resolved placeholder, {}, to {:?}", expression, value);
+        Ok(value)
+    }
+}
+
+fn render_value(value: Value, sources: &Sources) -> Result<Value> {
+    Ok(match value {
+        Value::String(text) => render_text(text, sources)?,
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| render_value(value, sources))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(values) => Value::Object(
+            values
+                .into_iter()
+                .map(|(key, value)| render_value(value, sources).map(|value| (key, value)))
+                .collect::<Result<_>>()?,
+        ),
+        value => value,
+    })
+}
+
+/// A string that is only a placeholder is replaced by the value found, keeping its JSON type, or
+/// null if nothing was found; placeholders mixed with other text are replaced with the text of
+/// each value, or nothing if a value wasn't found.
+fn render_text(text: String, sources: &Sources) -> Result<Value> {
+    if let Some(captures) = PLACEHOLDER.captures(&text) {
+        if captures[0].len() == text.len() {
+            return Ok(sources.resolve(&captures[1])?.unwrap_or(Value::Null));
+        }
+    } else {
+        return Ok(Value::String(text));
+    }
+    render_string(&text, sources).map(Value::String)
+}
+
+fn render_string(text: &str, sources: &Sources) -> Result<String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut last = 0;
+    for captures in PLACEHOLDER.captures_iter(text) {
+        let placeholder = captures
+            .get(0)
+            .expect("A match always has a whole capture!");
+        rendered.push_str(&text[last..placeholder.start()]);
+        match sources.resolve(&captures[1])? {
+            Some(Value::String(value)) => rendered.push_str(&value),
+            Some(Value::Null) | None => (),
+            Some(value) => rendered.push_str(&value.to_string()),
+        }
+        last = placeholder.end();
+    }
+    rendered.push_str(&text[last..]);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::{PathParams, SequenceMode};
+    use actix_web::{http::HeaderValue, test::TestRequest};
+    use chrono::NaiveDateTime;
+    use serde_json::json;
+
+    fn templated(payload: Value) -> Recipe {
+        let now = NaiveDateTime::from_timestamp(0, 0);
+        Recipe {
+            id: Uuid::new_v4(),
+            url: "http://test.local/api/users/{id}".to_owned(),
+            payload: payload.to_string().into_bytes(),
+            created_at: now,
+            updated_at: now,
+            status_code: 200,
+            templated: true,
+            content_type: shared::default_content_type(),
+            draft: false,
+            priority: 0,
+            scenario: None,
+            required_state: None,
+            new_state: None,
+            sequence_mode: SequenceMode::StickOnLast,
+            delay_type: None,
+            delay_millis: None,
+            delay_max_millis: None,
+            delay_std_dev_millis: None,
+            delay_sigma: None,
+            fault_type: None,
+            trickle_chunk_bytes: None,
+            trickle_interval_millis: None,
+            namespace: shared::DEFAULT_NAMESPACE.to_owned(),
+            file: None,
+        }
+    }
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let request = TestRequest::with_uri("/api/users/42?page=3")
+            .header("X-Request-Id", "abc")
+            .to_http_request();
+        let mut path_params = PathParams::new();
+        path_params.insert("id".to_owned(), "42".to_owned());
+        let body = json! {{"user": {"name": "Alice", "age": 30}}}.to_string();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: body.as_bytes(),
+            call_number: 1,
+            key_set: None,
+        };
+        let recipe = templated(json! {{
+            "id": "{{path.id}}",
+            "page": "{{ query.page }}",
+            "trace": "request {{header.X-Request-Id}}",
+            "name": "{{body./user/name}}",
+            "age": "{{body./user/age}}",
+            "label": "{{body./user/name}} is {{body./user/age}}",
+            "missing": "{{query.missing}}",
+            "items": ["{{path.id}}", 1],
+        }});
+        let rendered: Value = serde_json::from_slice(&recipe.render_payload(&context)?)?;
+        assert_eq!(
+            json! {{
+                "id": "42",
+                "page": "3",
+                "trace": "request abc",
+                "name": "Alice",
+                "age": 30,
+                "label": "Alice is 30",
+                "missing": null,
+                "items": ["42", 1],
+            }},
+            rendered
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_generated() -> Result<()> {
+        let request = TestRequest::default().to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        let recipe = templated(json! {{
+            "id": "{{uuid}}",
+            "at": "{{now}}",
+            "epoch": "{{timestamp}}",
+        }});
+        let rendered: Value = serde_json::from_slice(&recipe.render_payload(&context)?)?;
+        Uuid::parse_str(rendered["id"].as_str().unwrap_or_default())?;
+        DateTime::parse_from_rfc3339(rendered["at"].as_str().unwrap_or_default())?;
+        assert!(rendered["epoch"].is_i64());
+        Ok(())
+    }
+
+    #[test]
+    fn test_literal() -> Result<()> {
+        let request = TestRequest::default().to_http_request();
+        let path_params = PathParams::new();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        let mut recipe = templated(json! {{"id": "{{uuid}}"}});
+        recipe.templated = false;
+        assert_eq!(recipe.payload, recipe.render_payload(&context)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_text() -> Result<()> {
+        let request = TestRequest::with_uri("/api/users/42?page=3").to_http_request();
+        let mut path_params = PathParams::new();
+        path_params.insert("id".to_owned(), "42".to_owned());
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        let mut recipe = templated(Value::Null);
+        recipe.content_type = "application/xml".to_owned();
+        recipe.payload = b"<user id=\"{{path.id}}\" page=\"{{query.page}}\"/>".to_vec();
+        assert_eq!(
+            b"<user id=\"42\" page=\"3\"/>".to_vec(),
+            recipe.render_payload(&context)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreadable_claims() -> Result<()> {
+        let path_params = PathParams::new();
+        let recipe = templated(json! {{"name": "hi {{query.name}}", "sub": "{{claim.sub}}"}});
+        for authorization in ["Basic Ym9iOnB3", "Bearer not-a-jwt"] {
+            let request = TestRequest::with_uri("/api/users/42?name=x")
+                .header("Authorization", authorization)
+                .to_http_request();
+            let context = RequestContext {
+                request: &request,
+                path_params: &path_params,
+                body: &[],
+                call_number: 1,
+                key_set: None,
+            };
+            let rendered: Value = serde_json::from_slice(&recipe.render_payload(&context)?)?;
+            assert_eq!(json! {{"name": "hi x", "sub": null}}, rendered);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_not_text() -> Result<()> {
+        let path_params = PathParams::new();
+        let recipe = templated(json! {{"team": "{{header.X-Team}}", "of": "of {{header.X-Team}}"}});
+        let request = TestRequest::default()
+            .header("X-Team", HeaderValue::from_bytes(b"caf\xe9")?)
+            .to_http_request();
+        let context = RequestContext {
+            request: &request,
+            path_params: &path_params,
+            body: &[],
+            call_number: 1,
+            key_set: None,
+        };
+        let rendered: Value = serde_json::from_slice(&recipe.render_payload(&context)?)?;
+        assert_eq!(json! {{"team": null, "of": "of "}}, rendered);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate_template(&json! {{"id": "{{path.id}}", "at": ["{{now}}"]}}).is_ok());
+        assert!(validate_template(&json! {{"id": "{{nope}}"}}).is_err());
+        assert!(validate_template(&json! {{"id": "{{session.id}}"}}).is_err());
+        assert!(validate_template(&json! {{"id": "{{body.user}}"}}).is_err());
+        assert!(validate_template(&json! {{"id": "{{path.}}"}}).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::{bail, format_err, Result};
+use percent_encoding::percent_decode_str;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+};
+
+/// Values captured from a request path, decoded, keyed by parameter name; a trailing wildcard is
+/// keyed as `*`.
+pub(crate) type PathParams = HashMap<String, String>;
+
+pub(crate) const WILDCARD: &str = "*";
+
+/// A recipe URL whose path may contain parameters, like `{id}`, as whole segments, and may end in
+/// a wildcard, `*`, that matches the rest of the path; the query of a request is matched by rules
+/// instead, so a template has none.
+#[derive(Debug, PartialEq)]
+pub(crate) struct UrlTemplate {
+    base: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard,
+}
+
+impl Segment {
+    /// Literal segments are more specific than parameters which are more specific than wildcards.
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Literal(_) => 2,
+            Segment::Param(_) => 1,
+            Segment::Wildcard => 0,
+        }
+    }
+
+    fn accepts(&self, found: &str) -> bool {
+        match self {
+            Segment::Literal(literal) => literal == found,
+            Segment::Param(_) | Segment::Wildcard => true,
+        }
+    }
+}
+
+impl TryFrom<&str> for UrlTemplate {
+    type Error = anyhow::Error;
+
+    fn try_from(url: &str) -> Result<Self> {
+        let scheme_end = url
+            .find("://")
+            .ok_or_else(|| format_err!("The URL, {}, has to include a scheme!", url))?;
+        let path_start = url[scheme_end + 3..]
+            .find('/')
+            .map(|index| index + scheme_end + 3)
+            .unwrap_or_else(|| url.len());
+        let base = url[..path_start].to_owned();
+        let path = &url[path_start..];
+        if path.contains('?') {
+            bail!(
+                "The URL, {}, can't include a query, match query parameters with a \"QueryParam\" rule instead!",
+                url
+            )
+        }
+
+        let mut names = HashSet::new();
+        let raw: Vec<&str> = split_path(path).collect();
+        let segments = raw
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                if *segment == WILDCARD {
+                    if index + 1 == raw.len() {
+                        Ok(Segment::Wildcard)
+                    } else {
+                        bail!("A wildcard, \"*\", may only be the last segment of a URL path!")
+                    }
+                } else if segment.starts_with('{') && segment.ends_with('}') {
+                    let name = segment[1..segment.len() - 1].trim();
+                    if name.is_empty() || name.contains(&['{', '}'][..]) {
+                        bail!("The path parameter, {}, must have a name!", segment)
+                    }
+                    if !names.insert(name.to_owned()) {
+                        bail!("The path parameter, {}, is used more than once!", name)
+                    }
+                    Ok(Segment::Param(name.to_owned()))
+                } else if segment.contains(&['{', '}', '*'][..]) {
+                    bail!(
+                        "The path segment, {}, must be a whole parameter, like \"{{id}}\", a wildcard, \"*\", or contain neither!",
+                        segment
+                    )
+                } else {
+                    Ok(Segment::Literal((*segment).to_owned()))
+                }
+            })
+            .collect::<Result<Vec<Segment>>>()?;
+        Ok(Self { base, segments })
+    }
+}
+
+impl UrlTemplate {
+    /// The scheme and host of the template, which must match a request exactly.
+    pub(crate) fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Compares the path of a request to this template, returning any captured values if they
+    /// match.
+    pub(crate) fn captures(&self, path: &str) -> Option<PathParams> {
+        let mut params = PathParams::new();
+        let mut segments = split_path(path);
+        for expected in &self.segments {
+            match expected {
+                Segment::Literal(literal) => {
+                    if segments.next()? != literal {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), decode(segments.next()?));
+                }
+                Segment::Wildcard => {
+                    let rest = segments.by_ref().map(decode).collect::<Vec<String>>();
+                    if rest.is_empty() {
+                        return None;
+                    }
+                    params.insert(WILDCARD.to_owned(), rest.join("/"));
+                }
+            }
+        }
+        if segments.next().is_some() {
+            None
+        } else {
+            Some(params)
+        }
+    }
+
+    /// Ranks each segment so that templates can be compared, the greater being more specific.
+    pub(crate) fn specificity(&self) -> Vec<u8> {
+        self.segments.iter().map(Segment::rank).collect()
+    }
+
+    /// How many segments of a request path would have to be changed, added or removed for it to
+    /// match this template, so that the closest templates can be suggested when none match.
+    pub(crate) fn distance(&self, path: &str) -> usize {
+        let mut found: Vec<&str> = split_path(path).collect();
+        // a wildcard takes the rest of the path so anything past it costs nothing
+        if self.segments.last() == Some(&Segment::Wildcard) {
+            found.truncate(self.segments.len());
+        }
+        let mut previous: Vec<usize> = (0..=found.len()).collect();
+        for (index, expected) in self.segments.iter().enumerate() {
+            let mut current = vec![index + 1];
+            for (other, found) in found.iter().enumerate() {
+                let changed = previous[other] + usize::from(!expected.accepts(found));
+                current.push(changed.min(previous[other + 1] + 1).min(current[other] + 1));
+            }
+            previous = current;
+        }
+        previous[found.len()]
+    }
+
+    /// A concrete URL for this template, with a placeholder for each parameter, so that it can be
+    /// checked with an ordinary URL parser.
+    pub(crate) fn sample(&self) -> String {
+        let path = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(literal) => literal.as_str(),
+                Segment::Param(_) | Segment::Wildcard => "_",
+            })
+            .collect::<Vec<&str>>()
+            .join("/");
+        format!("{}/{}", self.base, path)
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.strip_prefix('/').unwrap_or(path).split('/')
+}
+
+/// Decodes a segment of a request path, which is percent encoded; one that doesn't decode to text
+/// is kept as it was sent.
+fn decode(segment: &str) -> String {
+    percent_decode_str(segment)
+        .decode_utf8()
+        .map(String::from)
+        .unwrap_or_else(|_| segment.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_literal() -> Result<()> {
+        let template = UrlTemplate::try_from("http://test.local/api/users")?;
+        assert_eq!("http://test.local", template.base());
+        assert_eq!(Some(PathParams::new()), template.captures("/api/users"));
+        assert_eq!(None, template.captures("/api/users/42"));
+        assert_eq!(None, template.captures("/api/user"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_params() -> Result<()> {
+        let template = UrlTemplate::try_from("http://test.local/api/users/{id}/orders/{order}")?;
+        let params = template
+            .captures("/api/users/42/orders/7")
+            .ok_or_else(|| format_err!("Should have matched!"))?;
+        assert_eq!(Some(&"42".to_owned()), params.get("id"));
+        assert_eq!(Some(&"7".to_owned()), params.get("order"));
+        assert_eq!(None, template.captures("/api/users/42/orders"));
+        assert_eq!(None, template.captures("/api/users/42/items/7"));
+
+        let params = template
+            .captures("/api/users/j%C3%B6rg%20m/orders/%FF")
+            .ok_or_else(|| format_err!("Should have matched!"))?;
+        assert_eq!(Some(&"jörg m".to_owned()), params.get("id"));
+        assert_eq!(Some(&"%FF".to_owned()), params.get("order"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wildcard() -> Result<()> {
+        let template = UrlTemplate::try_from("http://test.local/api/files/*")?;
+        let params = template
+            .captures("/api/files/a/b.txt")
+            .ok_or_else(|| format_err!("Should have matched!"))?;
+        assert_eq!(Some(&"a/b.txt".to_owned()), params.get(WILDCARD));
+        assert_eq!(None, template.captures("/api/files"));
+        let params = template
+            .captures("/api/files/my%20docs/a.txt")
+            .ok_or_else(|| format_err!("Should have matched!"))?;
+        assert_eq!(Some(&"my docs/a.txt".to_owned()), params.get(WILDCARD));
+        Ok(())
+    }
+
+    #[test]
+    fn test_query() {
+        assert!(UrlTemplate::try_from("http://test.local/api/users?page=1").is_err());
+    }
+
+    #[test]
+    fn test_specificity() -> Result<()> {
+        let literal = UrlTemplate::try_from("http://test.local/api/users/me")?;
+        let param = UrlTemplate::try_from("http://test.local/api/users/{id}")?;
+        let wildcard = UrlTemplate::try_from("http://test.local/api/users/*")?;
+        assert!(literal.specificity() > param.specificity());
+        assert!(param.specificity() > wildcard.specificity());
+        Ok(())
+    }
+
+    #[test]
+    fn test_distance() -> Result<()> {
+        let template = UrlTemplate::try_from("http://test.local/api/users/{id}/orders")?;
+        assert_eq!(0, template.distance("/api/users/42/orders"));
+        assert_eq!(1, template.distance("/api/users/42/order"));
+        assert_eq!(1, template.distance("/api/users/42"));
+        assert_eq!(1, template.distance("/api/v2/users/42/orders"));
+        assert_eq!(2, template.distance("/api/items"));
+
+        let template = UrlTemplate::try_from("http://test.local/api/files/*")?;
+        assert_eq!(0, template.distance("/api/files/a/b/c.txt"));
+        assert_eq!(1, template.distance("/api/file/a/b/c.txt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample() -> Result<()> {
+        let template = UrlTemplate::try_from("http://test.local/api/users/{id}/*")?;
+        assert_eq!("http://test.local/api/users/_/_", template.sample());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(UrlTemplate::try_from("test.local/api/users").is_err());
+        assert!(UrlTemplate::try_from("http://test.local/api/*/users").is_err());
+        assert!(UrlTemplate::try_from("http://test.local/api/users/{}").is_err());
+        assert!(UrlTemplate::try_from("http://test.local/api/users/user-{id}").is_err());
+        assert!(UrlTemplate::try_from("http://test.local/api/{id}/{id}").is_err());
+    }
+}
@@ -1,16 +1,85 @@
 table! {
+    journal_entries (id) {
+        id -> Uuid,
+        method -> Varchar,
+        url -> Varchar,
+        path -> Varchar,
+        body -> Bytea,
+        subject -> Nullable<Varchar>,
+        matched -> Bool,
+        recipe_id -> Nullable<Uuid>,
+        status_code -> Int4,
+        created_at -> Timestamp,
+        namespace -> Varchar,
+    }
+}
+
+table! {
+    journal_headers (id) {
+        id -> Uuid,
+        entry_id -> Uuid,
+        position -> Int4,
+        name -> Varchar,
+        value -> Varchar,
+    }
+}
+
+table! {
+    recipe_responses (id) {
+        id -> Uuid,
+        recipe_id -> Uuid,
+        position -> Int4,
+        status_code -> Int4,
+        content_type -> Varchar,
+        payload -> Bytea,
+        templated -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::{DelayTypeMapping, FaultTypeMapping, SequenceModeMapping};
+
     recipes (id) {
         id -> Uuid,
         url -> Varchar,
-        payload -> Text,
+        payload -> Bytea,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        status_code -> Int4,
+        templated -> Bool,
+        content_type -> Varchar,
+        draft -> Bool,
+        priority -> Int4,
+        scenario -> Nullable<Varchar>,
+        required_state -> Nullable<Varchar>,
+        new_state -> Nullable<Varchar>,
+        sequence_mode -> SequenceModeMapping,
+        delay_type -> Nullable<DelayTypeMapping>,
+        delay_millis -> Nullable<Int4>,
+        delay_max_millis -> Nullable<Int4>,
+        delay_std_dev_millis -> Nullable<Int4>,
+        delay_sigma -> Nullable<Float8>,
+        fault_type -> Nullable<FaultTypeMapping>,
+        trickle_chunk_bytes -> Nullable<Int4>,
+        trickle_interval_millis -> Nullable<Int4>,
+        namespace -> Varchar,
+        file -> Nullable<Varchar>,
     }
 }
 
 table! {
-    use diesel::{sql_types::{Uuid, Nullable}, types::Varchar};
-    use crate::models::{RuleTypeMapping, HttpVerbMapping};
+    scenarios (namespace, name) {
+        name -> Varchar,
+        state -> Varchar,
+        updated_at -> Timestamp,
+        namespace -> Varchar,
+    }
+}
+
+table! {
+    use diesel::{sql_types::{Array, Uuid, Nullable, Int4, Text}, types::Varchar};
+    use crate::models::{ApiKeyLocationMapping, RuleTypeMapping, HttpVerbMapping, MatchModeMapping};
 
     rules (id) {
         id -> Uuid,
@@ -19,9 +88,55 @@ table! {
         key_path -> Nullable<Varchar>,
         subject -> Nullable<Varchar>,
         http_method -> Nullable<HttpVerbMapping>,
+        header_name -> Nullable<Varchar>,
+        match_mode -> Nullable<MatchModeMapping>,
+        match_value -> Nullable<Varchar>,
+        param_name -> Nullable<Varchar>,
+        body_path -> Nullable<Varchar>,
+        form_field -> Nullable<Varchar>,
+        nth -> Nullable<Int4>,
+        claim -> Nullable<Varchar>,
+        algorithms -> Array<Text>,
+        username -> Nullable<Varchar>,
+        password -> Nullable<Varchar>,
+        key_location -> Nullable<ApiKeyLocationMapping>,
+        key_name -> Nullable<Varchar>,
+        api_key -> Nullable<Varchar>,
+        fingerprint -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    response_headers (id) {
+        id -> Uuid,
+        recipe_id -> Uuid,
+        position -> Int4,
+        name -> Varchar,
+        value -> Varchar,
+    }
+}
+
+table! {
+    served_counts (recipe_id) {
+        recipe_id -> Uuid,
+        served -> Int8,
     }
 }
 
+joinable!(journal_entries -> recipes (recipe_id));
+joinable!(journal_headers -> journal_entries (entry_id));
+joinable!(recipe_responses -> recipes (recipe_id));
 joinable!(rules -> recipes (recipe_id));
+joinable!(response_headers -> recipes (recipe_id));
+joinable!(served_counts -> recipes (recipe_id));
 
-allow_tables_to_appear_in_same_query!(recipes, rules,);
+allow_tables_to_appear_in_same_query!(
+    journal_entries,
+    journal_headers,
+    recipe_responses,
+    recipes,
+    rules,
+    response_headers,
+    scenarios,
+    served_counts,
+);
@@ -0,0 +1,938 @@
+use super::{ScenarioStep, Storage};
+use crate::models::{
+    JournalCascaded, JournalEntry, JournalHeader, NewDisruption, NewJournalEntry, NewJournalHeader,
+    NewRecipe, NewRecipeResponse, NewResponseHeader, NewRule, NewScenario, Recipe, RecipeCascaded,
+    RecipeResponse, ResponseHeader, Rule, Scenario,
+};
+use anyhow::{bail, format_err, Result};
+use chrono::{NaiveDateTime, Utc};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+use uuid::Uuid;
+
+/// Keeps everything in memory, so nothing outlives the server; enough for a CI job that loads its
+/// recipes from files and checks the journal before the server stops.
+#[derive(Default)]
+pub(crate) struct Memory {
+    tables: Mutex<Tables>,
+    /// Kept apart from the other tables, and shared with any transaction rather than copied into
+    /// it, since it grows with every call.
+    journal: Arc<Mutex<Journal>>,
+    /// In a transaction, the changes to the journal to make once the transaction is kept.
+    pending: Option<Mutex<Vec<JournalChange>>>,
+}
+
+/// The same tables that Postgres keeps, other than the journal's, with the rows of each in the
+/// order they were inserted.
+#[derive(Default, Clone)]
+struct Tables {
+    recipes: Vec<Recipe>,
+    rules: Vec<Rule>,
+    response_headers: Vec<ResponseHeader>,
+    recipe_responses: Vec<RecipeResponse>,
+    scenarios: Vec<Scenario>,
+    served_counts: HashMap<Uuid, i64>,
+}
+
+/// The journal's tables.
+#[derive(Default)]
+struct Journal {
+    entries: Vec<JournalEntry>,
+    headers: Vec<JournalHeader>,
+}
+
+type JournalChange = Box<dyn FnOnce(&mut Journal) + Send>;
+
+impl Memory {
+    fn lock(&self) -> MutexGuard<'_, Tables> {
+        // a panic while holding the lock can't leave a transaction half done, since its changes
+        // are only kept once all of them are made
+        self.tables.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the journal; whoever also needs the other tables locks them first.
+    fn lock_journal(&self) -> MutexGuard<'_, Journal> {
+        self.journal.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Changes the journal at once or, in a transaction, once the transaction is kept.
+    fn change_journal(&self, change: JournalChange) {
+        match &self.pending {
+            Some(pending) => pending
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(change),
+            None => change(&mut self.lock_journal()),
+        }
+    }
+}
+
+impl Tables {
+    fn has_recipe(&self, to_find: Uuid) -> Result<()> {
+        if self.recipes.iter().any(|recipe| recipe.id == to_find) {
+            Ok(())
+        } else {
+            bail!("There is no recipe with the ID, {}!", to_find)
+        }
+    }
+}
+
+impl Journal {
+    /// Deletes journal entries along with their headers.
+    fn delete_entries(&mut self, to_delete: impl Fn(&JournalEntry) -> bool) -> usize {
+        let deleted: Vec<Uuid> = self
+            .entries
+            .iter()
+            .filter(|entry| to_delete(entry))
+            .map(|entry| entry.id)
+            .collect();
+        self.entries.retain(|entry| !deleted.contains(&entry.id));
+        self.headers
+            .retain(|header| !deleted.contains(&header.entry_id));
+        deleted.len()
+    }
+
+    /// Journal entries for deleted recipes are kept, but no longer name them.
+    fn forget_recipes(&mut self, deleted: &[Uuid]) {
+        for entry in self.entries.iter_mut() {
+            if matches!(entry.recipe_id, Some(served) if deleted.contains(&served)) {
+                entry.recipe_id = None;
+            }
+        }
+    }
+}
+
+impl Storage for Memory {
+    fn check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn transaction(&self, changes: &mut dyn FnMut(&dyn Storage) -> Result<()>) -> Result<()> {
+        // holding the lock until the changes are kept means no other changes are lost in between
+        let mut tables = self.lock();
+        let changing = Memory {
+            tables: Mutex::new(tables.clone()),
+            journal: self.journal.clone(),
+            pending: Some(Mutex::default()),
+        };
+        changes(&changing)?;
+        let Memory {
+            tables: changed,
+            pending,
+            ..
+        } = changing;
+        *tables = changed.into_inner().unwrap_or_else(PoisonError::into_inner);
+        for change in pending
+            .into_iter()
+            .flat_map(|pending| pending.into_inner().unwrap_or_else(PoisonError::into_inner))
+        {
+            self.change_journal(change);
+        }
+        Ok(())
+    }
+
+    fn load_recipes(&self, owner: &str, offset: i64, limit: i64) -> Result<(i64, Vec<Recipe>)> {
+        let tables = self.lock();
+
+        let mut owned: Vec<&Recipe> = tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner)
+            .collect();
+        owned.sort_by(|a, b| in_listed_order(a, b));
+        let total = i64::try_from(owned.len())?;
+        let results = owned
+            .into_iter()
+            .skip(usize::try_from(offset)?)
+            .take(usize::try_from(limit)?)
+            .cloned()
+            .collect();
+        Ok((total, results))
+    }
+
+    fn find_recipe(&self, owner: &str, to_find: Uuid) -> Result<Recipe> {
+        let tables = self.lock();
+
+        tables
+            .recipes
+            .iter()
+            .find(|recipe| recipe.id == to_find && recipe.namespace == owner)
+            .cloned()
+            .ok_or_else(|| format_err!("There is no recipe with the ID, {}!", to_find))
+    }
+
+    fn find_rules(&self, parent: Uuid) -> Result<Vec<Rule>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .rules
+            .iter()
+            .filter(|rule| rule.recipe_id == parent)
+            .cloned()
+            .collect())
+    }
+
+    fn find_headers(&self, parent: Uuid) -> Result<Vec<ResponseHeader>> {
+        let tables = self.lock();
+
+        let mut headers: Vec<ResponseHeader> = tables
+            .response_headers
+            .iter()
+            .filter(|header| header.recipe_id == parent)
+            .cloned()
+            .collect();
+        headers.sort_by_key(|header| header.position);
+        Ok(headers)
+    }
+
+    fn find_responses(&self, parent: Uuid) -> Result<Vec<RecipeResponse>> {
+        let tables = self.lock();
+
+        let mut responses: Vec<RecipeResponse> = tables
+            .recipe_responses
+            .iter()
+            .filter(|response| response.recipe_id == parent)
+            .cloned()
+            .collect();
+        responses.sort_by_key(|response| response.position);
+        Ok(responses)
+    }
+
+    fn count_responses(&self, parents: &[Uuid]) -> Result<HashMap<Uuid, i64>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipe_responses
+            .iter()
+            .filter(|response| parents.contains(&response.recipe_id))
+            .fold(HashMap::new(), |mut counts, response| {
+                *counts.entry(response.recipe_id).or_insert(0) += 1;
+                counts
+            }))
+    }
+
+    fn find_served_recipes(&self, owner: &str, base: &str) -> Result<Vec<(Recipe, Vec<Rule>)>> {
+        let tables = self.lock();
+
+        let mut served: Vec<(Recipe, Vec<Rule>)> = tables
+            .recipes
+            .iter()
+            // drafts are recorded by the proxy and aren't served until they are promoted
+            .filter(|recipe| recipe.namespace == owner && is_on(&recipe.url, base) && !recipe.draft)
+            .map(|recipe| {
+                let rules = tables
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.recipe_id == recipe.id)
+                    .cloned()
+                    .collect();
+                (recipe.clone(), rules)
+            })
+            .collect();
+        served.sort_by_key(|(recipe, _)| recipe.id);
+        Ok(served)
+    }
+
+    fn find_recipe_urls(&self, owner: &str, base: &str) -> Result<Vec<String>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner && is_on(&recipe.url, base) && !recipe.draft)
+            .map(|recipe| recipe.url.clone())
+            .collect())
+    }
+
+    fn find_recipes_with_url(&self, owner: &str, to_find: &str) -> Result<Vec<Recipe>> {
+        let tables = self.lock();
+
+        let mut found: Vec<Recipe> = tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner && recipe.url == to_find)
+            .cloned()
+            .collect();
+        found.sort_by(in_listed_order);
+        Ok(found)
+    }
+
+    fn export_recipes(&self, owner: &str) -> Result<Vec<Recipe>> {
+        let tables = self.lock();
+
+        let mut found: Vec<Recipe> = tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner && recipe.file.is_none())
+            .cloned()
+            .collect();
+        found.sort_by(in_listed_order);
+        Ok(found)
+    }
+
+    fn find_recipes(&self, owner: &str, ids: &[Uuid]) -> Result<Vec<Recipe>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner && ids.contains(&recipe.id))
+            .cloned()
+            .collect())
+    }
+
+    fn cascade(&self, found: Vec<Recipe>) -> Result<Vec<RecipeCascaded>> {
+        found
+            .into_iter()
+            .map(|recipe| {
+                let rules = self.find_rules(recipe.id)?;
+                let headers = self.find_headers(recipe.id)?;
+                let responses = self.find_responses(recipe.id)?;
+                Ok(RecipeCascaded(recipe, rules, headers, responses))
+            })
+            .collect()
+    }
+
+    fn prioritize_recipes(&self, owner: &str, ids: &[Uuid]) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let mut count = 0;
+        for (index, to_update) in ids.iter().enumerate() {
+            let to_priority = i32::try_from(ids.len() - 1 - index)?;
+            if let Some(recipe) = tables
+                .recipes
+                .iter_mut()
+                .find(|recipe| recipe.id == *to_update && recipe.namespace == owner)
+            {
+                recipe.priority = to_priority;
+                recipe.updated_at = now();
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn create_recipe(
+        &self,
+        owner: &str,
+        to_id: Option<Uuid>,
+        to_create: NewRecipe,
+        disruption: NewDisruption,
+        from_file: Option<String>,
+    ) -> Result<Recipe> {
+        let mut tables = self.lock();
+
+        let to_id = to_id.unwrap_or_else(Uuid::new_v4);
+        if tables.recipes.iter().any(|recipe| recipe.id == to_id) {
+            bail!("There is already a recipe with the ID, {}!", to_id)
+        }
+        let created = to_recipe(to_id, owner, now(), to_create, disruption, from_file);
+        tables.recipes.push(created.clone());
+        Ok(created)
+    }
+
+    fn create_rules(&self, to_create: &[NewRule]) -> Result<Vec<Rule>> {
+        let mut tables = self.lock();
+
+        let mut created = Vec::new();
+        for rule in to_create {
+            tables.has_recipe(rule.recipe_id)?;
+            created.push(Rule {
+                id: Uuid::new_v4(),
+                recipe_id: rule.recipe_id,
+                rule_type: rule.rule_type.clone(),
+                key_path: rule.key_path.clone(),
+                subject: rule.subject.clone(),
+                http_method: rule.http_method.clone(),
+                header_name: rule.header_name.clone(),
+                match_mode: rule.match_mode.clone(),
+                match_value: rule.match_value.clone(),
+                param_name: rule.param_name.clone(),
+                body_path: rule.body_path.clone(),
+                form_field: rule.form_field.clone(),
+                nth: rule.nth,
+                claim: rule.claim.clone(),
+                algorithms: rule.algorithms.clone(),
+                username: rule.username.clone(),
+                password: rule.password.clone(),
+                key_location: rule.key_location,
+                key_name: rule.key_name.clone(),
+                api_key: rule.api_key.clone(),
+                fingerprint: rule.fingerprint.clone(),
+            });
+        }
+        tables.rules.extend(created.iter().cloned());
+        Ok(created)
+    }
+
+    fn delete_recipe(&self, owner: &str, to_delete: Uuid) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let before = tables.recipes.len();
+        tables
+            .recipes
+            .retain(|recipe| recipe.id != to_delete || recipe.namespace != owner);
+        let count = before - tables.recipes.len();
+        if count > 0 {
+            tables
+                .served_counts
+                .retain(|recipe_id, _| *recipe_id != to_delete);
+            self.change_journal(Box::new(move |journal| {
+                journal.forget_recipes(&[to_delete])
+            }));
+        }
+        Ok(count)
+    }
+
+    fn owns_recipe(&self, owner: &str, to_find: Uuid) -> Result<bool> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipes
+            .iter()
+            .any(|recipe| recipe.id == to_find && recipe.namespace == owner))
+    }
+
+    fn find_recipe_namespace(&self, to_find: Uuid) -> Result<Option<String>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipes
+            .iter()
+            .find(|recipe| recipe.id == to_find)
+            .map(|recipe| recipe.namespace.clone()))
+    }
+
+    fn find_recipe_file(&self, owner: &str, to_find: Uuid) -> Result<Option<String>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipes
+            .iter()
+            .find(|recipe| recipe.id == to_find && recipe.namespace == owner)
+            .and_then(|recipe| recipe.file.clone()))
+    }
+
+    fn find_file_recipes(&self) -> Result<Vec<(Uuid, String)>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.file.is_some())
+            .map(|recipe| (recipe.id, recipe.namespace.clone()))
+            .collect())
+    }
+
+    fn find_rule_ids(&self, parent: Uuid) -> Result<Vec<Uuid>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .rules
+            .iter()
+            .filter(|rule| rule.recipe_id == parent)
+            .map(|rule| rule.id)
+            .collect())
+    }
+
+    fn delete_rules(&self, parent: Uuid, to_retain: &[Rule]) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let before = tables.rules.len();
+        tables.rules.retain(|rule| {
+            rule.recipe_id != parent || to_retain.iter().any(|retained| retained.id == rule.id)
+        });
+        Ok(before - tables.rules.len())
+    }
+
+    fn replace_headers(
+        &self,
+        parent: Uuid,
+        to_create: &[NewResponseHeader],
+    ) -> Result<Vec<ResponseHeader>> {
+        let mut tables = self.lock();
+
+        tables
+            .response_headers
+            .retain(|header| header.recipe_id != parent);
+        let mut created = Vec::new();
+        for header in to_create {
+            tables.has_recipe(header.recipe_id)?;
+            created.push(ResponseHeader {
+                id: Uuid::new_v4(),
+                recipe_id: header.recipe_id,
+                position: header.position,
+                name: header.name.clone(),
+                value: header.value.clone(),
+            });
+        }
+        tables.response_headers.extend(created.iter().cloned());
+        Ok(created)
+    }
+
+    fn replace_responses(
+        &self,
+        parent: Uuid,
+        to_create: &[NewRecipeResponse],
+    ) -> Result<Vec<RecipeResponse>> {
+        let mut tables = self.lock();
+
+        tables
+            .recipe_responses
+            .retain(|response| response.recipe_id != parent);
+        let mut created = Vec::new();
+        for response in to_create {
+            tables.has_recipe(response.recipe_id)?;
+            created.push(RecipeResponse {
+                id: Uuid::new_v4(),
+                recipe_id: response.recipe_id,
+                position: response.position,
+                status_code: response.status_code,
+                content_type: response.content_type.clone(),
+                payload: response.payload.clone(),
+                templated: response.templated,
+            });
+        }
+        tables.recipe_responses.extend(created.iter().cloned());
+        Ok(created)
+    }
+
+    fn update_rules(&self, to_update: Vec<Rule>) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let mut count = 0;
+        for rule in to_update {
+            if let Some(updating) = tables
+                .rules
+                .iter_mut()
+                .find(|existing| existing.id == rule.id)
+            {
+                *updating = rule;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn update_recipe(
+        &self,
+        owner: &str,
+        to_update: Uuid,
+        changes: NewRecipe,
+        disruption: NewDisruption,
+        from_file: Option<String>,
+    ) -> Result<usize> {
+        let mut tables = self.lock();
+
+        match tables
+            .recipes
+            .iter_mut()
+            .find(|recipe| recipe.id == to_update && recipe.namespace == owner)
+        {
+            Some(recipe) => {
+                let from_file = from_file.or_else(|| recipe.file.take());
+                *recipe = to_recipe(
+                    to_update,
+                    owner,
+                    recipe.created_at,
+                    changes,
+                    disruption,
+                    from_file,
+                );
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn create_journal_entry(
+        &self,
+        to_create: NewJournalEntry,
+        headers: Vec<shared::Header>,
+    ) -> Result<Uuid> {
+        let mut journal = self.lock_journal();
+
+        let NewJournalEntry {
+            method,
+            url,
+            path,
+            body,
+            subject,
+            matched,
+            recipe_id,
+            status_code,
+            namespace,
+        } = to_create;
+        let id = Uuid::new_v4();
+        journal.entries.push(JournalEntry {
+            id,
+            method,
+            url,
+            path,
+            body,
+            subject,
+            matched,
+            recipe_id,
+            status_code,
+            created_at: now(),
+            namespace,
+        });
+        for (position, header) in headers.into_iter().enumerate() {
+            let NewJournalHeader {
+                entry_id,
+                position,
+                name,
+                value,
+            } = (id, position, header).into();
+            journal.headers.push(JournalHeader {
+                id: Uuid::new_v4(),
+                entry_id,
+                position,
+                name,
+                value,
+            });
+        }
+        Ok(id)
+    }
+
+    fn find_journal_entries(
+        &self,
+        owner: &str,
+        filter: &shared::JournalFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(i64, Vec<JournalCascaded>)> {
+        let journal = self.lock_journal();
+
+        let mut found: Vec<&JournalEntry> = journal
+            .entries
+            .iter()
+            .filter(|entry| is_filtered(entry, owner, filter))
+            .collect();
+        found.sort_by_key(|entry| entry.created_at);
+        let total = i64::try_from(found.len())?;
+        let entries = found
+            .into_iter()
+            .skip(usize::try_from(offset)?)
+            .take(usize::try_from(limit)?)
+            .map(|entry| {
+                let mut headers: Vec<JournalHeader> = journal
+                    .headers
+                    .iter()
+                    .filter(|header| header.entry_id == entry.id)
+                    .cloned()
+                    .collect();
+                headers.sort_by_key(|header| header.position);
+                JournalCascaded(entry.clone(), headers)
+            })
+            .collect();
+        Ok((total, entries))
+    }
+
+    fn count_journal_entries(&self, owner: &str, filter: &shared::JournalFilter) -> Result<i64> {
+        let journal = self.lock_journal();
+
+        let count = journal
+            .entries
+            .iter()
+            .filter(|entry| is_filtered(entry, owner, filter))
+            .count();
+        Ok(i64::try_from(count)?)
+    }
+
+    fn clear_journal(&self, owner: &str) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let owned: Vec<Uuid> = tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner)
+            .map(|recipe| recipe.id)
+            .collect();
+        tables
+            .served_counts
+            .retain(|recipe_id, _| !owned.contains(recipe_id));
+        Ok(self
+            .lock_journal()
+            .delete_entries(|entry| entry.namespace == owner))
+    }
+
+    fn claim_served(&self, to_serve: Uuid) -> Result<i64> {
+        let mut tables = self.lock();
+
+        tables.has_recipe(to_serve)?;
+        let served = tables.served_counts.entry(to_serve).or_insert(0);
+        *served += 1;
+        Ok(*served - 1)
+    }
+
+    fn count_calls(&self, owner: &str, to_match: &str, base: &str, called: &str) -> Result<i64> {
+        let journal = self.lock_journal();
+
+        let count = journal
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.namespace == owner
+                    && entry.method == to_match
+                    && entry.path == called
+                    && is_on(&entry.url, base)
+            })
+            .count();
+        Ok(i64::try_from(count)?)
+    }
+
+    fn find_scenario_states(
+        &self,
+        owner: &str,
+        names: &[String],
+    ) -> Result<HashMap<String, String>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .scenarios
+            .iter()
+            .filter(|scenario| scenario.namespace == owner && names.contains(&scenario.name))
+            .map(|scenario| (scenario.name.clone(), scenario.state.clone()))
+            .collect())
+    }
+
+    fn set_scenario_state(&self, to_change: NewScenario) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let NewScenario {
+            name,
+            state,
+            namespace,
+        } = to_change;
+        match tables
+            .scenarios
+            .iter_mut()
+            .find(|scenario| scenario.namespace == namespace && scenario.name == name)
+        {
+            Some(scenario) => {
+                scenario.state = state;
+                scenario.updated_at = now();
+            }
+            None => tables.scenarios.push(Scenario {
+                name,
+                state,
+                updated_at: now(),
+                namespace,
+            }),
+        }
+        Ok(1)
+    }
+
+    fn load_scenarios(&self, owner: &str) -> Result<(Vec<ScenarioStep>, Vec<Scenario>)> {
+        let tables = self.lock();
+
+        let mut steps: Vec<ScenarioStep> = Vec::new();
+        for recipe in tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner)
+        {
+            if let Some(named) = recipe.scenario.as_ref() {
+                let step = (
+                    named.clone(),
+                    recipe.required_state.clone(),
+                    recipe.new_state.clone(),
+                );
+                if !steps.contains(&step) {
+                    steps.push(step);
+                }
+            }
+        }
+        let current = tables
+            .scenarios
+            .iter()
+            .filter(|scenario| scenario.namespace == owner)
+            .cloned()
+            .collect();
+        Ok((steps, current))
+    }
+
+    fn reset_scenarios(&self, owner: &str, to_reset: Option<&str>) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let before = tables.scenarios.len();
+        tables.scenarios.retain(|scenario| {
+            scenario.namespace != owner
+                || matches!(to_reset, Some(to_reset) if scenario.name != to_reset)
+        });
+        Ok(before - tables.scenarios.len())
+    }
+
+    fn load_namespaces(&self) -> Result<Vec<(String, i64)>> {
+        let tables = self.lock();
+
+        Ok(tables
+            .recipes
+            .iter()
+            .fold(BTreeMap::new(), |mut counts, recipe| {
+                *counts.entry(recipe.namespace.clone()).or_insert(0) += 1;
+                counts
+            })
+            .into_iter()
+            .collect())
+    }
+
+    fn delete_namespace(&self, owner: &str) -> Result<usize> {
+        let mut tables = self.lock();
+
+        let owned: Vec<Uuid> = tables
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.namespace == owner)
+            .map(|recipe| recipe.id)
+            .collect();
+        tables.rules.retain(|rule| !owned.contains(&rule.recipe_id));
+        tables
+            .response_headers
+            .retain(|header| !owned.contains(&header.recipe_id));
+        tables
+            .recipe_responses
+            .retain(|response| !owned.contains(&response.recipe_id));
+        tables
+            .scenarios
+            .retain(|scenario| scenario.namespace != owner);
+        tables.recipes.retain(|recipe| recipe.namespace != owner);
+        tables
+            .served_counts
+            .retain(|recipe_id, _| !owned.contains(recipe_id));
+        let (owner, count) = (owner.to_owned(), owned.len());
+        self.change_journal(Box::new(move |journal| {
+            journal.delete_entries(|entry| entry.namespace == owner);
+            journal.forget_recipes(&owned);
+        }));
+        Ok(count)
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// Whether the URL is on the scheme and host, `base`.
+fn is_on(url: &str, base: &str) -> bool {
+    matches!(url.strip_prefix(base), Some(path) if path.starts_with('/'))
+}
+
+/// The order recipes are listed in, by URL and then in the order they are tried.
+fn in_listed_order(a: &Recipe, b: &Recipe) -> Ordering {
+    a.url
+        .cmp(&b.url)
+        .then_with(|| b.priority.cmp(&a.priority))
+        .then_with(|| a.created_at.cmp(&b.created_at))
+}
+
+fn to_recipe(
+    id: Uuid,
+    owner: &str,
+    created_at: NaiveDateTime,
+    recipe: NewRecipe,
+    disruption: NewDisruption,
+    file: Option<String>,
+) -> Recipe {
+    let NewRecipe {
+        url,
+        payload,
+        status_code,
+        templated,
+        content_type,
+        draft,
+        priority,
+        scenario,
+        required_state,
+        new_state,
+        sequence_mode,
+    } = recipe;
+    let NewDisruption {
+        delay_type,
+        delay_millis,
+        delay_max_millis,
+        delay_std_dev_millis,
+        delay_sigma,
+        fault_type,
+        trickle_chunk_bytes,
+        trickle_interval_millis,
+    } = disruption;
+    Recipe {
+        id,
+        url,
+        payload,
+        created_at,
+        updated_at: now(),
+        status_code,
+        templated,
+        content_type,
+        draft,
+        priority,
+        scenario,
+        required_state,
+        new_state,
+        sequence_mode,
+        delay_type,
+        delay_millis,
+        delay_max_millis,
+        delay_std_dev_millis,
+        delay_sigma,
+        fault_type,
+        trickle_chunk_bytes,
+        trickle_interval_millis,
+        namespace: owner.to_owned(),
+        file,
+    }
+}
+
+fn is_filtered(entry: &JournalEntry, owner: &str, filter: &shared::JournalFilter) -> bool {
+    let shared::JournalFilter {
+        method,
+        path,
+        url,
+        subject,
+        matched,
+        recipe_id,
+        status,
+    } = filter;
+    if entry.namespace != owner {
+        return false;
+    }
+    if let Some(method) = method {
+        if entry.method != method.to_uppercase() {
+            return false;
+        }
+    }
+    if let Some(path) = path {
+        if entry.path != *path {
+            return false;
+        }
+    }
+    if let Some(url) = url {
+        if entry.url != *url {
+            return false;
+        }
+    }
+    if subject.is_some() && entry.subject != *subject {
+        return false;
+    }
+    if let Some(matched) = matched {
+        if entry.matched != *matched {
+            return false;
+        }
+    }
+    if recipe_id.is_some() && entry.recipe_id != *recipe_id {
+        return false;
+    }
+    if let Some(status) = status {
+        if entry.status_code != i32::from(*status) {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,794 @@
+//! Where recipes, the journal and scenarios are kept, either in Postgres or, when running the
+//! server somewhere standing up a database is too much, like a CI job, in memory.
+mod memory;
+mod postgres;
+
+pub(crate) use memory::Memory;
+pub(crate) use postgres::Postgres;
+
+use crate::models::{
+    JournalCascaded, NewDisruption, NewJournalEntry, NewRecipe, NewRecipeResponse,
+    NewResponseHeader, NewRule, NewScenario, Recipe, RecipeCascaded, RecipeResponse,
+    ResponseHeader, Rule, Scenario,
+};
+use anyhow::Result;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A scenario named by a recipe, the state the recipe requires of it and the state that serving
+/// the recipe moves it to.
+pub(crate) type ScenarioStep = (String, Option<String>, Option<String>);
+
+/// Every query the server makes of its storage; each backend has to give the same results for
+/// them, which the tests below check. Recipes, the journal and scenarios all belong to a
+/// namespace, `owner`, and queries by ID only find those in the namespace, unless noted.
+pub(crate) trait Storage {
+    /// Fails if the storage can't be reached.
+    fn check(&self) -> Result<()>;
+
+    /// Makes the changes against a view of the storage where, if any of them fails, none of them
+    /// are kept.
+    fn transaction(&self, changes: &mut dyn FnMut(&dyn Storage) -> Result<()>) -> Result<()>;
+
+    /// A page of the recipes, in order by URL and then in the order they are tried, along with
+    /// the total number of recipes.
+    fn load_recipes(&self, owner: &str, offset: i64, limit: i64) -> Result<(i64, Vec<Recipe>)>;
+
+    fn find_recipe(&self, owner: &str, to_find: Uuid) -> Result<Recipe>;
+
+    fn find_rules(&self, parent: Uuid) -> Result<Vec<Rule>>;
+
+    /// The headers of a recipe's own response, in order by position.
+    fn find_headers(&self, parent: Uuid) -> Result<Vec<ResponseHeader>>;
+
+    /// The responses served after a recipe's own, in order by position.
+    fn find_responses(&self, parent: Uuid) -> Result<Vec<RecipeResponse>>;
+
+    /// How many responses each recipe has after its own, for those that have any.
+    fn count_responses(&self, parents: &[Uuid]) -> Result<HashMap<Uuid, i64>>;
+
+    /// The recipes, other than drafts, whose URL is on the scheme and host, `base`, along with
+    /// their rules, in order by ID.
+    fn find_served_recipes(&self, owner: &str, base: &str) -> Result<Vec<(Recipe, Vec<Rule>)>>;
+
+    /// The URLs of every recipe, other than drafts, on the scheme and host, `base`.
+    fn find_recipe_urls(&self, owner: &str, base: &str) -> Result<Vec<String>>;
+
+    /// The recipes with exactly this URL, by descending priority and then oldest first.
+    fn find_recipes_with_url(&self, owner: &str, to_find: &str) -> Result<Vec<Recipe>>;
+
+    /// Every recipe not loaded from a file, in order by URL and then in the order they are tried.
+    fn export_recipes(&self, owner: &str) -> Result<Vec<Recipe>>;
+
+    /// The recipes with these IDs, in any order.
+    fn find_recipes(&self, owner: &str, ids: &[Uuid]) -> Result<Vec<Recipe>>;
+
+    /// Loads the rules, headers and responses of each recipe, keeping the recipes in the same
+    /// order.
+    fn cascade(&self, found: Vec<Recipe>) -> Result<Vec<RecipeCascaded>>;
+
+    /// Gives the recipes descending priorities in the order of `ids`, so the first is tried first.
+    fn prioritize_recipes(&self, owner: &str, ids: &[Uuid]) -> Result<usize>;
+
+    /// Creates a recipe with the given ID, as when restoring one, or otherwise a new ID.
+    fn create_recipe(
+        &self,
+        owner: &str,
+        to_id: Option<Uuid>,
+        to_create: NewRecipe,
+        disruption: NewDisruption,
+        from_file: Option<String>,
+    ) -> Result<Recipe>;
+
+    fn create_rules(&self, to_create: &[NewRule]) -> Result<Vec<Rule>>;
+
+    /// Deletes only the recipe itself, so its rules, headers and responses have to be deleted
+    /// first; journal entries for it are kept but no longer name it.
+    fn delete_recipe(&self, owner: &str, to_delete: Uuid) -> Result<usize>;
+
+    fn owns_recipe(&self, owner: &str, to_find: Uuid) -> Result<bool>;
+
+    /// The namespace that owns the recipe with this ID, in case there is one.
+    fn find_recipe_namespace(&self, to_find: Uuid) -> Result<Option<String>>;
+
+    /// The file that the recipe is loaded from, if it is loaded from one.
+    fn find_recipe_file(&self, owner: &str, to_find: Uuid) -> Result<Option<String>>;
+
+    /// Every recipe loaded from a file, in any namespace, along with its namespace.
+    fn find_file_recipes(&self) -> Result<Vec<(Uuid, String)>>;
+
+    fn find_rule_ids(&self, parent: Uuid) -> Result<Vec<Uuid>>;
+
+    /// Deletes the rules of a recipe, other than those to retain.
+    fn delete_rules(&self, parent: Uuid, to_retain: &[Rule]) -> Result<usize>;
+
+    /// Headers are an ordered list so rather than reconciling individual rows, any existing
+    /// headers are removed and the new list is inserted in its place.
+    fn replace_headers(
+        &self,
+        parent: Uuid,
+        to_create: &[NewResponseHeader],
+    ) -> Result<Vec<ResponseHeader>>;
+
+    /// Like headers, responses are an ordered list that is replaced as a whole.
+    fn replace_responses(
+        &self,
+        parent: Uuid,
+        to_create: &[NewRecipeResponse],
+    ) -> Result<Vec<RecipeResponse>>;
+
+    fn update_rules(&self, to_update: Vec<Rule>) -> Result<usize>;
+
+    /// Updates a recipe and, when loading one from a file, the file it is loaded from, in case it
+    /// has moved.
+    fn update_recipe(
+        &self,
+        owner: &str,
+        to_update: Uuid,
+        changes: NewRecipe,
+        disruption: NewDisruption,
+        from_file: Option<String>,
+    ) -> Result<usize>;
+
+    fn create_journal_entry(
+        &self,
+        to_create: NewJournalEntry,
+        headers: Vec<shared::Header>,
+    ) -> Result<Uuid>;
+
+    /// A page of the journal entries that match the filter, oldest first, along with the total
+    /// number of entries that match.
+    fn find_journal_entries(
+        &self,
+        owner: &str,
+        filter: &shared::JournalFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(i64, Vec<JournalCascaded>)>;
+
+    fn count_journal_entries(&self, owner: &str, filter: &shared::JournalFilter) -> Result<i64>;
+
+    /// Deletes every journal entry in the namespace, along with their headers, and starts counting
+    /// how many times each of its recipes has been served over.
+    fn clear_journal(&self, owner: &str) -> Result<usize>;
+
+    /// Counts one more serving of the recipe, giving how many times it had been served before;
+    /// each call claims a count of its own, so calls made at the same time never share one.
+    fn claim_served(&self, to_serve: Uuid) -> Result<i64>;
+
+    /// How many calls with the method have been journaled for the path on the scheme and host,
+    /// `base`, whatever their query, since the journal was last cleared.
+    fn count_calls(&self, owner: &str, to_match: &str, base: &str, called: &str) -> Result<i64>;
+
+    /// The states of the named scenarios that have left their starting state, by name.
+    fn find_scenario_states(
+        &self,
+        owner: &str,
+        names: &[String],
+    ) -> Result<HashMap<String, String>>;
+
+    /// Moves a scenario to a new state, whether or not it has left its starting state before.
+    fn set_scenario_state(&self, to_change: NewScenario) -> Result<usize>;
+
+    /// Every scenario named by a recipe, with the states the recipe requires and moves to, along
+    /// with the scenarios that have left their starting state.
+    fn load_scenarios(&self, owner: &str) -> Result<(Vec<ScenarioStep>, Vec<Scenario>)>;
+
+    /// Moves one scenario, or every scenario in the namespace if no name is given, back to its
+    /// starting state.
+    fn reset_scenarios(&self, owner: &str, to_reset: Option<&str>) -> Result<usize>;
+
+    /// Every namespace that owns a recipe, in order by name, with how many recipes it owns.
+    fn load_namespaces(&self) -> Result<Vec<(String, i64)>>;
+
+    /// Deletes everything the namespace owns, its recipes, along with their rules, headers and
+    /// responses, its journal and its scenarios, all at once, returning how many recipes were
+    /// deleted.
+    fn delete_namespace(&self, owner: &str) -> Result<usize>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::{DelayType, HttpVerb, MatchMode, RuleType, SequenceMode};
+    use anyhow::{format_err, Context};
+    use std::env;
+
+    /// A behavior every backend must have; each is checked in a namespace of its own, so that they
+    /// can share a database with the server and each other.
+    type Behavior = fn(&dyn Storage, &str) -> Result<()>;
+
+    const BEHAVIORS: &[(&str, Behavior)] = &[
+        ("recipes", check_recipes),
+        ("rules, headers and responses", check_children),
+        ("served recipes", check_served),
+        ("recipe files", check_files),
+        ("transactions", check_transactions),
+        ("journal", check_journal),
+        ("scenarios", check_scenarios),
+        ("namespaces", check_namespaces),
+    ];
+
+    fn check_behaviors(store: &dyn Storage) -> Result<()> {
+        for (name, behavior) in BEHAVIORS {
+            let owner = format!("test-{}", Uuid::new_v4());
+            let checked = behavior(store, &owner).with_context(|| format!("Checking {}", name));
+            store.delete_namespace(&owner)?;
+            checked?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory() -> Result<()> {
+        check_behaviors(&Memory::default())
+    }
+
+    /// Only checked when TEST_DATABASE_URL names a database with every migration applied.
+    #[test]
+    fn test_postgres() -> Result<()> {
+        match env::var("TEST_DATABASE_URL") {
+            Ok(database_url) => check_behaviors(&Postgres::connect(&database_url)?),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn new_recipe(url: &str) -> NewRecipe {
+        NewRecipe {
+            url: url.to_owned(),
+            payload: b"{}".to_vec(),
+            status_code: 200,
+            templated: false,
+            content_type: shared::default_content_type(),
+            draft: false,
+            priority: 0,
+            scenario: None,
+            required_state: None,
+            new_state: None,
+            sequence_mode: SequenceMode::StickOnLast,
+        }
+    }
+
+    fn create(store: &dyn Storage, owner: &str, recipe: NewRecipe) -> Result<Recipe> {
+        store.create_recipe(owner, None, recipe, NewDisruption::default(), None)
+    }
+
+    fn new_entry(owner: &str, method: &str, url: &str, path: &str) -> NewJournalEntry {
+        NewJournalEntry {
+            method: method.to_owned(),
+            url: url.to_owned(),
+            path: path.to_owned(),
+            body: Vec::new(),
+            subject: None,
+            matched: false,
+            recipe_id: None,
+            status_code: 404,
+            namespace: owner.to_owned(),
+        }
+    }
+
+    fn ids(recipes: &[Recipe]) -> Vec<Uuid> {
+        recipes.iter().map(|recipe| recipe.id).collect()
+    }
+
+    fn check_recipes(store: &dyn Storage, owner: &str) -> Result<()> {
+        let other = format!("{}-other", owner);
+        let a = create(store, owner, new_recipe("http://test.local/api/b"))?;
+        let b = create(store, owner, new_recipe("http://test.local/api/a"))?;
+        let c = create(
+            store,
+            owner,
+            NewRecipe {
+                priority: 1,
+                ..new_recipe("http://test.local/api/a")
+            },
+        )?;
+
+        let (total, listed) = store.load_recipes(owner, 0, 10)?;
+        assert_eq!(3, total);
+        assert_eq!(vec![c.id, b.id, a.id], ids(&listed));
+        let (total, listed) = store.load_recipes(owner, 1, 1)?;
+        assert_eq!(3, total);
+        assert_eq!(vec![b.id], ids(&listed));
+
+        assert_eq!(a.url, store.find_recipe(owner, a.id)?.url);
+        assert!(store.find_recipe(&other, a.id).is_err());
+        assert!(store.owns_recipe(owner, a.id)?);
+        assert!(!store.owns_recipe(&other, a.id)?);
+        assert_eq!(Some(owner.to_owned()), store.find_recipe_namespace(a.id)?);
+        assert_eq!(None, store.find_recipe_namespace(Uuid::new_v4())?);
+
+        let disruption = NewDisruption {
+            delay_type: Some(DelayType::Fixed),
+            delay_millis: Some(100),
+            ..NewDisruption::default()
+        };
+        let changes = || NewRecipe {
+            status_code: 404,
+            ..new_recipe("http://test.local/api/c")
+        };
+        assert_eq!(
+            0,
+            store.update_recipe(&other, a.id, changes(), NewDisruption::default(), None)?
+        );
+        assert_eq!(
+            1,
+            store.update_recipe(owner, a.id, changes(), disruption, None)?
+        );
+        let updated = store.find_recipe(owner, a.id)?;
+        assert_eq!("http://test.local/api/c", updated.url);
+        assert_eq!(404, updated.status_code);
+        assert_eq!(Some(DelayType::Fixed), updated.delay_type);
+        assert_eq!(Some(100), updated.delay_millis);
+        assert_eq!(a.created_at, updated.created_at);
+
+        let mut found = ids(&store.find_recipes(owner, &[a.id, b.id, Uuid::new_v4()])?);
+        found.sort();
+        let mut expected = vec![a.id, b.id];
+        expected.sort();
+        assert_eq!(expected, found);
+        assert!(store.find_recipes(&other, &[a.id])?.is_empty());
+
+        let with_url = store.find_recipes_with_url(owner, "http://test.local/api/a")?;
+        assert_eq!(vec![c.id, b.id], ids(&with_url));
+        assert_eq!(2, store.prioritize_recipes(owner, &[b.id, c.id])?);
+        let with_url = store.find_recipes_with_url(owner, "http://test.local/api/a")?;
+        assert_eq!(vec![b.id, c.id], ids(&with_url));
+
+        assert!(store.load_namespaces()?.contains(&(owner.to_owned(), 3)));
+        Ok(())
+    }
+
+    fn check_children(store: &dyn Storage, owner: &str) -> Result<()> {
+        let recipe = create(store, owner, new_recipe("http://test.local/api/orders"))?;
+        let parent = recipe.id;
+
+        let created = store.create_rules(&[
+            NewRule {
+                http_method: Some(HttpVerb::Get),
+                ..NewRule::of_type(parent, RuleType::HttpMethod)
+            },
+            NewRule {
+                header_name: Some("X-Test".to_owned()),
+                match_mode: Some(MatchMode::Exact),
+                match_value: Some("one".to_owned()),
+                ..NewRule::of_type(parent, RuleType::Header)
+            },
+        ])?;
+        assert_eq!(2, created.len());
+        assert_eq!(2, store.find_rules(parent)?.len());
+        let mut rule_ids = store.find_rule_ids(parent)?;
+        rule_ids.sort();
+        let mut expected: Vec<Uuid> = created.iter().map(|rule| rule.id).collect();
+        expected.sort();
+        assert_eq!(expected, rule_ids);
+        assert!(store
+            .create_rules(&[NewRule::of_type(Uuid::new_v4(), RuleType::Authenticated)])
+            .is_err());
+
+        let header_rule = created[1].clone();
+        let retained = Rule {
+            match_value: Some("two".to_owned()),
+            ..header_rule
+        };
+        assert_eq!(1, store.update_rules(vec![retained.clone()])?);
+        assert_eq!(
+            1,
+            store.delete_rules(parent, std::slice::from_ref(&retained))?
+        );
+        let rules = store.find_rules(parent)?;
+        assert_eq!(1, rules.len());
+        assert_eq!(retained.id, rules[0].id);
+        assert_eq!(Some("two".to_owned()), rules[0].match_value);
+
+        let header = |position, name: &str| NewResponseHeader {
+            recipe_id: parent,
+            position,
+            name: name.to_owned(),
+            value: "value".to_owned(),
+        };
+        store.replace_headers(parent, &[header(1, "X-B"), header(0, "X-A")])?;
+        let names: Vec<String> = store
+            .find_headers(parent)?
+            .into_iter()
+            .map(|header| header.name)
+            .collect();
+        assert_eq!(vec!["X-A".to_owned(), "X-B".to_owned()], names);
+        store.replace_headers(parent, &[header(0, "X-C")])?;
+        assert_eq!(1, store.find_headers(parent)?.len());
+
+        let response = |position, status_code| NewRecipeResponse {
+            recipe_id: parent,
+            position,
+            status_code,
+            content_type: shared::default_content_type(),
+            payload: b"{}".to_vec(),
+            templated: false,
+        };
+        store.replace_responses(parent, &[response(1, 503), response(0, 201)])?;
+        let statuses: Vec<i32> = store
+            .find_responses(parent)?
+            .into_iter()
+            .map(|response| response.status_code)
+            .collect();
+        assert_eq!(vec![201, 503], statuses);
+        let counts = store.count_responses(&[parent, Uuid::new_v4()])?;
+        assert_eq!(1, counts.len());
+        assert_eq!(Some(&2), counts.get(&parent));
+
+        let cascaded = store.cascade(vec![recipe])?;
+        assert_eq!(1, cascaded.len());
+        let RecipeCascaded(_, rules, headers, responses) = &cascaded[0];
+        assert_eq!((1, 1, 2), (rules.len(), headers.len(), responses.len()));
+
+        store.delete_rules(parent, &[])?;
+        store.replace_headers(parent, &[])?;
+        store.replace_responses(parent, &[])?;
+        assert_eq!(1, store.delete_recipe(owner, parent)?);
+        assert!(store.find_recipe(owner, parent).is_err());
+        Ok(())
+    }
+
+    fn check_served(store: &dyn Storage, owner: &str) -> Result<()> {
+        let served = create(store, owner, new_recipe("http://test.local/api/users/{id}"))?;
+        store.create_rules(&[NewRule {
+            http_method: Some(HttpVerb::Get),
+            ..NewRule::of_type(served.id, RuleType::HttpMethod)
+        }])?;
+        create(
+            store,
+            owner,
+            NewRecipe {
+                draft: true,
+                ..new_recipe("http://test.local/api/draft")
+            },
+        )?;
+        create(store, owner, new_recipe("http://other.local/api/users"))?;
+        create(store, owner, new_recipe("http://test.localhost/api/users"))?;
+        create(store, owner, new_recipe("http://orders-service/api/orders"))?;
+
+        let found = store.find_served_recipes(owner, "http://test.local")?;
+        assert_eq!(1, found.len());
+        assert_eq!(served.id, found[0].0.id);
+        assert_eq!(1, found[0].1.len());
+        assert_eq!(
+            vec![served.url.clone()],
+            store.find_recipe_urls(owner, "http://test.local")?
+        );
+        assert!(store
+            .find_served_recipes(&format!("{}-other", owner), "http://test.local")?
+            .is_empty());
+        // a base is matched as written, even where it has characters LIKE treats as wildcards
+        assert!(store
+            .find_recipe_urls(owner, "http://orders_service")?
+            .is_empty());
+        assert!(store
+            .find_served_recipes(owner, "http://orders%")?
+            .is_empty());
+        Ok(())
+    }
+
+    fn check_files(store: &dyn Storage, owner: &str) -> Result<()> {
+        let loaded = store.create_recipe(
+            owner,
+            Some(Uuid::new_v4()),
+            new_recipe("http://test.local/api/loaded"),
+            NewDisruption::default(),
+            Some("orders.json".to_owned()),
+        )?;
+        let saved = create(store, owner, new_recipe("http://test.local/api/saved"))?;
+        assert!(store
+            .create_recipe(
+                owner,
+                Some(loaded.id),
+                new_recipe("http://test.local/api/again"),
+                NewDisruption::default(),
+                None,
+            )
+            .is_err());
+
+        assert_eq!(
+            Some("orders.json".to_owned()),
+            store.find_recipe_file(owner, loaded.id)?
+        );
+        assert_eq!(None, store.find_recipe_file(owner, saved.id)?);
+        let files: Vec<(Uuid, String)> = store
+            .find_file_recipes()?
+            .into_iter()
+            .filter(|(_, namespace)| namespace == owner)
+            .collect();
+        assert_eq!(vec![(loaded.id, owner.to_owned())], files);
+        assert_eq!(vec![saved.id], ids(&store.export_recipes(owner)?));
+
+        let changes = || new_recipe("http://test.local/api/loaded");
+        store.update_recipe(owner, loaded.id, changes(), NewDisruption::default(), None)?;
+        assert_eq!(
+            Some("orders.json".to_owned()),
+            store.find_recipe_file(owner, loaded.id)?
+        );
+        let moved = Some("moved/orders.json".to_owned());
+        store.update_recipe(
+            owner,
+            loaded.id,
+            changes(),
+            NewDisruption::default(),
+            moved.clone(),
+        )?;
+        assert_eq!(moved, store.find_recipe_file(owner, loaded.id)?);
+        Ok(())
+    }
+
+    fn check_transactions(store: &dyn Storage, owner: &str) -> Result<()> {
+        let failed = store.transaction(&mut |conn| {
+            let recipe = create(conn, owner, new_recipe("http://test.local/api/orders"))?;
+            assert!(conn.owns_recipe(owner, recipe.id)?);
+            Err(format_err!("Undo the recipe"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(0, store.load_recipes(owner, 0, 10)?.0);
+
+        store.transaction(&mut |conn| {
+            let recipe = create(conn, owner, new_recipe("http://test.local/api/orders"))?;
+            store_nested(conn, owner, recipe.id)
+        })?;
+        let (total, recipes) = store.load_recipes(owner, 0, 10)?;
+        assert_eq!(2, total);
+
+        // the journal keeps naming a recipe until its deletion is kept
+        let served = &recipes[0];
+        store.create_journal_entry(
+            NewJournalEntry {
+                matched: true,
+                recipe_id: Some(served.id),
+                status_code: 200,
+                ..new_entry(owner, "GET", "http://test.local", "/api/orders")
+            },
+            Vec::new(),
+        )?;
+        let named = || -> Result<Option<Uuid>> {
+            let (_, entries) =
+                store.find_journal_entries(owner, &shared::JournalFilter::default(), 0, 10)?;
+            Ok(entries
+                .into_iter()
+                .find_map(|JournalCascaded(entry, _)| entry.recipe_id))
+        };
+        let failed = store.transaction(&mut |conn| {
+            assert_eq!(1, conn.delete_recipe(owner, served.id)?);
+            Err(format_err!("Undo the deletion"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(Some(served.id), named()?);
+        store.transaction(&mut |conn| conn.delete_recipe(owner, served.id).map(|_| ()))?;
+        assert_eq!(None, named()?);
+        Ok(())
+    }
+
+    /// A transaction within a transaction sees the changes made so far and is kept along with them.
+    fn store_nested(conn: &dyn Storage, owner: &str, created: Uuid) -> Result<()> {
+        conn.transaction(&mut |nested| {
+            assert!(nested.owns_recipe(owner, created)?);
+            create(nested, owner, new_recipe("http://test.local/api/users"))?;
+            Ok(())
+        })
+    }
+
+    fn check_journal(store: &dyn Storage, owner: &str) -> Result<()> {
+        let recipe = create(store, owner, new_recipe("http://test.local/api/users"))?;
+        let served = |url: &str| NewJournalEntry {
+            subject: Some("alice".to_owned()),
+            matched: true,
+            recipe_id: Some(recipe.id),
+            status_code: 200,
+            ..new_entry(owner, "GET", url, "/api/users")
+        };
+        let header = |name: &str| shared::Header {
+            name: name.to_owned(),
+            value: "value".to_owned(),
+        };
+        let first = store.create_journal_entry(
+            served("http://test.local/api/users"),
+            vec![header("X-A"), header("X-B")],
+        )?;
+        store.create_journal_entry(
+            new_entry(owner, "POST", "http://test.local/api/users", "/api/users"),
+            Vec::new(),
+        )?;
+        store.create_journal_entry(served("http://test.local/api/users?page=2"), Vec::new())?;
+        store.create_journal_entry(
+            new_entry(owner, "GET", "http://other.local/api/users", "/api/users"),
+            Vec::new(),
+        )?;
+        store.create_journal_entry(
+            new_entry(
+                &format!("{}-other", owner),
+                "GET",
+                "http://test.local/api/users",
+                "/api/users",
+            ),
+            Vec::new(),
+        )?;
+
+        let count = |filter: shared::JournalFilter| store.count_journal_entries(owner, &filter);
+        assert_eq!(4, count(shared::JournalFilter::default())?);
+        let filtered = [
+            (
+                3,
+                shared::JournalFilter {
+                    method: Some("get".to_owned()),
+                    ..shared::JournalFilter::default()
+                },
+            ),
+            (
+                4,
+                shared::JournalFilter {
+                    path: Some("/api/users".to_owned()),
+                    ..shared::JournalFilter::default()
+                },
+            ),
+            (
+                1,
+                shared::JournalFilter {
+                    url: Some("http://test.local/api/users?page=2".to_owned()),
+                    ..shared::JournalFilter::default()
+                },
+            ),
+            (
+                2,
+                shared::JournalFilter {
+                    subject: Some("alice".to_owned()),
+                    matched: Some(true),
+                    recipe_id: Some(recipe.id),
+                    ..shared::JournalFilter::default()
+                },
+            ),
+            (
+                2,
+                shared::JournalFilter {
+                    status: Some(404),
+                    ..shared::JournalFilter::default()
+                },
+            ),
+        ];
+        for (expected, filter) in filtered.iter() {
+            assert_eq!(
+                *expected,
+                store.count_journal_entries(owner, filter)?,
+                "{:?}",
+                filter
+            );
+        }
+
+        let (total, entries) =
+            store.find_journal_entries(owner, &shared::JournalFilter::default(), 0, 2)?;
+        assert_eq!(4, total);
+        assert_eq!(2, entries.len());
+        let JournalCascaded(entry, headers) = &entries[0];
+        assert_eq!(first, entry.id);
+        let names: Vec<&str> = headers.iter().map(|header| header.name.as_str()).collect();
+        assert_eq!(vec!["X-A", "X-B"], names);
+        assert_eq!("POST", entries[1].0.method);
+
+        assert_eq!(0, store.claim_served(recipe.id)?);
+        assert_eq!(1, store.claim_served(recipe.id)?);
+        assert!(store.claim_served(Uuid::new_v4()).is_err());
+        assert_eq!(
+            2,
+            store.count_calls(owner, "GET", "http://test.local", "/api/users")?
+        );
+
+        // the journal outlives the recipes it names
+        store.delete_recipe(owner, recipe.id)?;
+        assert!(store.claim_served(recipe.id).is_err());
+        assert_eq!(4, count(shared::JournalFilter::default())?);
+
+        let orders = create(store, owner, new_recipe("http://test.local/api/orders"))?;
+        assert_eq!(0, store.claim_served(orders.id)?);
+        assert_eq!(4, store.clear_journal(owner)?);
+        assert_eq!(0, count(shared::JournalFilter::default())?);
+        // clearing the journal starts counting how many times each recipe is served over
+        assert_eq!(0, store.claim_served(orders.id)?);
+        let other = format!("{}-other", owner);
+        assert_eq!(
+            1,
+            store.count_journal_entries(&other, &shared::JournalFilter::default())?
+        );
+        store.clear_journal(&other)?;
+        Ok(())
+    }
+
+    fn check_scenarios(store: &dyn Storage, owner: &str) -> Result<()> {
+        let step = |required: Option<&str>, new: &str| NewRecipe {
+            scenario: Some("checkout".to_owned()),
+            required_state: required.map(str::to_owned),
+            new_state: Some(new.to_owned()),
+            ..new_recipe("http://test.local/api/checkout")
+        };
+        create(store, owner, step(None, "Paid"))?;
+        create(store, owner, step(Some("Paid"), "Shipped"))?;
+        create(store, owner, step(None, "Paid"))?;
+        create(store, owner, new_recipe("http://test.local/api/orders"))?;
+
+        let (mut steps, current) = store.load_scenarios(owner)?;
+        steps.sort();
+        assert_eq!(
+            vec![
+                ("checkout".to_owned(), None, Some("Paid".to_owned())),
+                (
+                    "checkout".to_owned(),
+                    Some("Paid".to_owned()),
+                    Some("Shipped".to_owned())
+                ),
+            ],
+            steps
+        );
+        assert!(current.is_empty());
+
+        let move_to = |namespace: &str, name: &str, state: &str| {
+            store.set_scenario_state(NewScenario {
+                name: name.to_owned(),
+                state: state.to_owned(),
+                namespace: namespace.to_owned(),
+            })
+        };
+        let other = format!("{}-other", owner);
+        move_to(owner, "checkout", "Paid")?;
+        move_to(owner, "checkout", "Shipped")?;
+        move_to(owner, "login", "LoggedIn")?;
+        move_to(&other, "checkout", "Paid")?;
+        let states = store.find_scenario_states(owner, &["checkout".to_owned()])?;
+        assert_eq!(1, states.len());
+        assert_eq!(Some(&"Shipped".to_owned()), states.get("checkout"));
+        assert_eq!(2, store.load_scenarios(owner)?.1.len());
+
+        assert_eq!(1, store.reset_scenarios(owner, Some("login"))?);
+        assert_eq!(1, store.reset_scenarios(owner, None)?);
+        assert!(store.load_scenarios(owner)?.1.is_empty());
+        let states = store.find_scenario_states(&other, &["checkout".to_owned()])?;
+        assert_eq!(Some(&"Paid".to_owned()), states.get("checkout"));
+        store.reset_scenarios(&other, None)?;
+        Ok(())
+    }
+
+    fn check_namespaces(store: &dyn Storage, owner: &str) -> Result<()> {
+        let recipe = create(store, owner, new_recipe("http://test.local/api/users"))?;
+        store.create_rules(&[NewRule::of_type(recipe.id, RuleType::Authenticated)])?;
+        store.replace_headers(
+            recipe.id,
+            &[NewResponseHeader {
+                recipe_id: recipe.id,
+                position: 0,
+                name: "X-Test".to_owned(),
+                value: "value".to_owned(),
+            }],
+        )?;
+        store.create_journal_entry(
+            new_entry(owner, "GET", "http://test.local/api/users", "/api/users"),
+            Vec::new(),
+        )?;
+        store.set_scenario_state(NewScenario {
+            name: "checkout".to_owned(),
+            state: "Paid".to_owned(),
+            namespace: owner.to_owned(),
+        })?;
+        let other = format!("{}-other", owner);
+        create(store, &other, new_recipe("http://test.local/api/users"))?;
+
+        assert_eq!(1, store.delete_namespace(owner)?);
+        let namespaces: Vec<String> = store
+            .load_namespaces()?
+            .into_iter()
+            .map(|(namespace, _)| namespace)
+            .collect();
+        assert!(!namespaces.contains(&owner.to_owned()));
+        assert!(namespaces.contains(&other));
+        assert!(store.find_rule_ids(recipe.id)?.is_empty());
+        assert!(store.find_headers(recipe.id)?.is_empty());
+        assert_eq!(
+            0,
+            store.count_journal_entries(owner, &shared::JournalFilter::default())?
+        );
+        assert!(store.load_scenarios(owner)?.1.is_empty());
+        assert_eq!(1, store.delete_namespace(&other)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,774 @@
+use super::{ScenarioStep, Storage};
+use crate::{
+    models::{
+        JournalCascaded, JournalEntry, JournalHeader, NewDisruption, NewJournalEntry,
+        NewJournalHeader, NewRecipe, NewRecipeResponse, NewResponseHeader, NewRule, NewScenario,
+        Recipe, RecipeCascaded, RecipeResponse, ResponseHeader, Rule, Scenario,
+    },
+    schema::{journal_entries, scenarios},
+};
+use anyhow::Result;
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    r2d2::{ConnectionManager, Pool, PooledConnection},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    ops::Deref,
+};
+use uuid::Uuid;
+
+pub(crate) type PgPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Keeps everything in Postgres, getting a connection from a pool for each query, or, during a
+/// transaction, running every query on the connection the transaction is open on.
+pub(crate) struct Postgres<C> {
+    source: C,
+}
+
+impl Postgres<PgPool> {
+    pub(crate) fn connect(database_url: &str) -> Result<Self> {
+        let manager: ConnectionManager<PgConnection> = ConnectionManager::new(database_url);
+        let source = Pool::new(manager)?;
+        Ok(Self { source })
+    }
+}
+
+/// Where the connections for queries come from.
+pub(crate) trait Connect {
+    type Connection: Deref<Target = PgConnection>;
+
+    fn connect(&self) -> Result<Self::Connection>;
+}
+
+impl Connect for PgPool {
+    type Connection = PooledConnection<ConnectionManager<PgConnection>>;
+
+    fn connect(&self) -> Result<Self::Connection> {
+        self.get().map_err(anyhow::Error::from)
+    }
+}
+
+impl<'a> Connect for &'a PgConnection {
+    type Connection = &'a PgConnection;
+
+    fn connect(&self) -> Result<Self::Connection> {
+        Ok(*self)
+    }
+}
+
+impl<C: Connect> Storage for Postgres<C> {
+    fn check(&self) -> Result<()> {
+        let conn = self.source.connect()?;
+
+        diesel::sql_query("select 1 = 1").execute(&*conn)?;
+        Ok(())
+    }
+
+    fn transaction(&self, changes: &mut dyn FnMut(&dyn Storage) -> Result<()>) -> Result<()> {
+        let conn = self.source.connect()?;
+
+        conn.transaction(|| changes(&Postgres { source: &*conn }))
+    }
+
+    fn load_recipes(&self, owner: &str, offset: i64, limit: i64) -> Result<(i64, Vec<Recipe>)> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        let total = recipes
+            .filter(namespace.eq(owner))
+            .count()
+            .first::<i64>(&*conn)?;
+
+        let results: Vec<Recipe> = recipes
+            .filter(namespace.eq(owner))
+            .offset(offset)
+            .limit(limit)
+            .order((url, priority.desc(), created_at))
+            .load::<Recipe>(&*conn)?;
+
+        Ok((total, results))
+    }
+
+    fn find_recipe(&self, owner: &str, to_find: Uuid) -> Result<Recipe> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .find(to_find)
+            .filter(namespace.eq(owner))
+            .first::<Recipe>(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_rules(&self, parent: Uuid) -> Result<Vec<Rule>> {
+        use crate::schema::rules::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        rules
+            .filter(recipe_id.eq(parent))
+            .load::<Rule>(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_headers(&self, parent: Uuid) -> Result<Vec<ResponseHeader>> {
+        use crate::schema::response_headers::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        response_headers
+            .filter(recipe_id.eq(parent))
+            .order(position)
+            .load::<ResponseHeader>(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_responses(&self, parent: Uuid) -> Result<Vec<RecipeResponse>> {
+        use crate::schema::recipe_responses::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipe_responses
+            .filter(recipe_id.eq(parent))
+            .order(position)
+            .load::<RecipeResponse>(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn count_responses(&self, parents: &[Uuid]) -> Result<HashMap<Uuid, i64>> {
+        use crate::schema::recipe_responses::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        Ok(recipe_responses
+            .filter(recipe_id.eq_any(parents))
+            .select(recipe_id)
+            .load::<Uuid>(&*conn)?
+            .into_iter()
+            .fold(HashMap::new(), |mut counts, parent| {
+                *counts.entry(parent).or_insert(0) += 1;
+                counts
+            }))
+    }
+
+    fn find_served_recipes(&self, owner: &str, base: &str) -> Result<Vec<(Recipe, Vec<Rule>)>> {
+        use crate::schema::{recipes, rules};
+
+        let conn = self.source.connect()?;
+
+        let joined: Vec<(Recipe, Option<Rule>)> = recipes::dsl::recipes
+            .filter(recipes::dsl::namespace.eq(owner))
+            .filter(recipes::dsl::url.like(on_base(base)))
+            // drafts are recorded by the proxy and aren't served until they are promoted
+            .filter(recipes::dsl::draft.eq(false))
+            .left_join(rules::dsl::rules)
+            .load::<(Recipe, Option<Rule>)>(&*conn)?;
+
+        // the query returns a denormalized Vec, meaning that while the rule have of each tuple is
+        // distinct, the associated recipe may be repeated; unzip here to re-normalize before
+        // returning
+        let (mut recipes, rules): (Vec<_>, Vec<_>) = joined.into_iter().unzip();
+
+        // sort followed by deduplicating yields a Vec of recipes that are unique by ID
+        recipes.sort_by_key(|recipe| recipe.id);
+        recipes.dedup_by_key(|recipe| recipe.id);
+
+        // this fold produces the result Vec who members are a tuple containing each recipe and its
+        // associated rules
+        let (recipes, _) = recipes
+            .into_iter()
+            // the accumulator is a tuple, the left half is the result Vec containing tuples of
+            // recipes and their related rules, the right half is the leftover unprocessed rules,
+            // if any
+            .fold((Vec::new(), rules), move |acc, recipe| {
+                let (mut recipes, rules) = acc;
+
+                // partitioning on the ID means the left half of the resulting tuple is only rules
+                // with this iterations recipe ID; the right half is the remaining rules which we
+                // pass along in the accumulator
+                let (for_recipe, rules): (Vec<Option<_>>, Vec<Option<_>>) =
+                    rules.into_iter().partition(|rule| {
+                        if let Some(rule) = rule {
+                            rule.recipe_id == recipe.id
+                        } else {
+                            false
+                        }
+                    });
+                let for_recipe: Vec<Rule> =
+                    for_recipe.into_iter().filter_map(|rule| rule).collect();
+
+                // push a new tuple for this iteration and the rules we matched to the recipe
+                recipes.push((recipe, for_recipe));
+
+                // return the accumulator to keep working
+                (recipes, rules)
+            });
+        Ok(recipes)
+    }
+
+    fn find_recipe_urls(&self, owner: &str, base: &str) -> Result<Vec<String>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .select(url)
+            .filter(namespace.eq(owner))
+            .filter(url.like(on_base(base)))
+            .filter(draft.eq(false))
+            .load::<String>(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_recipes_with_url(&self, owner: &str, to_find: &str) -> Result<Vec<Recipe>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .filter(namespace.eq(owner))
+            .filter(url.eq(to_find))
+            .order((priority.desc(), created_at))
+            .load(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn export_recipes(&self, owner: &str) -> Result<Vec<Recipe>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .filter(namespace.eq(owner))
+            .filter(file.is_null())
+            .order((url, priority.desc(), created_at))
+            .load(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_recipes(&self, owner: &str, ids: &[Uuid]) -> Result<Vec<Recipe>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .filter(namespace.eq(owner))
+            .filter(id.eq_any(ids))
+            .load::<Recipe>(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn cascade(&self, found: Vec<Recipe>) -> Result<Vec<RecipeCascaded>> {
+        use crate::schema::{recipe_responses, response_headers};
+
+        let conn = self.source.connect()?;
+
+        let rules: Vec<Vec<Rule>> = Rule::belonging_to(&found)
+            .load::<Rule>(&*conn)?
+            .grouped_by(&found);
+        let headers: Vec<Vec<ResponseHeader>> = ResponseHeader::belonging_to(&found)
+            .order(response_headers::position)
+            .load::<ResponseHeader>(&*conn)?
+            .grouped_by(&found);
+        let responses: Vec<Vec<RecipeResponse>> = RecipeResponse::belonging_to(&found)
+            .order(recipe_responses::position)
+            .load::<RecipeResponse>(&*conn)?
+            .grouped_by(&found);
+
+        Ok(found
+            .into_iter()
+            .zip(rules)
+            .zip(headers)
+            .zip(responses)
+            .map(|(((recipe, rules), headers), responses)| {
+                RecipeCascaded(recipe, rules, headers, responses)
+            })
+            .collect())
+    }
+
+    fn prioritize_recipes(&self, owner: &str, ids: &[Uuid]) -> Result<usize> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        conn.transaction(|| {
+            let mut count = 0;
+            for (index, to_update) in ids.iter().enumerate() {
+                let to_priority = i32::try_from(ids.len() - 1 - index)?;
+                count += diesel::update(recipes.find(to_update).filter(namespace.eq(owner)))
+                    .set(priority.eq(to_priority))
+                    .execute(&*conn)?;
+            }
+            Ok(count)
+        })
+    }
+
+    fn create_recipe(
+        &self,
+        owner: &str,
+        to_id: Option<Uuid>,
+        to_create: NewRecipe,
+        disruption: NewDisruption,
+        from_file: Option<String>,
+    ) -> Result<Recipe> {
+        use crate::schema::recipes;
+
+        let conn = self.source.connect()?;
+
+        diesel::insert_into(recipes::table)
+            .values((
+                to_create,
+                disruption,
+                recipes::namespace.eq(owner),
+                to_id.map(|to_id| recipes::id.eq(to_id)),
+                recipes::file.eq(from_file),
+            ))
+            .get_result(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn create_rules(&self, to_create: &[NewRule]) -> Result<Vec<Rule>> {
+        use crate::schema::rules;
+
+        let conn = self.source.connect()?;
+
+        diesel::insert_into(rules::table)
+            .values(to_create)
+            .get_results(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn delete_recipe(&self, owner: &str, to_delete: Uuid) -> Result<usize> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        diesel::delete(recipes.filter(id.eq(to_delete)).filter(namespace.eq(owner)))
+            .execute(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn owns_recipe(&self, owner: &str, to_find: Uuid) -> Result<bool> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        let count = recipes
+            .find(to_find)
+            .filter(namespace.eq(owner))
+            .count()
+            .first::<i64>(&*conn)?;
+        Ok(count > 0)
+    }
+
+    fn find_recipe_namespace(&self, to_find: Uuid) -> Result<Option<String>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .find(to_find)
+            .select(namespace)
+            .first(&*conn)
+            .optional()
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_recipe_file(&self, owner: &str, to_find: Uuid) -> Result<Option<String>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .find(to_find)
+            .filter(namespace.eq(owner))
+            .select(file)
+            .first::<Option<String>>(&*conn)
+            .optional()
+            .map(Option::flatten)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_file_recipes(&self) -> Result<Vec<(Uuid, String)>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        recipes
+            .filter(file.is_not_null())
+            .select((id, namespace))
+            .load(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_rule_ids(&self, parent: Uuid) -> Result<Vec<Uuid>> {
+        use crate::schema::rules::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        rules
+            .filter(recipe_id.eq(parent))
+            .select(id)
+            .load(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn delete_rules(&self, parent: Uuid, to_retain: &[Rule]) -> Result<usize> {
+        use crate::schema::rules::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        let ids: Vec<Uuid> = to_retain.iter().map(|rule| rule.id).collect();
+
+        diesel::delete(rules.filter(recipe_id.eq(parent).and(id.ne_all(ids))))
+            .execute(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn replace_headers(
+        &self,
+        parent: Uuid,
+        to_create: &[NewResponseHeader],
+    ) -> Result<Vec<ResponseHeader>> {
+        use crate::schema::response_headers::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        diesel::delete(response_headers.filter(recipe_id.eq(parent))).execute(&*conn)?;
+
+        diesel::insert_into(response_headers)
+            .values(to_create)
+            .get_results(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn replace_responses(
+        &self,
+        parent: Uuid,
+        to_create: &[NewRecipeResponse],
+    ) -> Result<Vec<RecipeResponse>> {
+        use crate::schema::recipe_responses::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        diesel::delete(recipe_responses.filter(recipe_id.eq(parent))).execute(&*conn)?;
+
+        diesel::insert_into(recipe_responses)
+            .values(to_create)
+            .get_results(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn update_rules(&self, to_update: Vec<Rule>) -> Result<usize> {
+        use crate::schema::rules::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        let mut count = 0;
+        for rule in to_update {
+            count += diesel::update(rules.find(rule.id))
+                .set(&rule)
+                .execute(&*conn)?;
+        }
+        Ok(count)
+    }
+
+    fn update_recipe(
+        &self,
+        owner: &str,
+        to_update: Uuid,
+        changes: NewRecipe,
+        disruption: NewDisruption,
+        from_file: Option<String>,
+    ) -> Result<usize> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        let count = diesel::update(recipes.find(to_update).filter(namespace.eq(owner)))
+            .set((
+                changes,
+                disruption,
+                from_file.map(|from_file| file.eq(from_file)),
+            ))
+            .execute(&*conn)?;
+        Ok(count)
+    }
+
+    fn create_journal_entry(
+        &self,
+        to_create: NewJournalEntry,
+        headers: Vec<shared::Header>,
+    ) -> Result<Uuid> {
+        use crate::schema::journal_headers;
+
+        let conn = self.source.connect()?;
+
+        let entry: JournalEntry = diesel::insert_into(journal_entries::table)
+            .values(to_create)
+            .get_result(&*conn)?;
+
+        let headers: Vec<NewJournalHeader> = headers
+            .into_iter()
+            .enumerate()
+            .map(|(position, header)| (entry.id, position, header).into())
+            .collect();
+        diesel::insert_into(journal_headers::table)
+            .values(headers)
+            .execute(&*conn)?;
+
+        Ok(entry.id)
+    }
+
+    fn find_journal_entries(
+        &self,
+        owner: &str,
+        filter: &shared::JournalFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(i64, Vec<JournalCascaded>)> {
+        use crate::schema::journal_headers::dsl::*;
+
+        let total = self.count_journal_entries(owner, filter)?;
+
+        let conn = self.source.connect()?;
+
+        let entries: Vec<JournalEntry> = filter_journal(owner, filter)
+            .order(journal_entries::created_at)
+            .offset(offset)
+            .limit(limit)
+            .load(&*conn)?;
+
+        let headers: Vec<Vec<JournalHeader>> = JournalHeader::belonging_to(&entries)
+            .order(position)
+            .load::<JournalHeader>(&*conn)?
+            .grouped_by(&entries);
+
+        Ok((
+            total,
+            entries
+                .into_iter()
+                .zip(headers)
+                .map(|(entry, headers)| JournalCascaded(entry, headers))
+                .collect(),
+        ))
+    }
+
+    fn count_journal_entries(&self, owner: &str, filter: &shared::JournalFilter) -> Result<i64> {
+        let conn = self.source.connect()?;
+
+        filter_journal(owner, filter)
+            .count()
+            .get_result(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn clear_journal(&self, owner: &str) -> Result<usize> {
+        use crate::schema::{recipes, served_counts};
+
+        let conn = self.source.connect()?;
+        let conn = &*conn;
+
+        conn.transaction(|| {
+            let owned = recipes::table
+                .filter(recipes::namespace.eq(owner))
+                .select(recipes::id);
+            diesel::delete(served_counts::table.filter(served_counts::recipe_id.eq_any(owned)))
+                .execute(conn)?;
+            // the headers are deleted along with their entries
+            diesel::delete(journal_entries::table.filter(journal_entries::namespace.eq(owner)))
+                .execute(conn)
+                .map_err(anyhow::Error::from)
+        })
+    }
+
+    fn claim_served(&self, to_serve: Uuid) -> Result<i64> {
+        use crate::schema::served_counts::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        // the count is taken and moved on in one statement, so no two calls can take the same one
+        let claimed = diesel::insert_into(served_counts)
+            .values((recipe_id.eq(to_serve), served.eq(1)))
+            .on_conflict(recipe_id)
+            .do_update()
+            .set(served.eq(served + 1))
+            .returning(served)
+            .get_result::<i64>(&*conn)?;
+        Ok(claimed - 1)
+    }
+
+    fn count_calls(&self, owner: &str, to_match: &str, base: &str, called: &str) -> Result<i64> {
+        use crate::schema::journal_entries::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        journal_entries
+            .filter(namespace.eq(owner))
+            .filter(method.eq(to_match))
+            .filter(path.eq(called))
+            .filter(url.like(on_base(base)))
+            .count()
+            .get_result(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn find_scenario_states(
+        &self,
+        owner: &str,
+        names: &[String],
+    ) -> Result<HashMap<String, String>> {
+        use crate::schema::scenarios::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        Ok(scenarios
+            .filter(namespace.eq(owner))
+            .filter(name.eq_any(names))
+            .select((name, state))
+            .load::<(String, String)>(&*conn)?
+            .into_iter()
+            .collect())
+    }
+
+    fn set_scenario_state(&self, to_change: NewScenario) -> Result<usize> {
+        use crate::schema::scenarios::dsl::*;
+        use diesel::{dsl::now, pg::upsert::excluded};
+
+        let conn = self.source.connect()?;
+
+        diesel::insert_into(scenarios)
+            .values(&to_change)
+            .on_conflict((namespace, name))
+            .do_update()
+            .set((state.eq(excluded(state)), updated_at.eq(now)))
+            .execute(&*conn)
+            .map_err(anyhow::Error::from)
+    }
+
+    fn load_scenarios(&self, owner: &str) -> Result<(Vec<ScenarioStep>, Vec<Scenario>)> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        let steps = recipes
+            .filter(namespace.eq(owner))
+            .filter(scenario.is_not_null())
+            .select((scenario, required_state, new_state))
+            .distinct()
+            .load::<(Option<String>, Option<String>, Option<String>)>(&*conn)?
+            .into_iter()
+            .filter_map(|(named, required, new)| named.map(|named| (named, required, new)))
+            .collect();
+        let current = scenarios::table
+            .filter(scenarios::namespace.eq(owner))
+            .load::<Scenario>(&*conn)?;
+        Ok((steps, current))
+    }
+
+    fn reset_scenarios(&self, owner: &str, to_reset: Option<&str>) -> Result<usize> {
+        use crate::schema::scenarios::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        let owned = scenarios.filter(namespace.eq(owner));
+        if let Some(to_reset) = to_reset {
+            diesel::delete(owned.filter(name.eq(to_reset))).execute(&*conn)
+        } else {
+            diesel::delete(owned).execute(&*conn)
+        }
+        .map_err(anyhow::Error::from)
+    }
+
+    fn load_namespaces(&self) -> Result<Vec<(String, i64)>> {
+        use crate::schema::recipes::dsl::*;
+
+        let conn = self.source.connect()?;
+
+        Ok(recipes
+            .select(namespace)
+            .load::<String>(&*conn)?
+            .into_iter()
+            .fold(BTreeMap::new(), |mut counts, owner| {
+                *counts.entry(owner).or_insert(0) += 1;
+                counts
+            })
+            .into_iter()
+            .collect())
+    }
+
+    fn delete_namespace(&self, owner: &str) -> Result<usize> {
+        use crate::schema::{recipe_responses, recipes, response_headers, rules};
+
+        let conn = self.source.connect()?;
+        let conn = &*conn;
+
+        conn.transaction(|| {
+            let owned = recipes::table
+                .filter(recipes::namespace.eq(owner))
+                .select(recipes::id);
+            diesel::delete(rules::table.filter(rules::recipe_id.eq_any(owned))).execute(conn)?;
+            diesel::delete(
+                response_headers::table.filter(response_headers::recipe_id.eq_any(owned)),
+            )
+            .execute(conn)?;
+            diesel::delete(
+                recipe_responses::table.filter(recipe_responses::recipe_id.eq_any(owned)),
+            )
+            .execute(conn)?;
+            diesel::delete(journal_entries::table.filter(journal_entries::namespace.eq(owner)))
+                .execute(conn)?;
+            diesel::delete(scenarios::table.filter(scenarios::namespace.eq(owner)))
+                .execute(conn)?;
+            diesel::delete(recipes::table.filter(recipes::namespace.eq(owner)))
+                .execute(conn)
+                .map_err(anyhow::Error::from)
+        })
+    }
+}
+
+/// A pattern to match, with LIKE, any URL on the scheme and host, `base`; a `%` or `_` in the base,
+/// as in a host like `orders_service`, only matches itself.
+fn on_base(base: &str) -> String {
+    let escaped = base
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("{}/%", escaped)
+}
+
+fn filter_journal<'a>(
+    owner: &'a str,
+    filter: &'a shared::JournalFilter,
+) -> journal_entries::BoxedQuery<'a, Pg> {
+    use crate::schema::journal_entries::dsl::*;
+
+    let mut query = journal_entries.filter(namespace.eq(owner)).into_boxed();
+    if let Some(to_match) = filter.method.as_ref() {
+        query = query.filter(method.eq(to_match.to_uppercase()));
+    }
+    if let Some(to_match) = filter.path.as_ref() {
+        query = query.filter(path.eq(to_match));
+    }
+    if let Some(to_match) = filter.url.as_ref() {
+        query = query.filter(url.eq(to_match));
+    }
+    if let Some(to_match) = filter.subject.as_ref() {
+        query = query.filter(subject.eq(to_match));
+    }
+    if let Some(to_match) = filter.matched {
+        query = query.filter(matched.eq(to_match));
+    }
+    if let Some(to_match) = filter.recipe_id {
+        query = query.filter(recipe_id.eq(to_match));
+    }
+    if let Some(to_match) = filter.status {
+        query = query.filter(status_code.eq(i32::from(to_match)));
+    }
+    query
+}
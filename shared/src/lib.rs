@@ -11,26 +11,212 @@ pub struct RecipesPage {
     pub recipes: Vec<Recipe>,
 }
 
-#[derive(Default, Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Recipe {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<Uuid>,
     pub url: String,
     #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub rules: Vec<Rule>,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<Header>,
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
     pub payload: Value,
+    /// Further responses served, in order, on the calls after the first that the recipe matches.
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub responses: Vec<RecipeResponse>,
+    /// What the recipe serves once it has served its own response and each of `responses`.
+    #[serde(default)]
+    pub sequence_mode: SequenceMode,
+    /// When set, placeholders in the payload, like `{{path.id}}`, are replaced with values from
+    /// each request; otherwise the payload is served literally.
+    #[serde(default)]
+    pub templated: bool,
+    /// Recorded by the proxy and not served until promoted by clearing this flag.
+    #[serde(default)]
+    pub draft: bool,
+    /// Recipes whose URLs match a call are tried highest priority first.
+    #[serde(default)]
+    pub priority: i32,
+    /// The name of the scenario whose state this recipe requires or changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+    /// The recipe is only served while its scenario is in this state; any state will do if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_state: Option<String>,
+    /// Serving the recipe moves its scenario to this state.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_state: Option<String>,
+    /// How long to wait before serving the recipe.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<Delay>,
+    /// How to break the response instead of serving it whole.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fault: Option<Fault>,
+    /// The file, in the recipe directory, that the recipe is loaded from; such a recipe can only be
+    /// changed by editing its file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<NaiveDateTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<NaiveDateTime>,
 }
 
+impl Default for Recipe {
+    fn default() -> Self {
+        Self {
+            id: None,
+            url: String::default(),
+            rules: Vec::new(),
+            status: default_status(),
+            headers: Vec::new(),
+            content_type: default_content_type(),
+            payload: Value::default(),
+            responses: Vec::new(),
+            sequence_mode: SequenceMode::default(),
+            templated: false,
+            draft: false,
+            priority: 0,
+            scenario: None,
+            required_state: None,
+            new_state: None,
+            delay: None,
+            fault: None,
+            file: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+/// Recipes that do not specify a status code are served as 200 OK.
+pub fn default_status() -> u16 {
+    200
+}
+
+/// Recipes that do not specify a content type are served as JSON.
+pub fn default_content_type() -> String {
+    "application/json".to_owned()
+}
+
+/// How a payload is written, which depends on the content type of its recipe: JSON as is, text as
+/// a JSON string, and anything else as a JSON string of base64 encoded bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayloadKind {
+    Json,
+    Text,
+    Binary,
+}
+
+impl PayloadKind {
+    pub fn of(content_type: &str) -> Self {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if essence == "application/json" || essence.ends_with("+json") {
+            PayloadKind::Json
+        } else if essence.starts_with("text/")
+            || essence.ends_with("/xml")
+            || essence.ends_with("+xml")
+            || essence == "application/x-www-form-urlencoded"
+            || essence == "application/javascript"
+        {
+            PayloadKind::Text
+        } else {
+            PayloadKind::Binary
+        }
+    }
+}
+
+/// One of the responses that a recipe serves in turn, after its own; headers are shared with the
+/// recipe's own response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecipeResponse {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+    pub payload: Value,
+    #[serde(default)]
+    pub templated: bool,
+}
+
+/// How a recipe with a sequence of responses carries on once it has served every one of them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SequenceMode {
+    /// Starts over from the recipe's own response.
+    Cycle,
+    /// Keeps serving the last response.
+    #[default]
+    StickOnLast,
+    /// Stops matching, so the next recipe for the URL is tried instead.
+    FallThrough,
+}
+
+/// How long a recipe waits before it is served, either fixed or drawn at random for each call.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Delay {
+    Fixed {
+        millis: u32,
+    },
+    /// Any delay from the minimum to the maximum, inclusive, is as likely as any other.
+    Uniform {
+        min_millis: u32,
+        max_millis: u32,
+    },
+    /// Delays cluster around the mean; a negative draw is no delay at all.
+    Normal {
+        mean_millis: u32,
+        std_dev_millis: u32,
+    },
+    /// Delays have a long tail above the median, like the latency of a real service; `sigma` is
+    /// the standard deviation of the logarithm of the delay.
+    LogNormal {
+        median_millis: u32,
+        sigma: f64,
+    },
+}
+
+/// A way to break the response to a call, for testing how a client copes with a failing service.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Closes the connection without a response.
+    ConnectionReset,
+    /// Sends the status, headers and the first half of the payload, then closes the connection.
+    PartialThenReset,
+    /// Sends the payload a chunk at a time, waiting between chunks.
+    Trickle {
+        chunk_bytes: u32,
+        interval_millis: u32,
+    },
+    /// Sends the status and headers without any payload.
+    EmptyReply,
+}
+
+/// A response header served along with the payload of a recipe; headers are applied in order so
+/// repeated names, like `Set-Cookie`, are all sent.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Rule {
     Authenticated {
         #[serde(skip_serializing_if = "Option::is_none")]
         id: Option<Uuid>,
         key_path: String,
+        /// The algorithms a token may be signed with; if empty, any that suits the key will do.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        algorithms: Vec<Algorithm>,
     },
     Subject {
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -42,6 +228,146 @@ pub enum Rule {
         id: Option<Uuid>,
         http_method: HttpVerb,
     },
+    Header {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        header_name: String,
+        match_mode: MatchMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        match_value: Option<String>,
+    },
+    PathParam {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        param_name: String,
+        match_mode: MatchMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        match_value: Option<String>,
+    },
+    Body {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        /// A JSON pointer, like `/order/id`, or a JSONPath, like `$.order.id`, to match a value
+        /// within a JSON body rather than the whole body.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        body_path: Option<String>,
+        /// The name of a field to match within a URL encoded form body.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        form_field: Option<String>,
+        match_mode: MatchMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        match_value: Option<String>,
+    },
+    QueryParam {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        param_name: String,
+        match_mode: MatchMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        match_value: Option<String>,
+    },
+    /// Matches only the nth call, counting from one, with the same method to the same URL, not
+    /// counting the query, since the journal was last cleared.
+    NthCall {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        nth: u32,
+    },
+    /// Matches a claim of the bearer token, registered or custom; each element of an array claim,
+    /// like `aud` or `roles`, is matched on its own.
+    Claim {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        /// The name of a top level claim, like `scope`, or a JSON pointer, like `/tenant/id`, or
+        /// a JSONPath, like `$.tenant.id`, to a claim nested within an object.
+        claim: String,
+        match_mode: MatchMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        match_value: Option<String>,
+    },
+    /// Matches a bearer token whose `exp` hasn't passed and whose `nbf` has, if it has them, at
+    /// the time of the request.
+    Unexpired {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+    },
+    /// Matches HTTP Basic credentials in the Authorization header.
+    BasicAuth {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        username: String,
+        /// The password to expect; if unset, any password will do.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+    },
+    /// Matches an API key sent in a named header or query parameter.
+    ApiKey {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        key_location: ApiKeyLocation,
+        key_name: String,
+        api_key: String,
+    },
+    /// Matches the client certificate of a call over TLS, as the proxy that terminates TLS
+    /// forwards it, by its subject, its SHA-256 fingerprint or both.
+    ClientCert {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Uuid>,
+        /// The subject as RFC 4514 writes it, like `CN=alice,O=Acme`, or just its common name.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subject: Option<String>,
+        /// The SHA-256 fingerprint as hexadecimal, with or without colons between the bytes.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fingerprint: Option<String>,
+    },
+}
+
+impl Rule {
+    pub fn id(&self) -> Option<Uuid> {
+        use Rule::*;
+        match self {
+            Authenticated { id, .. }
+            | Subject { id, .. }
+            | HttpMethod { id, .. }
+            | Header { id, .. }
+            | PathParam { id, .. }
+            | Body { id, .. }
+            | QueryParam { id, .. }
+            | NthCall { id, .. }
+            | Claim { id, .. }
+            | Unexpired { id }
+            | BasicAuth { id, .. }
+            | ApiKey { id, .. }
+            | ClientCert { id, .. } => *id,
+        }
+    }
+}
+
+/// Where a call sends an API key.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ApiKeyLocation {
+    Header,
+    Query,
+}
+
+/// How a rule compares the value it expects to the value found in a request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MatchMode {
+    Exact,
+    Present,
+    Absent,
+    CaseInsensitive,
+    Regex,
+    Contains,
+    /// Matches any of a comma separated list of values exactly.
+    AnyOf,
+}
+
+impl MatchMode {
+    /// Whether a rule using this mode must also have a value to compare against.
+    pub fn requires_value(&self) -> bool {
+        !matches!(self, MatchMode::Present | MatchMode::Absent)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -50,12 +376,77 @@ pub enum HttpVerb {
     Post,
     Put,
     Delete,
+    Patch,
+    Head,
+    Options,
+}
+
+/// An algorithm a JWT can be signed with, named as in the `alg` header of a token.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    HS256,
+    HS384,
+    HS512,
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+    PS384,
+    PS512,
+    ES256,
+    ES384,
+    ES512,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+impl Algorithm {
+    pub const ALL: [Algorithm; 12] = [
+        Algorithm::HS256,
+        Algorithm::HS384,
+        Algorithm::HS512,
+        Algorithm::RS256,
+        Algorithm::RS384,
+        Algorithm::RS512,
+        Algorithm::PS256,
+        Algorithm::PS384,
+        Algorithm::PS512,
+        Algorithm::ES256,
+        Algorithm::ES384,
+        Algorithm::ES512,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        use Algorithm::*;
+        match self {
+            HS256 => "HS256",
+            HS384 => "HS384",
+            HS512 => "HS512",
+            RS256 => "RS256",
+            RS384 => "RS384",
+            RS512 => "RS512",
+            PS256 => "PS256",
+            PS384 => "PS384",
+            PS512 => "PS512",
+            ES256 => "ES256",
+            ES384 => "ES384",
+            ES512 => "ES512",
+        }
+    }
+
+    /// The algorithm with the given name, which is case sensitive, like `RS256`.
+    pub fn named(name: &str) -> Option<Algorithm> {
+        Algorithm::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.name() == name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum KeyPathKind {
     Directory,
     File,
+    /// Keys are chosen from a JWKS document by the `kid` of each token.
+    Jwks,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -83,3 +474,125 @@ pub struct KeyPathComponent {
     pub leaf: bool,
     pub component: String,
 }
+
+/// A call to a mock endpoint, as it was received, and how it was answered.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JournalEntry {
+    pub id: Uuid,
+    pub method: String,
+    pub url: String,
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<Header>,
+    /// The body of the call as text; any bytes that aren't valid UTF-8 are replaced.
+    pub body: String,
+    /// The subject claim of the call's bearer token, if it had one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Whether a recipe was served, rather than the call being proxied or getting a 404.
+    pub matched: bool,
+    /// The recipe that was served, unless it has since been deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe_id: Option<Uuid>,
+    pub status: u16,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct JournalPage {
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+    pub entries: Vec<JournalEntry>,
+}
+
+/// Narrows the journal to the entries that match every filter that is set.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct JournalFilter {
+    /// The HTTP method, ignoring case.
+    pub method: Option<String>,
+    /// The path of the call, without the query.
+    pub path: Option<String>,
+    /// The whole URL of the call, including the scheme, host and query.
+    pub url: Option<String>,
+    pub subject: Option<String>,
+    pub matched: Option<bool>,
+    pub recipe_id: Option<Uuid>,
+    pub status: Option<u16>,
+}
+
+/// Every scenario starts in, and is reset to, this state.
+pub const SCENARIO_STARTED: &str = "Started";
+
+/// Whether a name, like that of a scenario, can be used as a path segment without encoding.
+pub fn is_url_safe(name: &str) -> bool {
+    name.chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// A named state shared by the recipes that refer to it, moved along as they are served.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub state: String,
+    /// Every state that the scenario's recipes require or move to, including the starting one.
+    #[serde(default = "Vec::new")]
+    pub states: Vec<String>,
+}
+
+/// Recipes, journal entries and scenarios belong to this namespace unless a call selects another.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// A call may name the namespace it works in with this header.
+pub const NAMESPACE_HEADER: &str = "X-Empholite-Namespace";
+
+/// A namespace that owns recipes, isolating them, and the calls served from them, from those of
+/// every other namespace.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Namespace {
+    pub name: String,
+    /// How many recipes the namespace owns.
+    pub recipes: i64,
+}
+
+/// What is wrong with one of the recipes in a bulk change, which is then left out along with all
+/// the others.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BulkError {
+    /// The position of the recipe in the change, counting from zero.
+    pub index: usize,
+    pub error: String,
+}
+
+/// What a token minted by the issuer claims and the key it is signed with.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TokenRequest {
+    /// Claims to include, which replace the `iat` and `exp` that the issuer sets.
+    #[serde(default)]
+    pub claims: serde_json::Map<String, Value>,
+    /// Seconds from now until the token expires, an hour unless set; a negative number mints a
+    /// token that has already expired.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<i64>,
+    /// The key to sign with, found the same way as the key path of an Authenticated rule; the
+    /// issuer signs with one of its own keys when it isn't set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<String>,
+    /// The `kid` for the header of the token; it names the issuer's own key unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// The algorithm to sign with; one that suits the key is chosen unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<Algorithm>,
+}
+
+/// A token minted by the issuer, with how it was signed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IssuedToken {
+    pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    /// When the token expires, in seconds since the epoch, if it has an `exp` claim.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}